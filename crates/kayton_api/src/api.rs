@@ -108,6 +108,17 @@ pub struct KaytonApi {
     pub get_function: crate::fns_registry::GetFunctionFn,
     pub register_type: crate::fns_registry::RegisterTypeFn,
     pub get_type: crate::fns_registry::GetTypeFn,
+
+    // ---- Maps ----
+    pub set_global_map_from_handles: crate::fns_map::SetGlobalMapFromHandlesFn,
+    pub get_global_map_len: crate::fns_map::GetGlobalMapLenFn,
+    pub get_map_len_by_handle: crate::fns_map::GetMapLenByHandleFn,
+    pub get_global_map_item: crate::fns_map::GetGlobalMapItemFn,
+    pub get_map_item_by_handle: crate::fns_map::GetMapItemByHandleFn,
+    pub get_map_entry_by_handle: crate::fns_map::GetMapEntryByHandleFn,
+    // Map keys and values are often plain i64/bool values without a global name
+    pub intern_i64: crate::fns_intern::InternI64Fn,
+    pub intern_bool: crate::fns_intern::InternBoolFn,
 }
 
 impl crate::types::KaytonContext {
@@ -264,4 +275,29 @@ impl crate::types::KaytonContext {
     ) -> Result<crate::types::HKayRef, crate::types::KaytonError> {
         (self.api().get_global_tuple_item_by_handle)(self, h, index)
     }
+
+    // ---- Map convenience ----
+    #[inline]
+    pub fn map_len_by_handle(
+        &mut self,
+        h: crate::types::HKayRef,
+    ) -> Result<usize, crate::types::KaytonError> {
+        (self.api().get_map_len_by_handle)(self, h)
+    }
+    #[inline]
+    pub fn map_item_by_handle(
+        &mut self,
+        h: crate::types::HKayRef,
+        key: crate::types::HKayRef,
+    ) -> Result<crate::types::HKayRef, crate::types::KaytonError> {
+        (self.api().get_map_item_by_handle)(self, h, key)
+    }
+    #[inline]
+    pub fn map_entry_by_handle(
+        &mut self,
+        h: crate::types::HKayRef,
+        index: usize,
+    ) -> Result<(crate::types::HKayRef, crate::types::HKayRef), crate::types::KaytonError> {
+        (self.api().get_map_entry_by_handle)(self, h, index)
+    }
 }
@@ -8,6 +8,12 @@ pub type InternU64Fn = fn(ctx: &mut KaytonContext, value: u64) -> Result<HKayRef
 /// Intern unnamed u8 value; returns a handle
 pub type InternU8Fn = fn(ctx: &mut KaytonContext, value: u8) -> Result<HKayRef, KaytonError>;
 
+/// Intern unnamed i64 value; returns a handle
+pub type InternI64Fn = fn(ctx: &mut KaytonContext, value: i64) -> Result<HKayRef, KaytonError>;
+
+/// Intern unnamed bool value; returns a handle
+pub type InternBoolFn = fn(ctx: &mut KaytonContext, value: bool) -> Result<HKayRef, KaytonError>;
+
 /// Intern unnamed f64 value; returns a handle
 pub type InternF64Fn = fn(ctx: &mut KaytonContext, value: f64) -> Result<HKayRef, KaytonError>;
 
@@ -0,0 +1,34 @@
+use crate::{HKayRef, KaytonContext, KaytonError};
+
+/// Create/overwrite a named map global from parallel slices of key and value handles.
+/// Returns the global handle for the map (kind = KIND_MAP).
+pub type SetGlobalMapFromHandlesFn = fn(
+    ctx: &mut KaytonContext,
+    name: &str,
+    keys: *const HKayRef,
+    values: *const HKayRef,
+    len: usize,
+) -> Result<HKayRef, KaytonError>;
+
+/// Number of entries of a map (by name).
+pub type GetGlobalMapLenFn = fn(ctx: &mut KaytonContext, name: &str) -> Result<usize, KaytonError>;
+
+/// Number of entries of a map (fast path by handle).
+pub type GetMapLenByHandleFn =
+    fn(ctx: &mut KaytonContext, h: HKayRef) -> Result<usize, KaytonError>;
+
+/// Look up the value stored under `key` (by name). Keys compare by value, so an
+/// interned `"a"` finds the entry stored under another `"a"` handle.
+pub type GetGlobalMapItemFn =
+    fn(ctx: &mut KaytonContext, name: &str, key: HKayRef) -> Result<HKayRef, KaytonError>;
+
+/// Look up the value stored under `key` (fast path by handle).
+pub type GetMapItemByHandleFn =
+    fn(ctx: &mut KaytonContext, h: HKayRef, key: HKayRef) -> Result<HKayRef, KaytonError>;
+
+/// Random access: entry i as (key, value) handles (fast path by handle).
+pub type GetMapEntryByHandleFn = fn(
+    ctx: &mut KaytonContext,
+    h: HKayRef,
+    index: usize,
+) -> Result<(HKayRef, HKayRef), KaytonError>;
@@ -22,3 +22,4 @@ pub const KIND_ISIZE: KindId = 16;
 pub const KIND_BOOL: KindId = 17;
 pub const KIND_TUPLE: KindId = 18;
 pub const KIND_KVEC: KindId = 19;
+pub const KIND_MAP: KindId = 20;
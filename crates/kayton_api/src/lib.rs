@@ -6,6 +6,7 @@ pub mod fns_dynamic;
 pub mod fns_float;
 pub mod fns_intern;
 pub mod fns_kvec;
+pub mod fns_map;
 pub mod fns_registry;
 pub mod fns_sint;
 pub mod fns_string;
@@ -16,7 +17,7 @@ pub mod types;
 pub mod vec_types;
 
 // Explicit re-exports (no globs); function typedefs are NOT re-exported.
-pub use types::{ErrorKind, GlobalStrBuf, HKayRef, KaytonContext, KaytonError};
+pub use types::{ErrorKind, GlobalStrBuf, HKayRef, KaytonContext, KaytonError, KaytonResult};
 pub use vec_types::KVec;
 
 pub use api::KaytonApi;
@@ -27,6 +28,7 @@ pub use fns_dynamic::*;
 pub use fns_float::*;
 pub use fns_intern::*;
 pub use fns_kvec::*;
+pub use fns_map::*;
 pub use fns_registry::*;
 pub use fns_sint::*;
 pub use fns_string::*;
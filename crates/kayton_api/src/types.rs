@@ -3,13 +3,16 @@ extern crate alloc;
 use alloc::{borrow::Cow, boxed::Box, string::String};
 use core::ffi::c_void;
 use core::fmt;
+use core::mem::MaybeUninit;
 
-/// Error kinds for Kayton API operations
+/// Error kinds for Kayton API operations. The discriminant is the kind's code in a
+/// `KaytonResult`, so existing codes must not change.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[non_exhaustive] // lets you add variants later without breaking users
+#[repr(u32)]
 pub enum ErrorKind {
-    NotFound,
-    Generic,
+    NotFound = 1,
+    Generic = 2,
 }
 
 /// Kayton API error type
@@ -173,6 +176,44 @@ impl Drop for GlobalStrBuf {
     }
 }
 
+/// Return value of a plugin function whose manifest declares a `Result` return.
+/// Compiled Kayton code reads it without linking against this crate, so the layout is fixed:
+/// `status` is 0 when `value` is set, otherwise the code of an `ErrorKind`, and `message`
+/// then carries the error text, which the caller frees.
+#[repr(C)]
+pub struct KaytonResult<T> {
+    pub status: u32,
+    pub value: MaybeUninit<T>,
+    pub message: GlobalStrBuf,
+}
+
+impl<T> KaytonResult<T> {
+    pub fn ok(value: T) -> Self {
+        Self {
+            status: 0,
+            value: MaybeUninit::new(value),
+            message: GlobalStrBuf::from_raw(core::ptr::null(), 0, 0),
+        }
+    }
+
+    pub fn err(error: KaytonError) -> Self {
+        Self {
+            status: error.kind() as u32,
+            value: MaybeUninit::uninit(),
+            message: GlobalStrBuf::new(String::from(error.message())),
+        }
+    }
+}
+
+impl<T> From<Result<T, KaytonError>> for KaytonResult<T> {
+    fn from(result: Result<T, KaytonError>) -> Self {
+        match result {
+            Ok(value) => Self::ok(value),
+            Err(error) => Self::err(error),
+        }
+    }
+}
+
 // ---------------- Registry-related core types ----------------
 
 /// Opaque raw function pointer used for registry lookups. Cast by the caller to the desired Rust ABI function type.
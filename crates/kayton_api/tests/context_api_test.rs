@@ -377,6 +377,55 @@ fn context_api_accessor_and_calls() {
         Ok(0)
     }
 
+    fn set_map_from_handles(
+        _ctx: &mut KaytonContext,
+        _name: &str,
+        _keys: *const HKayRef,
+        _values: *const HKayRef,
+        _len: usize,
+    ) -> Result<HKayRef, KaytonError> {
+        Ok(HKayRef { kind: 20, index: 0 })
+    }
+    fn get_global_map_len(_ctx: &mut KaytonContext, _name: &str) -> Result<usize, KaytonError> {
+        Ok(0)
+    }
+    fn get_map_len_by_handle(_ctx: &mut KaytonContext, _h: HKayRef) -> Result<usize, KaytonError> {
+        Ok(0)
+    }
+    fn get_global_map_item(
+        _ctx: &mut KaytonContext,
+        _name: &str,
+        _key: HKayRef,
+    ) -> Result<HKayRef, KaytonError> {
+        Ok(HKayRef { kind: 0, index: 0 })
+    }
+    fn get_map_item_by_handle(
+        _ctx: &mut KaytonContext,
+        _h: HKayRef,
+        _key: HKayRef,
+    ) -> Result<HKayRef, KaytonError> {
+        Ok(HKayRef { kind: 0, index: 0 })
+    }
+    fn get_map_entry_by_handle(
+        _ctx: &mut KaytonContext,
+        _h: HKayRef,
+        _index: usize,
+    ) -> Result<(HKayRef, HKayRef), KaytonError> {
+        Ok((HKayRef { kind: 0, index: 0 }, HKayRef { kind: 0, index: 0 }))
+    }
+    fn intern_i64(_ctx: &mut KaytonContext, _v: i64) -> Result<HKayRef, KaytonError> {
+        Ok(HKayRef {
+            kind: 14,
+            index: 14,
+        })
+    }
+    fn intern_bool(_ctx: &mut KaytonContext, _v: bool) -> Result<HKayRef, KaytonError> {
+        Ok(HKayRef {
+            kind: 17,
+            index: 17,
+        })
+    }
+
     let api = KaytonApi {
         size: core::mem::size_of::<KaytonApi>() as u64,
         set_global_u64: set_u64,
@@ -457,6 +506,14 @@ fn context_api_accessor_and_calls() {
         get_function: get_function,
         register_type: register_type,
         get_type: get_type,
+        set_global_map_from_handles: set_map_from_handles,
+        get_global_map_len: get_global_map_len,
+        get_map_len_by_handle: get_map_len_by_handle,
+        get_global_map_item: get_global_map_item,
+        get_map_item_by_handle: get_map_item_by_handle,
+        get_map_entry_by_handle: get_map_entry_by_handle,
+        intern_i64,
+        intern_bool,
     };
 
     let api_box = Box::new(api);
@@ -3,31 +3,67 @@ use std::collections::{HashMap, HashSet};
 use anyhow::{Context, Result};
 use core::ffi::c_void;
 use kayton_vm::{
-    Api, KaytonVm, ReportIntFn, ReportStrFn, VmKaytonContext, host_report_int, host_report_str,
-    set_report_host_from_ctx, set_stdout_callback,
+    Api, KaytonVm, ReportBoolFn, ReportErrorFn, ReportF64Fn, ReportIntFn, ReportMapFn, ReportStrFn,
+    ReportTupleFn, ReportVecBoolFn, ReportVecF64Fn, ReportVecI64Fn, VmHKayRef, VmKaytonContext,
+    host_report_bool, host_report_error, host_report_f64, host_report_int, host_report_map,
+    host_report_str, host_report_tuple, host_report_vec_bool, host_report_vec_f64,
+    host_report_vec_i64, set_report_host_from_ctx, set_stdout_callback, take_reported_names,
+    take_runtime_error,
 };
 use keyton_rust_compiler::compile_rust::compile_generated_rust_to_dylib;
-use keyton_rust_compiler::diagnostics::format_type_error;
-use keyton_rust_compiler::hir::lower_program;
-use keyton_rust_compiler::lexer::Lexer;
-use keyton_rust_compiler::parser::Parser;
+use keyton_rust_compiler::diagnostics::{
+    Source, format_parse_errors, format_resolve_error, format_type_error,
+};
+use keyton_rust_compiler::hir::lower_program_with_spans;
+use keyton_rust_compiler::parser::{Stmt, parse_source};
 use keyton_rust_compiler::rhir::{RustProgram, convert_to_rhir};
 use keyton_rust_compiler::rimport::env::discover_plugin_dll_path;
-use keyton_rust_compiler::rust_codegen::{CodeGenerator, RustCode};
-use keyton_rust_compiler::shir::{resolve_program, sym::SymbolId};
+use keyton_rust_compiler::rust_codegen::{
+    CodeGenerator, MAX_REPORTED_ITEMS, RustCode, captured_by_lambdas, tuple_reportable,
+};
+use keyton_rust_compiler::shir::resolve_program_with_spans;
+use keyton_rust_compiler::shir::resolver::ResolveError;
+use keyton_rust_compiler::shir::sym::{StructInfo, SymKind, SymbolId, Type};
 use libloading::Library;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum VarKind {
     Int,
+    Float,
+    Bool,
+    Str,
+    IntList,
+    FloatList,
+    BoolList,
+    /// A list of strings, stored as a `KIND_TUPLE` global of its items
+    StrList,
+    /// An optional value, stored as a `KIND_TUPLE` global of no items or one
+    Option(ScalarKind),
+    /// A dict stored as a `KIND_MAP` global, by key and value kind
+    Dict(ScalarKind, ScalarKind),
+    /// A tuple stored as a `KIND_TUPLE` global, by item kind
+    Tuple(Vec<ScalarKind>),
+    /// A struct stored as a `KIND_TUPLE` global of its field values, in declaration order
+    Struct {
+        name: String,
+        fields: Vec<(String, ScalarKind)>,
+    },
+}
+
+/// Kind of the keys or values of a persisted dict, or the items of a persisted tuple.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScalarKind {
+    Int,
+    Float,
+    Bool,
     Str,
 }
 
 pub struct InteractiveState {
     vm: KaytonVm,
-    /// Map of user-visible variable names to simple kinds for prelude/epilogue decisions
+    /// Map of user-visible variable names to simple kinds for prelude and report decisions
     pub globals: HashMap<String, VarKind>,
-    /// Persisted function definitions across inputs
+    /// Persisted function and struct definitions across inputs
     pub stored_functions: Vec<String>,
     /// Monotonic counter for labelling inputs in diagnostics
     pub input_counter: usize,
@@ -54,6 +90,222 @@ impl InteractiveState {
 pub struct PreparedCode {
     pub full_source: String,
     pub rust: RustCode,
+    /// Kinds of the globals the input assigns, stored in `InteractiveState::globals` once
+    /// they have been reported; `None` for values that do not persist
+    pub globals: Vec<(String, Option<VarKind>)>,
+    /// Notes for the user once the input has run, such as globals that do not persist
+    pub warnings: Vec<String>,
+}
+
+/// Whether an input line starts a definition block (`fn f():`, `struct P:` or
+/// `enum Shape:`) that is stored for later inputs instead of being run. Comment
+/// lines before the definition, such as its `##` doc comment, are skipped.
+pub fn starts_definition(line: &str) -> bool {
+    let line = line.trim();
+    let code = line
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !l.starts_with('#'))
+        .unwrap_or_default();
+    ["fn ", "struct ", "enum "]
+        .iter()
+        .any(|keyword| code.starts_with(keyword))
+        && line.ends_with(':')
+}
+
+/// Help for a stored function or struct, or a `Struct.method`: the header line of
+/// the definition, then its `##` doc comment. The latest definition of a name wins.
+pub fn help_text(state: &InteractiveState, name: &str) -> Option<String> {
+    state
+        .stored_functions
+        .iter()
+        .rev()
+        .find_map(|def| help_in_source(def, name))
+}
+
+/// Jupyter `inspect_request` helper: help for the name under the cursor, looked up
+/// in the cell itself and then in the stored definitions. `cursor_pos` counts
+/// characters, as in the messaging protocol.
+pub fn inspect_text(state: &InteractiveState, code: &str, cursor_pos: usize) -> Option<String> {
+    let chars: Vec<char> = code.chars().collect();
+    let is_name_char = |c: &char| c.is_alphanumeric() || *c == '_';
+    let cursor = cursor_pos.min(chars.len());
+    let mut start = cursor;
+    while start > 0 && (is_name_char(&chars[start - 1]) || chars[start - 1] == '.') {
+        start -= 1;
+    }
+    let mut end = cursor;
+    while end < chars.len() && is_name_char(&chars[end]) {
+        end += 1;
+    }
+    let dotted: String = chars[start..end].iter().collect();
+    let dotted = dotted.trim_matches('.');
+    // `p.shift` on a value rather than a struct falls back to the name alone
+    let last = dotted.rsplit('.').next().unwrap_or_default();
+    [dotted, last]
+        .into_iter()
+        .filter(|name| !name.is_empty())
+        .find_map(|name| help_in_source(code, name).or_else(|| help_text(state, name)))
+}
+
+fn help_in_source(source: &str, name: &str) -> Option<String> {
+    let stmts = parse_source(source).ok()?;
+    let (owner, method) = match name.split_once('.') {
+        Some((owner, method)) => (owner, Some(method)),
+        None => (name, None),
+    };
+    let header = |start: usize| {
+        let line = source[start..].lines().next().unwrap_or_default();
+        line.trim().trim_end_matches(':').to_string()
+    };
+    let render = |start: usize, doc: &Option<String>| match doc {
+        Some(doc) => format!("{}\n\n{}", header(start), doc),
+        None => header(start),
+    };
+    stmts.iter().find_map(|stmt| match (&stmt.node, method) {
+        (Stmt::FuncDef { name, doc, .. }, None) if name == owner => {
+            Some(render(stmt.span.start, doc))
+        }
+        (Stmt::StructDef { name, doc, .. }, None) if name == owner => {
+            Some(render(stmt.span.start, doc))
+        }
+        (Stmt::StructDef { name, methods, .. }, Some(method)) if name == owner => {
+            methods.iter().find_map(|m| match &m.node {
+                Stmt::FuncDef { name, doc, .. } if name == method => {
+                    Some(render(m.span.start, doc))
+                }
+                _ => None,
+            })
+        }
+        _ => None,
+    })
+}
+
+/// Lists persist when their elements are scalars; nested lists stay cell-local, as do
+/// ranges, enums, functions and the unit result of a call that returns nothing. Structs
+/// persist when they have from one to [`MAX_REPORTED_ITEMS`] scalar fields.
+/// `prepare_input` warns about a global that does not persist.
+fn var_kind_of(ty: &Type, structs: &HashMap<String, StructInfo>) -> Option<VarKind> {
+    match ty {
+        Type::Str => Some(VarKind::Str),
+        Type::F64 => Some(VarKind::Float),
+        Type::Bool => Some(VarKind::Bool),
+        Type::List(elem) => match elem.as_ref() {
+            Type::I64 | Type::Any => Some(VarKind::IntList),
+            Type::F64 => Some(VarKind::FloatList),
+            Type::Bool => Some(VarKind::BoolList),
+            Type::Str => Some(VarKind::StrList),
+            _ => None,
+        },
+        Type::Option(inner) => Some(VarKind::Option(scalar_kind(inner)?)),
+        Type::Dict(key, value) => Some(VarKind::Dict(scalar_kind(key)?, scalar_kind(value)?)),
+        Type::Tuple(items) if tuple_reportable(items) => Some(VarKind::Tuple(
+            items.iter().map(scalar_kind).collect::<Option<_>>()?,
+        )),
+        Type::Tuple(_)
+        | Type::Range
+        | Type::Iter(_)
+        | Type::Enum(_)
+        | Type::Func(..)
+        | Type::Unit => None,
+        Type::Struct(name) => {
+            let info = structs.get(name)?;
+            if !(1..=MAX_REPORTED_ITEMS).contains(&info.fields.len()) {
+                return None;
+            }
+            let fields = info
+                .fields
+                .iter()
+                .map(|(field, ty)| Some((field.clone(), scalar_kind(ty)?)))
+                .collect::<Option<_>>()?;
+            Some(VarKind::Struct {
+                name: name.clone(),
+                fields,
+            })
+        }
+        _ => Some(VarKind::Int),
+    }
+}
+
+/// Dicts persist only with scalar keys and values; unknown items become ints, as in lists.
+fn scalar_kind(ty: &Type) -> Option<ScalarKind> {
+    match ty {
+        Type::I64 | Type::Any => Some(ScalarKind::Int),
+        Type::F64 => Some(ScalarKind::Float),
+        Type::Bool => Some(ScalarKind::Bool),
+        Type::Str => Some(ScalarKind::Str),
+        _ => None,
+    }
+}
+
+fn scalar_type(kind: ScalarKind) -> Type {
+    match kind {
+        ScalarKind::Int => Type::I64,
+        ScalarKind::Float => Type::F64,
+        ScalarKind::Bool => Type::Bool,
+        ScalarKind::Str => Type::Str,
+    }
+}
+
+/// Rust literal for a dict key or value read back from the VM.
+fn scalar_literal(api: &Api, ctx: &mut VmKaytonContext, kind: ScalarKind, h: VmHKayRef) -> String {
+    match kind {
+        ScalarKind::Int => (api.get_global_i64_by_handle)(ctx, h)
+            .unwrap_or(0)
+            .to_string(),
+        ScalarKind::Float => {
+            let val = (api.get_global_f64_by_handle)(ctx, h).unwrap_or(0.0);
+            format!("f64::from_bits({:#x})", val.to_bits())
+        }
+        ScalarKind::Bool => (api.get_global_bool_by_handle)(ctx, h)
+            .unwrap_or(false)
+            .to_string(),
+        ScalarKind::Str => {
+            let s = (api.get_global_str_buf_by_handle)(ctx, h)
+                .ok()
+                .and_then(|buf| buf.as_str().map(escape_rust_string_literal))
+                .unwrap_or_default();
+            format!("\"{}\".to_string()", s)
+        }
+    }
+}
+
+fn scalar_rust_type(kind: ScalarKind) -> &'static str {
+    match kind {
+        ScalarKind::Int => "i64",
+        ScalarKind::Float => "f64",
+        ScalarKind::Bool => "bool",
+        ScalarKind::Str => "String",
+    }
+}
+
+/// Rust literal used when a tuple item can no longer be read back from the VM.
+fn scalar_default(kind: ScalarKind) -> String {
+    match kind {
+        ScalarKind::Int => "0".to_string(),
+        ScalarKind::Float => "0.0".to_string(),
+        ScalarKind::Bool => "false".to_string(),
+        ScalarKind::Str => "String::new()".to_string(),
+    }
+}
+
+fn kind_type(kind: &VarKind) -> Type {
+    match kind {
+        VarKind::Str => Type::Str,
+        VarKind::Float => Type::F64,
+        VarKind::Bool => Type::Bool,
+        VarKind::Int => Type::I64,
+        VarKind::IntList => Type::List(Box::new(Type::I64)),
+        VarKind::FloatList => Type::List(Box::new(Type::F64)),
+        VarKind::BoolList => Type::List(Box::new(Type::Bool)),
+        VarKind::StrList => Type::List(Box::new(Type::Str)),
+        VarKind::Option(inner) => Type::Option(Box::new(scalar_type(*inner))),
+        VarKind::Dict(key, value) => {
+            Type::Dict(Box::new(scalar_type(*key)), Box::new(scalar_type(*value)))
+        }
+        VarKind::Tuple(items) => Type::Tuple(items.iter().copied().map(scalar_type).collect()),
+        VarKind::Struct { name, .. } => Type::Struct(name.clone()),
+    }
 }
 
 fn escape_rust_string_literal(s: &str) -> String {
@@ -71,13 +323,16 @@ fn escape_rust_string_literal(s: &str) -> String {
     out
 }
 
-fn build_prelude_and_epilogue(
+/// The prelude loading the globals an input uses, and for each top-level statement the
+/// lines reporting the globals it changes.
+fn build_prelude_and_reports(
     vm: &KaytonVm,
     ctx: &mut VmKaytonContext,
     resolved: &keyton_rust_compiler::shir::resolver::ResolvedProgram,
     program: &RustProgram,
     globals: &HashMap<String, VarKind>,
-) -> (HashSet<SymbolId>, Vec<String>, Vec<String>) {
+    var_types: &HashMap<SymbolId, Type>,
+) -> (HashSet<SymbolId>, Vec<String>, Vec<Vec<(SymbolId, String)>>) {
     use keyton_rust_compiler::rhir::types::{RExpr, RStmt};
     fn collect_expr_syms(e: &RExpr, out: &mut HashSet<SymbolId>) {
         match e {
@@ -88,11 +343,26 @@ fn build_prelude_and_epilogue(
                 collect_expr_syms(left, out);
                 collect_expr_syms(right, out);
             }
-            RExpr::MacroCall { args, .. } => {
+            RExpr::Unary { expr, .. } | RExpr::Some { expr, .. } | RExpr::Unwrap { expr, .. } => {
+                collect_expr_syms(expr, out)
+            }
+            RExpr::Call { args, .. } | RExpr::MacroCall { args, .. } => {
                 for a in args {
                     collect_expr_syms(a, out);
                 }
             }
+            RExpr::Index { target, index, .. } => {
+                collect_expr_syms(target, out);
+                collect_expr_syms(index, out);
+            }
+            RExpr::Slice {
+                target, start, end, ..
+            } => {
+                collect_expr_syms(target, out);
+                for bound in [start, end].into_iter().flatten() {
+                    collect_expr_syms(bound, out);
+                }
+            }
             RExpr::InterpolatedString { parts, .. } => {
                 for p in parts {
                     if let keyton_rust_compiler::rhir::types::RStringPart::Expr { expr, .. } = p {
@@ -100,12 +370,43 @@ fn build_prelude_and_epilogue(
                     }
                 }
             }
+            RExpr::Dict { entries, .. } => {
+                for (k, v) in entries {
+                    collect_expr_syms(k, out);
+                    collect_expr_syms(v, out);
+                }
+            }
+            RExpr::Tuple { items, .. } => {
+                for item in items {
+                    collect_expr_syms(item, out);
+                }
+            }
+            RExpr::Range { start, end, .. } => {
+                collect_expr_syms(start, out);
+                collect_expr_syms(end, out);
+            }
+            RExpr::Field { target, .. } => collect_expr_syms(target, out),
+            RExpr::MethodCall { target, args, .. } => {
+                collect_expr_syms(target, out);
+                for a in args {
+                    collect_expr_syms(a, out);
+                }
+            }
+            RExpr::Construct { fields, .. } => {
+                for (_, value) in fields {
+                    collect_expr_syms(value, out);
+                }
+            }
+            RExpr::Variant { args, .. } => {
+                for a in args {
+                    collect_expr_syms(a, out);
+                }
+            }
+            RExpr::Lambda { body, .. } => collect_expr_syms(body, out),
             _ => {}
         }
     }
 
-    let mut used_syms: HashSet<SymbolId> = HashSet::new();
-    let mut assigned_syms: HashSet<SymbolId> = HashSet::new();
     fn walk_stmt(
         stmt: &RStmt,
         used_syms: &mut HashSet<SymbolId>,
@@ -117,7 +418,50 @@ fn build_prelude_and_epilogue(
                 assigned_syms.insert(*sym);
                 collect_expr_syms(expr, used_syms);
             }
-            RStmt::ExprStmt { expr, .. } => collect_expr_syms(expr, used_syms),
+            RStmt::ExprStmt { expr, .. } => {
+                collect_expr_syms(expr, used_syms);
+                // `append(xs, v)` changes the list in place, so it is stored back as well
+                if let RExpr::Call { args, .. } = expr {
+                    for arg in args {
+                        if let RExpr::Name {
+                            sym,
+                            ty: Type::List(_),
+                            ..
+                        } = arg
+                        {
+                            assigned_syms.insert(*sym);
+                        }
+                    }
+                }
+            }
+            // Writing an element loads the list and stores it back afterwards
+            RStmt::IndexAssign {
+                target,
+                index,
+                expr,
+                ..
+            } => {
+                collect_expr_syms(target, used_syms);
+                collect_expr_syms(target, assigned_syms);
+                collect_expr_syms(index, used_syms);
+                collect_expr_syms(expr, used_syms);
+            }
+            RStmt::TupleAssign { syms, expr, .. } => {
+                assigned_syms.extend(syms.iter().copied());
+                collect_expr_syms(expr, used_syms);
+            }
+            // Like an element, a field is written by loading the struct and storing it back
+            RStmt::FieldAssign { target, expr, .. } => {
+                collect_expr_syms(target, used_syms);
+                collect_expr_syms(target, assigned_syms);
+                collect_expr_syms(expr, used_syms);
+            }
+            RStmt::ForEach { iter, body, .. } => {
+                collect_expr_syms(iter, used_syms);
+                for s in body {
+                    walk_stmt(s, used_syms, assigned_syms);
+                }
+            }
             RStmt::ForRange {
                 start, end, body, ..
             } => {
@@ -127,6 +471,19 @@ fn build_prelude_and_epilogue(
                     walk_stmt(s, used_syms, assigned_syms);
                 }
             }
+            RStmt::While { cond, body, .. } => {
+                collect_expr_syms(cond, used_syms);
+                for s in body {
+                    walk_stmt(s, used_syms, assigned_syms);
+                }
+            }
+            // Function parameters and locals live in the function's own scope, not the VM
+            RStmt::Break { .. }
+            | RStmt::Continue { .. }
+            | RStmt::FuncDef { .. }
+            | RStmt::StructDef { .. }
+            | RStmt::EnumDef { .. }
+            | RStmt::Return { .. } => {}
             RStmt::If {
                 cond,
                 then_branch,
@@ -141,17 +498,70 @@ fn build_prelude_and_epilogue(
                     walk_stmt(s, used_syms, assigned_syms);
                 }
             }
+            RStmt::Match { subject, arms, .. } => {
+                collect_expr_syms(subject, used_syms);
+                for arm in arms {
+                    for s in &arm.body {
+                        walk_stmt(s, used_syms, assigned_syms);
+                    }
+                }
+            }
+            RStmt::Try {
+                body,
+                handlers,
+                finally,
+                ..
+            } => {
+                let handler_bodies = handlers.iter().flat_map(|h| &h.body);
+                for s in body.iter().chain(handler_bodies).chain(finally) {
+                    walk_stmt(s, used_syms, assigned_syms);
+                }
+            }
+            RStmt::Raise { message, .. } => {
+                if let Some(message) = message {
+                    collect_expr_syms(message, used_syms);
+                }
+            }
         }
     }
 
-    for stmt in &program.rhir {
-        walk_stmt(stmt, &mut used_syms, &mut assigned_syms);
-    }
+    // Methods may change the fields of their receiver, so used structs are stored back too
+    let changed_syms = |stmts: &[RStmt]| {
+        let mut used = HashSet::new();
+        let mut assigned = HashSet::new();
+        for stmt in stmts {
+            walk_stmt(stmt, &mut used, &mut assigned);
+        }
+        let used_structs: Vec<SymbolId> = used
+            .iter()
+            .filter(|sym| matches!(var_types.get(sym), Some(Type::Struct(_))))
+            .copied()
+            .collect();
+        assigned.extend(used_structs);
+        (used, assigned)
+    };
+    // Lambdas share the variables they capture, and may change the lists and structs
+    // among them in place whenever they are called
+    let changed_by_lambdas = |stmts: &[RStmt]| {
+        let mut captured = captured_by_lambdas(stmts);
+        captured.retain(|sym| {
+            !matches!(
+                var_types.get(sym),
+                Some(Type::I64 | Type::F64 | Type::Bool | Type::Str)
+            )
+        });
+        captured
+    };
+    let (mut used_syms, mut assigned_syms) = changed_syms(&program.rhir);
+    assigned_syms.extend(changed_by_lambdas(&program.rhir));
 
+    // Loop variables and other locals are never loaded from or stored to the VM
     let sym_infos = &resolved.symbols.infos;
+    used_syms.retain(|sym| sym_infos[sym.0 as usize].kind == SymKind::GlobalVar);
+    assigned_syms.retain(|sym| sym_infos[sym.0 as usize].kind == SymKind::GlobalVar);
     let mut prelude_lines: Vec<String> = Vec::new();
     let mut pre_assigned: HashSet<SymbolId> = HashSet::new();
-    let mut epilogue_lines: Vec<String> = Vec::new();
+    let mut report_lines: HashMap<SymbolId, String> = HashMap::new();
 
     let api: &Api = vm.api();
     for sym in used_syms.iter() {
@@ -165,18 +575,166 @@ fn build_prelude_and_epilogue(
                     }
                     pre_assigned.insert(*sym);
                 }
+                VarKind::Float => {
+                    // Round-trip the exact bits so no precision is lost between inputs
+                    let val = (api.get_global_f64)(ctx, name).unwrap_or(0.0);
+                    prelude_lines.push(format!(
+                        "let mut {} = f64::from_bits({:#x});",
+                        name,
+                        val.to_bits()
+                    ));
+                    pre_assigned.insert(*sym);
+                }
+                VarKind::Bool => {
+                    let val = (api.get_global_bool)(ctx, name).unwrap_or(false);
+                    prelude_lines.push(format!("let mut {} = {};", name, val));
+                    pre_assigned.insert(*sym);
+                }
                 VarKind::Str => {
                     match (api.get_global_str_buf)(ctx, name) {
                         Ok(buf) => {
                             if let Some(s) = buf.as_str() {
                                 let lit = escape_rust_string_literal(s);
-                                prelude_lines.push(format!("let mut {} = \"{}\";", name, lit));
+                                prelude_lines
+                                    .push(format!("let mut {} = \"{}\".to_string();", name, lit));
                             } else {
-                                prelude_lines.push(format!("let mut {} = \"\";", name));
+                                prelude_lines.push(format!("let mut {} = String::new();", name));
+                            }
+                        }
+                        Err(_) => prelude_lines.push(format!("let mut {} = String::new();", name)),
+                    }
+                    pre_assigned.insert(*sym);
+                }
+                VarKind::IntList | VarKind::FloatList | VarKind::BoolList => {
+                    let kvec = (api.get_global_kvec)(ctx, name).ok();
+                    let (elem_ty, items): (&str, Vec<String>) = match kind {
+                        VarKind::IntList => (
+                            "i64",
+                            kvec.and_then(|v| v.as_vec_i64())
+                                .unwrap_or_default()
+                                .iter()
+                                .map(|x| x.to_string())
+                                .collect(),
+                        ),
+                        VarKind::FloatList => (
+                            "f64",
+                            kvec.and_then(|v| v.as_vec_f64())
+                                .unwrap_or_default()
+                                .iter()
+                                .map(|x| format!("f64::from_bits({:#x})", x.to_bits()))
+                                .collect(),
+                        ),
+                        _ => (
+                            "bool",
+                            kvec.and_then(|v| v.as_vec_bool())
+                                .unwrap_or_default()
+                                .iter()
+                                .map(|x| x.to_string())
+                                .collect(),
+                        ),
+                    };
+                    prelude_lines.push(format!(
+                        "let mut {}: Vec<{}> = vec![{}];",
+                        name,
+                        elem_ty,
+                        items.join(", ")
+                    ));
+                    pre_assigned.insert(*sym);
+                }
+                VarKind::StrList | VarKind::Option(_) => {
+                    let item_kind = match kind {
+                        VarKind::Option(inner) => *inner,
+                        _ => ScalarKind::Str,
+                    };
+                    let mut items: Vec<String> = Vec::new();
+                    if let Some(h) = vm.resolve_name(name) {
+                        let len = (api.get_tuple_len_by_handle)(ctx, h).unwrap_or(0);
+                        for i in 0..len {
+                            if let Ok(ih) = (api.get_global_tuple_item_by_handle)(ctx, h, i) {
+                                items.push(scalar_literal(api, ctx, item_kind, ih));
+                            }
+                        }
+                    }
+                    let (ty, value) = match kind {
+                        VarKind::Option(_) => (
+                            format!("Option<{}>", scalar_rust_type(item_kind)),
+                            match items.pop() {
+                                Some(item) => format!("Some({})", item),
+                                None => "None".to_string(),
+                            },
+                        ),
+                        _ => (
+                            "Vec<String>".to_string(),
+                            format!("vec![{}]", items.join(", ")),
+                        ),
+                    };
+                    prelude_lines.push(format!("let mut {}: {} = {};", name, ty, value));
+                    pre_assigned.insert(*sym);
+                }
+                VarKind::Dict(key_kind, value_kind) => {
+                    let mut entries: Vec<String> = Vec::new();
+                    if let Some(h) = vm.resolve_name(name) {
+                        let len = (api.get_map_len_by_handle)(ctx, h).unwrap_or(0);
+                        for i in 0..len {
+                            if let Ok((kh, vh)) = (api.get_map_entry_by_handle)(ctx, h, i) {
+                                let key = scalar_literal(api, ctx, *key_kind, kh);
+                                let value = scalar_literal(api, ctx, *value_kind, vh);
+                                entries.push(format!("({}, {})", key, value));
                             }
                         }
-                        Err(_) => prelude_lines.push(format!("let mut {} = \"\";", name)),
                     }
+                    prelude_lines.push(format!(
+                        "let mut {}: HashMap<{}, {}> = HashMap::from([{}]);",
+                        name,
+                        scalar_rust_type(*key_kind),
+                        scalar_rust_type(*value_kind),
+                        entries.join(", ")
+                    ));
+                    pre_assigned.insert(*sym);
+                }
+                VarKind::Tuple(item_kinds) => {
+                    let mut items: Vec<String> = Vec::new();
+                    if let Some(h) = vm.resolve_name(name) {
+                        for (i, item_kind) in item_kinds.iter().enumerate() {
+                            let item = (api.get_global_tuple_item_by_handle)(ctx, h, i)
+                                .map(|ih| scalar_literal(api, ctx, *item_kind, ih));
+                            items.push(item.unwrap_or_else(|_| scalar_default(*item_kind)));
+                        }
+                    }
+                    let types: Vec<&str> =
+                        item_kinds.iter().copied().map(scalar_rust_type).collect();
+                    // A one-item tuple needs its trailing comma in both the type and the value
+                    let comma = if item_kinds.len() == 1 { "," } else { "" };
+                    prelude_lines.push(format!(
+                        "let mut {}: ({}{}) = ({}{});",
+                        name,
+                        types.join(", "),
+                        comma,
+                        items.join(", "),
+                        comma
+                    ));
+                    pre_assigned.insert(*sym);
+                }
+                VarKind::Struct {
+                    name: struct_name,
+                    fields,
+                } => {
+                    let mut values: Vec<String> = Vec::new();
+                    if let Some(h) = vm.resolve_name(name) {
+                        for (i, (field, field_kind)) in fields.iter().enumerate() {
+                            let value = (api.get_global_tuple_item_by_handle)(ctx, h, i)
+                                .map(|ih| scalar_literal(api, ctx, *field_kind, ih));
+                            let value = value.unwrap_or_else(|_| scalar_default(*field_kind));
+                            values.push(format!("{}: {}", field, value));
+                        }
+                    }
+                    prelude_lines.push(format!(
+                        "let mut {}: {} = {} {{ {} }};",
+                        name,
+                        struct_name,
+                        struct_name,
+                        values.join(", ")
+                    ));
                     pre_assigned.insert(*sym);
                 }
             }
@@ -185,21 +743,102 @@ fn build_prelude_and_epilogue(
 
     for sym in assigned_syms.iter() {
         let name = &sym_infos[sym.0 as usize].name;
-        let kind = globals.get(name).copied().unwrap_or(VarKind::Int);
+        // Prefer the type inferred in this input: a new or re-typed variable is not in `globals` yet
+        let kind = match var_types.get(sym) {
+            Some(ty) => var_kind_of(ty, &resolved.symbols.structs),
+            None => Some(globals.get(name).cloned().unwrap_or(VarKind::Int)),
+        };
+        let Some(kind) = kind else {
+            continue;
+        };
         match kind {
             VarKind::Int => {
-                epilogue_lines.push(format!(
-                    "unsafe {{ report_int(\"{}\", {} as i64); }}",
-                    name, name
-                ));
+                report_lines.insert(
+                    *sym,
+                    format!("unsafe {{ report_int(\"{}\", {} as i64); }}", name, name),
+                );
+            }
+            VarKind::Float => {
+                report_lines.insert(
+                    *sym,
+                    format!("unsafe {{ report_f64(\"{}\", {}); }}", name, name),
+                );
+            }
+            VarKind::Bool => {
+                report_lines.insert(
+                    *sym,
+                    format!("unsafe {{ report_bool(\"{}\", {}); }}", name, name),
+                );
             }
             VarKind::Str => {
-                epilogue_lines.push(format!("unsafe {{ report_str(\"{}\", {}); }}", name, name));
+                report_lines.insert(
+                    *sym,
+                    format!("unsafe {{ report_str(\"{}\", &{}); }}", name, name),
+                );
+            }
+            VarKind::IntList | VarKind::FloatList | VarKind::BoolList => {
+                let reporter = match kind {
+                    VarKind::IntList => "report_vec_i64",
+                    VarKind::FloatList => "report_vec_f64",
+                    _ => "report_vec_bool",
+                };
+                report_lines.insert(
+                    *sym,
+                    format!("unsafe {{ {}(\"{}\", &{}); }}", reporter, name, name),
+                );
+            }
+            VarKind::Dict(..) => {
+                report_lines.insert(
+                    *sym,
+                    format!("unsafe {{ report_map(\"{}\", &{}); }}", name, name),
+                );
+            }
+            VarKind::Tuple(_) | VarKind::StrList | VarKind::Option(_) => {
+                report_lines.insert(
+                    *sym,
+                    format!("unsafe {{ report_tuple(\"{}\", &{}); }}", name, name),
+                );
+            }
+            VarKind::Struct { fields, .. } => {
+                let values: Vec<String> = fields
+                    .iter()
+                    .map(|(field, _)| format!("{}.{}.clone()", name, field))
+                    .collect();
+                let comma = if fields.len() == 1 { "," } else { "" };
+                report_lines.insert(
+                    *sym,
+                    format!(
+                        "unsafe {{ report_tuple(\"{}\", &({}{})); }}",
+                        name,
+                        values.join(", "),
+                        comma
+                    ),
+                );
             }
         }
     }
 
-    (pre_assigned, prelude_lines, epilogue_lines)
+    // Each statement reports the globals it changed, so a later runtime error keeps them;
+    // what a lambda may change is reported after every statement from its creation on
+    let mut by_lambdas: HashSet<SymbolId> = HashSet::new();
+    let reports = program
+        .rhir
+        .iter()
+        .map(|stmt| {
+            let (_, mut changed) = changed_syms(std::slice::from_ref(stmt));
+            by_lambdas.extend(changed_by_lambdas(std::slice::from_ref(stmt)));
+            changed.extend(by_lambdas.iter().copied());
+            changed.retain(|sym| sym_infos[sym.0 as usize].kind == SymKind::GlobalVar);
+            let mut changed: Vec<SymbolId> = changed.into_iter().collect();
+            changed.sort_by_key(|sym| sym.0);
+            changed
+                .iter()
+                .filter_map(|sym| report_lines.get(sym).map(|line| (*sym, line.clone())))
+                .collect()
+        })
+        .collect();
+
+    (pre_assigned, prelude_lines, reports)
 }
 
 fn generate_injected_rust(
@@ -207,14 +846,14 @@ fn generate_injected_rust(
     rhir_program: &RustProgram,
     pre_assigned: &HashSet<SymbolId>,
     prelude_lines: &[String],
-    epilogue_lines: &[String],
+    reports: &[Vec<(SymbolId, String)>],
 ) -> RustCode {
     let mut codegen = CodeGenerator::new(resolved);
     codegen.generate_code_with_preassigned_and_prelude(
         rhir_program,
         pre_assigned,
         prelude_lines,
-        epilogue_lines,
+        reports,
     )
 }
 
@@ -233,54 +872,67 @@ pub fn prepare_input(
             full_source.push('\n');
         }
     }
+    let input_start = full_source.len();
     full_source.push_str(first_line_no_crlf);
 
-    let tokens = Lexer::new(&full_source).tokenize();
-    let ast = Parser::new(tokens).parse_program();
-    let hir = lower_program(ast);
-    let mut resolved = resolve_program(&hir);
+    // Diagnostics number lines from the start of the input, not of the stored definitions
+    let source = Source::with_input_start(&full_source, input_start);
+    let file_label = format!("<kayton-input-{}>", state.input_counter);
+    let ast = parse_source(&full_source)
+        .map_err(|errors| anyhow::anyhow!(format_parse_errors(&source, &errors, &file_label)))?;
+    let (hir, spans) = lower_program_with_spans(ast);
+    let mut resolved = resolve_program_with_spans(&hir, spans);
+    // Unresolved names may be REPL globals; the type checker reports the real ones below
+    if let Some(err) = resolved
+        .errors
+        .iter()
+        .find(|e| !matches!(e, ResolveError::UnresolvedName { .. }))
+    {
+        let msg = format_resolve_error(&source, err, &file_label);
+        return Err(anyhow::anyhow!(msg));
+    }
 
-    let mut predeclared: Vec<(String, keyton_rust_compiler::shir::sym::Type)> = Vec::new();
+    let mut predeclared: Vec<(String, Type)> = Vec::new();
     for (name, kind) in state.globals.iter() {
-        let ty = match kind {
-            VarKind::Str => keyton_rust_compiler::shir::sym::Type::Str,
-            VarKind::Int => keyton_rust_compiler::shir::sym::Type::I64,
-        };
-        predeclared.push((name.clone(), ty));
+        predeclared.push((name.clone(), kind_type(kind)));
     }
 
     let typed = keyton_rust_compiler::thir::typecheck_program_with_env(&mut resolved, &predeclared);
-    if !typed.report.errors.is_empty() {
-        for err in &typed.report.errors {
-            let file_label = format!("<kayton-input-{}>", state.input_counter);
-            if let Some(msg) = format_type_error(&full_source, &resolved, err, &file_label) {
-                return Err(anyhow::anyhow!(msg));
-            }
-        }
-        return Err(anyhow::anyhow!(format!(
-            "Type errors: {:?}",
-            typed.report.errors
-        )));
+    if let Some(err) = typed.report.errors.first() {
+        let msg = format_type_error(&source, &resolved, err, &file_label);
+        return Err(anyhow::anyhow!(msg));
     }
 
     let rhir_program = convert_to_rhir(&typed, &resolved);
 
     let mut ctx = state.vm_mut().context();
-    let (pre_assigned, prelude_lines, epilogue_lines) = build_prelude_and_epilogue(
+    let (pre_assigned, prelude_lines, reports) = build_prelude_and_reports(
         state.vm(),
         &mut ctx,
         &resolved,
         &rhir_program,
         &state.globals,
+        &typed.var_types,
     );
 
-    for (sid, ty) in typed.var_types.iter() {
-        let name = &resolved.symbols.infos[sid.0 as usize].name;
-        let kind = match ty {
-            keyton_rust_compiler::shir::sym::Type::Str => VarKind::Str,
-            _ => VarKind::Int,
-        };
-        state.globals.insert(name.clone(), kind);
+    // Re-typing a global gives it a new symbol; in symbol order the newest comes last
+    let mut typed_syms: Vec<_> = typed.var_types.iter().collect();
+    typed_syms.sort_by_key(|(sid, _)| sid.0);
+    let mut globals = Vec::new();
+    let mut warnings = Vec::new();
+    for (sid, ty) in typed_syms {
+        let info = &resolved.symbols.infos[sid.0 as usize];
+        if info.kind != SymKind::GlobalVar {
+            continue;
+        }
+        let kind = var_kind_of(ty, &resolved.symbols.structs);
+        if kind.is_none() {
+            warnings.push(format!(
+                "Warning: '{}' of type {} is not kept after this input",
+                info.name, ty
+            ));
+        }
+        globals.push((info.name.clone(), kind));
     }
 
     let rust_code = generate_injected_rust(
@@ -288,51 +940,95 @@ pub fn prepare_input(
         &rhir_program,
         &pre_assigned,
         &prelude_lines,
-        &epilogue_lines,
+        &reports,
     );
 
     Ok(PreparedCode {
         full_source,
         rust: rust_code,
+        globals,
+        warnings,
     })
 }
 
+/// Point the reporters and VM hooks of a loaded dylib at this state's VM.
+///
+/// # Safety
+///
+/// `lib` must be a dylib built by `compile_generated_rust_to_dylib`, whose setters have
+/// the signatures declared here.
+unsafe fn install_hooks(state: &mut InteractiveState, lib: &Library) {
+    unsafe {
+        // Set reporter hooks from VM context
+        type SetReportersFn = unsafe extern "C" fn(ReportIntFn, ReportStrFn);
+        if let Ok(setters) = lib.get::<SetReportersFn>(b"kayton_set_reporters") {
+            let mut ctx = state.vm_mut().context();
+            set_report_host_from_ctx(&mut ctx);
+            setters(
+                host_report_int as ReportIntFn,
+                host_report_str as ReportStrFn,
+            );
+        }
+        type SetF64ReporterFn = unsafe extern "C" fn(ReportF64Fn);
+        if let Ok(set_f64) = lib.get::<SetF64ReporterFn>(b"kayton_set_f64_reporter") {
+            set_f64(host_report_f64 as ReportF64Fn);
+        }
+        type SetBoolReporterFn = unsafe extern "C" fn(ReportBoolFn);
+        if let Ok(set_bool) = lib.get::<SetBoolReporterFn>(b"kayton_set_bool_reporter") {
+            set_bool(host_report_bool as ReportBoolFn);
+        }
+        type SetErrorReporterFn = unsafe extern "C" fn(ReportErrorFn);
+        if let Ok(set_error) = lib.get::<SetErrorReporterFn>(b"kayton_set_error_reporter") {
+            set_error(host_report_error as ReportErrorFn);
+        }
+        type SetVecReportersFn =
+            unsafe extern "C" fn(ReportVecI64Fn, ReportVecF64Fn, ReportVecBoolFn);
+        if let Ok(set_vec) = lib.get::<SetVecReportersFn>(b"kayton_set_vec_reporters") {
+            set_vec(
+                host_report_vec_i64 as ReportVecI64Fn,
+                host_report_vec_f64 as ReportVecF64Fn,
+                host_report_vec_bool as ReportVecBoolFn,
+            );
+        }
+        type SetMapReporterFn = unsafe extern "C" fn(ReportMapFn);
+        if let Ok(set_map) = lib.get::<SetMapReporterFn>(b"kayton_set_map_reporter") {
+            set_map(host_report_map as ReportMapFn);
+        }
+        type SetTupleReporterFn = unsafe extern "C" fn(ReportTupleFn);
+        if let Ok(set_tuple) = lib.get::<SetTupleReporterFn>(b"kayton_set_tuple_reporter") {
+            set_tuple(host_report_tuple as ReportTupleFn);
+        }
+
+        // Set VM hooks for plugin loading and function pointer lookups
+        type LoadPluginFn = extern "C" fn(module_ptr: *const u8, module_len: usize) -> i32;
+        type GetFunctionPtrFn =
+            extern "C" fn(name_ptr: *const u8, name_len: usize) -> *const c_void;
+        type SetVmHooksFn = unsafe extern "C" fn(LoadPluginFn, GetFunctionPtrFn);
+        if let Ok(set_vm_hooks) = lib.get::<SetVmHooksFn>(b"kayton_set_vm_hooks") {
+            set_current_vm_ptr(state.vm_mut());
+            set_vm_hooks(load_plugin_host, get_function_ptr_host);
+        }
+    }
+}
+
 /// Execute previously prepared code: compiles to a dylib and runs it, updating VM via reporter hooks.
 pub fn execute_prepared(state: &mut InteractiveState, prepared: &PreparedCode) -> Result<()> {
     match compile_generated_rust_to_dylib(&prepared.rust.source_code) {
         Ok(path) => unsafe {
             let lib = Library::new(&path).with_context(|| format!("load dylib: {:?}", path))?;
 
-            // Set reporter hooks from VM context
-            type SetReportersFn = unsafe extern "C" fn(ReportIntFn, ReportStrFn);
-            if let Ok(setters) = lib.get::<SetReportersFn>(b"kayton_set_reporters") {
-                let mut ctx = state.vm_mut().context();
-                set_report_host_from_ctx(&mut ctx);
-                setters(
-                    host_report_int as ReportIntFn,
-                    host_report_str as ReportStrFn,
-                );
-            }
-
-            // Set VM hooks for plugin loading and function pointer lookups
-            type LoadPluginFn = extern "C" fn(module_ptr: *const u8, module_len: usize) -> i32;
-            type GetFunctionPtrFn =
-                extern "C" fn(name_ptr: *const u8, name_len: usize) -> *const c_void;
-            type SetVmHooksFn = unsafe extern "C" fn(LoadPluginFn, GetFunctionPtrFn);
-            if let Ok(set_vm_hooks) = lib.get::<SetVmHooksFn>(b"kayton_set_vm_hooks") {
-                set_current_vm_ptr(state.vm_mut());
-                set_vm_hooks(load_plugin_host, get_function_ptr_host);
-            }
+            install_hooks(state, &lib);
 
             let func: libloading::Symbol<unsafe extern "C" fn()> =
                 lib.get(b"run").context("find run symbol")?;
+            take_reported_names();
             func();
         },
         Err(err) => {
             return Err(anyhow::anyhow!(format!("Compile error: {}", err)));
         }
     }
-    Ok(())
+    finish_run(state, prepared)
 }
 
 /// Execute prepared code and stream stdout in real time via provided callback.
@@ -367,26 +1063,7 @@ where
         Ok(path) => unsafe {
             let lib = Library::new(&path).with_context(|| format!("load dylib: {:?}", path))?;
 
-            // Set reporter hooks from VM context
-            type SetReportersFn = unsafe extern "C" fn(ReportIntFn, ReportStrFn);
-            if let Ok(setters) = lib.get::<SetReportersFn>(b"kayton_set_reporters") {
-                let mut ctx = state.vm_mut().context();
-                set_report_host_from_ctx(&mut ctx);
-                setters(
-                    host_report_int as ReportIntFn,
-                    host_report_str as ReportStrFn,
-                );
-            }
-
-            // Set VM hooks for plugin loading and function pointer lookups
-            type LoadPluginFn = extern "C" fn(module_ptr: *const u8, module_len: usize) -> i32;
-            type GetFunctionPtrFn =
-                extern "C" fn(name_ptr: *const u8, name_len: usize) -> *const c_void;
-            type SetVmHooksFn = unsafe extern "C" fn(LoadPluginFn, GetFunctionPtrFn);
-            if let Ok(set_vm_hooks) = lib.get::<SetVmHooksFn>(b"kayton_set_vm_hooks") {
-                set_current_vm_ptr(state.vm_mut());
-                set_vm_hooks(load_plugin_host, get_function_ptr_host);
-            }
+            install_hooks(state, &lib);
 
             // Install stdout streaming callback
             STDOUT_SINK.with(|slot| {
@@ -396,6 +1073,7 @@ where
 
             let func: libloading::Symbol<unsafe extern "C" fn()> =
                 lib.get(b"run").context("find run symbol")?;
+            take_reported_names();
             func();
 
             // Clear callback after execution
@@ -408,7 +1086,32 @@ where
             return Err(anyhow::anyhow!(format!("Compile error: {}", err)));
         }
     }
-    Ok(())
+    finish_run(state, prepared)
+}
+
+/// Store the kinds of the globals the run reported, and turn a runtime error raised by
+/// the executed code (e.g. an `IndexError`) into an `Err`.
+fn finish_run(state: &mut InteractiveState, prepared: &PreparedCode) -> Result<()> {
+    let error = take_runtime_error();
+    let reported: HashSet<String> = take_reported_names().into_iter().collect();
+    for (name, kind) in &prepared.globals {
+        // Globals the run did not get to before an error keep their previous values
+        if error.is_some() && !reported.contains(name) {
+            continue;
+        }
+        match kind {
+            Some(kind) => {
+                state.globals.insert(name.clone(), kind.clone());
+            }
+            None => {
+                state.globals.remove(name);
+            }
+        }
+    }
+    match error {
+        Some((kind, message)) => Err(anyhow::anyhow!("{}: {}", kind, message)),
+        None => Ok(()),
+    }
 }
 
 /// Kernel helper: set or clear the VM stdout streaming callback.
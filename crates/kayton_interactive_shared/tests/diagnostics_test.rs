@@ -0,0 +1,193 @@
+use kayton_interactive_shared::{InteractiveState, prepare_input};
+use keyton_rust_compiler::span::LineIndex;
+
+#[test]
+fn name_error_points_at_line_of_use() {
+    let mut state = InteractiveState::new();
+
+    let code = r#"a = 1
+b = 2
+c = a + missing
+"#;
+    let err = match prepare_input(&mut state, code) {
+        Ok(_) => panic!("expected a compile error"),
+        Err(e) => e.to_string(),
+    };
+
+    assert!(err.contains("line 3"), "unexpected message: {}", err);
+    assert!(
+        err.contains("name 'missing' is not defined"),
+        "unexpected message: {}",
+        err
+    );
+    // The caret line underlines exactly the offending name
+    let caret_line = err
+        .lines()
+        .find(|l| l.contains('^'))
+        .expect("caret line present");
+    assert_eq!(caret_line.matches('^').count(), "missing".len());
+}
+
+#[test]
+fn syntax_errors_are_all_reported() {
+    let mut state = InteractiveState::new();
+
+    let code = "a = (1]\nb = 2\nc = )\n";
+    let err = match prepare_input(&mut state, code) {
+        Ok(_) => panic!("expected a syntax error"),
+        Err(e) => e.to_string(),
+    };
+
+    assert_eq!(err.matches("SyntaxError").count(), 2, "{}", err);
+    assert!(err.contains("line 1"), "unexpected message: {}", err);
+    assert!(err.contains("line 3"), "unexpected message: {}", err);
+    assert!(err.contains("expected ')', found ']'"), "{}", err);
+}
+
+#[test]
+fn break_outside_loop_is_a_syntax_error() {
+    let mut state = InteractiveState::new();
+
+    let err = match prepare_input(&mut state, "x = 1\nbreak\n") {
+        Ok(_) => panic!("expected a compile error"),
+        Err(e) => e.to_string(),
+    };
+
+    assert!(err.contains("line 2"), "unexpected message: {}", err);
+    assert!(err.contains("'break' outside loop"), "{}", err);
+}
+
+#[test]
+fn non_bool_condition_is_a_type_error() {
+    let mut state = InteractiveState::new();
+
+    let err = match prepare_input(&mut state, "n = 3\nwhile n:\n    n = n - 1\n") {
+        Ok(_) => panic!("expected a type error"),
+        Err(e) => e.to_string(),
+    };
+
+    assert!(err.contains("line 2"), "unexpected message: {}", err);
+    assert!(err.contains("expected bool, found i64"), "{}", err);
+    let caret_line = err
+        .lines()
+        .find(|l| l.contains('^'))
+        .expect("caret line present");
+    assert_eq!(caret_line.matches('^').count(), 1);
+}
+
+#[test]
+fn lines_count_from_the_input_after_stored_definitions() {
+    let mut state = InteractiveState::new();
+    state
+        .stored_functions
+        .push("fn add(a, b):\n    return a + b\n".to_string());
+    state
+        .stored_functions
+        .push("fn twice(a):\n    return add(a, a)\n".to_string());
+
+    for (code, line) in [
+        ("x = 1\ny = add(x, missing)\n", "y = add(x, missing)"),
+        ("x = 1\ny = twice(x]\n", "y = twice(x]"),
+        ("x = 1\nwhile x:\n    x = 0\n", "while x:"),
+    ] {
+        let err = match prepare_input(&mut state, code) {
+            Ok(_) => panic!("expected a compile error"),
+            Err(e) => e.to_string(),
+        };
+        assert!(
+            err.contains("File \"<kayton-input-0>\", line 2,"),
+            "unexpected message: {}",
+            err
+        );
+        let plain = err.replace("\x1b[31m", "").replace("\x1b[0m", "");
+        assert!(plain.contains(line), "unexpected message: {}", err);
+    }
+}
+
+#[test]
+fn unknown_annotation_type_is_reported() {
+    let mut state = InteractiveState::new();
+
+    let err = match prepare_input(&mut state, "x = 1\nlet y: Widget = x\n") {
+        Ok(_) => panic!("expected a type error"),
+        Err(e) => e.to_string(),
+    };
+
+    assert!(err.contains("line 2"), "unexpected message: {}", err);
+    assert!(err.contains("unknown type 'Widget'"), "{}", err);
+}
+
+#[test]
+fn struct_calls_without_field_names_are_type_errors() {
+    let mut state = InteractiveState::new();
+    let def = "struct P:\n    x: i64\n    y: i64\n";
+
+    let err = match prepare_input(&mut state, &format!("{}p = P(1)\n", def)) {
+        Ok(_) => panic!("expected a type error"),
+        Err(e) => e.to_string(),
+    };
+    assert!(
+        err.contains("P() takes keyword arguments only; name its fields: P(x=..., y=...)"),
+        "{}",
+        err
+    );
+
+    let err = match prepare_input(&mut state, &format!("{}p = P()\n", def)) {
+        Ok(_) => panic!("expected a type error"),
+        Err(e) => e.to_string(),
+    };
+    assert!(
+        err.contains("P() missing required arguments: 'x', 'y'"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn malformed_format_spec_points_into_the_fstring() {
+    let mut state = InteractiveState::new();
+
+    let err = match prepare_input(&mut state, "n = 1\ns = f\"total: {n:>8,}\"\n") {
+        Ok(_) => panic!("expected a syntax error"),
+        Err(e) => e.to_string(),
+    };
+
+    assert!(err.contains("line 2"), "unexpected message: {}", err);
+    assert!(
+        err.contains("f-string: thousands separators are not supported"),
+        "{}",
+        err
+    );
+    // The caret line underlines the spec after the `:`
+    let caret_line = err
+        .lines()
+        .find(|l| l.contains('^'))
+        .expect("caret line present");
+    assert_eq!(caret_line.matches('^').count(), ">8,".len());
+}
+
+#[test]
+fn carets_line_up_under_cyrillic_and_cjk_text() {
+    let mut state = InteractiveState::new();
+
+    let code = "数据 = [1]\nимя = 数据 + нет\n";
+    let err = match prepare_input(&mut state, code) {
+        Ok(_) => panic!("expected a compile error"),
+        Err(e) => e.to_string(),
+    };
+
+    assert!(err.contains("line 2"), "unexpected message: {}", err);
+    assert!(err.contains("name 'нет' is not defined"), "{}", err);
+    let caret_line = err
+        .lines()
+        .find(|l| l.contains('^'))
+        .expect("caret line present");
+    assert_eq!(caret_line.matches('^').count(), 3);
+    // Four spaces of indent, then `имя = 数据 + `, where each CJK character is two columns wide
+    let padding = caret_line.len() - caret_line.trim_start().len();
+    assert_eq!(padding, 4 + 13);
+
+    // Columns count characters, not bytes
+    let index = LineIndex::new(code);
+    assert_eq!(index.line_col(code.find("нет").unwrap()), (2, 12));
+}
@@ -25,3 +25,36 @@ for x in 0..n:
     Ok(())
 }
 
+#[test]
+fn for_in_iterates_over_persisted_list_and_string() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let prepared = prepare_input(&mut state, "xs = [4, 5, 6]\nword = \"abc\"\n")?;
+    execute_prepared(&mut state, &prepared)?;
+    let code = r#"total = 0
+for x in xs:
+    total += x
+letters = 0
+for c in word:
+    if c != "b":
+        letters += 1
+"#;
+    let prepared = prepare_input(&mut state, code)?;
+    execute_prepared(&mut state, &prepared)?;
+
+    let mut text_of = |name: &str| {
+        let h = state.vm().resolve_name(name).expect("global is defined");
+        state.vm_mut().format_value_by_handle(h).unwrap_or_default()
+    };
+    assert_eq!(text_of("total"), "15");
+    assert_eq!(text_of("letters"), "2");
+
+    // An int has no items
+    let err = match prepare_input(&mut state, "for i in total:\n    print(i)\n") {
+        Ok(_) => panic!("iterating an int should not typecheck"),
+        Err(err) => err.to_string(),
+    };
+    assert!(err.contains("'i64' object is not iterable"), "{err}");
+
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use anyhow::Result;
+use kayton_interactive_shared::{
+    InteractiveState, execute_prepared, help_text, inspect_text, prepare_input, starts_definition,
+};
+
+#[test]
+fn stored_definitions_show_their_doc_comments() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let def = "## Greets someone by name.\n## Used by the examples.\nfn greet(name: str) -> str:\n    return \"hi \" + name  # joined with +\n";
+    // A cell holding a documented definition header is still stored as a definition
+    assert!(starts_definition(
+        "## Greets someone.\nfn greet(name: str) -> str:"
+    ));
+    state.stored_functions.push(def.to_string());
+    state.stored_functions.push(
+        "## A point on a line.\nstruct P:\n    x: i64\n    ## Moved by d.\n    fn shift(self, d: i64) -> i64:\n        return self.x + d\n"
+            .to_string(),
+    );
+
+    assert_eq!(
+        help_text(&state, "greet").as_deref(),
+        Some("fn greet(name: str) -> str\n\nGreets someone by name.\nUsed by the examples.")
+    );
+    assert_eq!(
+        help_text(&state, "P").as_deref(),
+        Some("struct P\n\nA point on a line.")
+    );
+    assert_eq!(
+        help_text(&state, "P.shift").as_deref(),
+        Some("fn shift(self, d: i64) -> i64\n\nMoved by d.")
+    );
+    assert_eq!(help_text(&state, "missing"), None);
+
+    // Comments do not change what the definitions do
+    let prepared = prepare_input(&mut state, "# a comment line\ngreet(\"bo\")  # call it")?;
+    execute_prepared(&mut state, &prepared)?;
+    let h = state.vm().resolve_name("__last").expect("last value");
+    assert_eq!(
+        state.vm_mut().format_value_by_handle(h).unwrap_or_default(),
+        "hi bo"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn inspect_finds_the_name_under_the_cursor() {
+    let state = InteractiveState::new();
+
+    let code = "## Doubles n.\nfn twice(n: i64) -> i64:\n    return n * 2\ny = twice(3)\n";
+    let cursor = code.find("twice(3)").unwrap() + 2;
+    assert_eq!(
+        inspect_text(&state, code, cursor).as_deref(),
+        Some("fn twice(n: i64) -> i64\n\nDoubles n.")
+    );
+    assert_eq!(inspect_text(&state, code, code.find("y =").unwrap()), None);
+}
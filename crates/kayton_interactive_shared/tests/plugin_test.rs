@@ -0,0 +1,104 @@
+use std::sync::OnceLock;
+
+use anyhow::Result;
+use kayton_api::types::RawFnPtr;
+use kayton_api::{KaytonError, KaytonResult};
+use kayton_interactive_shared::{InteractiveState, execute_prepared, prepare_input};
+
+fn parse_port(n: i64) -> KaytonResult<i64> {
+    if (1..=65535).contains(&n) {
+        KaytonResult::ok(n)
+    } else {
+        KaytonResult::err(KaytonError::generic(format!("port {} is out of range", n)))
+    }
+}
+
+fn find_user(id: i64) -> KaytonResult<i64> {
+    KaytonResult::err(KaytonError::not_found(format!("no user {}", id)))
+}
+
+fn count_to(n: i64) -> Box<dyn Iterator<Item = i64>> {
+    Box::new(0..n)
+}
+
+const MANIFEST: &str = r#"{"abi_version":1,"crate_name":"ports","crate_version":"0.1.0","functions":[
+{"stable_name":"parse_port","symbol":"parse_port","sig":{"params":["i64"],"ret":{"result":"i64"}}},
+{"stable_name":"find_user","symbol":"find_user","sig":{"params":["i64"],"ret":{"result":"i64"}}},
+{"stable_name":"count_to","symbol":"count_to","sig":{"params":["i64"],"ret":{"iter":"i64"}}}
+],"types":[]}"#;
+
+/// A REPL state with the plugin's functions registered, as its `kayton_plugin_register`
+/// would. The tests share one project-local environment holding the plugin's manifest,
+/// since the working directory belongs to the whole process.
+fn plugin_state() -> Result<InteractiveState> {
+    static PROJECT: OnceLock<()> = OnceLock::new();
+    PROJECT.get_or_init(|| {
+        let project =
+            std::env::temp_dir().join(format!("kayton_plugin_test_{}", std::process::id()));
+        let lib_dir = project.join(".kayton/libs/ports/0.1.0/host");
+        std::fs::create_dir_all(&lib_dir).expect("create the plugin environment");
+        std::fs::write(lib_dir.join("manifest.json"), MANIFEST).expect("write the manifest");
+        std::env::set_current_dir(&project).expect("enter the project");
+    });
+
+    let mut state = InteractiveState::new();
+    let mut ctx = state.vm_mut().context();
+    let register = ctx.api().register_function;
+    register(
+        &mut ctx,
+        "parse_port",
+        parse_port as fn(i64) -> KaytonResult<i64> as RawFnPtr,
+        0,
+    )?;
+    register(
+        &mut ctx,
+        "find_user",
+        find_user as fn(i64) -> KaytonResult<i64> as RawFnPtr,
+        0,
+    )?;
+    register(
+        &mut ctx,
+        "count_to",
+        count_to as fn(i64) -> Box<dyn Iterator<Item = i64>> as RawFnPtr,
+        0,
+    )?;
+    Ok(state)
+}
+
+#[test]
+fn plugin_errors_are_raised_as_exceptions() -> Result<()> {
+    let mut state = plugin_state()?;
+
+    let input = "from ports rimport parse_port, find_user\np = parse_port(8080)\ntry:\n    parse_port(70000)\nexcept RuntimeError as e:\n    print(e)\np";
+    let prepared = prepare_input(&mut state, input)?;
+    execute_prepared(&mut state, &prepared)?;
+    let mut text_of = |name: &str| {
+        let h = state.vm().resolve_name(name).expect("global is defined");
+        state.vm_mut().format_value_by_handle(h).unwrap_or_default()
+    };
+    assert_eq!(text_of("__stdout"), "port 70000 is out of range\n");
+    assert_eq!(text_of("__last"), "8080");
+
+    let prepared = prepare_input(&mut state, "from ports rimport find_user\nfind_user(3)")?;
+    let err = execute_prepared(&mut state, &prepared).expect_err("user is not found");
+    assert_eq!(err.to_string(), "NotFoundError: no user 3");
+
+    Ok(())
+}
+
+#[test]
+fn for_loops_walk_plugin_iterators() -> Result<()> {
+    let mut state = plugin_state()?;
+
+    let input = "from ports rimport count_to\ntotal = 0\nfor n in count_to(4):\n    total += n\nit = count_to(3)\nfor n in it:\n    total += n * 10\nfor n in it:\n    total += 1000\ntotal";
+    let prepared = prepare_input(&mut state, input)?;
+    execute_prepared(&mut state, &prepared)?;
+    let h = state
+        .vm()
+        .resolve_name("__last")
+        .expect("global is defined");
+    // The second loop over `it` finds it used up
+    assert_eq!(state.vm_mut().format_value_by_handle(h)?, "36");
+
+    Ok(())
+}
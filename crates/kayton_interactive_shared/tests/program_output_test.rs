@@ -1,5 +1,13 @@
 use anyhow::Result;
-use kayton_interactive_shared::{InteractiveState, execute_prepared, prepare_input};
+use kayton_interactive_shared::{
+    InteractiveState, execute_prepared, prepare_input, starts_definition,
+};
+
+/// The VM's rendering of the global `name`, which the test expects to be defined.
+fn text_of(state: &mut InteractiveState, name: &str) -> String {
+    let h = state.vm().resolve_name(name).expect("global is defined");
+    state.vm_mut().format_value_by_handle(h).unwrap_or_default()
+}
 
 #[test]
 fn program_values_match_expected() -> Result<()> {
@@ -66,3 +74,610 @@ print(a)"#;
 
     Ok(())
 }
+
+#[test]
+fn float_globals_persist_across_inputs() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let prepared = prepare_input(&mut state, "ratio = 0.1 + 0.2\n")?;
+    execute_prepared(&mut state, &prepared)?;
+    let prepared = prepare_input(&mut state, "scaled = ratio * 10\nscaled")?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(text_of(&mut state, "ratio"), "0.30000000000000004");
+    assert_eq!(text_of(&mut state, "scaled"), "3.0000000000000004");
+    assert_eq!(text_of(&mut state, "__last"), "3.0000000000000004");
+
+    Ok(())
+}
+
+#[test]
+fn bool_globals_persist_across_inputs() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let prepared = prepare_input(&mut state, "big = 10 > 3\n")?;
+    execute_prepared(&mut state, &prepared)?;
+    let prepared = prepare_input(&mut state, "n = 0\nif big:\n    n = 1\nnot big")?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(text_of(&mut state, "big"), "true");
+    assert_eq!(text_of(&mut state, "n"), "1");
+    assert_eq!(text_of(&mut state, "__last"), "false");
+
+    Ok(())
+}
+
+#[test]
+fn stored_function_with_loop_runs_in_later_input() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    state.stored_functions.push(
+        "fn count_down(n):\n    steps = 0\n    while n > 0:\n        n = n - 1\n        steps += 1\n    steps\n"
+            .to_string(),
+    );
+    state
+        .stored_functions
+        .push("fn greet(name: str) -> str:\n    f\"hi {name}\"\n".to_string());
+    let prepared = prepare_input(&mut state, "start = 4\n")?;
+    execute_prepared(&mut state, &prepared)?;
+    let prepared = prepare_input(
+        &mut state,
+        "steps = count_down(start)\nmsg = greet(\"bob\")\nmsg",
+    )?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(text_of(&mut state, "steps"), "4");
+    assert_eq!(text_of(&mut state, "msg"), "hi bob");
+    assert_eq!(text_of(&mut state, "__last"), "hi bob");
+    // The function's parameter and locals stay out of the VM
+    assert!(state.vm().resolve_name("n").is_none());
+
+    Ok(())
+}
+
+#[test]
+fn loop_locals_are_not_persisted() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let prepared = prepare_input(
+        &mut state,
+        "for i in 0..3:\n    if i > 0:\n        last = i\n",
+    )?;
+    execute_prepared(&mut state, &prepared)?;
+    let prepared = prepare_input(&mut state, "last * 10")?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(text_of(&mut state, "__last"), "20");
+    assert!(state.vm().resolve_name("i").is_none());
+    assert!(!state.globals.contains_key("i"));
+
+    Ok(())
+}
+
+#[test]
+fn out_of_range_index_is_an_index_error() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let prepared = prepare_input(&mut state, "word = \"kayton\"\nword[-2..]")?;
+    execute_prepared(&mut state, &prepared)?;
+    assert_eq!(text_of(&mut state, "__last"), "on");
+
+    let prepared = prepare_input(&mut state, "word[10]")?;
+    let err = execute_prepared(&mut state, &prepared).expect_err("index out of range");
+    assert_eq!(err.to_string(), "IndexError: string index out of range");
+
+    // The session keeps working after the error
+    let prepared = prepare_input(&mut state, "word[0]")?;
+    execute_prepared(&mut state, &prepared)?;
+
+    Ok(())
+}
+
+#[test]
+fn list_globals_persist_across_inputs() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let prepared = prepare_input(&mut state, "xs = [1, 2]\nws = [0.5, 1.5]\n")?;
+    execute_prepared(&mut state, &prepared)?;
+    let prepared = prepare_input(&mut state, "append(xs, 3)\n")?;
+    execute_prepared(&mut state, &prepared)?;
+    let prepared = prepare_input(&mut state, "xs[0] = 10\nsum(xs)")?;
+    execute_prepared(&mut state, &prepared)?;
+    assert_eq!(text_of(&mut state, "__last"), "15");
+    let prepared = prepare_input(&mut state, "total = sum(ws)\n")?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(text_of(&mut state, "xs"), "[10, 2, 3]");
+    assert_eq!(text_of(&mut state, "ws"), "[0.5, 1.5]");
+    assert_eq!(text_of(&mut state, "total"), "2.0");
+
+    // The persisted element type is enforced in later inputs
+    let err = match prepare_input(&mut state, "xs[1] = \"two\"\n") {
+        Ok(_) => panic!("str into list[i64] should not typecheck"),
+        Err(err) => err.to_string(),
+    };
+    assert!(err.contains("expected i64, found str"), "{err}");
+
+    Ok(())
+}
+
+#[test]
+fn str_list_and_optional_globals_persist_across_inputs() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let input =
+        "names = [\"ann\", \"bob\"]\nempty: list[str] = []\nn: i64? = None\nw: str? = \"hi\"";
+    let prepared = prepare_input(&mut state, input)?;
+    assert!(prepared.warnings.is_empty(), "{:?}", prepared.warnings);
+    execute_prepared(&mut state, &prepared)?;
+    let prepared = prepare_input(
+        &mut state,
+        "append(names, \"cy\")\nappend(empty, \"x\")\nn = 5",
+    )?;
+    execute_prepared(&mut state, &prepared)?;
+    let input = "print(names[2])\nprint(len(empty))\nprint(n)\nprint(w)\nlen(names)";
+    let prepared = prepare_input(&mut state, input)?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(text_of(&mut state, "__stdout"), "cy\n1\n5\nhi\n");
+    assert_eq!(text_of(&mut state, "__last"), "3");
+
+    Ok(())
+}
+
+#[test]
+fn optionals_cleared_to_none_end_their_while_loop() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let input = "fn maybe(n: i64) -> i64?:\n    return n\ny = maybe(3)\ntotal = 0\nwhile y is not None:\n    total += y\n    y = None\nprint(y)\ntotal";
+    let prepared = prepare_input(&mut state, input)?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(text_of(&mut state, "__stdout"), "None\n");
+    assert_eq!(text_of(&mut state, "__last"), "3");
+
+    Ok(())
+}
+
+#[test]
+fn globals_that_are_not_kept_are_reported() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let prepared = prepare_input(&mut state, "grid = [[1, 2], [3]]\nk = 1")?;
+    assert_eq!(
+        prepared.warnings,
+        ["Warning: 'grid' of type list[list[i64]] is not kept after this input"]
+    );
+    execute_prepared(&mut state, &prepared)?;
+    assert!(state.globals.contains_key("k"));
+    assert!(!state.globals.contains_key("grid"));
+
+    Ok(())
+}
+
+#[test]
+fn results_of_calls_that_return_nothing_are_not_kept() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let prepared = prepare_input(
+        &mut state,
+        "fn noret(x):\n    print(x)\nv = noret(4)\nprint(\"after\")",
+    )?;
+    assert_eq!(
+        prepared.warnings,
+        ["Warning: 'v' of type unit is not kept after this input"]
+    );
+    execute_prepared(&mut state, &prepared)?;
+    assert_eq!(text_of(&mut state, "__stdout"), "4\nafter\n");
+    assert!(!state.globals.contains_key("v"));
+
+    Ok(())
+}
+
+#[test]
+fn dict_globals_persist_across_inputs() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let prepared = prepare_input(&mut state, "ages = {\"ann\": 31}\nflags = {1: True}\n")?;
+    execute_prepared(&mut state, &prepared)?;
+    let prepared = prepare_input(&mut state, "ages[\"bob\"] = ages[\"ann\"] + 1\n")?;
+    execute_prepared(&mut state, &prepared)?;
+    let prepared = prepare_input(&mut state, "\"bob\" in ages and flags[1]")?;
+    execute_prepared(&mut state, &prepared)?;
+    assert_eq!(text_of(&mut state, "__last"), "true");
+    let prepared = prepare_input(&mut state, "total = sum(values(ages))\n")?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(text_of(&mut state, "flags"), "{1: true}");
+    assert_eq!(text_of(&mut state, "total"), "63");
+
+    // The persisted key type is enforced in later inputs
+    let err = match prepare_input(&mut state, "ages[1] = 2\n") {
+        Ok(_) => panic!("int key into dict[str, i64] should not typecheck"),
+        Err(err) => err.to_string(),
+    };
+    assert!(err.contains("expected str, found i64"), "{err}");
+
+    Ok(())
+}
+
+#[test]
+fn tuple_globals_persist_across_inputs() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let prepared = prepare_input(&mut state, "point = (3, \"north\", 1.5)\n")?;
+    execute_prepared(&mut state, &prepared)?;
+    let prepared = prepare_input(&mut state, "n, dir, speed = point\nn = n + 1\n")?;
+    execute_prepared(&mut state, &prepared)?;
+    let prepared = prepare_input(&mut state, "(dir, n)")?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(text_of(&mut state, "point"), "(3, \"north\", 1.5)");
+    assert_eq!(text_of(&mut state, "n"), "4");
+    assert_eq!(text_of(&mut state, "dir"), "north");
+    assert_eq!(text_of(&mut state, "__last"), "(\"north\", 4)");
+
+    // The persisted item types are enforced in later inputs
+    let err = match prepare_input(&mut state, "a, b = point\n") {
+        Ok(_) => panic!("unpacking three items into two names should not typecheck"),
+        Err(err) => err.to_string(),
+    };
+    assert!(
+        err.contains("too many values to unpack (expected 2)"),
+        "{err}"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn unpacking_a_tuple_leaves_it_usable() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let input = "p = (1, \"a\", 2.5)\nx, y, z = p\nprint(p)\nx, y, z = p\ny";
+    let prepared = prepare_input(&mut state, input)?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(
+        text_of(&mut state, "__stdout").trim_end_matches('\n'),
+        "(1, \"a\", 2.5)"
+    );
+    assert_eq!(text_of(&mut state, "__last"), "a");
+
+    Ok(())
+}
+
+#[test]
+fn struct_globals_persist_across_inputs() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let def = "struct Counter:\n    n: i64\n    label: str\n    fn bump(self, by):\n        self.n += by\n";
+    assert!(starts_definition(def.lines().next().unwrap_or_default()));
+    state.stored_functions.push(def.to_string());
+    let prepared = prepare_input(&mut state, "c = Counter(n=1, label=\"hits\")\n")?;
+    execute_prepared(&mut state, &prepared)?;
+    // A method changes the fields of the stored struct
+    let prepared = prepare_input(&mut state, "c.bump(2)\nc.label = \"total\"\n")?;
+    execute_prepared(&mut state, &prepared)?;
+    let prepared = prepare_input(&mut state, "total = c.n * 10\nc")?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(text_of(&mut state, "total"), "30");
+    assert_eq!(
+        text_of(&mut state, "__last"),
+        "Counter(n=3, label=\"total\")"
+    );
+
+    let err = match prepare_input(&mut state, "c.size\n") {
+        Ok(_) => panic!("reading a missing field should not typecheck"),
+        Err(err) => err.to_string(),
+    };
+    assert!(
+        err.contains("'Counter' object has no attribute 'size'"),
+        "{err}"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn structs_with_many_fields_persist_across_inputs() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    state.stored_functions.push(
+        "struct Row:\n    a: i64\n    b: i64\n    c: i64\n    d: i64\n    e: i64\n    f: i64\n    g: f64\n    h: str\n"
+            .to_string(),
+    );
+    let prepared = prepare_input(
+        &mut state,
+        "r = Row(a=1, b=2, c=3, d=4, e=5, f=6, g=7.5, h=\"eight\")\n",
+    )?;
+    assert!(prepared.warnings.is_empty(), "{:?}", prepared.warnings);
+    execute_prepared(&mut state, &prepared)?;
+    let prepared = prepare_input(&mut state, "total = r.a + r.f\nr.h")?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(text_of(&mut state, "total"), "7");
+    assert_eq!(text_of(&mut state, "__last"), "eight");
+
+    Ok(())
+}
+
+#[test]
+fn enum_definitions_and_match_across_inputs() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let def = "enum Shape:\n    Circle(f64)\n    Empty\n";
+    assert!(starts_definition(def.lines().next().unwrap_or_default()));
+    state.stored_functions.push(def.to_string());
+    let input = "s = Shape.Circle(2.0)\nmatch s:\n    case Shape.Circle(r):\n        area = r * r\n    case Shape.Empty:\n        area = 0.0\ns";
+    let prepared = prepare_input(&mut state, input)?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(text_of(&mut state, "area"), "4.0");
+    assert_eq!(text_of(&mut state, "__last"), "Shape.Circle(2.0)");
+
+    let err = match prepare_input(
+        &mut state,
+        "match Shape.Empty:\n    case Shape.Empty:\n        n = 0\n",
+    ) {
+        Ok(_) => panic!("a match without a Circle arm should not typecheck"),
+        Err(err) => err.to_string(),
+    };
+    assert!(
+        err.contains("match on 'Shape' does not handle Shape.Circle(_)"),
+        "{err}"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn optional_values_print_as_value_or_none() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let def = "struct Item:\n    name: str\n    price: f64?\n";
+    state.stored_functions.push(def.to_string());
+    let input = "a = Item(name=\"pen\", price=None)\nb = Item(name=\"ink\", price=2.5)\nprint(a)\nprint(b.price)\nn: str? = None\nprint(f\"n={n}\")\nn = \"x\"\nif n is not None:\n    print(f\"{n}!\")\nn";
+    let prepared = prepare_input(&mut state, input)?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(
+        text_of(&mut state, "__stdout").trim_end_matches('\n'),
+        "Item(name=\"pen\", price=None)\n2.5\nn=None\nx!"
+    );
+    assert_eq!(text_of(&mut state, "__last"), "x");
+
+    Ok(())
+}
+
+#[test]
+fn caught_errors_continue_the_program() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let input = "xs = [1, 2]\ntry:\n    xs[5] = 3\nexcept IndexError as e:\n    status = e\nfinally:\n    print(\"done\")\nn = 0\ntry:\n    n = 10 // n\nexcept ZeroDivisionError as e:\n    print(e)\n    n = 7\nn";
+    let prepared = prepare_input(&mut state, input)?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(
+        text_of(&mut state, "__stdout").trim_end_matches('\n'),
+        "done\ninteger division or modulo by zero"
+    );
+    assert_eq!(
+        text_of(&mut state, "status"),
+        "list assignment index out of range"
+    );
+    assert_eq!(text_of(&mut state, "__last"), "7");
+
+    Ok(())
+}
+
+#[test]
+fn dividing_by_zero_raises_at_run_time() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    for (input, message) in [
+        (
+            "1 // 0",
+            "ZeroDivisionError: integer division or modulo by zero",
+        ),
+        (
+            "print(7 % 0)",
+            "ZeroDivisionError: integer division or modulo by zero",
+        ),
+        ("x = 1 / 0", "ZeroDivisionError: division by zero"),
+        ("2.5 % 0.0", "ZeroDivisionError: float modulo"),
+    ] {
+        let prepared = prepare_input(&mut state, input)?;
+        let err = execute_prepared(&mut state, &prepared).expect_err(input);
+        assert_eq!(err.to_string(), message, "{}", input);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn functions_are_values_and_lambdas_capture_locals() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let input = "fn double(x: i64) -> i64:\n    return x * 2\nfn apply(f: fn(i64) -> i64, x: i64) -> i64:\n    return f(x)\nfn make_adder(n: i64) -> fn(i64) -> i64:\n    return fn(x) => x + n\nxs = [3, 1, 2]\noffset = 10\nys = map(xs, fn(x) => x + offset)\nevens = xs.filter(fn(x) => x % 2 == 0)\ndesc = sorted(xs, fn(x) => -x)\nprefix = \"hi \"\ngreet = fn(name: str) => f\"{prefix}{name}\"\nprint(greet(\"bo\"))\nprint(sorted(xs))\nadd5 = make_adder(5)\napply(double, 4) + add5(1) + apply(fn(x) => x * x, 3)";
+    let prepared = prepare_input(&mut state, input)?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(text_of(&mut state, "ys"), "[13, 11, 12]");
+    assert_eq!(text_of(&mut state, "evens"), "[2]");
+    assert_eq!(text_of(&mut state, "desc"), "[3, 2, 1]");
+    assert_eq!(
+        text_of(&mut state, "__stdout").trim_end_matches('\n'),
+        "hi bo\n[1, 2, 3]"
+    );
+    assert_eq!(text_of(&mut state, "__last"), "23");
+
+    Ok(())
+}
+
+#[test]
+fn lambda_parameters_are_inferred_from_their_use() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let input = "fn make_adder(n):\n    return fn(x) => x + n\ninc = fn(x) => x + 1\nhalf = fn(x) => x / 2.0\nadd5 = make_adder(5)\nprint(half(3.0))\ninc(2) + add5(1)";
+    let prepared = prepare_input(&mut state, input)?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(text_of(&mut state, "__stdout"), "1.5\n");
+    assert_eq!(text_of(&mut state, "__last"), "9");
+
+    Ok(())
+}
+
+#[test]
+fn lambdas_see_later_assignments_of_captured_variables() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let input = "fn outer():\n    total = 1\n    add = fn(x) => x + total\n    total = 5\n    return add(1)\nfn greet():\n    name = \"a\"\n    hi = fn(p: str) => p + name\n    for i in 0..2:\n        name = name + \"b\"\n    return hi(\"x\")\nprint(greet())\nouter()";
+    let prepared = prepare_input(&mut state, input)?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(text_of(&mut state, "__stdout"), "xabb\n");
+    assert_eq!(text_of(&mut state, "__last"), "6");
+
+    Ok(())
+}
+
+#[test]
+fn lambdas_capture_globals_by_reference() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let input = "k = 10\nf = fn(x: i64) => x + k\nk = 20\nprint(f(1))\nxs = [1]\ng = fn(x: i64) => len(xs) + x\nappend(xs, 2)\nxs[0] = 7\nprint(g(0))\nacc = [0]\nadd = fn(v: i64) => append(acc, v)\nadd(3)\nadd(4)";
+    let prepared = prepare_input(&mut state, input)?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(text_of(&mut state, "__stdout"), "21\n2\n");
+    assert_eq!(text_of(&mut state, "k"), "20");
+    assert_eq!(text_of(&mut state, "xs"), "[7, 2]");
+    // Appended by the lambda, and kept for the next input
+    assert_eq!(text_of(&mut state, "acc"), "[0, 3, 4]");
+
+    // A global from an earlier input is shared with the lambda as well
+    let prepared = prepare_input(&mut state, "h = fn(x: i64) => x * k\nk = 3\nh(2)")?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(text_of(&mut state, "__last"), "6");
+    assert_eq!(text_of(&mut state, "k"), "3");
+
+    Ok(())
+}
+
+#[test]
+fn strings_concatenate_escape_and_have_methods() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let input = "fn greet(name: str) -> str:\n    return \"hi \" + name\ns = \"a\"\ns = s + \"\\tb\"\nquote = \"say \\\"{x}\\\"\"\nnote = \"\"\"two\nlines\"\"\"\nwords = \" x, y ,z\".split(\",\")\nclean = \"-\".join(map(words, fn(w) => w.strip().upper()))\nprint(greet(quote))\nprint(note)\nprint(\" a  b \".split())\nlen(clean) + clean.find(\"Z\") * 10";
+    let prepared = prepare_input(&mut state, input)?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(text_of(&mut state, "s"), "a\tb");
+    assert_eq!(text_of(&mut state, "clean"), "X-Y-Z");
+    assert_eq!(
+        text_of(&mut state, "__stdout").trim_end_matches('\n'),
+        "hi say \"{x}\"\ntwo\nlines\n[\"a\", \"b\"]"
+    );
+    assert_eq!(text_of(&mut state, "__last"), "45");
+
+    Ok(())
+}
+
+#[test]
+fn fstrings_apply_format_specs_and_conversions() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let input = "n = 42\nratio = 2.0 / 3.0\nname = \"bo\"\nxs = [1, 2]\nprint(f\"[{n:>6}] [{n:<4}] [{n:*^7}] [{n:05}] [{n:+}] [{n:#x}] [{n:b}]\")\nprint(f\"{ratio:.2f} {ratio:7.3f} {n:.1f} {name!r} {name!r:>6} {xs:>7}\")\nprint(f\"{{{n}}} {\", \".join([\"a\", \"b\"])} { n + 1 }\")\nn";
+    let prepared = prepare_input(&mut state, input)?;
+    execute_prepared(&mut state, &prepared)?;
+
+    let stdout = text_of(&mut state, "__stdout");
+    assert_eq!(
+        stdout.trim_end_matches('\n'),
+        "[    42] [42  ] [**42***] [00042] [+42] [0x2a] [101010]\n0.67   0.667 42.0 'bo'   'bo'  [1, 2]\n{42} a, b 43"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn reprs_and_key_errors_quote_strings_like_python() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let input = "d = {\"a\": 1}\ntry:\n    x = d[\"zz\"]\nexcept KeyError as e:\n    print(e)\ntry:\n    raise KeyError(\"zz\")\nexcept KeyError as e:\n    print(e)\ns = \"it's\"\nprint(f\"{s!r} {\"a\\tb\"!r}\")";
+    let prepared = prepare_input(&mut state, input)?;
+    execute_prepared(&mut state, &prepared)?;
+
+    let stdout = text_of(&mut state, "__stdout");
+    assert_eq!(stdout, "'zz'\n'zz'\n\"it's\" 'a\\tb'\n");
+
+    Ok(())
+}
+
+#[test]
+fn brackets_span_lines_and_semicolons_separate_statements() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let input = "fn add(a,\n        b):\n    return a + b\nxs = [\n    1,\n    2,  # two\n]\nscores = {\n    \"a\": add(xs[0],\n              xs[1]),\n}\ntotal = 1 + \\\n    2\nif total > 2: total += 1; print(total)\nx = 10; y = 20\nx + y + scores[\"a\"]";
+    let prepared = prepare_input(&mut state, input)?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(text_of(&mut state, "xs"), "[1, 2]");
+    assert_eq!(text_of(&mut state, "__stdout").trim_end_matches('\n'), "4");
+    assert_eq!(text_of(&mut state, "__last"), "33");
+
+    Ok(())
+}
+
+#[test]
+fn comments_may_open_a_block() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let input = "fn double(n):\n    # twice n\n\n    return n * 2\ntotal = 0\nfor i in 0..3:\n    # accumulate\n    total += double(i)\nif total > 0:\n    ## a plain comment here\n    print(total)\ntotal";
+    let prepared = prepare_input(&mut state, input)?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(text_of(&mut state, "__stdout").trim_end_matches('\n'), "6");
+    assert_eq!(text_of(&mut state, "__last"), "6");
+
+    Ok(())
+}
+
+#[test]
+fn assigning_a_list_or_dict_copies_it() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let input = "xs = [1, 2]\nys = xs\nys[0] = 5\nd = {\"a\": 1}\ne = d\ne[\"a\"] = 2\nprint(xs)\nprint(d)\nys[0] + e[\"a\"]";
+    let prepared = prepare_input(&mut state, input)?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(
+        text_of(&mut state, "__stdout").trim_end_matches('\n'),
+        "[1, 2]\n{\"a\": 1}"
+    );
+    assert_eq!(text_of(&mut state, "ys"), "[5, 2]");
+    assert_eq!(text_of(&mut state, "__last"), "7");
+
+    Ok(())
+}
+
+#[test]
+fn assigning_a_struct_copies_it() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let input = "struct P:\n    x: i64\n    name: str\np = P(x=1, name=\"a\")\nq = p\nq.x = 3\nq.name = \"b\"\nprint(p)\np.x * 10 + q.x";
+    let prepared = prepare_input(&mut state, input)?;
+    execute_prepared(&mut state, &prepared)?;
+
+    assert_eq!(
+        text_of(&mut state, "__stdout").trim_end_matches('\n'),
+        "P(x=1, name=\"a\")"
+    );
+    assert_eq!(text_of(&mut state, "__last"), "13");
+
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use anyhow::Result;
 use kayton_interactive_shared::{
-    execute_prepared, prepare_input, set_stdout_callback_thunk, InteractiveState,
+    execute_prepared, inspect_text, prepare_input, set_stdout_callback_thunk, starts_definition,
+    InteractiveState,
 };
 use log::warn;
 use serde_json::{self, json, Value};
@@ -134,6 +135,41 @@ pub fn run_kernel(cfg: &ConnectionConfig) -> Result<()> {
                                 reply_content,
                             )?;
                         }
+                        "inspect_request" => {
+                            let code = pm
+                                .content
+                                .get("code")
+                                .and_then(|v| v.as_str())
+                                .unwrap_or("");
+                            let cursor_pos = pm
+                                .content
+                                .get("cursor_pos")
+                                .and_then(|v| v.as_u64())
+                                .unwrap_or(0) as usize;
+                            // Definitions show their header line and `##` doc comment
+                            let reply = match inspect_text(&state, code, cursor_pos) {
+                                Some(help) => serde_json::json!({
+                                    "status": "ok",
+                                    "found": true,
+                                    "data": { "text/plain": help },
+                                    "metadata": {},
+                                }),
+                                None => serde_json::json!({
+                                    "status": "ok",
+                                    "found": false,
+                                    "data": {},
+                                    "metadata": {},
+                                }),
+                            };
+                            send_reply(
+                                &shell,
+                                &pm.idents,
+                                &key_bytes,
+                                &pm.header,
+                                "inspect_reply",
+                                reply,
+                            )?;
+                        }
                         "execute_request" => {
                             let code = pm
                                 .content
@@ -155,10 +191,8 @@ pub fn run_kernel(cfg: &ConnectionConfig) -> Result<()> {
                                 code.trim_end_matches(&['\n', '\r'][..]).to_string();
                             let first_line_trimmed = first_line_no_crlf.trim();
 
-                            // Handle multiline function entry: if cell starts with `fn ` and ends with ':' we store definitions
-                            if first_line_trimmed.starts_with("fn ")
-                                && first_line_trimmed.ends_with(':')
-                            {
+                            // Handle multiline function or struct entry: such cells are stored as definitions
+                            if starts_definition(first_line_trimmed) {
                                 state.stored_functions.push(first_line_no_crlf);
                                 // Do not execute immediately; acknowledge success
                                 let reply = serde_json::json!({
@@ -248,6 +282,15 @@ pub fn run_kernel(cfg: &ConnectionConfig) -> Result<()> {
                                             )?;
                                         } else {
                                             // Stdout already streamed live by callback
+                                            for warning in &prep.warnings {
+                                                let _ = publish_stream(
+                                                    &iopub,
+                                                    &key_bytes,
+                                                    &pm.header,
+                                                    "stderr",
+                                                    &format!("{}\n", warning),
+                                                );
+                                            }
 
                                             // Publish only the value of the last expression (if any)
                                             if let Some(h) = state.vm().resolve_name("__last") {
@@ -6,15 +6,15 @@ macro_rules! kayton_manifest {
     (
         crate_name = $crate_name:expr,
         crate_version = $crate_version:expr,
-        functions = [ $( { stable: $stable:expr, symbol: $symbol:expr, params: [ $( $p:ident ),* ], ret: $r:ident } ),* $(,)? ],
+        functions = [ $( { stable: $stable:expr, symbol: $symbol:expr, params: [ $( $p:ident $( ( $pi:ident ) )? ),* ], ret: $r:ident $( ( $ri:ident ) )? } ),* $(,)? ],
         types = [ $( { name: $tname:expr, kind: $tkind:ident, size: $tsize:expr, align: $talign:expr } ),* $(,)? ]
     ) => {{
         use $crate::manifest::{Manifest, FunctionEntry, Signature, TypeEntry, TypeKind};
         let mut fns = alloc::vec::Vec::new();
         $(
             let mut params = alloc::vec::Vec::new();
-            $( params.push(TypeKind::$p); )*
-            fns.push(FunctionEntry{ stable_name: $stable.to_string(), symbol: $symbol.to_string(), sig: Signature{ params, ret: TypeKind::$r } });
+            $( params.push($crate::__kayton_type_kind!($p $( ( $pi ) )?)); )*
+            fns.push(FunctionEntry{ stable_name: $stable.to_string(), symbol: $symbol.to_string(), sig: Signature{ params, ret: $crate::__kayton_type_kind!($r $( ( $ri ) )?) } });
         )*
         let mut tys = alloc::vec::Vec::new();
         $( tys.push(TypeEntry{ name: $tname.to_string(), kind: TypeKind::$tkind, size: $tsize, align: $talign }); )*
@@ -22,6 +22,21 @@ macro_rules! kayton_manifest {
     }};
 }
 
+/// A `TypeKind` written as in `kayton_manifest!`: `I64`, or `Option(I64)` and `Result(I64)` for
+/// a wrapped kind.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __kayton_type_kind {
+    ($kind:ident) => {
+        $crate::manifest::TypeKind::$kind
+    };
+    ($wrapper:ident ( $inner:ident )) => {
+        $crate::manifest::TypeKind::$wrapper(alloc::boxed::Box::new(
+            $crate::manifest::TypeKind::$inner,
+        ))
+    };
+}
+
 /// Define default exported ABI symbols using the provided manifest value and register function.
 ///
 /// Usage:
@@ -1,10 +1,10 @@
 extern crate alloc;
 
-use alloc::{string::String, vec::Vec};
+use alloc::{boxed::Box, string::String, vec::Vec};
 use serde::{Deserialize, Serialize};
 
 /// Supported simple kinds across the plugin boundary.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum TypeKind {
     Unit,
@@ -16,6 +16,13 @@ pub enum TypeKind {
     StringBuf,
     VecI64,
     VecF64,
+    /// A value of the inner kind or none at all
+    Option(Box<TypeKind>),
+    /// A value of the inner kind, or a `KaytonError` the caller raises as an exception
+    Result(Box<TypeKind>),
+    /// An iterator over items of the inner kind, returned as a
+    /// `Box<dyn Iterator<Item = T>>`; a `for` loop consumes it
+    Iter(Box<TypeKind>),
     Dynamic,
 }
 
@@ -50,3 +50,64 @@ fn manifest_macro_and_json_roundtrip() {
     let parsed: kayton_plugin_sdk::Manifest = serde_json::from_slice(json).unwrap();
     assert_eq!(parsed, manifest);
 }
+
+#[test]
+fn manifest_macro_accepts_option_kinds() {
+    let manifest = kayton_manifest!(
+        crate_name = "test_crate",
+        crate_version = "0.1.0",
+        functions = [
+            { stable: "find", symbol: "find_fn", params: [I64, Option(F64)], ret: Option(I64) },
+        ],
+        types = []
+    );
+
+    let sig = &manifest.functions[0].sig;
+    assert_eq!(
+        sig.params,
+        vec![TypeKind::I64, TypeKind::Option(Box::new(TypeKind::F64))]
+    );
+    assert_eq!(sig.ret, TypeKind::Option(Box::new(TypeKind::I64)));
+
+    let json = manifest_to_static_json(&manifest);
+    let parsed: kayton_plugin_sdk::Manifest = serde_json::from_slice(json).unwrap();
+    assert_eq!(parsed, manifest);
+}
+
+#[test]
+fn manifest_macro_accepts_result_kinds() {
+    let manifest = kayton_manifest!(
+        crate_name = "test_crate",
+        crate_version = "0.1.0",
+        functions = [
+            { stable: "parse", symbol: "parse_fn", params: [StaticStr], ret: Result(I64) },
+        ],
+        types = []
+    );
+
+    let sig = &manifest.functions[0].sig;
+    assert_eq!(sig.ret, TypeKind::Result(Box::new(TypeKind::I64)));
+
+    let json = manifest_to_static_json(&manifest);
+    let parsed: kayton_plugin_sdk::Manifest = serde_json::from_slice(json).unwrap();
+    assert_eq!(parsed, manifest);
+}
+
+#[test]
+fn manifest_macro_accepts_iter_kinds() {
+    let manifest = kayton_manifest!(
+        crate_name = "test_crate",
+        crate_version = "0.1.0",
+        functions = [
+            { stable: "count", symbol: "count_fn", params: [I64], ret: Iter(I64) },
+        ],
+        types = []
+    );
+
+    let sig = &manifest.functions[0].sig;
+    assert_eq!(sig.ret, TypeKind::Iter(Box::new(TypeKind::I64)));
+
+    let json = manifest_to_static_json(&manifest);
+    let parsed: kayton_plugin_sdk::Manifest = serde_json::from_slice(json).unwrap();
+    assert_eq!(parsed, manifest);
+}
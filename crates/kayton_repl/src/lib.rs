@@ -1,13 +1,17 @@
 use std::io::{self, Write};
 
 use anyhow::Result;
-use kayton_interactive_shared::{InteractiveState, execute_prepared, prepare_input};
+use kayton_interactive_shared::{
+    InteractiveState, execute_prepared, help_text, prepare_input, starts_definition,
+};
 
 /// Run the Kayton REPL loop
 pub fn run_repl() -> Result<()> {
     let mut state = InteractiveState::new();
     let stdin = io::stdin();
     let mut stdout = io::stdout();
+    // `##` lines entered right before a definition become its doc comment
+    let mut doc_lines = String::new();
     loop {
         write!(stdout, ">>> ")?;
         stdout.flush()?;
@@ -21,9 +25,26 @@ pub fn run_repl() -> Result<()> {
         let first_line_no_crlf = line.trim_end_matches(&['\n', '\r'][..]).to_string();
         let first_line_trimmed = first_line_no_crlf.trim();
 
-        // Multiline function entry like Python: if first line starts with `fn` and ends with ':'
-        if first_line_trimmed.starts_with("fn ") && first_line_trimmed.ends_with(':') {
-            let mut block = String::new();
+        // `:help name` shows a stored definition with its doc comment
+        if let Some(name) = first_line_trimmed.strip_prefix(":help") {
+            match help_text(&state, name.trim()) {
+                Some(help) => println!("{}", help),
+                None => println!("no help for '{}'", name.trim()),
+            }
+            continue;
+        }
+
+        if first_line_trimmed.starts_with('#') {
+            if first_line_trimmed.starts_with("##") {
+                doc_lines.push_str(first_line_trimmed);
+                doc_lines.push('\n');
+            }
+            continue;
+        }
+
+        // Multiline function or struct entry like Python: `fn f():` or `struct P:`
+        if starts_definition(first_line_trimmed) {
+            let mut block = std::mem::take(&mut doc_lines);
             block.push_str(&first_line_no_crlf);
             block.push('\n');
 
@@ -41,18 +62,22 @@ pub fn run_repl() -> Result<()> {
                 if cont_no_crlf.is_empty() {
                     break; // blank line terminates the block
                 }
-                // Ensure body lines are indented by 4 spaces
-                block.push_str("    ");
-                block.push_str(cont_no_crlf.trim_start());
+                // Unindented body lines get 4 spaces; nested blocks keep their indentation
+                if !cont_no_crlf.starts_with([' ', '\t']) {
+                    block.push_str("    ");
+                }
+                block.push_str(cont_no_crlf);
                 block.push('\n');
             }
 
-            // Persist function definition across entries
+            // Persist the definition across entries
             state.stored_functions.push(block);
             // Do not compile/run immediately; continue to next prompt
             continue;
         }
 
+        doc_lines.clear();
+
         // Skip empty input
         if first_line_trimmed.is_empty() {
             continue;
@@ -67,8 +92,13 @@ pub fn run_repl() -> Result<()> {
                 continue;
             }
         };
-        if let Err(e) = execute_prepared(&mut state, &prep) {
-            eprintln!("{}", e);
+        match execute_prepared(&mut state, &prep) {
+            Ok(()) => {
+                for warning in &prep.warnings {
+                    eprintln!("{}", warning);
+                }
+            }
+            Err(e) => eprintln!("{}", e),
         }
         state.input_counter += 1;
     }
@@ -68,3 +68,72 @@ print(y)"#;
 
     Ok(())
 }
+
+#[test]
+fn cjk_and_cyrillic_names_round_trip() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    // A definition block entered at the prompt is stored, as the REPL does
+    state
+        .stored_functions
+        .push("fn 合计(xs):\n    return xs[0] + xs[1]\n".to_string());
+    for line in [
+        "数据 = [20, 22]",
+        "имя = \"Мир\"",
+        "print(f\"Привет, {имя}!\")",
+        "合计(数据)",
+    ] {
+        let prepared = prepare_input(&mut state, line)?;
+        execute_prepared(&mut state, &prepared)?;
+    }
+
+    let mut text_of = |name: &str| {
+        let h = state.vm().resolve_name(name).expect("global is defined");
+        state.vm_mut().format_value_by_handle(h).unwrap_or_default()
+    };
+    assert_eq!(text_of("имя"), "Мир");
+    assert_eq!(text_of("数据"), "[20, 22]");
+    assert_eq!(text_of("__last"), "42");
+    assert_eq!(text_of("__stdout").trim_end_matches('\n'), "Привет, Мир!");
+
+    Ok(())
+}
+
+#[test]
+fn state_after_a_runtime_error_keeps_what_ran() -> Result<()> {
+    let mut state = InteractiveState::new();
+
+    let prepared = prepare_input(&mut state, "a = 1\nxs = [1]")?;
+    execute_prepared(&mut state, &prepared)?;
+
+    // Assignments before the failing line are kept, later ones never happen
+    let prepared = prepare_input(&mut state, "a = 2\nxs = [1, 2]\ny = xs[5]\nz = 3")?;
+    let err = execute_prepared(&mut state, &prepared).expect_err("index out of range");
+    assert_eq!(err.to_string(), "IndexError: list index out of range");
+    for name in ["y", "z"] {
+        let err = match prepare_input(&mut state, name) {
+            Ok(_) => panic!("'{name}' should not be defined"),
+            Err(e) => e.to_string(),
+        };
+        assert!(
+            err.contains(&format!("name '{name}' is not defined")),
+            "{err}"
+        );
+    }
+
+    // A global given a new type before the error keeps its new type and value
+    let prepared = prepare_input(&mut state, "a = \"two\"\nxs[9]")?;
+    assert!(execute_prepared(&mut state, &prepared).is_err());
+    let prepared = prepare_input(&mut state, "print(a + \"!\")\nlen(xs)")?;
+    execute_prepared(&mut state, &prepared)?;
+
+    let mut text_of = |name: &str| {
+        let h = state.vm().resolve_name(name).expect("global is defined");
+        state.vm_mut().format_value_by_handle(h).unwrap_or_default()
+    };
+    assert_eq!(text_of("xs"), "[1, 2]");
+    assert_eq!(text_of("__last"), "2");
+    assert!(text_of("__stdout").ends_with("two!\n"));
+
+    Ok(())
+}
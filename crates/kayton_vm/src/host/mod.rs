@@ -10,6 +10,7 @@ mod dyn_store;
 mod vm_fns_dynamic;
 mod vm_fns_float;
 mod vm_fns_intern;
+mod vm_fns_map;
 mod vm_fns_sint;
 mod vm_fns_string;
 mod vm_fns_tuple;
@@ -46,6 +47,10 @@ pub struct HostState {
     tuple_items: Vec<HKayRef>,
     tuples: Vec<(u32, u32)>,
 
+    // Map storage: flat (key, value) entries and (start,len) metadata per map
+    map_entries: Vec<(HKayRef, HKayRef)>,
+    maps: Vec<(u32, u32)>,
+
     next_kind_id: KindId,
     dyn_kinds: BTreeMap<KindId, DynKindStore>,
 
@@ -79,6 +84,8 @@ impl HostState {
             kvecs: Vec::new(),
             tuple_items: Vec::new(),
             tuples: Vec::new(),
+            map_entries: Vec::new(),
+            maps: Vec::new(),
             next_kind_id: 1000,
             dyn_kinds: BTreeMap::new(),
             functions: BTreeMap::new(),
@@ -5,7 +5,9 @@ use kayton_api::types::{HKayRef, KaytonError};
 
 use super::HostState;
 use crate::kinds::pack_handle;
-use kayton_api::kinds::{KIND_F32, KIND_F64, KIND_STATICSTR, KIND_STRBUF, KIND_U8, KIND_U64};
+use kayton_api::kinds::{
+    KIND_BOOL, KIND_F32, KIND_F64, KIND_I64, KIND_STATICSTR, KIND_STRBUF, KIND_U8, KIND_U64,
+};
 
 impl HostState {
     pub fn intern_u64(&mut self, value: u64) -> Result<HKayRef, KaytonError> {
@@ -20,6 +22,18 @@ impl HostState {
         Ok(pack_handle(KIND_U8, idx))
     }
 
+    pub fn intern_i64(&mut self, value: i64) -> Result<HKayRef, KaytonError> {
+        let idx = self.i64s.len() as u32;
+        self.i64s.push(value);
+        Ok(pack_handle(KIND_I64, idx))
+    }
+
+    pub fn intern_bool(&mut self, value: bool) -> Result<HKayRef, KaytonError> {
+        let idx = self.bools.len() as u32;
+        self.bools.push(value);
+        Ok(pack_handle(KIND_BOOL, idx))
+    }
+
     pub fn intern_f64(&mut self, value: f64) -> Result<HKayRef, KaytonError> {
         let idx = self.f64s.len() as u32;
         self.f64s.push(value);
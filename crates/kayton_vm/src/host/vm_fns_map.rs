@@ -0,0 +1,121 @@
+use kayton_api::types::{HKayRef, KaytonError};
+
+use super::HostState;
+use crate::kinds::{pack_handle, unpack_handle};
+use kayton_api::kinds::{
+    KIND_BOOL, KIND_I8, KIND_I16, KIND_I32, KIND_I64, KIND_MAP, KIND_STATICSTR, KIND_STRBUF,
+    KIND_U8, KIND_U16, KIND_U32, KIND_U64,
+};
+
+/// Value of a map key, so that keys stored under different handles compare equal.
+#[derive(PartialEq)]
+enum KeyValue<'a> {
+    Int(i128),
+    Bool(bool),
+    Str(&'a str),
+}
+
+impl HostState {
+    pub fn set_map_from_handles(
+        &mut self,
+        name: &str,
+        keys: *const HKayRef,
+        values: *const HKayRef,
+        len: usize,
+    ) -> Result<HKayRef, KaytonError> {
+        if len > 0 && (keys.is_null() || values.is_null()) {
+            return Err(KaytonError::generic("null entries with nonzero len"));
+        }
+        let start = self.map_entries.len() as u32;
+        // Safety: caller promises `len` keys and `len` values
+        let (keys, values) = unsafe {
+            (
+                core::slice::from_raw_parts(keys, len),
+                core::slice::from_raw_parts(values, len),
+            )
+        };
+        self.map_entries
+            .extend(keys.iter().copied().zip(values.iter().copied()));
+        let map_idx = self.maps.len() as u32;
+        self.maps.push((start, len as u32));
+        let h = pack_handle(KIND_MAP, map_idx);
+        self.bind_name(name, h);
+        Ok(h)
+    }
+
+    pub fn get_map_len_by_name(&self, name: &str) -> Result<usize, KaytonError> {
+        let h = self
+            .resolve(name)
+            .ok_or_else(|| KaytonError::not_found("no global"))?;
+        self.get_map_len_by_handle(h)
+    }
+
+    pub fn get_map_len_by_handle(&self, h: HKayRef) -> Result<usize, KaytonError> {
+        self.map_slice(h).map(|entries| entries.len())
+    }
+
+    pub fn get_map_item_by_name(&self, name: &str, key: HKayRef) -> Result<HKayRef, KaytonError> {
+        let h = self
+            .resolve(name)
+            .ok_or_else(|| KaytonError::not_found("no global"))?;
+        self.get_map_item_by_handle(h, key)
+    }
+
+    pub fn get_map_item_by_handle(&self, h: HKayRef, key: HKayRef) -> Result<HKayRef, KaytonError> {
+        let wanted = self.key_value(key);
+        // A later entry for the same key wins, as if it had been inserted afterwards
+        self.map_slice(h)?
+            .iter()
+            .rev()
+            .find(|(k, _)| *k == key || (wanted.is_some() && self.key_value(*k) == wanted))
+            .map(|&(_, v)| v)
+            .ok_or_else(|| KaytonError::not_found("key not found"))
+    }
+
+    pub fn get_map_entry_by_index(
+        &self,
+        h: HKayRef,
+        index: usize,
+    ) -> Result<(HKayRef, HKayRef), KaytonError> {
+        self.map_slice(h)?
+            .get(index)
+            .copied()
+            .ok_or_else(|| KaytonError::generic("map index out of range"))
+    }
+
+    fn map_slice(&self, h: HKayRef) -> Result<&[(HKayRef, HKayRef)], KaytonError> {
+        let (k, idx) = unpack_handle(h);
+        if k != KIND_MAP {
+            return Err(KaytonError::generic("wrong kind"));
+        }
+        let (start, len) = *self
+            .maps
+            .get(idx as usize)
+            .ok_or_else(|| KaytonError::generic("index out of range"))?;
+        self.map_entries
+            .get(start as usize..(start as usize + len as usize))
+            .ok_or_else(|| KaytonError::generic("map storage out of range"))
+    }
+
+    fn key_value(&self, h: HKayRef) -> Option<KeyValue<'_>> {
+        let (k, idx) = unpack_handle(h);
+        let idx = idx as usize;
+        match k {
+            KIND_I64 => self.i64s.get(idx).map(|&v| KeyValue::Int(v as i128)),
+            KIND_I32 => self.i32s.get(idx).map(|&v| KeyValue::Int(v as i128)),
+            KIND_I16 => self.i16s.get(idx).map(|&v| KeyValue::Int(v as i128)),
+            KIND_I8 => self.i8s.get(idx).map(|&v| KeyValue::Int(v as i128)),
+            KIND_U64 => self.u64s.get(idx).map(|&v| KeyValue::Int(v as i128)),
+            KIND_U32 => self.u32s.get(idx).map(|&v| KeyValue::Int(v as i128)),
+            KIND_U16 => self.u16s.get(idx).map(|&v| KeyValue::Int(v as i128)),
+            KIND_U8 => self.u8s.get(idx).map(|&v| KeyValue::Int(v as i128)),
+            KIND_BOOL => self.bools.get(idx).map(|&v| KeyValue::Bool(v)),
+            KIND_STATICSTR => self.static_strs.get(idx).map(|&s| KeyValue::Str(s)),
+            KIND_STRBUF => match self.str_bufs.get(idx) {
+                Some(Some(sb)) => sb.as_str().map(KeyValue::Str),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
@@ -7,8 +7,8 @@ mod vm;
 
 pub use kayton_api::kinds::{
     KIND_BOOL, KIND_F32, KIND_F64, KIND_I8, KIND_I16, KIND_I32, KIND_I64, KIND_I128, KIND_ISIZE,
-    KIND_KVEC, KIND_STATICSTR, KIND_STRBUF, KIND_TUPLE, KIND_U8, KIND_U16, KIND_U32, KIND_U64,
-    KIND_U128, KIND_USIZE,
+    KIND_KVEC, KIND_MAP, KIND_STATICSTR, KIND_STRBUF, KIND_TUPLE, KIND_U8, KIND_U16, KIND_U32,
+    KIND_U64, KIND_U128, KIND_USIZE,
 };
 pub use vm::KaytonVm;
 
@@ -18,8 +18,11 @@ pub use kayton_api::{
     KaytonContext as VmKaytonContext, KaytonError as VmKaytonError,
 };
 
-// Reporter helpers used by dynamically compiled code epilogues
+// Reporter helpers used by dynamically compiled code to store globals
 pub use reporters::{
-    OnStdoutFn, ReportIntFn, ReportStrFn, host_report_int, host_report_str,
-    set_report_host_from_ctx, set_stdout_callback,
+    OnStdoutFn, ReportBoolFn, ReportErrorFn, ReportF64Fn, ReportIntFn, ReportMapFn, ReportStrFn,
+    ReportStrRef, ReportTupleFn, ReportVecBoolFn, ReportVecF64Fn, ReportVecI64Fn, host_report_bool,
+    host_report_error, host_report_f64, host_report_int, host_report_map, host_report_str,
+    host_report_tuple, host_report_vec_bool, host_report_vec_f64, host_report_vec_i64,
+    set_report_host_from_ctx, set_stdout_callback, take_reported_names, take_runtime_error,
 };
@@ -1,8 +1,44 @@
-use crate::{Api, VmGlobalStrBuf, VmKaytonContext};
+use std::cell::RefCell;
+use std::sync::{Mutex, PoisonError};
 
-pub type ReportIntFn = extern "C" fn(name_ptr: *const u8, name_len: usize, value: i64);
+use crate::{Api, VmGlobalStrBuf, VmHKayRef, VmKaytonContext};
+use kayton_api::KVec;
+use kayton_api::kinds::{KIND_BOOL, KIND_F64, KIND_I64, KIND_STRBUF};
+
+pub type ReportIntFn = unsafe extern "C" fn(name_ptr: *const u8, name_len: usize, value: i64);
 pub type ReportStrFn =
-    extern "C" fn(name_ptr: *const u8, name_len: usize, str_ptr: *const u8, str_len: usize);
+    unsafe extern "C" fn(name_ptr: *const u8, name_len: usize, str_ptr: *const u8, str_len: usize);
+pub type ReportF64Fn = unsafe extern "C" fn(name_ptr: *const u8, name_len: usize, value: f64);
+pub type ReportBoolFn = unsafe extern "C" fn(name_ptr: *const u8, name_len: usize, value: bool);
+pub type ReportVecI64Fn =
+    unsafe extern "C" fn(name_ptr: *const u8, name_len: usize, data: *const i64, len: usize);
+pub type ReportVecF64Fn =
+    unsafe extern "C" fn(name_ptr: *const u8, name_len: usize, data: *const f64, len: usize);
+pub type ReportVecBoolFn =
+    unsafe extern "C" fn(name_ptr: *const u8, name_len: usize, data: *const bool, len: usize);
+/// Reports a map as parallel key and value arrays. Each array holds `len` items of
+/// `i64`, `f64`, `bool` or [`ReportStrRef`], as given by its `KIND_I64`, `KIND_F64`,
+/// `KIND_BOOL` or `KIND_STRBUF` kind.
+pub type ReportMapFn = unsafe extern "C" fn(
+    name_ptr: *const u8,
+    name_len: usize,
+    key_kind: u32,
+    keys: *const core::ffi::c_void,
+    value_kind: u32,
+    values: *const core::ffi::c_void,
+    len: usize,
+);
+/// Reports a tuple item by item: `kinds[i]` is the kind of the value `items[i]` points
+/// to, one of the item kinds of [`ReportMapFn`].
+pub type ReportTupleFn = unsafe extern "C" fn(
+    name_ptr: *const u8,
+    name_len: usize,
+    kinds: *const u32,
+    items: *const *const core::ffi::c_void,
+    len: usize,
+);
+pub type ReportErrorFn =
+    unsafe extern "C" fn(kind_ptr: *const u8, kind_len: usize, msg_ptr: *const u8, msg_len: usize);
 
 static mut HOST_PTRS: Option<(usize, usize)> = None; // (host_data, api_ptr)
 
@@ -11,6 +47,35 @@ static mut HOST_PTRS: Option<(usize, usize)> = None; // (host_data, api_ptr)
 pub type OnStdoutFn = extern "C" fn(text_ptr: *const u8, text_len: usize);
 static mut ON_STDOUT: Option<OnStdoutFn> = None;
 
+// Runtime error (kind, message) raised by the last run of client code, if any
+static LAST_ERROR: Mutex<Option<(String, String)>> = Mutex::new(None);
+
+thread_local! {
+    // Names of the globals reported by client code since the last take_reported_names().
+    // Client code reports on the thread that runs it, so runs on other threads don't mix in.
+    static REPORTED_NAMES: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Take the names of the globals reported by client code since the last call, in the
+/// order they were reported. A run that stops at a runtime error has reported only
+/// the globals it got to.
+pub fn take_reported_names() -> Vec<String> {
+    REPORTED_NAMES.with(|names| names.take())
+}
+
+fn note_reported(name: &str) {
+    REPORTED_NAMES.with(|names| names.borrow_mut().push(name.to_string()));
+}
+
+/// Take the runtime error reported by client code since the last call.
+#[inline]
+pub fn take_runtime_error() -> Option<(String, String)> {
+    LAST_ERROR
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take()
+}
+
 #[inline]
 pub fn set_stdout_callback(cb: Option<OnStdoutFn>) {
     unsafe {
@@ -25,7 +90,12 @@ pub fn set_report_host_from_ctx(ctx: &mut VmKaytonContext) {
     }
 }
 
-pub extern "C" fn host_report_int(name_ptr: *const u8, name_len: usize, value: i64) {
+/// Store an `i64` reported by client code as a global.
+///
+/// # Safety
+///
+/// `name_ptr` must point to `name_len` readable bytes.
+pub unsafe extern "C" fn host_report_int(name_ptr: *const u8, name_len: usize, value: i64) {
     unsafe {
         let name_slice = core::slice::from_raw_parts(name_ptr, name_len);
         if let Ok(name) = core::str::from_utf8(name_slice) {
@@ -39,12 +109,67 @@ pub extern "C" fn host_report_int(name_ptr: *const u8, name_len: usize, value: i
                 let api_ptr = ctx.api;
                 let api: &Api = &*api_ptr;
                 let _ = (api.set_global_u64)(&mut ctx, name, value as u64);
+                note_reported(name);
             }
         }
     }
 }
 
-pub extern "C" fn host_report_str(
+/// Store an `f64` reported by client code as a global.
+///
+/// # Safety
+///
+/// `name_ptr` must point to `name_len` readable bytes.
+pub unsafe extern "C" fn host_report_f64(name_ptr: *const u8, name_len: usize, value: f64) {
+    unsafe {
+        let name_slice = core::slice::from_raw_parts(name_ptr, name_len);
+        if let Ok(name) = core::str::from_utf8(name_slice)
+            && let Some((host_data, api_ptr)) = HOST_PTRS
+        {
+            let mut ctx = VmKaytonContext {
+                abi_version: 1,
+                host_data: host_data as *mut core::ffi::c_void,
+                api: api_ptr as *const Api,
+            };
+            let api_ptr = ctx.api;
+            let api: &Api = &*api_ptr;
+            let _ = (api.set_global_f64)(&mut ctx, name, value);
+            note_reported(name);
+        }
+    }
+}
+
+/// Store a `bool` reported by client code as a global.
+///
+/// # Safety
+///
+/// `name_ptr` must point to `name_len` readable bytes.
+pub unsafe extern "C" fn host_report_bool(name_ptr: *const u8, name_len: usize, value: bool) {
+    unsafe {
+        let name_slice = core::slice::from_raw_parts(name_ptr, name_len);
+        if let Ok(name) = core::str::from_utf8(name_slice)
+            && let Some((host_data, api_ptr)) = HOST_PTRS
+        {
+            let mut ctx = VmKaytonContext {
+                abi_version: 1,
+                host_data: host_data as *mut core::ffi::c_void,
+                api: api_ptr as *const Api,
+            };
+            let api_ptr = ctx.api;
+            let api: &Api = &*api_ptr;
+            let _ = (api.set_global_bool)(&mut ctx, name, value);
+            note_reported(name);
+        }
+    }
+}
+
+/// Store a string reported by client code as a global, appending to the buffer for
+/// `__stdout`.
+///
+/// # Safety
+///
+/// `name_ptr` and `str_ptr` must point to `name_len` and `str_len` readable bytes.
+pub unsafe extern "C" fn host_report_str(
     name_ptr: *const u8,
     name_len: usize,
     str_ptr: *const u8,
@@ -84,8 +209,219 @@ pub extern "C" fn host_report_str(
                 } else {
                     let buf = VmGlobalStrBuf::new(val.to_string());
                     let _ = (api.set_global_str_buf)(&mut ctx, name, buf);
+                    note_reported(name);
+                }
+            }
+        }
+    }
+}
+
+/// Store a list reported by client code as a `KVec` global.
+unsafe fn set_kvec_global(name_ptr: *const u8, name_len: usize, value: KVec) {
+    unsafe {
+        let name_slice = core::slice::from_raw_parts(name_ptr, name_len);
+        if let Ok(name) = core::str::from_utf8(name_slice)
+            && let Some((host_data, api_ptr)) = HOST_PTRS
+        {
+            let mut ctx = VmKaytonContext {
+                abi_version: 1,
+                host_data: host_data as *mut core::ffi::c_void,
+                api: api_ptr as *const Api,
+            };
+            let api_ptr = ctx.api;
+            let api: &Api = &*api_ptr;
+            let _ = (api.set_global_kvec)(&mut ctx, name, value);
+            note_reported(name);
+        }
+    }
+}
+
+/// Store a list of `i64` reported by client code.
+///
+/// # Safety
+///
+/// `name_ptr` must point to `name_len` readable bytes and `data` to `len` items.
+pub unsafe extern "C" fn host_report_vec_i64(
+    name_ptr: *const u8,
+    name_len: usize,
+    data: *const i64,
+    len: usize,
+) {
+    unsafe {
+        let items = core::slice::from_raw_parts(data, len).to_vec();
+        set_kvec_global(name_ptr, name_len, KVec::from_vec_i64(items));
+    }
+}
+
+/// Store a list of `f64` reported by client code.
+///
+/// # Safety
+///
+/// `name_ptr` must point to `name_len` readable bytes and `data` to `len` items.
+pub unsafe extern "C" fn host_report_vec_f64(
+    name_ptr: *const u8,
+    name_len: usize,
+    data: *const f64,
+    len: usize,
+) {
+    unsafe {
+        let items = core::slice::from_raw_parts(data, len).to_vec();
+        set_kvec_global(name_ptr, name_len, KVec::from_vec_f64(items));
+    }
+}
+
+/// Store a list of `bool` reported by client code.
+///
+/// # Safety
+///
+/// `name_ptr` must point to `name_len` readable bytes and `data` to `len` items.
+pub unsafe extern "C" fn host_report_vec_bool(
+    name_ptr: *const u8,
+    name_len: usize,
+    data: *const bool,
+    len: usize,
+) {
+    unsafe {
+        let items = core::slice::from_raw_parts(data, len).to_vec();
+        set_kvec_global(name_ptr, name_len, KVec::from_vec_bool(items));
+    }
+}
+
+/// A borrowed UTF-8 string inside a reported map.
+#[repr(C)]
+pub struct ReportStrRef {
+    pub ptr: *const u8,
+    pub len: usize,
+}
+
+/// Intern `len` reported items of the given kind as unnamed VM values.
+unsafe fn intern_items(
+    ctx: &mut VmKaytonContext,
+    api: &Api,
+    kind: u32,
+    data: *const core::ffi::c_void,
+    len: usize,
+) -> Option<Vec<VmHKayRef>> {
+    unsafe {
+        let mut out = Vec::with_capacity(len);
+        for i in 0..len {
+            let h = match kind {
+                KIND_I64 => (api.intern_i64)(ctx, *(data as *const i64).add(i)),
+                KIND_F64 => (api.intern_f64)(ctx, *(data as *const f64).add(i)),
+                KIND_BOOL => (api.intern_bool)(ctx, *(data as *const bool).add(i)),
+                KIND_STRBUF => {
+                    let s = &*(data as *const ReportStrRef).add(i);
+                    let bytes = core::slice::from_raw_parts(s.ptr, s.len);
+                    (api.intern_str_buf)(ctx, core::str::from_utf8(bytes).ok()?)
+                }
+                _ => return None,
+            };
+            out.push(h.ok()?);
+        }
+        Some(out)
+    }
+}
+
+/// Store a map reported by client code, as laid out by [`ReportMapFn`].
+///
+/// # Safety
+///
+/// `name_ptr` must point to `name_len` readable bytes, and `keys` and `values` to `len`
+/// items of their kinds.
+pub unsafe extern "C" fn host_report_map(
+    name_ptr: *const u8,
+    name_len: usize,
+    key_kind: u32,
+    keys: *const core::ffi::c_void,
+    value_kind: u32,
+    values: *const core::ffi::c_void,
+    len: usize,
+) {
+    unsafe {
+        let name_slice = core::slice::from_raw_parts(name_ptr, name_len);
+        if let Ok(name) = core::str::from_utf8(name_slice)
+            && let Some((host_data, api_ptr)) = HOST_PTRS
+        {
+            let mut ctx = VmKaytonContext {
+                abi_version: 1,
+                host_data: host_data as *mut core::ffi::c_void,
+                api: api_ptr as *const Api,
+            };
+            let api_ptr = ctx.api;
+            let api: &Api = &*api_ptr;
+            if let (Some(key_handles), Some(value_handles)) = (
+                intern_items(&mut ctx, api, key_kind, keys, len),
+                intern_items(&mut ctx, api, value_kind, values, len),
+            ) {
+                let _ = (api.set_global_map_from_handles)(
+                    &mut ctx,
+                    name,
+                    key_handles.as_ptr(),
+                    value_handles.as_ptr(),
+                    len,
+                );
+                note_reported(name);
+            }
+        }
+    }
+}
+
+/// Store a tuple reported by client code, as laid out by [`ReportTupleFn`].
+///
+/// # Safety
+///
+/// `name_ptr` must point to `name_len` readable bytes, and `kinds` and `items` to `len`
+/// entries, each item pointing to a value of its kind.
+pub unsafe extern "C" fn host_report_tuple(
+    name_ptr: *const u8,
+    name_len: usize,
+    kinds: *const u32,
+    items: *const *const core::ffi::c_void,
+    len: usize,
+) {
+    unsafe {
+        let name_slice = core::slice::from_raw_parts(name_ptr, name_len);
+        if let Ok(name) = core::str::from_utf8(name_slice)
+            && let Some((host_data, api_ptr)) = HOST_PTRS
+        {
+            let mut ctx = VmKaytonContext {
+                abi_version: 1,
+                host_data: host_data as *mut core::ffi::c_void,
+                api: api_ptr as *const Api,
+            };
+            let api_ptr = ctx.api;
+            let api: &Api = &*api_ptr;
+            let mut handles = Vec::with_capacity(len);
+            for i in 0..len {
+                match intern_items(&mut ctx, api, *kinds.add(i), *items.add(i), 1) {
+                    Some(h) => handles.extend(h),
+                    None => return,
                 }
             }
+            let _ = (api.set_global_tuple_from_handles)(&mut ctx, name, handles.as_ptr(), len);
+            note_reported(name);
         }
     }
 }
+
+/// Record the runtime error raised by client code for [`take_runtime_error`].
+///
+/// # Safety
+///
+/// `kind_ptr` and `msg_ptr` must point to `kind_len` and `msg_len` readable bytes.
+pub unsafe extern "C" fn host_report_error(
+    kind_ptr: *const u8,
+    kind_len: usize,
+    msg_ptr: *const u8,
+    msg_len: usize,
+) {
+    unsafe {
+        let kind = core::slice::from_raw_parts(kind_ptr, kind_len);
+        let msg = core::slice::from_raw_parts(msg_ptr, msg_len);
+        let error = (
+            String::from_utf8_lossy(kind).into_owned(),
+            String::from_utf8_lossy(msg).into_owned(),
+        );
+        *LAST_ERROR.lock().unwrap_or_else(PoisonError::into_inner) = Some(error);
+    }
+}
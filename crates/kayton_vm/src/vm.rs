@@ -366,6 +366,40 @@ impl KaytonVm {
                 let s = unsafe { &*(ctx.host_data as *mut HostState) };
                 s.get_type(name)
             },
+
+            // ---- Maps ----
+            set_global_map_from_handles: |ctx, name, keys, values, len| {
+                let s = unsafe { &mut *(ctx.host_data as *mut HostState) };
+                s.set_map_from_handles(name, keys, values, len)
+            },
+            get_global_map_len: |ctx, name| {
+                let s = unsafe { &*(ctx.host_data as *mut HostState) };
+                s.get_map_len_by_name(name)
+            },
+            get_map_len_by_handle: |ctx, h| {
+                let s = unsafe { &*(ctx.host_data as *mut HostState) };
+                s.get_map_len_by_handle(h)
+            },
+            get_global_map_item: |ctx, name, key| {
+                let s = unsafe { &*(ctx.host_data as *mut HostState) };
+                s.get_map_item_by_name(name, key)
+            },
+            get_map_item_by_handle: |ctx, h, key| {
+                let s = unsafe { &*(ctx.host_data as *mut HostState) };
+                s.get_map_item_by_handle(h, key)
+            },
+            get_map_entry_by_handle: |ctx, h, index| {
+                let s = unsafe { &*(ctx.host_data as *mut HostState) };
+                s.get_map_entry_by_index(h, index)
+            },
+            intern_i64: |ctx, v| {
+                let s = unsafe { &mut *(ctx.host_data as *mut HostState) };
+                s.intern_i64(v)
+            },
+            intern_bool: |ctx, v| {
+                let s = unsafe { &mut *(ctx.host_data as *mut HostState) };
+                s.intern_bool(v)
+            },
         });
 
         KaytonVm {
@@ -404,8 +438,8 @@ impl KaytonVm {
     ) -> Result<String, KaytonError> {
         use crate::{
             KIND_BOOL, KIND_F32, KIND_F64, KIND_I8, KIND_I16, KIND_I32, KIND_I64, KIND_I128,
-            KIND_ISIZE, KIND_KVEC, KIND_STATICSTR, KIND_STRBUF, KIND_TUPLE, KIND_U8, KIND_U16,
-            KIND_U32, KIND_U64, KIND_U128, KIND_USIZE,
+            KIND_ISIZE, KIND_KVEC, KIND_MAP, KIND_STATICSTR, KIND_STRBUF, KIND_TUPLE, KIND_U8,
+            KIND_U16, KIND_U32, KIND_U64, KIND_U128, KIND_USIZE,
         };
 
         let mut ctx = self.context();
@@ -440,9 +474,9 @@ impl KaytonVm {
         } else if k == KIND_BOOL {
             (api.get_global_bool_by_handle)(&mut ctx, h).map(|v| v.to_string())?
         } else if k == KIND_F64 {
-            (api.get_global_f64_by_handle)(&mut ctx, h).map(|v| v.to_string())?
+            (api.get_global_f64_by_handle)(&mut ctx, h).map(|v| format!("{:?}", v))?
         } else if k == KIND_F32 {
-            (api.get_global_f32_by_handle)(&mut ctx, h).map(|v| v.to_string())?
+            (api.get_global_f32_by_handle)(&mut ctx, h).map(|v| format!("{:?}", v))?
         } else if k == KIND_STATICSTR {
             (api.get_global_static_str_by_handle)(&mut ctx, h).map(|v| v.to_string())?
         } else if k == KIND_STRBUF {
@@ -453,24 +487,58 @@ impl KaytonVm {
                 "<invalid-str>".to_string()
             }
         } else if k == KIND_TUPLE {
-            // Recursively format tuple items
+            // Recursively format tuple items, strings quoted as in a Kayton tuple
             let len = (api.get_tuple_len_by_handle)(&mut ctx, h)?;
             let mut items: Vec<String> = Vec::with_capacity(len);
             for i in 0..len {
                 let ih = (api.get_global_tuple_item_by_handle)(&mut ctx, h, i)?;
-                let s = self.format_value_by_handle(ih)?;
+                let s = self.format_map_item(ih)?;
                 items.push(s);
             }
-            format!("({})", items.join(", "))
+            if len == 1 {
+                format!("({},)", items[0])
+            } else {
+                format!("({})", items.join(", "))
+            }
+        } else if k == KIND_MAP {
+            // Maps written by Kayton code format like their Kayton values, strings quoted
+            let len = (api.get_map_len_by_handle)(&mut ctx, h)?;
+            let mut entries: Vec<String> = Vec::with_capacity(len);
+            for i in 0..len {
+                let (kh, vh) = (api.get_map_entry_by_handle)(&mut ctx, h, i)?;
+                let key = self.format_map_item(kh)?;
+                let value = self.format_map_item(vh)?;
+                entries.push(format!("{}: {}", key, value));
+            }
+            format!("{{{}}}", entries.join(", "))
         } else if k == KIND_KVEC {
             let kv = (api.get_global_kvec_by_handle)(&mut ctx, h)?;
-            format!("<kvec kind={} len_bytes={}>", kv.kind, kv.len)
+            // Lists written by Kayton code format like their Kayton values
+            let items = if kv.kind == KIND_I64 {
+                kv.as_vec_i64().map(|v| format!("{:?}", v))
+            } else if kv.kind == KIND_F64 {
+                kv.as_vec_f64().map(|v| format!("{:?}", v))
+            } else if kv.kind == KIND_BOOL {
+                kv.as_vec_bool().map(|v| format!("{:?}", v))
+            } else {
+                None
+            };
+            items.unwrap_or_else(|| format!("<kvec kind={} len_bytes={}>", kv.kind, kv.len))
         } else {
             format!("<kind {} @{}>", h.kind, h.index)
         };
         Ok(out)
     }
 
+    fn format_map_item(&mut self, h: kayton_api::types::HKayRef) -> Result<String, KaytonError> {
+        let s = self.format_value_by_handle(h)?;
+        if h.kind == crate::KIND_STRBUF || h.kind == crate::KIND_STATICSTR {
+            Ok(format!("{:?}", s))
+        } else {
+            Ok(s)
+        }
+    }
+
     /// Convenience: snapshot and format all globals as strings.
     pub fn read_all_globals_as_strings(&mut self) -> Vec<(String, String)> {
         let snapshot = self.snapshot_globals();
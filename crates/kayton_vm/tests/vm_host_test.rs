@@ -206,3 +206,46 @@ fn test_drop_dyn_ptr_api_calls_drop_fn_and_clears_slot() {
     // Ensure slot is cleared
     assert!((api.get_global_dyn_ptr_by_handle)(&mut ctx, h).is_err());
 }
+
+#[test]
+fn test_map_set_lookup_by_value_and_format() {
+    let mut vm = KaytonVm::new();
+    let mut ctx = vm.context();
+    let api: &Api = unsafe { &*ctx.api };
+
+    let keys = [
+        (api.intern_str_buf)(&mut ctx, "a").unwrap(),
+        (api.intern_str_buf)(&mut ctx, "b").unwrap(),
+    ];
+    let values = [
+        (api.intern_i64)(&mut ctx, 1).unwrap(),
+        (api.intern_bool)(&mut ctx, true).unwrap(),
+    ];
+    let h = (api.set_global_map_from_handles)(
+        &mut ctx,
+        "d",
+        keys.as_ptr(),
+        values.as_ptr(),
+        keys.len(),
+    )
+    .unwrap();
+    assert_eq!((api.get_global_map_len)(&mut ctx, "d").unwrap(), 2);
+    assert_eq!(ctx.map_len_by_handle(h).unwrap(), 2);
+
+    // Keys are compared by value, not by handle
+    let key_b = (api.intern_str_buf)(&mut ctx, "b").unwrap();
+    let got = (api.get_global_map_item)(&mut ctx, "d", key_b).unwrap();
+    assert!((api.get_global_bool_by_handle)(&mut ctx, got).unwrap());
+    let (k0, v0) = ctx.map_entry_by_handle(h, 0).unwrap();
+    assert_eq!(k0, keys[0]);
+    assert_eq!((api.get_global_i64_by_handle)(&mut ctx, v0).unwrap(), 1);
+
+    let missing = (api.intern_str_buf)(&mut ctx, "zz").unwrap();
+    let err = ctx.map_item_by_handle(h, missing).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    assert_eq!(
+        vm.format_value_by_handle(h).unwrap(),
+        "{\"a\": 1, \"b\": true}"
+    );
+}
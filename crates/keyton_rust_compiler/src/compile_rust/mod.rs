@@ -4,12 +4,13 @@ use std::process::Command;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::hir::lower_program;
-use crate::lexer::Lexer;
-use crate::parser::Parser;
+use crate::diagnostics::{Source, format_parse_errors, format_resolve_error};
+use crate::hir::lower_program_with_spans;
+use crate::parser::parse_source;
 use crate::rhir::convert_to_rhir;
 use crate::rust_codegen::generate_rust_code;
-use crate::shir::resolve_program;
+use crate::shir::resolve_program_with_spans;
+use crate::shir::resolver::ResolveError;
 use crate::thir::typecheck_program;
 
 /// Build a temporary Rust crate that compiles to a `dylib` and returns the built library path.
@@ -34,21 +35,20 @@ crate-type = ["dylib"]
 
     // Transform the generated main program into a library that exposes `run()`
     // 1) Define a local `println!` macro that accepts a single expression and uses `{}` formatting.
-    // 2) Replace `fn main()` with `#[no_mangle] pub extern "C" fn run()`.
-    let replaced = source_code.replace("fn main() {", "#[no_mangle]\npub extern \"C\" fn run() {");
+    // 2) Rename `fn main()` to `fn kayton_main()`, which `run()` calls while catching runtime errors.
+    let replaced = source_code.replace("fn main() {", "fn kayton_main() {");
 
-    // If replacement did not occur for some reason, add a `run` wrapper that calls `main()`
+    // If replacement did not occur for some reason, add a `kayton_main` that calls `main()`
     let lib_body = if replaced == source_code {
-        format!(
-            "{}\n\n#[no_mangle]\npub extern \"C\" fn run() {{\n    main();\n}}\n",
-            source_code
-        )
+        format!("{}\n\nfn kayton_main() {{\n    main();\n}}\n", source_code)
     } else {
         replaced
     };
 
     // Prepend compatibility macro for println! and REPL reporting hooks
     let macro_header = r#"
+use std::collections::HashMap;
+
 macro_rules! println {
     ($e:expr) => {{
         let s = ::std::format!("{}\n", $e);
@@ -65,12 +65,19 @@ macro_rules! println {
 
 // ----- REPL host reporting hooks (set by host via kayton_set_reporters) -----
 #[allow(non_camel_case_types)]
-type ReportIntFn = extern "C" fn(name_ptr: *const u8, name_len: usize, value: i64);
+type ReportIntFn = unsafe extern "C" fn(name_ptr: *const u8, name_len: usize, value: i64);
+#[allow(non_camel_case_types)]
+type ReportStrFn = unsafe extern "C" fn(name_ptr: *const u8, name_len: usize, str_ptr: *const u8, str_len: usize);
+
+#[allow(non_camel_case_types)]
+type ReportF64Fn = unsafe extern "C" fn(name_ptr: *const u8, name_len: usize, value: f64);
 #[allow(non_camel_case_types)]
-type ReportStrFn = extern "C" fn(name_ptr: *const u8, name_len: usize, str_ptr: *const u8, str_len: usize);
+type ReportBoolFn = unsafe extern "C" fn(name_ptr: *const u8, name_len: usize, value: bool);
 
 static mut REPORT_INT: Option<ReportIntFn> = None;
 static mut REPORT_STR: Option<ReportStrFn> = None;
+static mut REPORT_F64: Option<ReportF64Fn> = None;
+static mut REPORT_BOOL: Option<ReportBoolFn> = None;
 
 #[no_mangle]
 pub extern "C" fn kayton_set_reporters(int_fn: ReportIntFn, str_fn: ReportStrFn) {
@@ -80,11 +87,195 @@ pub extern "C" fn kayton_set_reporters(int_fn: ReportIntFn, str_fn: ReportStrFn)
     }
 }
 
+// Optional: hosts that persist float and bool globals also register these reporters
+#[no_mangle]
+pub extern "C" fn kayton_set_f64_reporter(f64_fn: ReportF64Fn) {
+    unsafe {
+        REPORT_F64 = Some(f64_fn);
+    }
+}
+#[no_mangle]
+pub extern "C" fn kayton_set_bool_reporter(bool_fn: ReportBoolFn) {
+    unsafe {
+        REPORT_BOOL = Some(bool_fn);
+    }
+}
+
+// Lists are copied out element by element: (name, data pointer, element count)
+#[allow(non_camel_case_types)]
+type ReportVecI64Fn = unsafe extern "C" fn(name_ptr: *const u8, name_len: usize, data: *const i64, len: usize);
+#[allow(non_camel_case_types)]
+type ReportVecF64Fn = unsafe extern "C" fn(name_ptr: *const u8, name_len: usize, data: *const f64, len: usize);
+#[allow(non_camel_case_types)]
+type ReportVecBoolFn = unsafe extern "C" fn(name_ptr: *const u8, name_len: usize, data: *const bool, len: usize);
+
+static mut REPORT_VEC_I64: Option<ReportVecI64Fn> = None;
+static mut REPORT_VEC_F64: Option<ReportVecF64Fn> = None;
+static mut REPORT_VEC_BOOL: Option<ReportVecBoolFn> = None;
+
+// Optional: hosts that persist list globals register these reporters
+#[no_mangle]
+pub extern "C" fn kayton_set_vec_reporters(i64_fn: ReportVecI64Fn, f64_fn: ReportVecF64Fn, bool_fn: ReportVecBoolFn) {
+    unsafe {
+        REPORT_VEC_I64 = Some(i64_fn);
+        REPORT_VEC_F64 = Some(f64_fn);
+        REPORT_VEC_BOOL = Some(bool_fn);
+    }
+}
+
+#[inline]
+unsafe fn report_vec_i64(name: &str, v: &[i64]) {
+    if let Some(f) = REPORT_VEC_I64 { f(name.as_ptr(), name.len(), v.as_ptr(), v.len()); }
+}
+#[inline]
+unsafe fn report_vec_f64(name: &str, v: &[f64]) {
+    if let Some(f) = REPORT_VEC_F64 { f(name.as_ptr(), name.len(), v.as_ptr(), v.len()); }
+}
+#[inline]
+unsafe fn report_vec_bool(name: &str, v: &[bool]) {
+    if let Some(f) = REPORT_VEC_BOOL { f(name.as_ptr(), name.len(), v.as_ptr(), v.len()); }
+}
+
+// Dicts are copied out as parallel key and value arrays, each tagged with its kind id:
+// (name, key kind, keys, value kind, values, entry count)
+#[allow(non_camel_case_types)]
+type ReportMapFn = unsafe extern "C" fn(name_ptr: *const u8, name_len: usize, key_kind: u32, keys: *const c_void, value_kind: u32, values: *const c_void, len: usize);
+
+static mut REPORT_MAP: Option<ReportMapFn> = None;
+
+// Optional: hosts that persist dict globals register this reporter
+#[no_mangle]
+pub extern "C" fn kayton_set_map_reporter(map_fn: ReportMapFn) {
+    unsafe {
+        REPORT_MAP = Some(map_fn);
+    }
+}
+
+/// A string item of a reported dict, borrowed from the dict.
+#[repr(C)]
+struct ReportStrRef {
+    ptr: *const u8,
+    len: usize,
+}
+
+/// A dict key or value type the host can store: its kind id and its C representation.
+trait ReportItem {
+    const KIND: u32;
+    type Repr;
+    fn repr(&self) -> Self::Repr;
+}
+impl ReportItem for i64 {
+    const KIND: u32 = 14;
+    type Repr = i64;
+    fn repr(&self) -> i64 { *self }
+}
+impl ReportItem for f64 {
+    const KIND: u32 = 3;
+    type Repr = f64;
+    fn repr(&self) -> f64 { *self }
+}
+impl ReportItem for bool {
+    const KIND: u32 = 17;
+    type Repr = bool;
+    fn repr(&self) -> bool { *self }
+}
+impl ReportItem for String {
+    const KIND: u32 = 6;
+    type Repr = ReportStrRef;
+    fn repr(&self) -> ReportStrRef { ReportStrRef { ptr: self.as_ptr(), len: self.len() } }
+}
+
+#[inline]
+unsafe fn report_map<K: ReportItem, V: ReportItem>(name: &str, d: &HashMap<K, V>) {
+    if let Some(f) = REPORT_MAP {
+        // `keys()` and `values()` visit the entries in the same order
+        let keys: Vec<K::Repr> = d.keys().map(ReportItem::repr).collect();
+        let values: Vec<V::Repr> = d.values().map(ReportItem::repr).collect();
+        f(name.as_ptr(), name.len(), K::KIND, keys.as_ptr() as *const c_void, V::KIND, values.as_ptr() as *const c_void, d.len());
+    }
+}
+
+// Tuples are copied out item by item, each tagged with its kind id:
+// (name, item kinds, pointers to the items, item count)
+#[allow(non_camel_case_types)]
+type ReportTupleFn = unsafe extern "C" fn(name_ptr: *const u8, name_len: usize, kinds: *const u32, items: *const *const c_void, len: usize);
+
+static mut REPORT_TUPLE: Option<ReportTupleFn> = None;
+
+// Optional: hosts that persist tuple globals register this reporter
+#[no_mangle]
+pub extern "C" fn kayton_set_tuple_reporter(tuple_fn: ReportTupleFn) {
+    unsafe {
+        REPORT_TUPLE = Some(tuple_fn);
+    }
+}
+
+/// A tuple whose items are all `ReportItem`s.
+trait ReportTuple {
+    fn report(&self, f: ReportTupleFn, name: &str);
+}
+macro_rules! report_tuple_impl {
+    ($($t:ident $i:tt),+) => {
+        impl<$($t: ReportItem),+> ReportTuple for ($($t,)+) {
+            fn report(&self, f: ReportTupleFn, name: &str) {
+                let reprs = ($(self.$i.repr(),)+);
+                let kinds = [$(<$t as ReportItem>::KIND),+];
+                let items = [$(&reprs.$i as *const _ as *const c_void),+];
+                unsafe { f(name.as_ptr(), name.len(), kinds.as_ptr(), items.as_ptr(), kinds.len()) };
+            }
+        }
+    };
+}
+report_tuple_impl!(A 0);
+report_tuple_impl!(A 0, B 1);
+report_tuple_impl!(A 0, B 1, C 2);
+report_tuple_impl!(A 0, B 1, C 2, D 3);
+report_tuple_impl!(A 0, B 1, C 2, D 3, E 4);
+report_tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5);
+report_tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+report_tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+report_tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8);
+report_tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9);
+report_tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10);
+report_tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
+
+/// Report items of one type as a tuple; a list of strings and an optional value are
+/// stored this way.
+fn report_items<'a, T: ReportItem + 'a>(f: ReportTupleFn, name: &str, items: impl Iterator<Item = &'a T>) {
+    let reprs: Vec<T::Repr> = items.map(ReportItem::repr).collect();
+    let kinds = vec![T::KIND; reprs.len()];
+    let items: Vec<*const c_void> = reprs.iter().map(|r| r as *const _ as *const c_void).collect();
+    unsafe { f(name.as_ptr(), name.len(), kinds.as_ptr(), items.as_ptr(), kinds.len()) };
+}
+impl<T: ReportItem> ReportTuple for Vec<T> {
+    fn report(&self, f: ReportTupleFn, name: &str) {
+        report_items(f, name, self.iter())
+    }
+}
+impl<T: ReportItem> ReportTuple for Option<T> {
+    fn report(&self, f: ReportTupleFn, name: &str) {
+        report_items(f, name, self.iter())
+    }
+}
+
+#[inline]
+unsafe fn report_tuple<T: ReportTuple>(name: &str, t: &T) {
+    if let Some(f) = REPORT_TUPLE { t.report(f, name); }
+}
+
 #[inline]
 unsafe fn report_int(name: &str, value: i64) {
     if let Some(f) = REPORT_INT { f(name.as_ptr(), name.len(), value); }
 }
 #[inline]
+unsafe fn report_f64(name: &str, value: f64) {
+    if let Some(f) = REPORT_F64 { f(name.as_ptr(), name.len(), value); }
+}
+#[inline]
+unsafe fn report_bool(name: &str, value: bool) {
+    if let Some(f) = REPORT_BOOL { f(name.as_ptr(), name.len(), value); }
+}
+#[inline]
 unsafe fn report_str(name: &str, s: &str) {
     if let Some(f) = REPORT_STR { f(name.as_ptr(), name.len(), s.as_ptr(), s.len()); }
 }
@@ -116,6 +307,276 @@ unsafe fn load_plugin(name: &str) -> bool {
 unsafe fn get_fn_ptr(name: &str) -> *const c_void {
     if let Some(f) = GET_FUNCTION_PTR { f(name.as_ptr(), name.len()) } else { core::ptr::null() }
 }
+
+// ----- Runtime errors (reported to the host via kayton_set_error_reporter) -----
+#[allow(non_camel_case_types)]
+type ReportErrorFn = unsafe extern "C" fn(kind_ptr: *const u8, kind_len: usize, msg_ptr: *const u8, msg_len: usize);
+
+static mut REPORT_ERROR: Option<ReportErrorFn> = None;
+
+#[no_mangle]
+pub extern "C" fn kayton_set_error_reporter(error_fn: ReportErrorFn) {
+    unsafe {
+        REPORT_ERROR = Some(error_fn);
+    }
+}
+
+/// Panic payload carrying a Kayton exception such as `IndexError`.
+struct KaytonError {
+    kind: &'static str,
+    message: String,
+}
+
+fn kayton_raise(kind: &'static str, message: String) -> ! {
+    ::std::panic::panic_any(KaytonError { kind, message })
+}
+
+/// The exception a panic payload carries; Rust's own panics become a `RuntimeError`.
+fn __kayton_exception(payload: Box<dyn ::std::any::Any + Send>) -> KaytonError {
+    let message = match payload.downcast::<KaytonError>() {
+        Ok(err) => return *err,
+        Err(payload) => match payload.downcast::<String>() {
+            Ok(s) => *s,
+            Err(payload) => payload.downcast_ref::<&str>().map(|s| s.to_string()).unwrap_or_default(),
+        },
+    };
+    KaytonError { kind: "RuntimeError", message }
+}
+
+/// Run one block of a `try` statement, catching the exception it raises.
+fn __kayton_try<T>(block: impl FnOnce() -> T) -> Result<T, KaytonError> {
+    ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(block)).map_err(__kayton_exception)
+}
+
+/// Whether `except kind:` handles `err`; `Exception` handles every kind.
+fn __kayton_catches(err: &KaytonError, kind: &str) -> bool {
+    kind == "Exception" || err.kind == kind
+}
+
+/// How a block of a `try` statement ended: `break`, `continue` and `return` inside it
+/// are carried out after the `finally` block has run.
+#[allow(dead_code)]
+enum KaytonFlow<T> {
+    Next,
+    Break,
+    Continue,
+    Return(T),
+}
+
+/// A string handed over by a plugin, laid out as `kayton_api::GlobalStrBuf`.
+#[repr(C)]
+struct KaytonStrBuf {
+    ptr: *const u8,
+    len: usize,
+    capacity: usize,
+    drop_fn: Option<fn(*const u8, usize, usize)>,
+}
+
+/// What a plugin function declared to return a `Result` returns, laid out as
+/// `kayton_api::KaytonResult`: `status` is 0 for a value, otherwise the code of a
+/// `kayton_api::ErrorKind`.
+#[repr(C)]
+struct KaytonResult<T> {
+    status: u32,
+    value: ::std::mem::MaybeUninit<T>,
+    message: KaytonStrBuf,
+}
+
+/// The value of a plugin call returning a result; an error is raised as the matching
+/// Kayton exception.
+#[allow(dead_code)]
+fn __kayton_check<T>(result: KaytonResult<T>) -> T {
+    if result.status == 0 {
+        return unsafe { result.value.assume_init() };
+    }
+    let buf = &result.message;
+    let message = if buf.ptr.is_null() {
+        String::new()
+    } else {
+        String::from_utf8_lossy(unsafe { ::std::slice::from_raw_parts(buf.ptr, buf.len) }).into_owned()
+    };
+    if let Some(drop_fn) = buf.drop_fn {
+        drop_fn(buf.ptr, buf.len, buf.capacity);
+    }
+    match result.status {
+        1 => kayton_raise("NotFoundError", message),
+        _ => kayton_raise("RuntimeError", message),
+    }
+}
+
+/// A function registered by a plugin, or a `NameError` if no loaded plugin registered it.
+#[allow(dead_code)]
+fn __kayton_plugin_fn(name: &str) -> *const c_void {
+    let ptr = unsafe { get_fn_ptr(name) };
+    if ptr.is_null() {
+        kayton_raise("NameError", ::std::format!("plugin function '{}' is not loaded", name));
+    }
+    ptr
+}
+
+fn __kayton_zero_division(message: &str) -> ! {
+    kayton_raise("ZeroDivisionError", message.to_string())
+}
+
+/// `l / r`, which is always a float division.
+fn __kayton_div(l: f64, r: f64) -> f64 {
+    if r == 0.0 {
+        __kayton_zero_division("division by zero");
+    }
+    l / r
+}
+
+/// `l // r`: like Python, the quotient rounds toward negative infinity.
+fn __kayton_floordiv(l: i64, r: i64) -> i64 {
+    let Some(q) = l.checked_div(r) else {
+        if r == 0 {
+            __kayton_zero_division("integer division or modulo by zero");
+        }
+        kayton_raise("OverflowError", "integer division result too large".to_string());
+    };
+    if l % r != 0 && (l < 0) != (r < 0) { q - 1 } else { q }
+}
+
+/// `l % r`: like Python, the remainder takes the sign of the divisor.
+fn __kayton_mod(l: i64, r: i64) -> i64 {
+    let Some(m) = l.checked_rem(r) else {
+        if r == 0 {
+            __kayton_zero_division("integer division or modulo by zero");
+        }
+        // i64::MIN % -1
+        return 0;
+    };
+    if m != 0 && (m < 0) != (r < 0) { m + r } else { m }
+}
+
+fn __kayton_floordiv_f64(l: f64, r: f64) -> f64 {
+    if r == 0.0 {
+        __kayton_zero_division("float floor division by zero");
+    }
+    (l / r).floor()
+}
+
+fn __kayton_mod_f64(l: f64, r: f64) -> f64 {
+    if r == 0.0 {
+        __kayton_zero_division("float modulo");
+    }
+    let m = l % r;
+    if m != 0.0 && (m < 0.0) != (r < 0.0) { m + r } else { m }
+}
+
+/// Resolve a Python-style index (negative counts from the end) into `0..len`.
+fn __kayton_index(len: usize, index: i64, what: &str) -> usize {
+    let i = if index < 0 { index + len as i64 } else { index };
+    if i < 0 || i >= len as i64 {
+        kayton_raise("IndexError", ::std::format!("{} index out of range", what));
+    }
+    i as usize
+}
+
+/// Resolve slice bounds; like Python, out-of-range bounds are clamped rather than an error.
+fn __kayton_bounds(len: usize, start: Option<i64>, end: Option<i64>) -> (usize, usize) {
+    let clamp = |b: i64| (if b < 0 { b + len as i64 } else { b }).clamp(0, len as i64) as usize;
+    let start = start.map_or(0, clamp);
+    let end = end.map_or(len, clamp);
+    (start, end.max(start))
+}
+
+fn __kayton_item<'a, T>(xs: &'a [T], index: i64, what: &str) -> &'a T {
+    &xs[__kayton_index(xs.len(), index, what)]
+}
+
+fn __kayton_item_mut<'a, T>(xs: &'a mut [T], index: i64, what: &str) -> &'a mut T {
+    let i = __kayton_index(xs.len(), index, what);
+    &mut xs[i]
+}
+
+fn __kayton_str_index(s: &str, index: i64) -> String {
+    let i = __kayton_index(s.chars().count(), index, "string");
+    s.chars().nth(i).map(String::from).unwrap_or_default()
+}
+
+fn __kayton_str_slice(s: &str, start: Option<i64>, end: Option<i64>) -> String {
+    let (start, end) = __kayton_bounds(s.chars().count(), start, end);
+    s.chars().skip(start).take(end - start).collect()
+}
+
+fn __kayton_list_slice<T: Clone>(xs: &[T], start: Option<i64>, end: Option<i64>) -> Vec<T> {
+    let (start, end) = __kayton_bounds(xs.len(), start, end);
+    xs[start..end].to_vec()
+}
+
+/// A string as Python's `repr` shows it: in single quotes, unless only double quotes
+/// avoid escaping one inside, with backslashes and control characters escaped.
+fn __kayton_repr_str(s: &str) -> String {
+    let quote = if s.contains('\'') && !s.contains('"') { '"' } else { '\'' };
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push(quote);
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c == quote => { out.push('\\'); out.push(c); }
+            c if c.is_control() => out.push_str(&::std::format!("\\x{:02x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push(quote);
+    out
+}
+
+/// A dict key as Python shows it in a `KeyError`: strings are quoted.
+trait KeyRepr {
+    fn key_repr(&self) -> String;
+}
+impl KeyRepr for str {
+    fn key_repr(&self) -> String { __kayton_repr_str(self) }
+}
+impl KeyRepr for i64 {
+    fn key_repr(&self) -> String { self.to_string() }
+}
+impl KeyRepr for bool {
+    fn key_repr(&self) -> String { (if *self { "True" } else { "False" }).to_string() }
+}
+
+fn __kayton_dict_get<'a, K, V, Q>(d: &'a HashMap<K, V>, key: &Q) -> &'a V
+where
+    K: ::std::borrow::Borrow<Q> + ::std::hash::Hash + Eq,
+    Q: KeyRepr + ::std::hash::Hash + Eq + ?Sized,
+{
+    match d.get(key) {
+        Some(v) => v,
+        None => kayton_raise("KeyError", key.key_repr()),
+    }
+}
+
+fn __kayton_dict_get_mut<'a, K, V, Q>(d: &'a mut HashMap<K, V>, key: &Q) -> &'a mut V
+where
+    K: ::std::borrow::Borrow<Q> + ::std::hash::Hash + Eq,
+    Q: KeyRepr + ::std::hash::Hash + Eq + ?Sized,
+{
+    match d.get_mut(key) {
+        Some(v) => v,
+        None => kayton_raise("KeyError", key.key_repr()),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn run() {
+    // Errors are reported below instead of through the default panic message
+    let previous_hook = ::std::panic::take_hook();
+    ::std::panic::set_hook(Box::new(|_| {}));
+    let result = ::std::panic::catch_unwind(kayton_main);
+    ::std::panic::set_hook(previous_hook);
+    if let Err(payload) = result {
+        let KaytonError { kind, message } = __kayton_exception(payload);
+        match unsafe { REPORT_ERROR } {
+            Some(f) => unsafe { f(kind.as_ptr(), kind.len(), message.as_ptr(), message.len()) },
+            None => ::std::eprintln!("{}: {}", kind, message),
+        }
+    }
+}
 "#;
 
     let lib_src = format!("{}\n{}", macro_header, lib_body);
@@ -193,10 +654,19 @@ fn unique_temp_lib_path(ext: &str) -> PathBuf {
 
 /// End-to-end: take source from our language, generate Rust, build dylib, return path.
 pub fn compile_lang_source_to_dylib(source: &str) -> anyhow::Result<PathBuf> {
-    let tokens = Lexer::new(source).tokenize();
-    let ast = Parser::new(tokens).parse_program();
-    let hir = lower_program(ast);
-    let mut resolved = resolve_program(&hir);
+    let text = Source::new(source);
+    let ast = parse_source(source)
+        .map_err(|errors| anyhow::anyhow!(format_parse_errors(&text, &errors, "<input>")))?;
+    let (hir, spans) = lower_program_with_spans(ast);
+    let mut resolved = resolve_program_with_spans(&hir, spans);
+    // Unresolved names are reported by the type checker as unknown variables
+    if let Some(err) = resolved
+        .errors
+        .iter()
+        .find(|e| !matches!(e, ResolveError::UnresolvedName { .. }))
+    {
+        return Err(anyhow::anyhow!(format_resolve_error(&text, err, "<input>")));
+    }
     let typed = typecheck_program(&mut resolved);
     if !typed.report.errors.is_empty() {
         return Err(anyhow::anyhow!(format!(
@@ -4,6 +4,7 @@ use std::sync::Mutex;
 
 static CAPTURED_STDOUT: Mutex<Vec<u8>> = Mutex::new(Vec::new());
 static CAPTURED_LAST_INT: Mutex<Option<i64>> = Mutex::new(None);
+static CAPTURED_ERROR: Mutex<Option<(String, String)>> = Mutex::new(None);
 
 extern "C" fn report_int(name_ptr: *const u8, name_len: usize, value: i64) {
     unsafe {
@@ -28,6 +29,22 @@ extern "C" fn report_str(name_ptr: *const u8, name_len: usize, str_ptr: *const u
     }
 }
 
+extern "C" fn report_error(
+    kind_ptr: *const u8,
+    kind_len: usize,
+    msg_ptr: *const u8,
+    msg_len: usize,
+) {
+    unsafe {
+        let kind = std::slice::from_raw_parts(kind_ptr, kind_len);
+        let msg = std::slice::from_raw_parts(msg_ptr, msg_len);
+        CAPTURED_ERROR.lock().unwrap().replace((
+            String::from_utf8_lossy(kind).into_owned(),
+            String::from_utf8_lossy(msg).into_owned(),
+        ));
+    }
+}
+
 fn take_last_int() -> i64 {
     if let Some(v) = CAPTURED_LAST_INT.lock().unwrap().take() {
         return v;
@@ -280,3 +297,782 @@ fn compile_and_run_typed_vec_append_sum() {
     let value = take_last_int();
     assert_eq!(value, 5);
 }
+
+#[test]
+fn compile_and_run_operators_follow_python_semantics() {
+    let src = r#"a = -7 // 2
+b = -7 % 3
+c = 0
+if a == -4 and b == 2 and not 3 <= 2:
+    c = a * b - 1
+c
+"#;
+    let lib_path = compile_lang_source_to_dylib(src).expect("compile to dylib");
+    unsafe {
+        let lib = Library::new(&lib_path).expect("load dylib");
+        let set_reporters: libloading::Symbol<
+            unsafe extern "C" fn(
+                extern "C" fn(*const u8, usize, i64),
+                extern "C" fn(*const u8, usize, *const u8, usize),
+            ),
+        > = lib
+            .get(b"kayton_set_reporters")
+            .expect("find reporters symbol");
+        let run: libloading::Symbol<unsafe extern "C" fn()> =
+            lib.get(b"run").expect("find run symbol");
+        CAPTURED_STDOUT.lock().unwrap().clear();
+        CAPTURED_LAST_INT.lock().unwrap().take();
+        set_reporters(report_int, report_str);
+        run();
+    }
+    let value = take_last_int();
+    assert_eq!(value, -9);
+}
+
+#[test]
+fn compile_and_run_while_with_break_and_continue() {
+    let src = r#"i = 0
+s = 0
+while True:
+    i += 1
+    if i % 2 == 0:
+        continue
+    if i > 9:
+        break
+    s += i
+s
+"#;
+    let lib_path = compile_lang_source_to_dylib(src).expect("compile to dylib");
+    unsafe {
+        let lib = Library::new(&lib_path).expect("load dylib");
+        let set_reporters: libloading::Symbol<
+            unsafe extern "C" fn(
+                extern "C" fn(*const u8, usize, i64),
+                extern "C" fn(*const u8, usize, *const u8, usize),
+            ),
+        > = lib
+            .get(b"kayton_set_reporters")
+            .expect("find reporters symbol");
+        let run: libloading::Symbol<unsafe extern "C" fn()> =
+            lib.get(b"run").expect("find run symbol");
+        CAPTURED_STDOUT.lock().unwrap().clear();
+        CAPTURED_LAST_INT.lock().unwrap().take();
+        set_reporters(report_int, report_str);
+        run();
+    }
+    let value = take_last_int();
+    assert_eq!(value, 25);
+}
+
+#[test]
+fn compile_and_run_elif_chain_with_inline_bodies() {
+    let src = r#"total = 0
+for i in 0..10: total += i
+grade = 0
+if total > 100: grade = 1
+elif total > 40:
+    grade = 2
+elif total > 10: grade = 3
+else: grade = 4
+grade
+"#;
+    let lib_path = compile_lang_source_to_dylib(src).expect("compile to dylib");
+    unsafe {
+        let lib = Library::new(&lib_path).expect("load dylib");
+        let set_reporters: libloading::Symbol<
+            unsafe extern "C" fn(
+                extern "C" fn(*const u8, usize, i64),
+                extern "C" fn(*const u8, usize, *const u8, usize),
+            ),
+        > = lib
+            .get(b"kayton_set_reporters")
+            .expect("find reporters symbol");
+        let run: libloading::Symbol<unsafe extern "C" fn()> =
+            lib.get(b"run").expect("find run symbol");
+        CAPTURED_STDOUT.lock().unwrap().clear();
+        CAPTURED_LAST_INT.lock().unwrap().take();
+        set_reporters(report_int, report_str);
+        run();
+    }
+    let value = take_last_int();
+    assert_eq!(value, 2);
+}
+
+#[test]
+fn compile_and_run_recursive_function_with_early_return() {
+    let src = r#"fn fib(n):
+    if n < 2:
+        return n
+    fib(n - 1) + fib(n - 2)
+fn first_multiple(k: i64, limit: i64) -> i64:
+    i = 1
+    while i < limit:
+        if i % k == 0:
+            return i
+        i += 1
+    -1
+fib(10) + first_multiple(7, 100)
+"#;
+    let lib_path = compile_lang_source_to_dylib(src).expect("compile to dylib");
+    unsafe {
+        let lib = Library::new(&lib_path).expect("load dylib");
+        let set_reporters: libloading::Symbol<
+            unsafe extern "C" fn(
+                extern "C" fn(*const u8, usize, i64),
+                extern "C" fn(*const u8, usize, *const u8, usize),
+            ),
+        > = lib
+            .get(b"kayton_set_reporters")
+            .expect("find reporters symbol");
+        let run: libloading::Symbol<unsafe extern "C" fn()> =
+            lib.get(b"run").expect("find run symbol");
+        CAPTURED_STDOUT.lock().unwrap().clear();
+        CAPTURED_LAST_INT.lock().unwrap().take();
+        set_reporters(report_int, report_str);
+        run();
+    }
+    let value = take_last_int();
+    assert_eq!(value, 62);
+}
+
+#[test]
+fn compile_and_run_names_first_assigned_in_branches() {
+    let src = r#"flag = 3 > 2
+if flag:
+    picked = 10
+else:
+    picked = 20
+for i in 0..4:
+    seen = i
+picked + seen
+"#;
+    let lib_path = compile_lang_source_to_dylib(src).expect("compile to dylib");
+    unsafe {
+        let lib = Library::new(&lib_path).expect("load dylib");
+        let set_reporters: libloading::Symbol<
+            unsafe extern "C" fn(
+                extern "C" fn(*const u8, usize, i64),
+                extern "C" fn(*const u8, usize, *const u8, usize),
+            ),
+        > = lib
+            .get(b"kayton_set_reporters")
+            .expect("find reporters symbol");
+        let run: libloading::Symbol<unsafe extern "C" fn()> =
+            lib.get(b"run").expect("find run symbol");
+        CAPTURED_STDOUT.lock().unwrap().clear();
+        CAPTURED_LAST_INT.lock().unwrap().take();
+        set_reporters(report_int, report_str);
+        run();
+    }
+    let value = take_last_int();
+    assert_eq!(value, 13);
+}
+
+#[test]
+fn compile_and_run_indexing_and_slicing() {
+    let src = r#"xs = [10, 20, 30, 40]
+xs[1] = xs[-1] + 2
+xs[0] += 5
+part = xs[1..3]
+word = "kayton"
+if word[-1] == "n" and word[1..3] == "ay" and word[4..] == "on":
+    part[0] = part[0] + 1
+part[0] + xs[0] + sum(xs[..2])
+"#;
+    let lib_path = compile_lang_source_to_dylib(src).expect("compile to dylib");
+    unsafe {
+        let lib = Library::new(&lib_path).expect("load dylib");
+        let set_reporters: libloading::Symbol<
+            unsafe extern "C" fn(
+                extern "C" fn(*const u8, usize, i64),
+                extern "C" fn(*const u8, usize, *const u8, usize),
+            ),
+        > = lib
+            .get(b"kayton_set_reporters")
+            .expect("find reporters symbol");
+        let run: libloading::Symbol<unsafe extern "C" fn()> =
+            lib.get(b"run").expect("find run symbol");
+        CAPTURED_STDOUT.lock().unwrap().clear();
+        CAPTURED_LAST_INT.lock().unwrap().take();
+        set_reporters(report_int, report_str);
+        run();
+    }
+    let value = take_last_int();
+    assert_eq!(value, 43 + 15 + 57);
+}
+
+#[test]
+fn compile_and_run_reports_index_error() {
+    let src = r#"xs = [1, 2]
+xs[2]
+"#;
+    let lib_path = compile_lang_source_to_dylib(src).expect("compile to dylib");
+    unsafe {
+        let lib = Library::new(&lib_path).expect("load dylib");
+        let set_error_reporter: libloading::Symbol<
+            unsafe extern "C" fn(extern "C" fn(*const u8, usize, *const u8, usize)),
+        > = lib
+            .get(b"kayton_set_error_reporter")
+            .expect("find error reporter symbol");
+        let run: libloading::Symbol<unsafe extern "C" fn()> =
+            lib.get(b"run").expect("find run symbol");
+        set_error_reporter(report_error);
+        run();
+    }
+    assert_eq!(
+        CAPTURED_ERROR.lock().unwrap().take(),
+        Some((
+            "IndexError".to_string(),
+            "list index out of range".to_string()
+        ))
+    );
+}
+
+#[test]
+fn compile_and_run_float_list_index_and_sum() {
+    let src = "ws = [0.5, 1.5]\nws[0] = 2.0\nappend(ws, 0.25)\nprint(sum(ws))\n";
+    let lib_path = compile_lang_source_to_dylib(src).expect("compile to dylib");
+    unsafe {
+        let lib = Library::new(&lib_path).expect("load dylib");
+        let set_reporters: libloading::Symbol<
+            unsafe extern "C" fn(
+                extern "C" fn(*const u8, usize, i64),
+                extern "C" fn(*const u8, usize, *const u8, usize),
+            ),
+        > = lib
+            .get(b"kayton_set_reporters")
+            .expect("find reporters symbol");
+        let run: libloading::Symbol<unsafe extern "C" fn()> =
+            lib.get(b"run").expect("find run symbol");
+        CAPTURED_STDOUT.lock().unwrap().clear();
+        set_reporters(report_int, report_str);
+        run();
+    }
+    let output = {
+        let mut lock = CAPTURED_STDOUT.lock().unwrap();
+        let s = String::from_utf8(lock.clone()).expect("utf8");
+        lock.clear();
+        s
+    };
+    assert_eq!(output.trim_end(), "3.75");
+}
+
+#[test]
+fn compile_and_run_dict_lookup_membership_and_values() {
+    let src = r#"d = {"a": 1, "b": 2}
+d["c"] = d["a"] + d["b"]
+d["a"] += 10
+total = sum(d.values())
+if "c" in d and "z" not in d:
+    total = total + 100
+seen = {}
+seen[3] = True
+if 3 in seen and seen[3]:
+    total = total + 1000
+total
+"#;
+    let lib_path = compile_lang_source_to_dylib(src).expect("compile to dylib");
+    unsafe {
+        let lib = Library::new(&lib_path).expect("load dylib");
+        let set_reporters: libloading::Symbol<
+            unsafe extern "C" fn(
+                extern "C" fn(*const u8, usize, i64),
+                extern "C" fn(*const u8, usize, *const u8, usize),
+            ),
+        > = lib
+            .get(b"kayton_set_reporters")
+            .expect("find reporters symbol");
+        let run: libloading::Symbol<unsafe extern "C" fn()> =
+            lib.get(b"run").expect("find run symbol");
+        CAPTURED_STDOUT.lock().unwrap().clear();
+        CAPTURED_LAST_INT.lock().unwrap().take();
+        set_reporters(report_int, report_str);
+        run();
+    }
+    assert_eq!(take_last_int(), 11 + 2 + 3 + 100 + 1000);
+}
+
+#[test]
+fn compile_and_run_tuple_swap_and_unpacking_loop() {
+    let src = r#"fn divmod_(a, b):
+    return a // b, a % b
+a, b = 1, 20
+a, b = b, a
+q, r = divmod_(a, 6)
+pair = (q, "x")
+total = a * 100 + pair[0] * 10 + r
+for k, v in items({"x": 1000}):
+    if k == pair[1]:
+        total = total + v
+total
+"#;
+    let lib_path = compile_lang_source_to_dylib(src).expect("compile to dylib");
+    unsafe {
+        let lib = Library::new(&lib_path).expect("load dylib");
+        let set_reporters: libloading::Symbol<
+            unsafe extern "C" fn(
+                extern "C" fn(*const u8, usize, i64),
+                extern "C" fn(*const u8, usize, *const u8, usize),
+            ),
+        > = lib
+            .get(b"kayton_set_reporters")
+            .expect("find reporters symbol");
+        let run: libloading::Symbol<unsafe extern "C" fn()> =
+            lib.get(b"run").expect("find run symbol");
+        CAPTURED_STDOUT.lock().unwrap().clear();
+        CAPTURED_LAST_INT.lock().unwrap().take();
+        set_reporters(report_int, report_str);
+        run();
+    }
+    // a = 20, b = 1, divmod_(20, 6) = (3, 2)
+    assert_eq!(take_last_int(), 2000 + 30 + 2 + 1000);
+}
+
+#[test]
+fn compile_and_run_for_in_over_lists_strings_dicts_and_ranges() {
+    let src = r#"xs = [1, 2, 3]
+total = 0
+for x in xs:
+    append(xs, x)
+    total += x
+for c in "abc":
+    if c == "b":
+        total += 10
+ages = {"ann": 1, "bob": 2}
+for name in ages:
+    total += ages[name] * 100
+r = 1..4
+for i in r:
+    total += i * 1000
+if 3 in r and 4 not in r:
+    total += sum(xs) // 12 * 10000
+total
+"#;
+    let lib_path = compile_lang_source_to_dylib(src).expect("compile to dylib");
+    unsafe {
+        let lib = Library::new(&lib_path).expect("load dylib");
+        let set_reporters: libloading::Symbol<
+            unsafe extern "C" fn(
+                extern "C" fn(*const u8, usize, i64),
+                extern "C" fn(*const u8, usize, *const u8, usize),
+            ),
+        > = lib
+            .get(b"kayton_set_reporters")
+            .expect("find reporters symbol");
+        let run: libloading::Symbol<unsafe extern "C" fn()> =
+            lib.get(b"run").expect("find run symbol");
+        CAPTURED_STDOUT.lock().unwrap().clear();
+        CAPTURED_LAST_INT.lock().unwrap().take();
+        set_reporters(report_int, report_str);
+        run();
+    }
+    // The loop over `xs` sees the three items it started with, so `xs` doubles to sum 12
+    assert_eq!(take_last_int(), 6 + 10 + 300 + 6000 + 10000);
+}
+
+#[test]
+fn compile_and_run_nested_list_assignment() {
+    let src = "grid = [[1, 2], [3]]\ngrid[1][0] = 30\ngrid[0][-1] += 5\ngrid[0][0] + grid[0][1] + grid[1][0]\n";
+    let lib_path = compile_lang_source_to_dylib(src).expect("compile to dylib");
+    unsafe {
+        let lib = Library::new(&lib_path).expect("load dylib");
+        let set_reporters: libloading::Symbol<
+            unsafe extern "C" fn(
+                extern "C" fn(*const u8, usize, i64),
+                extern "C" fn(*const u8, usize, *const u8, usize),
+            ),
+        > = lib
+            .get(b"kayton_set_reporters")
+            .expect("find reporters symbol");
+        let run: libloading::Symbol<unsafe extern "C" fn()> =
+            lib.get(b"run").expect("find run symbol");
+        CAPTURED_STDOUT.lock().unwrap().clear();
+        CAPTURED_LAST_INT.lock().unwrap().take();
+        set_reporters(report_int, report_str);
+        run();
+    }
+    assert_eq!(take_last_int(), 1 + 7 + 30);
+}
+
+#[test]
+fn compile_and_run_assignment_into_dict_of_lists() {
+    let src = "d = {\"a\": [1, 2], \"b\": [3]}\nd[\"a\"][0] = 5\nd[\"b\"][-1] += 27\nrows = [{\"n\": 1}]\nrows[0][\"n\"] = 100\nd[\"a\"][0] + d[\"a\"][1] + d[\"b\"][0] + rows[0][\"n\"]\n";
+    let lib_path = compile_lang_source_to_dylib(src).expect("compile to dylib");
+    unsafe {
+        let lib = Library::new(&lib_path).expect("load dylib");
+        let set_reporters: libloading::Symbol<
+            unsafe extern "C" fn(
+                extern "C" fn(*const u8, usize, i64),
+                extern "C" fn(*const u8, usize, *const u8, usize),
+            ),
+        > = lib
+            .get(b"kayton_set_reporters")
+            .expect("find reporters symbol");
+        let run: libloading::Symbol<unsafe extern "C" fn()> =
+            lib.get(b"run").expect("find run symbol");
+        CAPTURED_STDOUT.lock().unwrap().clear();
+        CAPTURED_LAST_INT.lock().unwrap().take();
+        set_reporters(report_int, report_str);
+        run();
+    }
+    assert_eq!(take_last_int(), 5 + 2 + 30 + 100);
+}
+
+#[test]
+fn compile_and_run_reports_key_error() {
+    let src = "d = {\"a\": 1}\nd[\"b\"]\n";
+    let lib_path = compile_lang_source_to_dylib(src).expect("compile to dylib");
+    unsafe {
+        let lib = Library::new(&lib_path).expect("load dylib");
+        let set_error_reporter: libloading::Symbol<
+            unsafe extern "C" fn(extern "C" fn(*const u8, usize, *const u8, usize)),
+        > = lib
+            .get(b"kayton_set_error_reporter")
+            .expect("find error reporter symbol");
+        let run: libloading::Symbol<unsafe extern "C" fn()> =
+            lib.get(b"run").expect("find run symbol");
+        set_error_reporter(report_error);
+        run();
+    }
+    assert_eq!(
+        CAPTURED_ERROR.lock().unwrap().take(),
+        Some(("KeyError".to_string(), "'b'".to_string()))
+    );
+}
+
+#[test]
+fn compile_and_run_struct_fields_and_methods() {
+    let src = r#"struct Point:
+    x: i64
+    y: i64
+    fn shifted(self, dx: i64) -> Point:
+        return Point(x=self.x + dx, y=self.y)
+    fn move_by(self, dy):
+        self.y += dy
+    fn norm1(self) -> i64:
+        return self.x + self.y
+struct Line:
+    start: Point
+    tags: list[i64]
+p = Point(x=1, y=2)
+q = p.shifted(10)
+q.move_by(5)
+line = Line(start=p, tags=[])
+line.start.x = 100
+append(line.tags, 7)
+q.norm1() * 1000 + p.x * 100 + line.start.norm1() + line.tags[0]
+"#;
+    let lib_path = compile_lang_source_to_dylib(src).expect("compile to dylib");
+    unsafe {
+        let lib = Library::new(&lib_path).expect("load dylib");
+        let set_reporters: libloading::Symbol<
+            unsafe extern "C" fn(
+                extern "C" fn(*const u8, usize, i64),
+                extern "C" fn(*const u8, usize, *const u8, usize),
+            ),
+        > = lib
+            .get(b"kayton_set_reporters")
+            .expect("find reporters symbol");
+        let run: libloading::Symbol<unsafe extern "C" fn()> =
+            lib.get(b"run").expect("find run symbol");
+        CAPTURED_STDOUT.lock().unwrap().clear();
+        CAPTURED_LAST_INT.lock().unwrap().take();
+        set_reporters(report_int, report_str);
+        run();
+    }
+    // q = Point(11, 7); `line` holds its own copy of `p`, so `p.x` stays 1
+    assert_eq!(take_last_int(), 18 * 1000 + 100 + 102 + 7);
+}
+
+#[test]
+fn compile_and_run_enums_and_match() {
+    let src = r#"enum Shape:
+    Circle(i64)
+    Rect(i64, i64)
+    Empty
+fn area(s: Shape) -> i64:
+    match s:
+        case Shape.Circle(r):
+            return 3 * r * r
+        case Shape.Rect(w, h):
+            return w * h
+        case Shape.Empty:
+            return 0
+fn code(word: str, n: i64) -> i64:
+    match word, n:
+        case "hi", 0:
+            return 1
+        case "hi", _:
+            return 2
+        case _, -1:
+            return 3
+        case _:
+            return 4
+shapes = [Shape.Circle(1), Shape.Rect(2, 3), Shape.Empty]
+total = 0
+for s in shapes:
+    total += area(s)
+match shapes[1] == Shape.Rect(2, 3):
+    case True:
+        bonus = 100
+    case False:
+        bonus = 0
+total + bonus + code("hi", 0) * 1000 + code("hi", 5) * 10000 + code("yo", -1) * 100000 + code("yo", 1) * 1000000
+"#;
+    let lib_path = compile_lang_source_to_dylib(src).expect("compile to dylib");
+    unsafe {
+        let lib = Library::new(&lib_path).expect("load dylib");
+        let set_reporters: libloading::Symbol<
+            unsafe extern "C" fn(
+                extern "C" fn(*const u8, usize, i64),
+                extern "C" fn(*const u8, usize, *const u8, usize),
+            ),
+        > = lib
+            .get(b"kayton_set_reporters")
+            .expect("find reporters symbol");
+        let run: libloading::Symbol<unsafe extern "C" fn()> =
+            lib.get(b"run").expect("find run symbol");
+        CAPTURED_STDOUT.lock().unwrap().clear();
+        CAPTURED_LAST_INT.lock().unwrap().take();
+        set_reporters(report_int, report_str);
+        run();
+    }
+    assert_eq!(take_last_int(), 4321109);
+}
+
+#[test]
+fn compile_and_run_optional_values() {
+    let src = r#"struct Node:
+    value: i64
+    next: i64?
+fn find(xs: list[i64], target: i64) -> i64?:
+    i = 0
+    for x in xs:
+        if x == target:
+            return i
+        i += 1
+    return None
+fn or_zero(x: i64?) -> i64:
+    if x is None:
+        return 0
+    else:
+        return x
+fn label(name: str?) -> str:
+    if name is not None:
+        return name
+    return "anon"
+total = or_zero(find([5, 7, 9], 9)) * 10 + or_zero(find([5, 7, 9], 4))
+c: i64? = None
+if c is None:
+    c = 3
+hits = 0
+if c is not None:
+    hits = c
+count = 0
+k: i64? = 5
+while k is not None:
+    count += k
+    if k == 1:
+        k = None
+    else:
+        k = k - 1
+n = Node(value=1, next=None)
+n.next = 4
+bonus = 0
+if label("bo") == "bo" and label(None) == "anon":
+    bonus = 1000000
+bonus + total * 10000 + hits * 1000 + count * 10 + or_zero(n.next)
+"#;
+    let lib_path = compile_lang_source_to_dylib(src).expect("compile to dylib");
+    unsafe {
+        let lib = Library::new(&lib_path).expect("load dylib");
+        let set_reporters: libloading::Symbol<
+            unsafe extern "C" fn(
+                extern "C" fn(*const u8, usize, i64),
+                extern "C" fn(*const u8, usize, *const u8, usize),
+            ),
+        > = lib
+            .get(b"kayton_set_reporters")
+            .expect("find reporters symbol");
+        let run: libloading::Symbol<unsafe extern "C" fn()> =
+            lib.get(b"run").expect("find run symbol");
+        CAPTURED_STDOUT.lock().unwrap().clear();
+        CAPTURED_LAST_INT.lock().unwrap().take();
+        set_reporters(report_int, report_str);
+        run();
+    }
+    assert_eq!(take_last_int(), 1203154);
+}
+
+#[test]
+fn compile_and_run_try_except_finally() {
+    let src = r#"fn safe_div(a: i64, b: i64) -> i64:
+    try:
+        return a // b
+    except ZeroDivisionError:
+        return -1
+fn first(xs: list[i64]) -> i64:
+    try:
+        return xs[0]
+    except IndexError as e:
+        if e == "list index out of range":
+            return 2
+        return 3
+fn check(n: i64) -> i64:
+    if n < 0:
+        raise ValueError("negative")
+    return n
+fn guarded(n: i64) -> i64:
+    try:
+        return check(n)
+    except ValueError:
+        return 10
+found = 0
+finals = 0
+for i in 0..10:
+    try:
+        if i == 2:
+            continue
+        if i == 5:
+            break
+        found += i
+    finally:
+        finals += 1
+d = {"a": 1}
+missing = 0
+try:
+    missing = d["b"]
+except KeyError:
+    missing = 7
+which = 0
+try:
+    d["a"] = [1][3]
+except KeyError:
+    which = 1
+except IndexError:
+    which = 2
+caught = 0
+try:
+    try:
+        raise NotFoundError("gone")
+    except NotFoundError:
+        raise
+except Exception as e:
+    if e == "gone":
+        caught = 1
+safe_div(7, 2) + safe_div(1, 0) * 10 + first([]) * 100 + guarded(-5) * 1000 + guarded(4) * 100000 + found * 1000000 + finals * 10000000 + missing * 100000000 + which * 1000000000 + caught * 10000000000
+"#;
+    let lib_path = compile_lang_source_to_dylib(src).expect("compile to dylib");
+    unsafe {
+        let lib = Library::new(&lib_path).expect("load dylib");
+        let set_reporters: libloading::Symbol<
+            unsafe extern "C" fn(
+                extern "C" fn(*const u8, usize, i64),
+                extern "C" fn(*const u8, usize, *const u8, usize),
+            ),
+        > = lib
+            .get(b"kayton_set_reporters")
+            .expect("find reporters symbol");
+        let run: libloading::Symbol<unsafe extern "C" fn()> =
+            lib.get(b"run").expect("find run symbol");
+        CAPTURED_STDOUT.lock().unwrap().clear();
+        CAPTURED_LAST_INT.lock().unwrap().take();
+        set_reporters(report_int, report_str);
+        run();
+    }
+    assert_eq!(take_last_int(), 12_768_410_193);
+}
+
+#[test]
+fn compile_and_run_reports_raised_error() {
+    let src = "fn parse(s: str) -> i64:\n    raise ValueError(f\"bad input: {s}\")\nparse(\"x\")\n";
+    let lib_path = compile_lang_source_to_dylib(src).expect("compile to dylib");
+    unsafe {
+        let lib = Library::new(&lib_path).expect("load dylib");
+        let set_error_reporter: libloading::Symbol<
+            unsafe extern "C" fn(extern "C" fn(*const u8, usize, *const u8, usize)),
+        > = lib
+            .get(b"kayton_set_error_reporter")
+            .expect("find error reporter symbol");
+        let run: libloading::Symbol<unsafe extern "C" fn()> =
+            lib.get(b"run").expect("find run symbol");
+        set_error_reporter(report_error);
+        run();
+    }
+    assert_eq!(
+        CAPTURED_ERROR.lock().unwrap().take(),
+        Some(("ValueError".to_string(), "bad input: x".to_string()))
+    );
+}
+
+#[test]
+fn compile_and_run_lambdas_and_function_values() {
+    let src = r#"fn compose(f: fn(i64) -> i64, g: fn(i64) -> i64) -> fn(i64) -> i64:
+    return fn(x) => g(f(x))
+fn inc(x):
+    return x + 1
+weights = [10, 20, 30]
+weigh = fn(i: i64) => weights[i]
+weights = [0, 0, 0]
+twice = compose(inc, fn(x) => x * 2)
+picked = filter(map([1, 2, 3, 4], twice), fn(x) => x > 5)
+ordered = sorted(["bob", "al", "cy"], fn(n) => n)
+ok = 0
+if ordered[0] == "al":
+    ok = 1
+weigh(1) + twice(3) * 100 + sum(picked) * 1000 + ok * 100000
+"#;
+    let lib_path = compile_lang_source_to_dylib(src).expect("compile to dylib");
+    unsafe {
+        let lib = Library::new(&lib_path).expect("load dylib");
+        let set_reporters: libloading::Symbol<
+            unsafe extern "C" fn(
+                extern "C" fn(*const u8, usize, i64),
+                extern "C" fn(*const u8, usize, *const u8, usize),
+            ),
+        > = lib
+            .get(b"kayton_set_reporters")
+            .expect("find reporters symbol");
+        let run: libloading::Symbol<unsafe extern "C" fn()> =
+            lib.get(b"run").expect("find run symbol");
+        CAPTURED_STDOUT.lock().unwrap().clear();
+        CAPTURED_LAST_INT.lock().unwrap().take();
+        set_reporters(report_int, report_str);
+        run();
+    }
+    // The lambda sees `weights` as reassigned after it was created
+    assert_eq!(take_last_int(), 124_800);
+}
+
+#[test]
+fn compile_and_run_string_operations() {
+    let src = r#"s = "a" + "b\tc"
+words = "x, y ,z".split(",")
+joined = "-".join(map(words, fn(w) => w.strip().upper()))
+text = """two
+lines"""
+ok = 0
+if "abc" < "abd" and s >= "a" and joined.lower().replace("-", "") == "xyz" and text.startswith("two"):
+    ok = 1
+len(joined) + len(words) * 10 + "hello".find("ll") * 100 + len(s) * 1000 + ok * 10000
+"#;
+    let lib_path = compile_lang_source_to_dylib(src).expect("compile to dylib");
+    unsafe {
+        let lib = Library::new(&lib_path).expect("load dylib");
+        let set_reporters: libloading::Symbol<
+            unsafe extern "C" fn(
+                extern "C" fn(*const u8, usize, i64),
+                extern "C" fn(*const u8, usize, *const u8, usize),
+            ),
+        > = lib
+            .get(b"kayton_set_reporters")
+            .expect("find reporters symbol");
+        let run: libloading::Symbol<unsafe extern "C" fn()> =
+            lib.get(b"run").expect("find run symbol");
+        CAPTURED_STDOUT.lock().unwrap().clear();
+        CAPTURED_LAST_INT.lock().unwrap().take();
+        set_reporters(report_int, report_str);
+        run();
+    }
+    assert_eq!(take_last_int(), 14_235);
+}
@@ -1,46 +1,375 @@
-use crate::shir::resolver::ResolvedProgram;
+use unicode_width::UnicodeWidthStr;
+
+use crate::parser::ParseError;
+use crate::shir::resolver::{ResolveError, ResolvedProgram};
+use crate::shir::sym::Type;
+use crate::span::{LineIndex, Span};
 use crate::thir::types::TypeError;
 
-fn highlight_var_in_source(source: &str, var_name: &str, indent: &str) -> (String, usize) {
-    let pos_opt = source.find(var_name);
-    let highlighted_code_line = if let Some(p) = pos_opt {
-        let before = &source[..p];
-        let after = &source[p + var_name.len()..];
-        format!("{}{}\x1b[31m{}\x1b[0m{}", indent, before, var_name, after)
+/// The text diagnostics point into. A REPL input is compiled after the definitions kept
+/// from earlier inputs; `input_start` is where the input itself begins, and its lines are
+/// numbered from there, as the user typed them.
+#[derive(Debug, Clone, Copy)]
+pub struct Source<'a> {
+    pub text: &'a str,
+    pub input_start: usize,
+}
+
+impl<'a> Source<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            input_start: 0,
+        }
+    }
+
+    /// `text` whose first `input_start` bytes are definitions from earlier inputs.
+    pub fn with_input_start(text: &'a str, input_start: usize) -> Self {
+        Self { text, input_start }
+    }
+}
+
+/// Render a Python-style traceback pointing at `span` in `source`.
+/// The offending range is highlighted in red and underlined with carets.
+fn render_at(source: &Source, span: Span, file_label: &str, kind: &str, message: &str) -> String {
+    let index = LineIndex::new(source.text);
+    let (line, _) = index.line_col(span.start);
+    let line_range = index.line_range(line);
+    let line_text = source.text[line_range.start..line_range.end].trim_end_matches('\r');
+    // A span before the input points into a stored definition
+    let (file_label, line_no) = if span.start >= source.input_start {
+        (file_label, line + 1 - index.line_col(source.input_start).0)
     } else {
-        format!("{}{}", indent, source)
+        ("<stored definitions>", line)
     };
-    let pos = pos_opt.unwrap_or(0);
-    (highlighted_code_line, pos)
+
+    // Clamp the highlight to the first line of the span
+    let hl_start = (span.start - line_range.start).min(line_text.len());
+    let hl_end = (span.end.max(span.start) - line_range.start)
+        .min(line_text.len())
+        .max(hl_start);
+    let before = &line_text[..hl_start];
+    let marked = &line_text[hl_start..hl_end];
+    let after = &line_text[hl_end..];
+
+    let indent = "    ";
+    let mut out = String::new();
+    out.push_str("\x1b[31mCompilation failed:\x1b[0m\n");
+    out.push_str(&format!(
+        "  File \"{}\", line {}, in <module>\n",
+        file_label, line_no
+    ));
+    out.push_str(&format!(
+        "{}{}\x1b[31m{}\x1b[0m{}\n",
+        indent, before, marked, after
+    ));
+    // Carets go by terminal columns, where CJK characters take two and combining marks none
+    out.push_str(&format!(
+        "{}\x1b[31m{}\x1b[0m\n",
+        " ".repeat(indent.len() + before.width()),
+        "^".repeat(marked.width().max(1))
+    ));
+    out.push_str(&format!("\x1b[1;31m{}\x1b[0m: {}", kind, message));
+    out
 }
 
 pub fn format_type_error(
-    source: &str,
+    source: &Source,
     resolved: &ResolvedProgram,
     err: &TypeError,
     file_label: &str,
-) -> Option<String> {
+) -> String {
+    let span_of = |hir_id| resolved.spans.get(hir_id).copied().unwrap_or_default();
     match err {
-        TypeError::UnknownVarType { sym, .. } => {
+        TypeError::UnknownVarType { hir_id, sym } => {
             let var_name = &resolved.symbols.infos[sym.0 as usize].name;
-            let indent = "    ";
-            let (highlighted, pos) = highlight_var_in_source(source, var_name, indent);
-            let caret_pos = indent.len() + pos;
-            let caret_line = format!("{}\x1b[31m^\x1b[0m", " ".repeat(caret_pos));
-
-            let mut out = String::new();
-            out.push_str("\x1b[31mCompilation failed:\x1b[0m\n");
-            out.push_str(&format!("  File \"{}\", line 1, in <module>\n", file_label));
-            out.push_str(&highlighted);
-            out.push('\n');
-            out.push_str(&caret_line);
-            out.push('\n');
-            out.push_str(&format!(
-                "\x1b[1;31mNameError\x1b[0m: name '{}' is not defined",
-                var_name
-            ));
-            Some(out)
+            render_at(
+                source,
+                span_of(hir_id),
+                file_label,
+                "NameError",
+                &format!("name '{}' is not defined", var_name),
+            )
+        }
+        TypeError::TypeMismatch {
+            hir_id,
+            expected,
+            found,
+        } => render_at(
+            source,
+            span_of(hir_id),
+            file_label,
+            "TypeError",
+            &format!("expected {}, found {}", expected, found),
+        ),
+        TypeError::NotCallable { hir_id, callee } => render_at(
+            source,
+            span_of(hir_id),
+            file_label,
+            "TypeError",
+            &format!("'{}' is not callable", callee),
+        ),
+        TypeError::ArityMismatch {
+            hir_id,
+            expected,
+            found,
+        } => render_at(
+            source,
+            span_of(hir_id),
+            file_label,
+            "TypeError",
+            &format!("expected {} arguments, found {}", expected, found),
+        ),
+        TypeError::ShadowingImpossible { hir_id, var_name } => render_at(
+            source,
+            span_of(hir_id),
+            file_label,
+            "TypeError",
+            &format!("'{}' cannot change its type inside a loop", var_name),
+        ),
+        TypeError::MissingReturn { hir_id, func } => render_at(
+            source,
+            span_of(hir_id),
+            file_label,
+            "TypeError",
+            &format!("function '{}' does not return a value on every path", func),
+        ),
+        TypeError::CannotInferParam { hir_id, param } => render_at(
+            source,
+            span_of(hir_id),
+            file_label,
+            "TypeError",
+            &format!(
+                "cannot infer the type of parameter '{}'; add an annotation",
+                param
+            ),
+        ),
+        TypeError::NotIndexable { hir_id, ty } => render_at(
+            source,
+            span_of(hir_id),
+            file_label,
+            "TypeError",
+            &format!("'{}' object is not subscriptable", ty),
+        ),
+        TypeError::ItemAssignment { hir_id, ty } => render_at(
+            source,
+            span_of(hir_id),
+            file_label,
+            "TypeError",
+            &format!("'{}' object does not support item assignment", ty),
+        ),
+        TypeError::UnhashableKey { hir_id, ty } => render_at(
+            source,
+            span_of(hir_id),
+            file_label,
+            "TypeError",
+            &format!("unhashable type: '{}'", ty),
+        ),
+        TypeError::NotContainer { hir_id, ty } => render_at(
+            source,
+            span_of(hir_id),
+            file_label,
+            "TypeError",
+            &format!("argument of type '{}' is not iterable", ty),
+        ),
+        TypeError::BadFormat { hir_id, option, ty } => render_at(
+            source,
+            span_of(hir_id),
+            file_label,
+            "TypeError",
+            &format!(
+                "format option '{}' does not apply to a value of type '{}'",
+                option, ty
+            ),
+        ),
+        TypeError::NoLen { hir_id, ty } => render_at(
+            source,
+            span_of(hir_id),
+            file_label,
+            "TypeError",
+            &format!("object of type '{}' has no len()", ty),
+        ),
+        TypeError::NotIterable { hir_id, ty } => render_at(
+            source,
+            span_of(hir_id),
+            file_label,
+            "TypeError",
+            &format!("'{}' object is not iterable", ty),
+        ),
+        TypeError::UnpackMismatch {
+            hir_id,
+            expected,
+            found,
+        } => {
+            let message = match found {
+                Type::Tuple(items) if items.len() < *expected => format!(
+                    "not enough values to unpack (expected {}, got {})",
+                    expected,
+                    items.len()
+                ),
+                Type::Tuple(_) => format!("too many values to unpack (expected {})", expected),
+                other => format!("cannot unpack non-tuple type '{}'", other),
+            };
+            render_at(source, span_of(hir_id), file_label, "ValueError", &message)
         }
-        _ => None,
+        TypeError::TupleIndex { hir_id, len } => render_at(
+            source,
+            span_of(hir_id),
+            file_label,
+            "IndexError",
+            &format!(
+                "tuple index must be an integer literal below the tuple length {}",
+                len
+            ),
+        ),
+        TypeError::NoAttribute { hir_id, ty, name } => render_at(
+            source,
+            span_of(hir_id),
+            file_label,
+            "AttributeError",
+            &format!("'{}' object has no attribute '{}'", ty, name),
+        ),
+        TypeError::MissingField {
+            hir_id,
+            strukt,
+            fields,
+        } => {
+            let quoted: Vec<String> = fields.iter().map(|f| format!("'{}'", f)).collect();
+            let noun = if fields.len() == 1 {
+                "argument"
+            } else {
+                "arguments"
+            };
+            render_at(
+                source,
+                span_of(hir_id),
+                file_label,
+                "TypeError",
+                &format!(
+                    "{}() missing required {}: {}",
+                    strukt,
+                    noun,
+                    quoted.join(", ")
+                ),
+            )
+        }
+        TypeError::UnexpectedField {
+            hir_id,
+            strukt,
+            field,
+        } => render_at(
+            source,
+            span_of(hir_id),
+            file_label,
+            "TypeError",
+            &format!(
+                "{}() got an unexpected keyword argument '{}'",
+                strukt, field
+            ),
+        ),
+        TypeError::NonExhaustiveMatch {
+            hir_id,
+            ty,
+            missing,
+        } => render_at(
+            source,
+            span_of(hir_id),
+            file_label,
+            "TypeError",
+            &format!("match on '{}' does not handle {}", ty, missing),
+        ),
     }
 }
+
+pub fn format_resolve_error(source: &Source, err: &ResolveError, file_label: &str) -> String {
+    match err {
+        ResolveError::UnresolvedName { span, name } => render_at(
+            source,
+            *span,
+            file_label,
+            "NameError",
+            &format!("name '{}' is not defined", name),
+        ),
+        ResolveError::ImportError { span, message } => {
+            let message = message.trim_start_matches("ImportError: ");
+            render_at(source, *span, file_label, "ImportError", message)
+        }
+        ResolveError::OutsideLoop { span, keyword } => render_at(
+            source,
+            *span,
+            file_label,
+            "SyntaxError",
+            &format!("'{}' outside loop", keyword),
+        ),
+        ResolveError::OutsideFunction { span } => render_at(
+            source,
+            *span,
+            file_label,
+            "SyntaxError",
+            "'return' outside function",
+        ),
+        ResolveError::CapturedVariable { span, name } => render_at(
+            source,
+            *span,
+            file_label,
+            "NameError",
+            &format!(
+                "function cannot use '{}' from an enclosing scope; pass it as a parameter",
+                name
+            ),
+        ),
+        ResolveError::UnknownType { span, name } => render_at(
+            source,
+            *span,
+            file_label,
+            "TypeError",
+            &format!("unknown type '{}'", name),
+        ),
+        ResolveError::UnknownException { span, name } => render_at(
+            source,
+            *span,
+            file_label,
+            "NameError",
+            &format!("unknown exception kind '{}'", name),
+        ),
+        ResolveError::ReraiseOutsideExcept { span } => render_at(
+            source,
+            *span,
+            file_label,
+            "SyntaxError",
+            "bare 'raise' outside except",
+        ),
+        ResolveError::PositionalFields {
+            span,
+            strukt,
+            fields,
+        } => {
+            let named: Vec<String> = fields.iter().map(|f| format!("{}=...", f)).collect();
+            render_at(
+                source,
+                *span,
+                file_label,
+                "TypeError",
+                &format!(
+                    "{}() takes keyword arguments only; name its fields: {}({})",
+                    strukt,
+                    strukt,
+                    named.join(", ")
+                ),
+            )
+        }
+    }
+}
+
+pub fn format_parse_error(source: &Source, err: &ParseError, file_label: &str) -> String {
+    render_at(source, err.span, file_label, "SyntaxError", &err.message())
+}
+
+/// Render every syntax error of one compilation, separated by blank lines.
+pub fn format_parse_errors(source: &Source, errors: &[ParseError], file_label: &str) -> String {
+    errors
+        .iter()
+        .map(|err| format_parse_error(source, err, file_label))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
@@ -0,0 +1,159 @@
+//! Format specifications of f-string fields, as in `f"{ratio!r:>8.2f}"`.
+//!
+//! The Python mini-language is parsed here and mapped onto the placeholders of Rust's
+//! `format!`. Parts that Rust cannot express, such as `=` alignment or thousands
+//! separators, are rejected when the f-string is lexed.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+/// Everything after the expression of an f-string field. The default formats the
+/// value the way `print` does.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FormatSpec {
+    /// `!r`: format the value's repr, e.g. strings with their quotes
+    pub repr: bool,
+    pub fill: Option<char>,
+    pub align: Option<Align>,
+    /// `+`: show the sign of positive numbers too
+    pub plus: bool,
+    /// `#`: prefix `0x`, `0o` or `0b` to integers in those bases
+    pub alternate: bool,
+    /// `0`: pad numbers with zeros after the sign
+    pub zero: bool,
+    pub width: Option<usize>,
+    pub precision: Option<usize>,
+    /// The presentation type: one of `d`, `s`, `f`, `F`, `x`, `X`, `o` and `b`
+    pub kind: Option<char>,
+}
+
+impl FormatSpec {
+    /// Parse the text after `:` in a field; the error describes the first problem.
+    pub fn parse(text: &str) -> Result<FormatSpec, String> {
+        let mut spec = FormatSpec::default();
+        let chars: Vec<char> = text.chars().collect();
+        let mut i = 0;
+        let align_of = |c: Option<&char>| match c {
+            Some('<') => Some(Align::Left),
+            Some('>') => Some(Align::Right),
+            Some('^') => Some(Align::Center),
+            _ => None,
+        };
+        if let Some(align) = align_of(chars.get(1)) {
+            spec.fill = Some(chars[0]);
+            spec.align = Some(align);
+            i = 2;
+        } else if let Some(align) = align_of(chars.first()) {
+            spec.align = Some(align);
+            i = 1;
+        }
+        if i == 0 && (chars.first() == Some(&'=') || chars.get(1) == Some(&'=')) {
+            return Err("'=' alignment is not supported".to_string());
+        }
+        match chars.get(i) {
+            Some('+') => {
+                spec.plus = true;
+                i += 1;
+            }
+            Some('-') => i += 1,
+            Some(' ') => return Err("the ' ' sign option is not supported".to_string()),
+            _ => {}
+        }
+        if chars.get(i) == Some(&'#') {
+            spec.alternate = true;
+            i += 1;
+        }
+        if chars.get(i) == Some(&'0') {
+            spec.zero = true;
+            i += 1;
+        }
+        spec.width = digits(&chars, &mut i);
+        if matches!(chars.get(i), Some(',' | '_')) {
+            return Err("thousands separators are not supported".to_string());
+        }
+        if chars.get(i) == Some(&'.') {
+            i += 1;
+            spec.precision = digits(&chars, &mut i);
+            if spec.precision.is_none() {
+                return Err("expected a precision after '.'".to_string());
+            }
+        }
+        if let Some(&c) = chars.get(i) {
+            if !matches!(c, 'd' | 's' | 'f' | 'F' | 'x' | 'X' | 'o' | 'b') {
+                return Err(format!("unknown format type '{}'", c));
+            }
+            spec.kind = Some(c);
+            i += 1;
+        }
+        if i < chars.len() {
+            return Err(format!("invalid format specifier '{}'", text));
+        }
+        let integer_kind = matches!(spec.kind, Some('d' | 'x' | 'X' | 'o' | 'b'));
+        if integer_kind && spec.precision.is_some() {
+            return Err("precision is not allowed with integer format types".to_string());
+        }
+        if spec.alternate && !matches!(spec.kind, Some('x' | 'X' | 'o' | 'b')) {
+            return Err("'#' needs an 'x', 'o' or 'b' format type".to_string());
+        }
+        Ok(spec)
+    }
+
+    /// Whether the spec has options besides `!r`, such as a width.
+    pub fn has_options(&self) -> bool {
+        FormatSpec {
+            repr: false,
+            ..self.clone()
+        } != FormatSpec::default()
+    }
+
+    /// The options part of a Rust `format!` placeholder, after the `:`.
+    pub fn rust_options(&self) -> String {
+        let mut out = String::new();
+        if let Some(align) = self.align {
+            if let Some(fill) = self.fill {
+                out.push(fill);
+            }
+            out.push(match align {
+                Align::Left => '<',
+                Align::Right => '>',
+                Align::Center => '^',
+            });
+        }
+        if self.plus {
+            out.push('+');
+        }
+        if self.alternate {
+            out.push('#');
+        }
+        if self.zero {
+            out.push('0');
+        }
+        if let Some(width) = self.width {
+            out.push_str(&width.to_string());
+        }
+        // Fixed-point formats show six decimals unless told otherwise, as in Python
+        let precision = match self.kind {
+            Some('f' | 'F') => Some(self.precision.unwrap_or(6)),
+            _ => self.precision,
+        };
+        if let Some(precision) = precision {
+            out.push_str(&format!(".{}", precision));
+        }
+        if let Some(kind @ ('x' | 'X' | 'o' | 'b')) = self.kind {
+            out.push(kind);
+        }
+        out
+    }
+}
+
+fn digits(chars: &[char], i: &mut usize) -> Option<usize> {
+    let start = *i;
+    while chars.get(*i).is_some_and(char::is_ascii_digit) {
+        *i += 1;
+    }
+    chars[start..*i].iter().collect::<String>().parse().ok()
+}
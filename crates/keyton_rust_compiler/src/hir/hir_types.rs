@@ -1,3 +1,6 @@
+use crate::format_spec::FormatSpec;
+use crate::parser::{FieldDef, Param, TypeExpr, VariantDef};
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct HirId(pub u32);
 
@@ -16,6 +19,24 @@ pub enum HirStmt {
     Assign {
         hir_id: HirId,
         name: String,
+        ty: Option<TypeExpr>,
+        expr: HirExpr,
+    },
+    IndexAssign {
+        hir_id: HirId,
+        target: HirExpr,
+        index: HirExpr,
+        expr: HirExpr,
+    },
+    TupleAssign {
+        hir_id: HirId,
+        names: Vec<String>,
+        expr: HirExpr,
+    },
+    FieldAssign {
+        hir_id: HirId,
+        target: HirExpr,
+        field: String,
         expr: HirExpr,
     },
     ExprStmt {
@@ -29,6 +50,23 @@ pub enum HirStmt {
         end: HirExpr,
         body: Vec<HirStmt>,
     },
+    ForEach {
+        hir_id: HirId,
+        vars: Vec<String>,
+        iter: HirExpr,
+        body: Vec<HirStmt>,
+    },
+    While {
+        hir_id: HirId,
+        cond: HirExpr,
+        body: Vec<HirStmt>,
+    },
+    Break {
+        hir_id: HirId,
+    },
+    Continue {
+        hir_id: HirId,
+    },
     If {
         hir_id: HirId,
         cond: HirExpr,
@@ -38,9 +76,83 @@ pub enum HirStmt {
     FuncDef {
         hir_id: HirId,
         name: String,
-        params: Vec<String>,
+        params: Vec<Param>,
+        ret: Option<TypeExpr>,
         body: Vec<HirStmt>,
     },
+    Return {
+        hir_id: HirId,
+        expr: Option<HirExpr>,
+    },
+    /// `methods` are `FuncDef`s
+    StructDef {
+        hir_id: HirId,
+        name: String,
+        fields: Vec<FieldDef>,
+        methods: Vec<HirStmt>,
+    },
+    EnumDef {
+        hir_id: HirId,
+        name: String,
+        variants: Vec<VariantDef>,
+    },
+    Match {
+        hir_id: HirId,
+        subject: HirExpr,
+        arms: Vec<HirMatchArm>,
+    },
+    Try {
+        hir_id: HirId,
+        body: Vec<HirStmt>,
+        handlers: Vec<HirExceptHandler>,
+        finally: Vec<HirStmt>,
+    },
+    /// A bare `raise` has neither a kind nor a message
+    Raise {
+        hir_id: HirId,
+        kind: Option<String>,
+        message: Option<HirExpr>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HirExceptHandler {
+    pub hir_id: HirId,
+    pub kind: Option<String>,
+    pub name: Option<String>,
+    pub body: Vec<HirStmt>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HirMatchArm {
+    pub hir_id: HirId,
+    pub pattern: HirPattern,
+    pub body: Vec<HirStmt>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HirPattern {
+    Wildcard {
+        hir_id: HirId,
+    },
+    Bind {
+        hir_id: HirId,
+        name: String,
+    },
+    Literal {
+        hir_id: HirId,
+        value: HirExpr,
+    },
+    Variant {
+        hir_id: HirId,
+        enum_name: String,
+        variant: String,
+        args: Vec<HirPattern>,
+    },
+    Tuple {
+        hir_id: HirId,
+        items: Vec<HirPattern>,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -49,6 +161,10 @@ pub enum HirExpr {
         hir_id: HirId,
         value: i64,
     },
+    Float {
+        hir_id: HirId,
+        value: f64,
+    },
     Str {
         hir_id: HirId,
         value: String,
@@ -57,6 +173,9 @@ pub enum HirExpr {
         hir_id: HirId,
         value: bool,
     },
+    None {
+        hir_id: HirId,
+    },
     Ident {
         hir_id: HirId,
         name: String,
@@ -67,24 +186,104 @@ pub enum HirExpr {
         op: HirBinOp,
         right: Box<HirExpr>,
     },
+    Unary {
+        hir_id: HirId,
+        op: HirUnaryOp,
+        expr: Box<HirExpr>,
+    },
     Call {
         hir_id: HirId,
         func: Box<HirExpr>,
         args: Vec<HirExpr>,
     },
+    Index {
+        hir_id: HirId,
+        target: Box<HirExpr>,
+        index: Box<HirExpr>,
+    },
+    Slice {
+        hir_id: HirId,
+        target: Box<HirExpr>,
+        start: Option<Box<HirExpr>>,
+        end: Option<Box<HirExpr>>,
+    },
     InterpolatedString {
         hir_id: HirId,
         parts: Vec<HirStringPart>,
     },
+    Dict {
+        hir_id: HirId,
+        entries: Vec<(HirExpr, HirExpr)>,
+    },
+    Tuple {
+        hir_id: HirId,
+        items: Vec<HirExpr>,
+    },
+    Range {
+        hir_id: HirId,
+        start: Box<HirExpr>,
+        end: Box<HirExpr>,
+    },
+    Field {
+        hir_id: HirId,
+        target: Box<HirExpr>,
+        name: String,
+    },
+    MethodCall {
+        hir_id: HirId,
+        target: Box<HirExpr>,
+        method: String,
+        args: Vec<HirExpr>,
+    },
+    Construct {
+        hir_id: HirId,
+        name: String,
+        fields: Vec<(String, HirExpr)>,
+    },
+    Lambda {
+        hir_id: HirId,
+        params: Vec<Param>,
+        body: Box<HirExpr>,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum HirBinOp {
     Add,
+    Sub,
+    Mul,
+    Div,
+    FloorDiv,
+    Mod,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    In,
+    NotIn,
+    Is,
+    IsNot,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HirUnaryOp {
+    Neg,
+    Not,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum HirStringPart {
-    Text { hir_id: HirId, text: String },
-    Expr { hir_id: HirId, expr: Box<HirExpr> },
+    Text {
+        hir_id: HirId,
+        text: String,
+    },
+    Expr {
+        hir_id: HirId,
+        expr: Box<HirExpr>,
+        spec: FormatSpec,
+    },
 }
@@ -2,9 +2,12 @@ pub mod hir_types;
 
 use std::collections::HashMap;
 
-use crate::parser::{BinOp, Expr, Stmt, StringPart};
-use crate::span::Span;
-use hir_types::{HirBinOp, HirExpr, HirId, HirStmt, HirStringPart};
+use crate::parser::{BinOp, ExceptHandler, Expr, MatchArm, Pattern, Stmt, StringPart, UnaryOp};
+use crate::span::{Span, Spanned};
+use hir_types::{
+    HirBinOp, HirExceptHandler, HirExpr, HirId, HirMatchArm, HirPattern, HirStmt, HirStringPart,
+    HirUnaryOp,
+};
 
 struct LoweringCtx {
     next_id: u32,
@@ -19,61 +22,102 @@ impl LoweringCtx {
         }
     }
 
-    fn new_id(&mut self) -> HirId {
-        let id = self.next_id;
+    fn new_id(&mut self, span: Span) -> HirId {
+        let hir_id = HirId(self.next_id);
         self.next_id += 1;
-        let hir_id = HirId(id);
-        // Dummy span info for now; real spans not tracked
-        let span = Span::new(id as usize, id as usize);
         self.spans.insert(hir_id, span);
         hir_id
     }
 }
 
-pub fn lower_program(ast: Vec<Stmt>) -> Vec<HirStmt> {
+pub fn lower_program(ast: Vec<Spanned<Stmt>>) -> Vec<HirStmt> {
     lower_program_with_spans(ast).0
 }
 
-pub fn lower_program_with_spans(ast: Vec<Stmt>) -> (Vec<HirStmt>, HashMap<HirId, Span>) {
+pub fn lower_program_with_spans(ast: Vec<Spanned<Stmt>>) -> (Vec<HirStmt>, HashMap<HirId, Span>) {
     let mut ctx = LoweringCtx::new();
     let hir = ast.into_iter().map(|s| lower_stmt(&mut ctx, s)).collect();
     (hir, ctx.spans)
 }
 
-fn lower_stmt(ctx: &mut LoweringCtx, stmt: Stmt) -> HirStmt {
-    match stmt {
+fn lower_stmt(ctx: &mut LoweringCtx, stmt: Spanned<Stmt>) -> HirStmt {
+    let span = stmt.span;
+    match stmt.node {
         Stmt::RImportModule { module } => HirStmt::RImportModule {
-            hir_id: ctx.new_id(),
+            hir_id: ctx.new_id(span),
             module,
         },
         Stmt::RImportItems { module, items } => HirStmt::RImportItems {
-            hir_id: ctx.new_id(),
+            hir_id: ctx.new_id(span),
             module,
             items,
         },
-        Stmt::Assign { name, expr } => HirStmt::Assign {
-            hir_id: ctx.new_id(),
+        Stmt::Assign { name, ty, expr } => HirStmt::Assign {
+            hir_id: ctx.new_id(span),
             name,
+            ty,
             expr: lower_expr(ctx, expr),
         },
+        Stmt::IndexAssign {
+            target,
+            index,
+            expr,
+        } => HirStmt::IndexAssign {
+            hir_id: ctx.new_id(span),
+            target: lower_expr(ctx, target),
+            index: lower_expr(ctx, index),
+            expr: lower_expr(ctx, expr),
+        },
+        Stmt::TupleAssign { names, expr } => HirStmt::TupleAssign {
+            hir_id: ctx.new_id(span),
+            names,
+            expr: lower_expr(ctx, expr),
+        },
+        Stmt::FieldAssign {
+            target,
+            field,
+            expr,
+        } => HirStmt::FieldAssign {
+            hir_id: ctx.new_id(span),
+            target: lower_expr(ctx, target),
+            field,
+            expr: lower_expr(ctx, expr),
+        },
+        Stmt::ForEach { vars, iter, body } => HirStmt::ForEach {
+            hir_id: ctx.new_id(span),
+            vars,
+            iter: lower_expr(ctx, iter),
+            body: body.into_iter().map(|s| lower_stmt(ctx, s)).collect(),
+        },
         Stmt::ForRange {
             var,
             start,
             end,
             body,
         } => HirStmt::ForRange {
-            hir_id: ctx.new_id(),
+            hir_id: ctx.new_id(span),
             var,
             start: lower_expr(ctx, start),
             end: lower_expr(ctx, end),
             body: body.into_iter().map(|s| lower_stmt(ctx, s)).collect(),
         },
+        Stmt::While { cond, body } => HirStmt::While {
+            hir_id: ctx.new_id(span),
+            cond: lower_expr(ctx, cond),
+            body: body.into_iter().map(|s| lower_stmt(ctx, s)).collect(),
+        },
+        Stmt::Break => HirStmt::Break {
+            hir_id: ctx.new_id(span),
+        },
+        Stmt::Continue => HirStmt::Continue {
+            hir_id: ctx.new_id(span),
+        },
         Stmt::If {
             cond,
             then_branch,
             else_branch,
         } => HirStmt::If {
-            hir_id: ctx.new_id(),
+            hir_id: ctx.new_id(span),
             cond: lower_expr(ctx, cond),
             then_branch: then_branch
                 .into_iter()
@@ -85,77 +129,268 @@ fn lower_stmt(ctx: &mut LoweringCtx, stmt: Stmt) -> HirStmt {
                 .collect(),
         },
         Stmt::ExprStmt(expr) => HirStmt::ExprStmt {
-            hir_id: ctx.new_id(),
+            hir_id: ctx.new_id(span),
             expr: lower_expr(ctx, expr),
         },
-        Stmt::FuncDef { name, params, body } => HirStmt::FuncDef {
-            hir_id: ctx.new_id(),
+        // Doc comments stay in the AST for tooling; lowering has no use for them
+        Stmt::FuncDef {
             name,
             params,
+            ret,
+            body,
+            ..
+        } => HirStmt::FuncDef {
+            hir_id: ctx.new_id(span),
+            name,
+            params,
+            ret,
             body: body.into_iter().map(|s| lower_stmt(ctx, s)).collect(),
         },
-        Stmt::Return(expr) => HirStmt::ExprStmt {
-            // For now, returns are not first-class; treat as expression stmt in HIR
-            hir_id: ctx.new_id(),
-            expr: lower_expr(ctx, expr),
+        Stmt::Return(expr) => HirStmt::Return {
+            hir_id: ctx.new_id(span),
+            expr: expr.map(|e| lower_expr(ctx, e)),
+        },
+        Stmt::StructDef {
+            name,
+            fields,
+            methods,
+            ..
+        } => HirStmt::StructDef {
+            hir_id: ctx.new_id(span),
+            name,
+            fields,
+            methods: methods.into_iter().map(|s| lower_stmt(ctx, s)).collect(),
+        },
+        Stmt::EnumDef { name, variants } => HirStmt::EnumDef {
+            hir_id: ctx.new_id(span),
+            name,
+            variants,
+        },
+        Stmt::Match { subject, arms } => HirStmt::Match {
+            hir_id: ctx.new_id(span),
+            subject: lower_expr(ctx, subject),
+            arms: arms.into_iter().map(|arm| lower_arm(ctx, arm)).collect(),
+        },
+        Stmt::Try {
+            body,
+            handlers,
+            finally,
+        } => HirStmt::Try {
+            hir_id: ctx.new_id(span),
+            body: body.into_iter().map(|s| lower_stmt(ctx, s)).collect(),
+            handlers: handlers
+                .into_iter()
+                .map(|h| lower_handler(ctx, h))
+                .collect(),
+            finally: finally.into_iter().map(|s| lower_stmt(ctx, s)).collect(),
+        },
+        Stmt::Raise { kind, message } => HirStmt::Raise {
+            hir_id: ctx.new_id(span),
+            kind,
+            message: message.map(|e| lower_expr(ctx, e)),
+        },
+    }
+}
+
+fn lower_handler(ctx: &mut LoweringCtx, handler: ExceptHandler) -> HirExceptHandler {
+    HirExceptHandler {
+        hir_id: ctx.new_id(handler.span),
+        kind: handler.kind,
+        name: handler.name,
+        body: handler
+            .body
+            .into_iter()
+            .map(|s| lower_stmt(ctx, s))
+            .collect(),
+    }
+}
+
+fn lower_arm(ctx: &mut LoweringCtx, arm: MatchArm) -> HirMatchArm {
+    HirMatchArm {
+        hir_id: ctx.new_id(arm.pattern.span),
+        pattern: lower_pattern(ctx, arm.pattern),
+        body: arm.body.into_iter().map(|s| lower_stmt(ctx, s)).collect(),
+    }
+}
+
+fn lower_pattern(ctx: &mut LoweringCtx, pattern: Spanned<Pattern>) -> HirPattern {
+    let span = pattern.span;
+    match pattern.node {
+        Pattern::Wildcard => HirPattern::Wildcard {
+            hir_id: ctx.new_id(span),
+        },
+        Pattern::Bind(name) => HirPattern::Bind {
+            hir_id: ctx.new_id(span),
+            name,
+        },
+        Pattern::Literal(value) => HirPattern::Literal {
+            hir_id: ctx.new_id(span),
+            value: lower_expr(ctx, Spanned::new(value, span)),
+        },
+        Pattern::Variant {
+            enum_name,
+            variant,
+            args,
+        } => HirPattern::Variant {
+            hir_id: ctx.new_id(span),
+            enum_name,
+            variant,
+            args: args.into_iter().map(|p| lower_pattern(ctx, p)).collect(),
+        },
+        Pattern::Tuple(items) => HirPattern::Tuple {
+            hir_id: ctx.new_id(span),
+            items: items.into_iter().map(|p| lower_pattern(ctx, p)).collect(),
         },
     }
 }
 
-fn lower_expr(ctx: &mut LoweringCtx, expr: Expr) -> HirExpr {
-    match expr {
+fn lower_expr(ctx: &mut LoweringCtx, expr: Spanned<Expr>) -> HirExpr {
+    let span = expr.span;
+    match expr.node {
         Expr::Int(n) => HirExpr::Int {
-            hir_id: ctx.new_id(),
+            hir_id: ctx.new_id(span),
             value: n,
         },
+        Expr::Float(x) => HirExpr::Float {
+            hir_id: ctx.new_id(span),
+            value: x,
+        },
         Expr::Str(s) => HirExpr::Str {
-            hir_id: ctx.new_id(),
+            hir_id: ctx.new_id(span),
             value: s,
         },
         Expr::Bool(b) => HirExpr::Bool {
-            hir_id: ctx.new_id(),
+            hir_id: ctx.new_id(span),
             value: b,
         },
+        Expr::None => HirExpr::None {
+            hir_id: ctx.new_id(span),
+        },
         Expr::Ident(s) => HirExpr::Ident {
-            hir_id: ctx.new_id(),
+            hir_id: ctx.new_id(span),
             name: s,
         },
         Expr::Binary { left, op, right } => HirExpr::Binary {
-            hir_id: ctx.new_id(),
+            hir_id: ctx.new_id(span),
             left: Box::new(lower_expr(ctx, *left)),
             op: lower_bin_op(op),
             right: Box::new(lower_expr(ctx, *right)),
         },
+        Expr::Unary { op, expr } => HirExpr::Unary {
+            hir_id: ctx.new_id(span),
+            op: lower_unary_op(op),
+            expr: Box::new(lower_expr(ctx, *expr)),
+        },
         Expr::Call { func, args } => HirExpr::Call {
-            hir_id: ctx.new_id(),
+            hir_id: ctx.new_id(span),
             func: Box::new(lower_expr(ctx, *func)),
             args: args.into_iter().map(|a| lower_expr(ctx, a)).collect(),
         },
+        Expr::Index { target, index } => HirExpr::Index {
+            hir_id: ctx.new_id(span),
+            target: Box::new(lower_expr(ctx, *target)),
+            index: Box::new(lower_expr(ctx, *index)),
+        },
+        Expr::Slice { target, start, end } => HirExpr::Slice {
+            hir_id: ctx.new_id(span),
+            target: Box::new(lower_expr(ctx, *target)),
+            start: start.map(|e| Box::new(lower_expr(ctx, *e))),
+            end: end.map(|e| Box::new(lower_expr(ctx, *e))),
+        },
         Expr::InterpolatedString(parts) => HirExpr::InterpolatedString {
-            hir_id: ctx.new_id(),
+            hir_id: ctx.new_id(span),
             parts: parts
                 .into_iter()
-                .map(|p| lower_string_part(ctx, p))
+                .map(|p| lower_string_part(ctx, p, span))
+                .collect(),
+        },
+        Expr::Dict(entries) => HirExpr::Dict {
+            hir_id: ctx.new_id(span),
+            entries: entries
+                .into_iter()
+                .map(|(k, v)| (lower_expr(ctx, k), lower_expr(ctx, v)))
                 .collect(),
         },
+        Expr::Tuple(items) => HirExpr::Tuple {
+            hir_id: ctx.new_id(span),
+            items: items.into_iter().map(|e| lower_expr(ctx, e)).collect(),
+        },
+        Expr::Range { start, end } => HirExpr::Range {
+            hir_id: ctx.new_id(span),
+            start: Box::new(lower_expr(ctx, *start)),
+            end: Box::new(lower_expr(ctx, *end)),
+        },
+        Expr::Field { target, name } => HirExpr::Field {
+            hir_id: ctx.new_id(span),
+            target: Box::new(lower_expr(ctx, *target)),
+            name,
+        },
+        Expr::MethodCall {
+            target,
+            method,
+            args,
+        } => HirExpr::MethodCall {
+            hir_id: ctx.new_id(span),
+            target: Box::new(lower_expr(ctx, *target)),
+            method,
+            args: args.into_iter().map(|a| lower_expr(ctx, a)).collect(),
+        },
+        Expr::Construct { name, fields } => HirExpr::Construct {
+            hir_id: ctx.new_id(span),
+            name,
+            fields: fields
+                .into_iter()
+                .map(|(field, value)| (field, lower_expr(ctx, value)))
+                .collect(),
+        },
+        Expr::Lambda { params, body } => HirExpr::Lambda {
+            hir_id: ctx.new_id(span),
+            params,
+            body: Box::new(lower_expr(ctx, *body)),
+        },
     }
 }
 
 fn lower_bin_op(op: BinOp) -> HirBinOp {
     match op {
         BinOp::Add => HirBinOp::Add,
+        BinOp::Sub => HirBinOp::Sub,
+        BinOp::Mul => HirBinOp::Mul,
+        BinOp::Div => HirBinOp::Div,
+        BinOp::FloorDiv => HirBinOp::FloorDiv,
+        BinOp::Mod => HirBinOp::Mod,
+        BinOp::Eq => HirBinOp::Eq,
+        BinOp::NotEq => HirBinOp::NotEq,
+        BinOp::Lt => HirBinOp::Lt,
+        BinOp::LtEq => HirBinOp::LtEq,
+        BinOp::Gt => HirBinOp::Gt,
+        BinOp::GtEq => HirBinOp::GtEq,
+        BinOp::In => HirBinOp::In,
+        BinOp::NotIn => HirBinOp::NotIn,
+        BinOp::Is => HirBinOp::Is,
+        BinOp::IsNot => HirBinOp::IsNot,
+        BinOp::And => HirBinOp::And,
+        BinOp::Or => HirBinOp::Or,
+    }
+}
+
+fn lower_unary_op(op: UnaryOp) -> HirUnaryOp {
+    match op {
+        UnaryOp::Neg => HirUnaryOp::Neg,
+        UnaryOp::Not => HirUnaryOp::Not,
     }
 }
 
-fn lower_string_part(ctx: &mut LoweringCtx, part: StringPart) -> HirStringPart {
+fn lower_string_part(ctx: &mut LoweringCtx, part: StringPart, span: Span) -> HirStringPart {
     match part {
         StringPart::Text(t) => HirStringPart::Text {
-            hir_id: ctx.new_id(),
+            hir_id: ctx.new_id(span),
             text: t,
         },
-        StringPart::Expr(e) => HirStringPart::Expr {
-            hir_id: ctx.new_id(),
+        StringPart::Expr(e, spec) => HirStringPart::Expr {
+            hir_id: ctx.new_id(e.span),
             expr: Box::new(lower_expr(ctx, *e)),
+            spec,
         },
     }
 }
@@ -1,5 +1,6 @@
 use super::hir_types::*;
 use super::*;
+use crate::format_spec::FormatSpec;
 use crate::lexer::Lexer;
 use crate::parser::Parser;
 
@@ -9,8 +10,8 @@ fn program1_hir() {
 x = x + 1
 print(x)
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     assert_eq!(
         hir,
@@ -18,6 +19,7 @@ print(x)
             HirStmt::Assign {
                 hir_id: HirId(1),
                 name: "x".to_string(),
+                ty: None,
                 expr: HirExpr::Int {
                     hir_id: HirId(2),
                     value: 12
@@ -26,6 +28,7 @@ print(x)
             HirStmt::Assign {
                 hir_id: HirId(3),
                 name: "x".to_string(),
+                ty: None,
                 expr: HirExpr::Binary {
                     hir_id: HirId(4),
                     left: Box::new(HirExpr::Ident {
@@ -63,8 +66,8 @@ fn program4_hir() {
 x = "Hello"
 print(x)
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     assert_eq!(
         hir,
@@ -72,6 +75,7 @@ print(x)
             HirStmt::Assign {
                 hir_id: HirId(1),
                 name: "x".to_string(),
+                ty: None,
                 expr: HirExpr::Int {
                     hir_id: HirId(2),
                     value: 12
@@ -80,6 +84,7 @@ print(x)
             HirStmt::Assign {
                 hir_id: HirId(3),
                 name: "x".to_string(),
+                ty: None,
                 expr: HirExpr::Str {
                     hir_id: HirId(4),
                     value: "Hello".to_string()
@@ -106,8 +111,8 @@ print(x)
 #[test]
 fn program2_hir() {
     let input = r#"print("Hello, World")"#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     assert_eq!(
         hir,
@@ -133,8 +138,8 @@ fn program3_hir() {
     let input = r#"x = 12
 print(f"{x}")
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     assert_eq!(
         hir,
@@ -142,6 +147,7 @@ print(f"{x}")
             HirStmt::Assign {
                 hir_id: HirId(1),
                 name: "x".to_string(),
+                ty: None,
                 expr: HirExpr::Int {
                     hir_id: HirId(2),
                     value: 12
@@ -168,6 +174,7 @@ print(f"{x}")
                                     hir_id: HirId(9),
                                     name: "x".to_string(),
                                 }),
+                                spec: FormatSpec::default(),
                             },
                             HirStringPart::Text {
                                 hir_id: HirId(10),
@@ -2,15 +2,23 @@ use std::collections::VecDeque;
 use std::iter::Peekable;
 use std::str::Chars;
 
+use unicode_normalization::UnicodeNormalization;
+
+use crate::format_spec::FormatSpec;
+use crate::parser::{ParseError, ParseErrorKind};
+use crate::span::{Span, Spanned};
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Int(i64),
+    Float(f64),
     Str(String),
     Ident(String),
     LetKw,
     FnKw,
     ReturnKw,
     IfKw,
+    ElifKw,
     ElseKw,
     TrueKw,
     FalseKw,
@@ -22,6 +30,7 @@ pub enum Token {
     RParen,
     Comma,
     Colon,
+    Semicolon,
     Indent,
     Dedent,
     Newline,
@@ -34,119 +43,367 @@ pub enum Token {
     Dot,
     LBracket,
     RBracket,
+    LBrace,
+    RBrace,
     LAngle,
     RAngle,
+    Minus,
+    Arrow,
+    FatArrow,
+    Star,
+    Slash,
+    SlashSlash,
+    Percent,
+    EqualEqual,
+    NotEqual,
+    LessEqual,
+    GreaterEqual,
+    AndKw,
+    OrKw,
+    NotKw,
+    WhileKw,
+    BreakKw,
+    ContinueKw,
+    StructKw,
+    EnumKw,
+    MatchKw,
+    CaseKw,
+    IsKw,
+    NoneKw,
+    Question,
+    TryKw,
+    ExceptKw,
+    FinallyKw,
+    RaiseKw,
+    AsKw,
+    /// The text of the `##` lines right before a `fn` or `struct`, one line each
+    DocComment(String),
+}
+
+impl Token {
+    /// Human-readable description used in syntax error messages.
+    pub fn describe(&self) -> String {
+        match self {
+            Token::Int(n) => format!("integer {}", n),
+            Token::Float(x) => format!("float {}", x),
+            Token::Str(_) => "string literal".to_string(),
+            Token::InterpolatedString(_) => "f-string".to_string(),
+            Token::Ident(name) => format!("identifier '{}'", name),
+            Token::LetKw => "'let'".to_string(),
+            Token::FnKw => "'fn'".to_string(),
+            Token::ReturnKw => "'return'".to_string(),
+            Token::IfKw => "'if'".to_string(),
+            Token::ElifKw => "'elif'".to_string(),
+            Token::ElseKw => "'else'".to_string(),
+            Token::TrueKw => "'True'".to_string(),
+            Token::FalseKw => "'False'".to_string(),
+            Token::RimportKw => "'rimport'".to_string(),
+            Token::FromKw => "'from'".to_string(),
+            Token::ForKw => "'for'".to_string(),
+            Token::InKw => "'in'".to_string(),
+            Token::Plus => "'+'".to_string(),
+            Token::PlusEqual => "'+='".to_string(),
+            Token::Equal => "'='".to_string(),
+            Token::LParen => "'('".to_string(),
+            Token::RParen => "')'".to_string(),
+            Token::LBracket => "'['".to_string(),
+            Token::RBracket => "']'".to_string(),
+            Token::LBrace => "'{'".to_string(),
+            Token::RBrace => "'}'".to_string(),
+            Token::LAngle => "'<'".to_string(),
+            Token::RAngle => "'>'".to_string(),
+            Token::Minus => "'-'".to_string(),
+            Token::Arrow => "'->'".to_string(),
+            Token::FatArrow => "'=>'".to_string(),
+            Token::Star => "'*'".to_string(),
+            Token::Slash => "'/'".to_string(),
+            Token::SlashSlash => "'//'".to_string(),
+            Token::Percent => "'%'".to_string(),
+            Token::EqualEqual => "'=='".to_string(),
+            Token::NotEqual => "'!='".to_string(),
+            Token::LessEqual => "'<='".to_string(),
+            Token::GreaterEqual => "'>='".to_string(),
+            Token::AndKw => "'and'".to_string(),
+            Token::OrKw => "'or'".to_string(),
+            Token::NotKw => "'not'".to_string(),
+            Token::WhileKw => "'while'".to_string(),
+            Token::BreakKw => "'break'".to_string(),
+            Token::ContinueKw => "'continue'".to_string(),
+            Token::StructKw => "'struct'".to_string(),
+            Token::EnumKw => "'enum'".to_string(),
+            Token::MatchKw => "'match'".to_string(),
+            Token::CaseKw => "'case'".to_string(),
+            Token::IsKw => "'is'".to_string(),
+            Token::NoneKw => "'None'".to_string(),
+            Token::Question => "'?'".to_string(),
+            Token::TryKw => "'try'".to_string(),
+            Token::ExceptKw => "'except'".to_string(),
+            Token::FinallyKw => "'finally'".to_string(),
+            Token::RaiseKw => "'raise'".to_string(),
+            Token::AsKw => "'as'".to_string(),
+            Token::Comma => "','".to_string(),
+            Token::Colon => "':'".to_string(),
+            Token::Semicolon => "';'".to_string(),
+            Token::Dot => "'.'".to_string(),
+            Token::DotDot => "'..'".to_string(),
+            Token::Indent => "indent".to_string(),
+            Token::Dedent => "dedent".to_string(),
+            Token::Newline => "newline".to_string(),
+            Token::EOF => "end of input".to_string(),
+            Token::DocComment(_) => "doc comment".to_string(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum FStringPart {
     Text(String),
-    Expr(String),
+    /// A `{expr!r:spec}` field; `offset` is the byte offset of `source` in the input
+    Expr {
+        source: String,
+        offset: usize,
+        spec: FormatSpec,
+    },
 }
 
 pub struct Lexer<'a> {
     chars: Peekable<Chars<'a>>,
+    /// Byte offset of the next character in `chars`
+    pos: usize,
+    /// Byte offset where the token currently being lexed starts
+    tok_start: usize,
     at_line_start: bool,
+    /// Number of `(`, `[` and `{` still open; line breaks inside them are not tokens
+    depth: usize,
     indent_stack: Vec<usize>,
-    pending: VecDeque<Token>,
+    pending: VecDeque<Spanned<Token>>,
+    errors: Vec<ParseError>,
+    /// `##` lines read since the last line of code, and the range they cover
+    doc: Vec<String>,
+    doc_span: Span,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
+        Self::with_offset(input, 0)
+    }
+
+    /// Create a lexer whose spans are shifted by `offset` bytes. Used for source fragments
+    /// (e.g. f-string expressions) that live inside a larger input.
+    pub fn with_offset(input: &'a str, offset: usize) -> Self {
         Self {
             chars: input.chars().peekable(),
+            pos: offset,
+            tok_start: offset,
             at_line_start: true,
+            depth: 0,
             indent_stack: vec![0],
             pending: VecDeque::new(),
+            errors: Vec::new(),
+            doc: Vec::new(),
+            doc_span: Span::new(offset, offset),
         }
     }
 
     /// Tokenize the input. The resulting token stream will always end with `Token::EOF`.
-    pub fn tokenize(mut self) -> Vec<Token> {
+    /// Every token carries the byte range it was read from. Lexing does not stop at the
+    /// first problem: all errors found in the input are returned together.
+    pub fn tokenize(self) -> Result<Vec<Spanned<Token>>, Vec<ParseError>> {
+        let (tokens, errors) = self.tokenize_recovering();
+        if errors.is_empty() {
+            Ok(tokens)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Like [`Lexer::tokenize`], but always returns the recovered token stream alongside
+    /// any errors so the parser can still report problems of its own.
+    pub fn tokenize_recovering(mut self) -> (Vec<Spanned<Token>>, Vec<ParseError>) {
         let mut tokens = Vec::new();
         loop {
             let tok = self.next_token();
-            let end = tok == Token::EOF;
+            let end = tok.node == Token::EOF;
             tokens.push(tok);
             if end {
                 break;
             }
         }
-        tokens
+        (tokens, self.errors)
     }
 
-    fn next_token(&mut self) -> Token {
+    fn next_token(&mut self) -> Spanned<Token> {
         if let Some(tok) = self.pending.pop_front() {
             return tok;
         }
 
         // Handle indentation at start of line
         if self.at_line_start {
-            // Count spaces; tabs are not allowed anywhere
+            let line_start = self.pos;
+            // Count spaces; tabs are not allowed anywhere, but count as 4 spaces for recovery
             let mut spaces = 0usize;
             loop {
                 match self.chars.peek().copied() {
                     Some(' ') => {
-                        self.chars.next();
+                        self.bump();
                         spaces += 1;
                     }
-                    Some('\t') => panic!("tabs are not allowed"),
+                    Some('\t') => {
+                        self.error_at_next_char(ParseErrorKind::Tab);
+                        spaces += 4;
+                    }
                     _ => break,
                 }
             }
 
+            // A line holding only a comment counts as blank
+            if self.chars.peek() == Some(&'#') {
+                self.lex_comment();
+                if self.chars.peek().is_none() {
+                    self.at_line_start = false;
+                    return self.next_token();
+                }
+            }
+
             // Blank line handling
             if let Some('\n') = self.chars.peek().copied() {
-                self.chars.next();
+                let nl_start = self.pos;
+                self.bump();
                 // Stay at start of line for the next token
                 self.at_line_start = true;
-                return Token::Newline;
+                return Spanned::new(Token::Newline, Span::new(nl_start, self.pos));
             }
 
+            let doc = self.take_doc();
             let current = *self.indent_stack.last().unwrap();
             if spaces == current {
                 // No change in indent
                 self.at_line_start = false;
+                if let Some(doc) = doc {
+                    return doc;
+                }
             } else if spaces > current {
-                // Enforce exactly +4 spaces
+                // Enforce exactly +4 spaces; keep the actual level so the block still parses
                 if spaces != current + 4 {
-                    panic!("indentation must increase by exactly 4 spaces");
+                    self.errors.push(ParseError::new(
+                        Span::new(line_start, self.pos),
+                        ParseErrorKind::BadIndent {
+                            expected: current + 4,
+                            found: spaces,
+                        },
+                    ));
                 }
                 self.indent_stack.push(spaces);
                 self.at_line_start = false;
-                return Token::Indent;
+                self.pending.extend(doc);
+                return Spanned::new(Token::Indent, Span::new(line_start, self.pos));
             } else {
-                // Dedent(s) to a previous level
+                // Dedent(s) to a previous level; they are zero-width at the start of the line's content
+                let here = Span::new(self.pos, self.pos);
                 while let Some(&top) = self.indent_stack.last() {
                     if top > spaces {
                         self.indent_stack.pop();
-                        self.pending.push_back(Token::Dedent);
+                        self.pending.push_back(Spanned::new(Token::Dedent, here));
                     } else {
                         break;
                     }
                 }
-                // After popping, the top must match exactly
+                // After popping, the top must match exactly; otherwise adopt the new level
                 if *self.indent_stack.last().unwrap() != spaces {
-                    panic!("invalid dedent level; must match a previous indentation");
+                    self.errors.push(ParseError::new(
+                        Span::new(line_start, self.pos),
+                        ParseErrorKind::InvalidDedent,
+                    ));
+                    self.indent_stack.push(spaces);
                 }
                 self.at_line_start = false;
+                self.pending.extend(doc);
                 if let Some(tok) = self.pending.pop_front() {
                     return tok;
                 }
             }
         }
 
-        self.skip_inline_spaces();
+        self.skip_trivia();
+        self.tok_start = self.pos;
+        let tok = self.next_token_kind();
+        Spanned::new(tok, Span::new(self.tok_start, self.pos))
+    }
+
+    /// Skip spaces and comments, and line breaks that do not end the logical line:
+    /// those inside brackets and those escaped with a trailing `\`.
+    fn skip_trivia(&mut self) {
+        loop {
+            self.skip_inline_spaces();
+            if self.chars.peek() == Some(&'#') {
+                self.lex_comment();
+            }
+            match self.chars.peek().copied() {
+                Some('\n') if self.depth > 0 => {
+                    self.bump();
+                }
+                Some('\\') if self.peek_next() == Some('\n') => {
+                    self.bump();
+                    self.bump();
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Skip a comment up to the end of its line. A `##` comment on a line of its own
+    /// is kept as documentation for the definition that follows.
+    fn lex_comment(&mut self) {
+        let start = self.pos;
+        let mut text = String::new();
+        while let Some(c) = self.chars.peek().copied() {
+            if c == '\n' {
+                break;
+            }
+            self.bump();
+            text.push(c);
+        }
+        if let Some(doc) = text.strip_prefix("##")
+            && self.at_line_start
+        {
+            if self.doc.is_empty() {
+                self.doc_span.start = start;
+            }
+            self.doc_span.end = self.pos;
+            let doc = doc.strip_prefix(' ').unwrap_or(doc);
+            self.doc.push(doc.trim_end().to_string());
+        }
+    }
+
+    /// The doc comment for the line about to be lexed, if that line starts a `fn` or
+    /// `struct`. Documentation before anything else is an ordinary comment.
+    fn take_doc(&mut self) -> Option<Spanned<Token>> {
+        if self.doc.is_empty() {
+            return None;
+        }
+        let lines = std::mem::take(&mut self.doc);
+        let rest: String = self.chars.clone().take(7).collect();
+        if !(rest.starts_with("fn ") || rest.starts_with("struct ")) {
+            return None;
+        }
+        Some(Spanned::new(
+            Token::DocComment(lines.join("\n")),
+            self.doc_span,
+        ))
+    }
+
+    fn next_token_kind(&mut self) -> Token {
         let ch = match self.chars.peek().copied() {
             Some(c) => c,
             None => {
                 // At EOF, emit any remaining dedents
                 if self.indent_stack.len() > 1 {
+                    let here = Span::new(self.pos, self.pos);
                     while self.indent_stack.len() > 1 {
                         self.indent_stack.pop();
-                        self.pending.push_back(Token::Dedent);
+                        self.pending.push_back(Spanned::new(Token::Dedent, here));
                     }
-                    return self.pending.pop_front().unwrap();
+                    return self.pending.pop_front().unwrap().node;
                 }
                 return Token::EOF;
             }
@@ -154,67 +411,140 @@ impl<'a> Lexer<'a> {
 
         match ch {
             '\n' => {
-                self.chars.next();
+                self.bump();
                 self.at_line_start = true;
                 Token::Newline
             }
             '=' => {
-                self.chars.next();
-                Token::Equal
+                self.bump();
+                match self.chars.peek().copied() {
+                    Some('=') => {
+                        self.bump();
+                        Token::EqualEqual
+                    }
+                    Some('>') => {
+                        self.bump();
+                        Token::FatArrow
+                    }
+                    _ => Token::Equal,
+                }
+            }
+            '!' if self.peek_next() == Some('=') => {
+                self.bump();
+                self.bump();
+                Token::NotEqual
+            }
+            '-' => {
+                self.bump();
+                if let Some('>') = self.chars.peek().copied() {
+                    self.bump();
+                    Token::Arrow
+                } else {
+                    Token::Minus
+                }
+            }
+            '*' => {
+                self.bump();
+                Token::Star
+            }
+            '/' => {
+                self.bump();
+                if let Some('/') = self.chars.peek().copied() {
+                    self.bump();
+                    Token::SlashSlash
+                } else {
+                    Token::Slash
+                }
+            }
+            '%' => {
+                self.bump();
+                Token::Percent
+            }
+            '?' => {
+                self.bump();
+                Token::Question
             }
             '+' => {
-                self.chars.next();
+                self.bump();
                 if let Some('=') = self.chars.peek().copied() {
-                    self.chars.next();
+                    self.bump();
                     Token::PlusEqual
                 } else {
                     Token::Plus
                 }
             }
             '(' => {
-                self.chars.next();
+                self.bump();
+                self.depth += 1;
                 Token::LParen
             }
             ')' => {
-                self.chars.next();
+                self.bump();
+                self.depth = self.depth.saturating_sub(1);
                 Token::RParen
             }
             ',' => {
-                self.chars.next();
+                self.bump();
                 Token::Comma
             }
             ':' => {
-                self.chars.next();
+                self.bump();
                 Token::Colon
             }
+            ';' => {
+                self.bump();
+                Token::Semicolon
+            }
             '.' => {
                 // Possibly Dot or DotDot
-                self.chars.next();
+                self.bump();
                 if let Some('.') = self.chars.peek().copied() {
-                    self.chars.next();
+                    self.bump();
                     Token::DotDot
                 } else {
                     Token::Dot
                 }
             }
             '[' => {
-                self.chars.next();
+                self.bump();
+                self.depth += 1;
                 Token::LBracket
             }
             ']' => {
-                self.chars.next();
+                self.bump();
+                self.depth = self.depth.saturating_sub(1);
                 Token::RBracket
             }
+            '{' => {
+                self.bump();
+                self.depth += 1;
+                Token::LBrace
+            }
+            '}' => {
+                self.bump();
+                self.depth = self.depth.saturating_sub(1);
+                Token::RBrace
+            }
             '<' => {
-                self.chars.next();
-                Token::LAngle
+                self.bump();
+                if let Some('=') = self.chars.peek().copied() {
+                    self.bump();
+                    Token::LessEqual
+                } else {
+                    Token::LAngle
+                }
             }
             '>' => {
-                self.chars.next();
-                Token::RAngle
+                self.bump();
+                if let Some('=') = self.chars.peek().copied() {
+                    self.bump();
+                    Token::GreaterEqual
+                } else {
+                    Token::RAngle
+                }
             }
             '0'..='9' => self.lex_number(ch),
-            'a'..='z' | 'A'..='Z' | '_' => {
+            c if c == '_' || unicode_ident::is_xid_start(c) => {
                 if ch == 'f' {
                     if let Some('"') = self.peek_next() {
                         return self.lex_fstring();
@@ -223,112 +553,436 @@ impl<'a> Lexer<'a> {
                 self.lex_ident(ch)
             }
             '"' => self.lex_string(),
-            '\t' => panic!("tabs are not allowed"),
             _ => {
-                // Unknown character, skip and continue
-                self.chars.next();
-                self.next_token()
+                // Unknown character: report it, skip and continue
+                self.error_at_next_char(ParseErrorKind::UnexpectedChar(ch));
+                self.skip_inline_spaces();
+                self.tok_start = self.pos;
+                self.next_token_kind()
             }
         }
     }
 
+    /// Lex an integer or a float literal (`1.5`, `2e-3`). A `.` only starts a fraction
+    /// when a digit follows, so `0..3` still lexes as a range.
     fn lex_number(&mut self, first: char) -> Token {
         let mut num = first.to_string();
-        self.chars.next();
-        while let Some(c) = self.chars.peek() {
+        self.bump();
+        self.push_digits(&mut num);
+
+        let mut is_float = false;
+        if self.chars.peek() == Some(&'.') && self.peek_next().is_some_and(|c| c.is_ascii_digit()) {
+            is_float = true;
+            num.push('.');
+            self.bump();
+            self.push_digits(&mut num);
+        }
+        if matches!(self.chars.peek(), Some('e' | 'E')) && self.exponent_follows() {
+            is_float = true;
+            num.push('e');
+            self.bump();
+            if let Some(sign @ ('+' | '-')) = self.chars.peek().copied() {
+                num.push(sign);
+                self.bump();
+            }
+            self.push_digits(&mut num);
+        }
+
+        if is_float {
+            // A digit sequence with an optional fraction and exponent always parses
+            return Token::Float(num.parse().unwrap_or(f64::INFINITY));
+        }
+        match num.parse() {
+            Ok(n) => Token::Int(n),
+            Err(_) => {
+                self.errors.push(ParseError::new(
+                    Span::new(self.tok_start, self.pos),
+                    ParseErrorKind::IntegerTooLarge,
+                ));
+                Token::Int(0)
+            }
+        }
+    }
+
+    fn push_digits(&mut self, num: &mut String) {
+        while let Some(c) = self.chars.peek().copied() {
             if c.is_ascii_digit() {
-                num.push(*c);
-                self.chars.next();
+                num.push(c);
+                self.bump();
             } else {
                 break;
             }
         }
-        Token::Int(num.parse().unwrap())
+    }
+
+    /// Whether the `e`/`E` under the cursor begins an exponent (`e3`, `e-3`, `e+3`)
+    fn exponent_follows(&self) -> bool {
+        let mut iter = self.chars.clone();
+        iter.next();
+        match iter.next() {
+            Some('+' | '-') => iter.next().is_some_and(|c| c.is_ascii_digit()),
+            Some(c) => c.is_ascii_digit(),
+            None => false,
+        }
     }
 
     fn lex_ident(&mut self, first: char) -> Token {
         let mut ident = first.to_string();
-        self.chars.next();
+        self.bump();
         while let Some(c) = self.chars.peek() {
-            if c.is_ascii_alphanumeric() || *c == '_' {
+            if unicode_ident::is_xid_continue(*c) {
                 ident.push(*c);
-                self.chars.next();
+                self.bump();
             } else {
                 break;
             }
         }
+        // Spellings of the same name (`é` and `e` + U+0301, `ﬁ` and `fi`) are one identifier,
+        // as in Python; rustc would otherwise see two spellings of one name
+        if !ident.is_ascii() {
+            ident = ident.nfkc().collect();
+        }
         match ident.as_str() {
             "let" => Token::LetKw,
             "fn" => Token::FnKw,
             "return" => Token::ReturnKw,
             "for" => Token::ForKw,
+            "while" => Token::WhileKw,
+            "break" => Token::BreakKw,
+            "continue" => Token::ContinueKw,
+            "struct" => Token::StructKw,
+            "enum" => Token::EnumKw,
+            "match" => Token::MatchKw,
+            "case" => Token::CaseKw,
+            "try" => Token::TryKw,
+            "except" => Token::ExceptKw,
+            "finally" => Token::FinallyKw,
+            "raise" => Token::RaiseKw,
+            "as" => Token::AsKw,
             "in" => Token::InKw,
             "if" => Token::IfKw,
+            "elif" => Token::ElifKw,
             "else" => Token::ElseKw,
             "rimport" => Token::RimportKw,
             "from" => Token::FromKw,
             "True" => Token::TrueKw,
             "False" => Token::FalseKw,
+            "and" => Token::AndKw,
+            "or" => Token::OrKw,
+            "not" => Token::NotKw,
+            "is" => Token::IsKw,
+            "None" => Token::NoneKw,
             _ => Token::Ident(ident),
         }
     }
 
+    /// Lex `"..."`, or `"""..."""`, which may span several lines.
     fn lex_string(&mut self) -> Token {
-        self.chars.next(); // skip opening quote
+        let triple = self.at_triple_quote();
+        let quotes = if triple { 3 } else { 1 };
+        for _ in 0..quotes {
+            self.bump();
+        }
         let mut s = String::new();
-        while let Some(c) = self.chars.next() {
-            if c == '"' {
-                break;
-            } else {
-                s.push(c);
+        loop {
+            match self.chars.peek().copied() {
+                Some('"') if !triple || self.at_triple_quote() => {
+                    for _ in 0..quotes {
+                        self.bump();
+                    }
+                    break;
+                }
+                Some('\n') if !triple => {
+                    self.unterminated_string();
+                    break;
+                }
+                None => {
+                    self.unterminated_string();
+                    break;
+                }
+                Some('\\') => {
+                    if let Some(c) = self.lex_escape() {
+                        s.push(c);
+                    }
+                }
+                Some(c) => {
+                    self.bump();
+                    s.push(c);
+                }
             }
         }
         Token::Str(s)
     }
 
+    fn at_triple_quote(&self) -> bool {
+        let mut iter = self.chars.clone();
+        (0..3).all(|_| iter.next() == Some('"'))
+    }
+
+    /// Lex a backslash escape such as `\n`, `\x41`, `\u00e9` or `\101` into the character
+    /// it stands for, as Python does. A backslash before a line break continues the
+    /// string on the next line, adding nothing. Unknown escapes are reported and dropped.
+    fn lex_escape(&mut self) -> Option<char> {
+        let start = self.pos;
+        self.bump(); // skip the backslash
+        // At the end of input the enclosing string reports that it is unterminated
+        let c = self.chars.peek().copied()?;
+        self.bump();
+        let mut digits = String::new();
+        let value = match c {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            'a' => Some('\x07'),
+            'b' => Some('\x08'),
+            'f' => Some('\x0c'),
+            'v' => Some('\x0b'),
+            '\\' | '"' | '\'' => Some(c),
+            '\n' => return None,
+            // Up to three octal digits, `\0` among them
+            '0'..='7' => {
+                digits.push(c);
+                self.lex_digits(&mut digits, 3, |d| d.is_digit(8));
+                u32::from_str_radix(&digits, 8)
+                    .ok()
+                    .and_then(char::from_u32)
+            }
+            'x' | 'u' | 'U' => {
+                let len = match c {
+                    'x' => 2,
+                    'u' => 4,
+                    _ => 8,
+                };
+                self.lex_digits(&mut digits, len, |d| d.is_ascii_hexdigit());
+                u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .filter(|_| digits.len() == len)
+                    .and_then(char::from_u32)
+            }
+            _ => None,
+        };
+        if value.is_none() {
+            self.errors.push(ParseError::new(
+                Span::new(start, self.pos),
+                ParseErrorKind::InvalidEscape(format!("\\{}{}", c, digits)),
+            ));
+        }
+        value
+    }
+
+    /// Append the digits of an escape to `digits` until it holds `max` of them.
+    fn lex_digits(&mut self, digits: &mut String, max: usize, is_digit: impl Fn(char) -> bool) {
+        while digits.len() < max
+            && let Some(d) = self.chars.peek().copied().filter(|&d| is_digit(d))
+        {
+            self.bump();
+            digits.push(d);
+        }
+    }
+
     fn lex_fstring(&mut self) -> Token {
-        self.chars.next(); // consume 'f'
-        self.chars.next(); // consume opening quote
-        let mut parts = vec![FStringPart::Text(String::new())];
-        let mut current_index = 0; // index of current text part
-        while let Some(c) = self.chars.next() {
+        self.bump(); // consume 'f'
+        self.bump(); // consume opening quote
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        loop {
+            match self.chars.peek().copied() {
+                Some('\n') | None => {
+                    self.unterminated_string();
+                    break;
+                }
+                Some('\\') => {
+                    if let Some(c) = self.lex_escape() {
+                        text.push(c);
+                    }
+                }
+                Some('"') => {
+                    self.bump();
+                    break;
+                }
+                // `{{` and `}}` stand for literal braces
+                Some(c @ ('{' | '}')) if self.peek_next() == Some(c) => {
+                    self.bump();
+                    self.bump();
+                    text.push(c);
+                }
+                Some('}') => {
+                    self.error_at_next_char(ParseErrorKind::InvalidFString(
+                        "single '}' is not allowed".to_string(),
+                    ));
+                }
+                Some('{') => {
+                    self.bump();
+                    parts.push(FStringPart::Text(std::mem::take(&mut text)));
+                    match self.lex_fstring_field() {
+                        Some(field) => parts.push(field),
+                        None => {
+                            self.unterminated_string();
+                            break;
+                        }
+                    }
+                }
+                Some(c) => {
+                    self.bump();
+                    text.push(c);
+                }
+            }
+        }
+        parts.push(FStringPart::Text(text));
+        Token::InterpolatedString(parts)
+    }
+
+    /// Lex an f-string field after its `{`, through the closing `}`. The expression
+    /// ends at the first `!`, `:` or `}` outside brackets and string literals.
+    /// `None` when the f-string ends first, which is reported as unterminated.
+    fn lex_fstring_field(&mut self) -> Option<FStringPart> {
+        let offset = self.pos;
+        let mut source = String::new();
+        let mut depth = 0usize;
+        loop {
+            let c = self.chars.peek().copied()?;
             match c {
-                '"' => break,
-                '{' => {
-                    let mut expr_src = String::new();
-                    while let Some(ch) = self.chars.next() {
-                        if ch == '}' {
+                '\n' => return None,
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' if depth > 0 => depth -= 1,
+                '}' => break,
+                ':' if depth == 0 => break,
+                '!' if depth == 0 && self.peek_next() != Some('=') => break,
+                '"' => {
+                    // A string literal inside the expression, which may hold braces
+                    source.push(c);
+                    self.bump();
+                    loop {
+                        let c = self.chars.peek().copied()?;
+                        if c == '\n' {
+                            return None;
+                        }
+                        source.push(c);
+                        self.bump();
+                        if c == '\\' {
+                            source.push(self.bump().filter(|&c| c != '\n')?);
+                        } else if c == '"' {
                             break;
-                        } else {
-                            expr_src.push(ch);
                         }
                     }
-                    parts.push(FStringPart::Expr(expr_src));
-                    parts.push(FStringPart::Text(String::new()));
-                    current_index = parts.len() - 1;
+                    continue;
                 }
-                _ => {
-                    if let FStringPart::Text(ref mut t) = parts[current_index] {
-                        t.push(c);
+                _ => {}
+            }
+            source.push(c);
+            self.bump();
+        }
+        let mut spec = FormatSpec::default();
+        if self.chars.peek() == Some(&'!') {
+            let start = self.pos;
+            self.bump();
+            let conversion = self.chars.peek().copied();
+            if !matches!(conversion, Some('}' | ':' | '"' | '\n') | None) {
+                self.bump();
+            }
+            spec.repr = conversion == Some('r');
+            if !matches!(conversion, Some('r' | 's')) {
+                self.errors.push(ParseError::new(
+                    Span::new(start, self.pos),
+                    ParseErrorKind::InvalidFString("expected 's' or 'r' after '!'".to_string()),
+                ));
+            }
+        }
+        if self.chars.peek() == Some(&':') {
+            self.bump();
+            let start = self.pos;
+            let mut text = String::new();
+            let mut nested = 0usize;
+            loop {
+                match self.chars.peek().copied()? {
+                    '\n' => return None,
+                    '}' if nested == 0 => break,
+                    c => {
+                        match c {
+                            '{' => nested += 1,
+                            '}' => nested -= 1,
+                            _ => {}
+                        }
+                        self.bump();
+                        text.push(c);
                     }
                 }
             }
+            let parsed = if text.contains('{') {
+                Err("nested fields in format specs are not supported".to_string())
+            } else {
+                FormatSpec::parse(&text)
+            };
+            match parsed {
+                Ok(parsed) => {
+                    spec = FormatSpec {
+                        repr: spec.repr,
+                        ..parsed
+                    }
+                }
+                Err(reason) => self.errors.push(ParseError::new(
+                    Span::new(start, self.pos),
+                    ParseErrorKind::InvalidFString(reason),
+                )),
+            }
         }
-        Token::InterpolatedString(parts)
+        if self.chars.peek() != Some(&'}') {
+            // Only a bad conversion leaves anything before the `}`
+            let start = self.pos;
+            while !matches!(self.chars.peek(), Some('}' | '\n' | '"') | None) {
+                self.bump();
+            }
+            self.errors.push(ParseError::new(
+                Span::new(start, self.pos),
+                ParseErrorKind::InvalidFString("expected '}'".to_string()),
+            ));
+            if self.chars.peek() != Some(&'}') {
+                return None;
+            }
+        }
+        self.bump();
+        Some(FStringPart::Expr {
+            source,
+            offset,
+            spec,
+        })
     }
 
     fn skip_inline_spaces(&mut self) {
         while let Some(&c) = self.chars.peek() {
             if c == ' ' || c == '\r' {
-                self.chars.next();
+                self.bump();
             } else if c == '\t' {
-                panic!("tabs are not allowed");
+                self.error_at_next_char(ParseErrorKind::Tab);
             } else {
                 break;
             }
         }
     }
 
+    /// Consume the next character and record an error covering it.
+    fn error_at_next_char(&mut self, kind: ParseErrorKind) {
+        let start = self.pos;
+        self.bump();
+        self.errors
+            .push(ParseError::new(Span::new(start, self.pos), kind));
+    }
+
+    /// Record an unterminated string running from the current token start to here.
+    fn unterminated_string(&mut self) {
+        self.errors.push(ParseError::new(
+            Span::new(self.tok_start, self.pos),
+            ParseErrorKind::UnterminatedString,
+        ));
+    }
+
+    /// Consume one character, keeping the byte offset in sync.
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
     fn peek_next(&mut self) -> Option<char> {
         let mut iter = self.chars.clone();
         iter.next();
@@ -1,4 +1,15 @@
 use super::*;
+use crate::format_spec::Align;
+use crate::parser::{ParseError, ParseErrorKind};
+
+fn token_kinds(input: &str) -> Vec<Token> {
+    Lexer::new(input)
+        .tokenize()
+        .unwrap()
+        .into_iter()
+        .map(|t| t.node)
+        .collect()
+}
 
 #[test]
 fn program1_tokens() {
@@ -6,7 +17,7 @@ fn program1_tokens() {
 x = x + 1
 print(x)
 "#;
-    let tokens = Lexer::new(input).tokenize();
+    let tokens = token_kinds(input);
     assert_eq!(
         tokens,
         vec![
@@ -36,7 +47,7 @@ fn program4_tokens() {
 x = "Hello"
 print(x)
 "#;
-    let tokens = Lexer::new(input).tokenize();
+    let tokens = token_kinds(input);
     assert_eq!(
         tokens,
         vec![
@@ -61,7 +72,7 @@ print(x)
 #[test]
 fn program2_tokens() {
     let input = r#"print("Hello, World")"#;
-    let tokens = Lexer::new(input).tokenize();
+    let tokens = token_kinds(input);
     assert_eq!(
         tokens,
         vec![
@@ -79,7 +90,7 @@ fn program3_tokens() {
     let input = r#"x = 12
 print(f"{x}")
 "#;
-    let tokens = Lexer::new(input).tokenize();
+    let tokens = token_kinds(input);
     assert_eq!(
         tokens,
         vec![
@@ -91,11 +102,382 @@ print(f"{x}")
             Token::LParen,
             Token::InterpolatedString(vec![
                 FStringPart::Text("".to_string()),
-                FStringPart::Expr("x".to_string()),
+                FStringPart::Expr {
+                    source: "x".to_string(),
+                    offset: 16,
+                    spec: FormatSpec::default(),
+                },
+                FStringPart::Text("".to_string()),
+            ]),
+            Token::RParen,
+            Token::Newline,
+            Token::EOF,
+        ]
+    );
+}
+
+#[test]
+fn token_spans_are_byte_offsets() {
+    let input = "x = 12\nif x:\n    print(\"hi\")\n";
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let spans: Vec<(Token, Span)> = tokens.into_iter().map(|t| (t.node, t.span)).collect();
+    assert_eq!(
+        spans,
+        vec![
+            (Token::Ident("x".to_string()), Span::new(0, 1)),
+            (Token::Equal, Span::new(2, 3)),
+            (Token::Int(12), Span::new(4, 6)),
+            (Token::Newline, Span::new(6, 7)),
+            (Token::IfKw, Span::new(7, 9)),
+            (Token::Ident("x".to_string()), Span::new(10, 11)),
+            (Token::Colon, Span::new(11, 12)),
+            (Token::Newline, Span::new(12, 13)),
+            (Token::Indent, Span::new(13, 17)),
+            (Token::Ident("print".to_string()), Span::new(17, 22)),
+            (Token::LParen, Span::new(22, 23)),
+            (Token::Str("hi".to_string()), Span::new(23, 27)),
+            (Token::RParen, Span::new(27, 28)),
+            (Token::Newline, Span::new(28, 29)),
+            (Token::Dedent, Span::new(29, 29)),
+            (Token::EOF, Span::new(29, 29)),
+        ]
+    );
+}
+
+#[test]
+fn lexer_errors_are_collected() {
+    let input = "if x:\n\ty = 1\nz = $\ns = \"open\n";
+    let errors = Lexer::new(input).tokenize().unwrap_err();
+    let kinds: Vec<ParseErrorKind> = errors.iter().map(|e| e.kind.clone()).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            ParseErrorKind::Tab,
+            ParseErrorKind::UnexpectedChar('$'),
+            ParseErrorKind::UnterminatedString,
+        ]
+    );
+    assert_eq!(errors[0].span, Span::new(6, 7));
+    assert_eq!(errors[1].span, Span::new(17, 18));
+    assert_eq!(errors[2].span, Span::new(23, 28));
+}
+
+#[test]
+fn bad_indentation_is_reported_not_panicking() {
+    let input = "if x:\n  y = 1\nz = 2\n";
+    let errors = Lexer::new(input).tokenize().unwrap_err();
+    assert_eq!(
+        errors,
+        vec![ParseError::new(
+            Span::new(6, 8),
+            ParseErrorKind::BadIndent {
+                expected: 4,
+                found: 2
+            }
+        )]
+    );
+}
+
+#[test]
+fn operator_tokens() {
+    let tokens = token_kinds("a - b * c / d // e % f == g != h < i <= j > k >= l and not m or n");
+    let ops: Vec<Token> = tokens
+        .into_iter()
+        .filter(|t| !matches!(t, Token::Ident(_) | Token::EOF))
+        .collect();
+    assert_eq!(
+        ops,
+        vec![
+            Token::Minus,
+            Token::Star,
+            Token::Slash,
+            Token::SlashSlash,
+            Token::Percent,
+            Token::EqualEqual,
+            Token::NotEqual,
+            Token::LAngle,
+            Token::LessEqual,
+            Token::RAngle,
+            Token::GreaterEqual,
+            Token::AndKw,
+            Token::NotKw,
+            Token::OrKw,
+        ]
+    );
+}
+
+#[test]
+fn float_literals() {
+    assert_eq!(
+        token_kinds("1.5 2e-3 3E+2 0..3 7.x"),
+        vec![
+            Token::Float(1.5),
+            Token::Float(0.002),
+            Token::Float(300.0),
+            Token::Int(0),
+            Token::DotDot,
+            Token::Int(3),
+            Token::Int(7),
+            Token::Dot,
+            Token::Ident("x".to_string()),
+            Token::EOF,
+        ]
+    );
+}
+
+#[test]
+fn oversized_integer_is_an_error() {
+    let errors = Lexer::new("x = 99999999999999999999\n")
+        .tokenize()
+        .unwrap_err();
+    assert_eq!(
+        errors,
+        vec![ParseError::new(
+            Span::new(4, 24),
+            ParseErrorKind::IntegerTooLarge
+        )]
+    );
+}
+
+#[test]
+fn string_escapes_and_triple_quotes() {
+    assert_eq!(
+        token_kinds(
+            r#""a\tb\n\"q\" \\ é" """two
+lines "x" \
+joined""""#
+        ),
+        vec![
+            Token::Str("a\tb\n\"q\" \\ é".to_string()),
+            Token::Str("two\nlines \"x\" joined".to_string()),
+            Token::EOF,
+        ]
+    );
+    let errors = Lexer::new("s = \"a\\qb\"\n").tokenize().unwrap_err();
+    assert_eq!(
+        errors,
+        vec![ParseError::new(
+            Span::new(6, 8),
+            ParseErrorKind::InvalidEscape("\\q".to_string())
+        )]
+    );
+}
+
+#[test]
+fn hex_unicode_octal_and_control_escapes() {
+    assert_eq!(
+        token_kinds(r#""\x41\U0001F600\101\0\12|\a\b\f\v""#),
+        vec![
+            Token::Str("A\u{1F600}A\0\n|\x07\x08\x0c\x0b".to_string()),
+            Token::EOF,
+        ]
+    );
+    let errors = Lexer::new("s = \"\\x4g \\U00110000\"\n")
+        .tokenize()
+        .unwrap_err();
+    assert_eq!(
+        errors,
+        vec![
+            ParseError::new(
+                Span::new(5, 8),
+                ParseErrorKind::InvalidEscape("\\x4".to_string())
+            ),
+            ParseError::new(
+                Span::new(10, 20),
+                ParseErrorKind::InvalidEscape("\\U00110000".to_string())
+            ),
+        ]
+    );
+}
+
+#[test]
+fn fstring_fields_with_specs_conversions_and_braces() {
+    assert_eq!(
+        token_kinds(r#"f"{{{x!r:>8}}} {", ".join(ys)}""#),
+        vec![
+            Token::InterpolatedString(vec![
+                FStringPart::Text("{".to_string()),
+                FStringPart::Expr {
+                    source: "x".to_string(),
+                    offset: 5,
+                    spec: FormatSpec {
+                        repr: true,
+                        align: Some(Align::Right),
+                        width: Some(8),
+                        ..FormatSpec::default()
+                    },
+                },
+                FStringPart::Text("} ".to_string()),
+                FStringPart::Expr {
+                    source: r#"", ".join(ys)"#.to_string(),
+                    offset: 16,
+                    spec: FormatSpec::default(),
+                },
                 FStringPart::Text("".to_string()),
             ]),
+            Token::EOF,
+        ]
+    );
+    let errors = Lexer::new("s = f\"{x:=8} {y!q} }\"\n")
+        .tokenize()
+        .unwrap_err();
+    let fstring_error = |start, end, reason: &str| {
+        ParseError::new(
+            Span::new(start, end),
+            ParseErrorKind::InvalidFString(reason.to_string()),
+        )
+    };
+    assert_eq!(
+        errors,
+        vec![
+            fstring_error(9, 11, "'=' alignment is not supported"),
+            fstring_error(15, 17, "expected 's' or 'r' after '!'"),
+            fstring_error(19, 20, "single '}' is not allowed"),
+        ]
+    );
+    let errors = Lexer::new("s = f\"{x:>4\n").tokenize().unwrap_err();
+    assert_eq!(
+        errors,
+        vec![ParseError::new(
+            Span::new(4, 11),
+            ParseErrorKind::UnterminatedString
+        )]
+    );
+}
+
+#[test]
+fn comments_are_skipped_and_doc_comments_precede_definitions() {
+    let input = "x = 1  # trailing\n# whole line\n## Add one.\n##   keeps indent\nfn inc(n):\n    ## not followed by a definition\n    n + 1\n";
+    assert_eq!(
+        token_kinds(input),
+        vec![
+            Token::Ident("x".to_string()),
+            Token::Equal,
+            Token::Int(1),
+            Token::Newline,
+            Token::Newline,
+            Token::Newline,
+            Token::Newline,
+            Token::DocComment("Add one.\n  keeps indent".to_string()),
+            Token::FnKw,
+            Token::Ident("inc".to_string()),
+            Token::LParen,
+            Token::Ident("n".to_string()),
+            Token::RParen,
+            Token::Colon,
+            Token::Newline,
+            Token::Newline,
+            Token::Indent,
+            Token::Ident("n".to_string()),
+            Token::Plus,
+            Token::Int(1),
+            Token::Newline,
+            Token::Dedent,
+            Token::EOF,
+        ]
+    );
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    assert_eq!(tokens[7].span, Span::new(31, 60));
+}
+
+#[test]
+fn line_breaks_inside_brackets_and_after_backslash_are_not_tokens() {
+    let input = "xs = [\n    1,  # one\n\n  2,\n]\ny = 1 + \\\n    2; z = 3\n";
+    assert_eq!(
+        token_kinds(input),
+        vec![
+            Token::Ident("xs".to_string()),
+            Token::Equal,
+            Token::LBracket,
+            Token::Int(1),
+            Token::Comma,
+            Token::Int(2),
+            Token::Comma,
+            Token::RBracket,
+            Token::Newline,
+            Token::Ident("y".to_string()),
+            Token::Equal,
+            Token::Int(1),
+            Token::Plus,
+            Token::Int(2),
+            Token::Semicolon,
+            Token::Ident("z".to_string()),
+            Token::Equal,
+            Token::Int(3),
+            Token::Newline,
+            Token::EOF,
+        ]
+    );
+
+    // Indentation only counts at the start of a logical line
+    let input = "if f(a,\n        b):\n    c = (1,\n  2)\nd\n";
+    assert_eq!(
+        token_kinds(input),
+        vec![
+            Token::IfKw,
+            Token::Ident("f".to_string()),
+            Token::LParen,
+            Token::Ident("a".to_string()),
+            Token::Comma,
+            Token::Ident("b".to_string()),
+            Token::RParen,
+            Token::Colon,
+            Token::Newline,
+            Token::Indent,
+            Token::Ident("c".to_string()),
+            Token::Equal,
+            Token::LParen,
+            Token::Int(1),
+            Token::Comma,
+            Token::Int(2),
             Token::RParen,
             Token::Newline,
+            Token::Dedent,
+            Token::Ident("d".to_string()),
+            Token::Newline,
+            Token::EOF,
+        ]
+    );
+}
+
+#[test]
+fn unicode_identifiers() {
+    let input = "数据 = имя_1 + café\n";
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let kinds: Vec<Token> = tokens.iter().map(|t| t.node.clone()).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            Token::Ident("数据".to_string()),
+            Token::Equal,
+            Token::Ident("имя_1".to_string()),
+            Token::Plus,
+            Token::Ident("café".to_string()),
+            Token::Newline,
+            Token::EOF,
+        ]
+    );
+    // Spans stay byte offsets
+    assert_eq!(tokens[0].span, Span::new(0, 6));
+    assert_eq!(tokens[2].span, Span::new(9, 17));
+
+    // Symbols that cannot be part of a name are still reported
+    let errors = Lexer::new("x€ = 1\n").tokenize().unwrap_err();
+    assert_eq!(errors[0].kind, ParseErrorKind::UnexpectedChar('€'));
+    assert_eq!(errors[0].span, Span::new(1, 4));
+}
+
+#[test]
+fn identifiers_are_nfkc_normalized() {
+    // `café` spelled with a combining acute accent, and `ﬁle` with the `ﬁ` ligature
+    let input = "cafe\u{301} = ﬁle\n";
+    assert_eq!(
+        token_kinds(input),
+        vec![
+            Token::Ident("caf\u{e9}".to_string()),
+            Token::Equal,
+            Token::Ident("file".to_string()),
+            Token::Newline,
             Token::EOF,
         ]
     );
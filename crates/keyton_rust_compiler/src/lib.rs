@@ -1,5 +1,6 @@
 pub mod compile_rust;
 pub mod diagnostics;
+pub mod format_spec;
 pub mod hir;
 pub mod lexer;
 pub mod parser;
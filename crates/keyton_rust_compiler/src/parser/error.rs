@@ -0,0 +1,110 @@
+use crate::lexer::Token;
+use crate::span::Span;
+
+/// A syntax error found while lexing or parsing. Both phases keep going after an
+/// error so that several problems can be reported at once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub span: Span,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    /// A tab character was used for indentation or spacing
+    Tab,
+    /// Indentation increased by something other than 4 spaces
+    BadIndent {
+        expected: usize,
+        found: usize,
+    },
+    /// Indentation decreased to a level no enclosing block uses
+    InvalidDedent,
+    UnexpectedChar(char),
+    UnterminatedString,
+    /// A backslash escape a string cannot contain, such as `\q`
+    InvalidEscape(String),
+    /// A malformed f-string field, such as a bad format spec or a lone `}`
+    InvalidFString(String),
+    /// An integer literal that does not fit in an `i64`
+    IntegerTooLarge,
+    /// `a < b < c`; comparisons must be combined with `and`
+    ChainedComparison,
+    /// A type given the wrong number of type arguments, e.g. `list[i64, str]`
+    TypeArguments {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    UnexpectedToken {
+        expected: Vec<String>,
+        found: Token,
+    },
+    /// A struct method whose first parameter is not `self`
+    MissingSelf {
+        method: String,
+    },
+}
+
+impl ParseError {
+    pub fn new(span: Span, kind: ParseErrorKind) -> Self {
+        Self { span, kind }
+    }
+
+    pub fn unexpected(span: Span, expected: &[&str], found: Token) -> Self {
+        Self::new(
+            span,
+            ParseErrorKind::UnexpectedToken {
+                expected: expected.iter().map(|s| s.to_string()).collect(),
+                found,
+            },
+        )
+    }
+
+    pub fn message(&self) -> String {
+        match &self.kind {
+            ParseErrorKind::Tab => "tabs are not allowed; indent with 4 spaces".to_string(),
+            ParseErrorKind::BadIndent { expected, found } => format!(
+                "indentation must increase by exactly 4 spaces (expected {}, found {})",
+                expected, found
+            ),
+            ParseErrorKind::InvalidDedent => {
+                "unindent does not match any outer indentation level".to_string()
+            }
+            ParseErrorKind::UnexpectedChar(c) => format!("invalid character '{}'", c),
+            ParseErrorKind::UnterminatedString => "unterminated string literal".to_string(),
+            ParseErrorKind::InvalidEscape(escape) => {
+                format!("invalid escape sequence '{}'", escape)
+            }
+            ParseErrorKind::InvalidFString(reason) => format!("f-string: {}", reason),
+            ParseErrorKind::IntegerTooLarge => "integer literal is too large for i64".to_string(),
+            ParseErrorKind::ChainedComparison => {
+                "comparison operators cannot be chained; combine them with 'and'".to_string()
+            }
+            ParseErrorKind::TypeArguments {
+                name, expected: 0, ..
+            } => format!("type '{}' does not take type arguments", name),
+            ParseErrorKind::TypeArguments {
+                name,
+                expected,
+                found,
+            } => format!(
+                "type '{}' takes {} type argument(s), found {}",
+                name, expected, found
+            ),
+            ParseErrorKind::UnexpectedToken { expected, found } => match expected.as_slice() {
+                [] => format!("unexpected {}", found.describe()),
+                [one] => format!("expected {}, found {}", one, found.describe()),
+                many => format!(
+                    "expected one of {}, found {}",
+                    many.join(", "),
+                    found.describe()
+                ),
+            },
+            ParseErrorKind::MissingSelf { method } => format!(
+                "method '{}' must take 'self' as its first parameter",
+                method
+            ),
+        }
+    }
+}
@@ -1,4 +1,12 @@
+use crate::format_spec::FormatSpec;
 use crate::lexer::{FStringPart, Lexer, Token};
+use crate::span::{Span, Spanned};
+
+mod error;
+
+pub use error::{ParseError, ParseErrorKind};
+
+type PResult<T> = Result<T, ParseError>;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Stmt {
@@ -13,211 +21,798 @@ pub enum Stmt {
     },
     Assign {
         name: String,
-        expr: Expr,
+        /// Declared type of `let x: T = ...` or `x: T = ...`
+        ty: Option<TypeExpr>,
+        expr: Spanned<Expr>,
+    },
+    /// `target[index] = expr`
+    IndexAssign {
+        target: Spanned<Expr>,
+        index: Spanned<Expr>,
+        expr: Spanned<Expr>,
+    },
+    /// `target.field = expr`
+    FieldAssign {
+        target: Spanned<Expr>,
+        field: String,
+        expr: Spanned<Expr>,
+    },
+    /// `a, b = expr`, unpacking a tuple
+    TupleAssign {
+        names: Vec<String>,
+        expr: Spanned<Expr>,
     },
     ForRange {
         var: String,
-        start: Expr,
-        end: Expr,
-        body: Vec<Stmt>,
+        start: Spanned<Expr>,
+        end: Spanned<Expr>,
+        body: Vec<Spanned<Stmt>>,
     },
+    /// `for x in expr:` over a list, string, dict or range; several names unpack
+    /// each item, as in `for k, v in items(d):`
+    ForEach {
+        vars: Vec<String>,
+        iter: Spanned<Expr>,
+        body: Vec<Spanned<Stmt>>,
+    },
+    While {
+        cond: Spanned<Expr>,
+        body: Vec<Spanned<Stmt>>,
+    },
+    Break,
+    Continue,
     If {
-        cond: Expr,
-        then_branch: Vec<Stmt>,
-        else_branch: Vec<Stmt>,
+        cond: Spanned<Expr>,
+        then_branch: Vec<Spanned<Stmt>>,
+        else_branch: Vec<Spanned<Stmt>>,
     },
-    ExprStmt(Expr),
+    ExprStmt(Spanned<Expr>),
     FuncDef {
         name: String,
-        params: Vec<String>,
-        body: Vec<Stmt>,
+        params: Vec<Param>,
+        /// Declared return type (`-> T`)
+        ret: Option<TypeExpr>,
+        body: Vec<Spanned<Stmt>>,
+        /// Text of the `##` comment lines above the definition
+        doc: Option<String>,
+    },
+    /// `return` with an optional value
+    Return(Option<Spanned<Expr>>),
+    /// `struct Name:` with typed fields and methods; every method is a `FuncDef`
+    /// whose first parameter is `self`
+    StructDef {
+        name: String,
+        fields: Vec<FieldDef>,
+        methods: Vec<Spanned<Stmt>>,
+        doc: Option<String>,
+    },
+    /// `enum Name:` with one variant per line
+    EnumDef {
+        name: String,
+        variants: Vec<VariantDef>,
+    },
+    /// `match subject:` with one `case` arm per pattern, tried in order
+    Match {
+        subject: Spanned<Expr>,
+        arms: Vec<MatchArm>,
+    },
+    /// `try:` with its `except` handlers, tried in order, and a `finally:` block
+    Try {
+        body: Vec<Spanned<Stmt>>,
+        handlers: Vec<ExceptHandler>,
+        finally: Vec<Spanned<Stmt>>,
+    },
+    /// `raise Kind("message")` or `raise Kind`; a bare `raise` re-raises the exception
+    /// being handled
+    Raise {
+        kind: Option<String>,
+        message: Option<Spanned<Expr>>,
+    },
+}
+
+/// A type annotation as written in the source.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeExpr {
+    /// `i64`, `str`, `bool`, `f64` or a type exported by a plugin
+    Name(String),
+    /// `list[T]`, also written `Vec<T>`
+    List(Box<TypeExpr>),
+    /// `dict[K, V]`
+    Dict(Box<TypeExpr>, Box<TypeExpr>),
+    /// `tuple[A, B, ...]`
+    Tuple(Vec<TypeExpr>),
+    /// `T?`, also written `Option[T]`
+    Option(Box<TypeExpr>),
+    /// `fn(A, B) -> R`, a function value
+    Func(Vec<TypeExpr>, Box<TypeExpr>),
+}
+
+/// A function parameter with its optional declared type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub ty: Option<TypeExpr>,
+}
+
+/// A struct field; unlike parameters, fields always declare their type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDef {
+    pub name: String,
+    pub ty: TypeExpr,
+}
+
+/// An enum variant: `Empty`, or `Circle(f64)` with the types of its payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantDef {
+    pub name: String,
+    pub fields: Vec<TypeExpr>,
+}
+
+/// `except Kind as name:` and its body; a bare `except:` handles every exception.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExceptHandler {
+    /// Span of the `except` clause up to its ':'
+    pub span: Span,
+    pub kind: Option<String>,
+    pub name: Option<String>,
+    pub body: Vec<Spanned<Stmt>>,
+}
+
+/// `case pattern:` and its body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Spanned<Pattern>,
+    pub body: Vec<Spanned<Stmt>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    /// `_` matches anything
+    Wildcard,
+    /// A name matches anything and binds it
+    Bind(String),
+    /// An int, float, string or bool literal, compared for equality
+    Literal(Expr),
+    /// `Shape.Circle(r)`, or `Shape.Empty` for a variant without payload
+    Variant {
+        enum_name: String,
+        variant: String,
+        args: Vec<Spanned<Pattern>>,
     },
-    Return(Expr),
+    /// `(a, b)`, matching each item of a tuple
+    Tuple(Vec<Spanned<Pattern>>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
     Int(i64),
+    Float(f64),
     Str(String),
     Ident(String),
     Bool(bool),
+    None,
     Binary {
-        left: Box<Expr>,
+        left: Box<Spanned<Expr>>,
         op: BinOp,
-        right: Box<Expr>,
+        right: Box<Spanned<Expr>>,
+    },
+    Unary {
+        op: UnaryOp,
+        expr: Box<Spanned<Expr>>,
     },
     Call {
-        func: Box<Expr>,
-        args: Vec<Expr>,
+        func: Box<Spanned<Expr>>,
+        args: Vec<Spanned<Expr>>,
+    },
+    /// `target[index]`
+    Index {
+        target: Box<Spanned<Expr>>,
+        index: Box<Spanned<Expr>>,
+    },
+    /// `target[start..end]`; either bound may be omitted
+    Slice {
+        target: Box<Spanned<Expr>>,
+        start: Option<Box<Spanned<Expr>>>,
+        end: Option<Box<Spanned<Expr>>>,
     },
     InterpolatedString(Vec<StringPart>),
+    /// `{key: value, ...}`
+    Dict(Vec<(Spanned<Expr>, Spanned<Expr>)>),
+    /// `(a, b)`, or `(a,)` with one item
+    Tuple(Vec<Spanned<Expr>>),
+    /// `start..end` used as a value, as in `r = 0..n`
+    Range {
+        start: Box<Spanned<Expr>>,
+        end: Box<Spanned<Expr>>,
+    },
+    /// `target.name`
+    Field {
+        target: Box<Spanned<Expr>>,
+        name: String,
+    },
+    /// `target.method(args)`; a method of a struct, or a function such as `append`
+    /// taking the target as its first argument
+    MethodCall {
+        target: Box<Spanned<Expr>>,
+        method: String,
+        args: Vec<Spanned<Expr>>,
+    },
+    /// `Name(field=value, ...)`, constructing a struct
+    Construct {
+        name: String,
+        fields: Vec<(String, Spanned<Expr>)>,
+    },
+    /// `fn(x, y) => x + y`; the body may use variables of the enclosing scope
+    Lambda {
+        params: Vec<Param>,
+        body: Box<Spanned<Expr>>,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum BinOp {
     Add,
+    Sub,
+    Mul,
+    Div,
+    FloorDiv,
+    Mod,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    /// Membership test `x in xs`
+    In,
+    NotIn,
+    /// `x is None`; the right side is always `None`
+    Is,
+    IsNot,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum StringPart {
     Text(String),
-    Expr(Box<Expr>),
+    /// A field and how it is formatted, as in `{ratio:.2f}`
+    Expr(Box<Spanned<Expr>>, FormatSpec),
 }
 
 pub struct Parser {
-    tokens: Vec<Token>,
+    tokens: Vec<Spanned<Token>>,
     pos: usize,
+    /// End offset of the most recently consumed token
+    prev_end: usize,
+    /// Errors recovered from so far; statements that failed to parse are dropped
+    errors: Vec<ParseError>,
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, pos: 0 }
+    pub fn new(tokens: Vec<Spanned<Token>>) -> Self {
+        Self {
+            tokens,
+            pos: 0,
+            prev_end: 0,
+            errors: Vec::new(),
+        }
     }
 
-    pub fn parse_program(&mut self) -> Vec<Stmt> {
+    /// Parse the whole token stream. A statement that fails to parse is reported and
+    /// skipped up to the next statement boundary, so every syntax error in the input
+    /// is returned in one pass.
+    pub fn parse_program(&mut self) -> Result<Vec<Spanned<Stmt>>, Vec<ParseError>> {
         let mut stmts = Vec::new();
         self.skip_newlines();
         while !self.is_at_end() {
-            if let Some(stmt) = self.parse_stmt() {
+            if let Some(stmt) = self.parse_stmt_recovering() {
                 stmts.push(stmt);
             }
             self.skip_newlines();
         }
-        stmts
+        if self.errors.is_empty() {
+            Ok(stmts)
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    /// Parse an expression. Precedence, loosest first:
+    /// `or`, `and`, `not`, comparisons, `+ -`, `* / // %`, unary `-`, postfix.
+    /// Binary operators are left-associative; comparisons do not chain.
+    pub fn parse_expr(&mut self) -> PResult<Spanned<Expr>> {
+        self.parse_or()
+    }
+
+    fn parse_or(&mut self) -> PResult<Spanned<Expr>> {
+        let start = self.start();
+        let mut left = self.parse_and()?;
+        while matches!(self.peek(), Token::OrKw) {
+            self.advance();
+            let right = self.parse_and()?;
+            left = self.binary(start, left, BinOp::Or, right);
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> PResult<Spanned<Expr>> {
+        let start = self.start();
+        let mut left = self.parse_not()?;
+        while matches!(self.peek(), Token::AndKw) {
+            self.advance();
+            let right = self.parse_not()?;
+            left = self.binary(start, left, BinOp::And, right);
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> PResult<Spanned<Expr>> {
+        if matches!(self.peek(), Token::NotKw) {
+            let start = self.start();
+            self.advance();
+            let expr = self.parse_not()?;
+            return Ok(self.unary(start, UnaryOp::Not, expr));
+        }
+        self.parse_comparison()
     }
 
-    pub fn parse_expr(&mut self) -> Expr {
-        let mut left = self.parse_primary();
-        while matches!(self.peek(), Token::Plus) {
+    fn parse_comparison(&mut self) -> PResult<Spanned<Expr>> {
+        let start = self.start();
+        let left = self.parse_additive()?;
+        let Some(op) = self.peek_comparison() else {
+            return Ok(left);
+        };
+        self.advance();
+        if matches!(op, BinOp::NotIn | BinOp::IsNot) {
             self.advance();
-            let right = self.parse_primary();
-            left = Expr::Binary {
+        }
+        // Without object identity, `is` only tests for `None`
+        if matches!(op, BinOp::Is | BinOp::IsNot) && !matches!(self.peek(), Token::NoneKw) {
+            return Err(self.unexpected(&["'None'"]));
+        }
+        let right = self.parse_additive()?;
+        if self.peek_comparison().is_some() {
+            return Err(ParseError::new(
+                self.peek_span(),
+                ParseErrorKind::ChainedComparison,
+            ));
+        }
+        Ok(self.binary(start, left, op, right))
+    }
+
+    /// The comparison operator at the cursor; `not in` and `is not` span two tokens.
+    fn peek_comparison(&self) -> Option<BinOp> {
+        if matches!(self.peek(), Token::NotKw) && self.peek_next_is(Token::InKw) {
+            return Some(BinOp::NotIn);
+        }
+        if matches!(self.peek(), Token::IsKw) && self.peek_next_is(Token::NotKw) {
+            return Some(BinOp::IsNot);
+        }
+        comparison_op(&self.peek())
+    }
+
+    fn parse_additive(&mut self) -> PResult<Spanned<Expr>> {
+        let start = self.start();
+        let mut left = self.parse_term()?;
+        loop {
+            let op = match self.peek() {
+                Token::Plus => BinOp::Add,
+                Token::Minus => BinOp::Sub,
+                _ => break,
+            };
+            self.advance();
+            let right = self.parse_term()?;
+            left = self.binary(start, left, op, right);
+        }
+        Ok(left)
+    }
+
+    fn parse_term(&mut self) -> PResult<Spanned<Expr>> {
+        let start = self.start();
+        let mut left = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Token::Star => BinOp::Mul,
+                Token::Slash => BinOp::Div,
+                Token::SlashSlash => BinOp::FloorDiv,
+                Token::Percent => BinOp::Mod,
+                _ => break,
+            };
+            self.advance();
+            let right = self.parse_unary()?;
+            left = self.binary(start, left, op, right);
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> PResult<Spanned<Expr>> {
+        if matches!(self.peek(), Token::Minus) {
+            let start = self.start();
+            self.advance();
+            let expr = self.parse_unary()?;
+            return Ok(self.unary(start, UnaryOp::Neg, expr));
+        }
+        self.parse_primary()
+    }
+
+    fn binary(
+        &self,
+        start: usize,
+        left: Spanned<Expr>,
+        op: BinOp,
+        right: Spanned<Expr>,
+    ) -> Spanned<Expr> {
+        Spanned::new(
+            Expr::Binary {
                 left: Box::new(left),
-                op: BinOp::Add,
+                op,
                 right: Box::new(right),
-            };
+            },
+            self.finish(start),
+        )
+    }
+
+    fn unary(&self, start: usize, op: UnaryOp, expr: Spanned<Expr>) -> Spanned<Expr> {
+        Spanned::new(
+            Expr::Unary {
+                op,
+                expr: Box::new(expr),
+            },
+            self.finish(start),
+        )
+    }
+
+    /// Parse one statement; on error record it and resynchronize at the next statement.
+    fn parse_stmt_recovering(&mut self) -> Option<Spanned<Stmt>> {
+        self.recovering(Self::parse_stmt).flatten()
+    }
+
+    /// Run `parse`; on error record it and resynchronize at the next statement.
+    fn recovering<T>(&mut self, parse: impl FnOnce(&mut Self) -> PResult<T>) -> Option<T> {
+        let before = self.pos;
+        match parse(self) {
+            Ok(node) => Some(node),
+            Err(err) => {
+                self.errors.push(err);
+                self.synchronize();
+                if self.pos == before {
+                    // Always make progress, even if the offending token is a boundary
+                    self.advance();
+                }
+                None
+            }
+        }
+    }
+
+    /// Skip to the start of the next statement: past the end of the current line and
+    /// past any indented block that belongs to it. A dedent closing the enclosing
+    /// block is left for the block parser.
+    fn synchronize(&mut self) {
+        let mut depth = 0usize;
+        loop {
+            match self.peek() {
+                Token::EOF => return,
+                Token::Newline if depth == 0 => {
+                    self.advance();
+                    if !matches!(self.peek(), Token::Indent) {
+                        return;
+                    }
+                }
+                Token::Dedent if depth == 0 => return,
+                Token::Indent => {
+                    depth += 1;
+                    self.advance();
+                }
+                Token::Dedent => {
+                    depth -= 1;
+                    self.advance();
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                _ => {
+                    self.advance();
+                }
+            }
         }
-        left
     }
 
-    fn parse_stmt(&mut self) -> Option<Stmt> {
+    fn parse_stmt(&mut self) -> PResult<Option<Spanned<Stmt>>> {
         if self.is_at_end() {
-            return None;
+            return Ok(None);
+        }
+        let doc = self.take_doc();
+        let start = self.start();
+        let mut stmt = self.parse_stmt_kind()?;
+        let span = self.finish(start);
+        if let Stmt::FuncDef { doc: slot, .. } | Stmt::StructDef { doc: slot, .. } = &mut stmt {
+            *slot = doc;
+        }
+        // Simple statements end the line or a `;`; compound ones end with their block
+        if !matches!(
+            stmt,
+            Stmt::If { .. }
+                | Stmt::ForRange { .. }
+                | Stmt::ForEach { .. }
+                | Stmt::While { .. }
+                | Stmt::FuncDef { .. }
+                | Stmt::StructDef { .. }
+                | Stmt::EnumDef { .. }
+                | Stmt::Match { .. }
+                | Stmt::Try { .. }
+        ) {
+            if matches!(self.peek(), Token::Semicolon) {
+                self.advance();
+            } else if !matches!(self.peek(), Token::Newline | Token::Dedent | Token::EOF) {
+                return Err(self.unexpected(&["newline"]));
+            }
         }
+        Ok(Some(Spanned::new(stmt, span)))
+    }
+
+    fn parse_stmt_kind(&mut self) -> PResult<Stmt> {
         // rimport statements
         if matches!(self.peek(), Token::RimportKw) {
             self.advance(); // 'rimport'
-            let module = match self.advance() {
-                Token::Ident(s) => s,
-                other => panic!("expected module name after rimport, found {:?}", other),
-            };
-            return Some(Stmt::RImportModule { module });
+            let module = self.expect_ident("module name")?;
+            return Ok(Stmt::RImportModule { module });
         }
         // from X rimport A, B, ...
         if matches!(self.peek(), Token::FromKw) {
             self.advance(); // 'from'
-            let module = match self.advance() {
-                Token::Ident(s) => s,
-                other => panic!("expected module name after from, found {:?}", other),
-            };
-            self.expect(Token::RimportKw);
+            let module = self.expect_ident("module name")?;
+            self.expect(Token::RimportKw)?;
             let mut items = Vec::new();
             loop {
-                let name = match self.advance() {
-                    Token::Ident(s) => s,
-                    other => panic!("expected identifier in rimport list, found {:?}", other),
-                };
-                items.push(name);
+                items.push(self.expect_ident("identifier")?);
                 if matches!(self.peek(), Token::Comma) {
                     self.advance();
                     continue;
                 }
                 break;
             }
-            return Some(Stmt::RImportItems { module, items });
+            return Ok(Stmt::RImportItems { module, items });
         }
         // Let declaration (desugars to assignment)
         if matches!(self.peek(), Token::LetKw) {
             self.advance(); // 'let'
-            let name = match self.advance() {
-                Token::Ident(s) => s,
-                other => panic!("expected identifier after let, found {:?}", other),
-            };
-            self.expect(Token::Colon);
-            // Skip type tokens until '='
-            while !matches!(self.peek(), Token::Equal) {
-                self.advance();
-            }
-            self.expect(Token::Equal);
-            let expr = self.parse_expr();
-            return Some(Stmt::Assign { name, expr });
+            let name = self.expect_ident("identifier")?;
+            self.expect(Token::Colon)?;
+            let ty = Some(self.parse_type()?);
+            self.expect(Token::Equal)?;
+            let expr = self.parse_expr()?;
+            return Ok(Stmt::Assign { name, ty, expr });
         }
         // For loop
         if matches!(self.peek(), Token::ForKw) {
-            return Some(self.parse_for_range());
+            return self.parse_for();
+        }
+        // While loop and loop control
+        if matches!(self.peek(), Token::WhileKw) {
+            return self.parse_while();
+        }
+        if matches!(self.peek(), Token::BreakKw) {
+            self.advance();
+            return Ok(Stmt::Break);
+        }
+        if matches!(self.peek(), Token::ContinueKw) {
+            self.advance();
+            return Ok(Stmt::Continue);
         }
         // If statement
         if matches!(self.peek(), Token::IfKw) {
-            return Some(self.parse_if());
+            return self.parse_if();
         }
         // Function definition
         if matches!(self.peek(), Token::FnKw) {
-            return Some(self.parse_func_def());
+            return self.parse_func_def();
+        }
+        if matches!(self.peek(), Token::StructKw) {
+            return self.parse_struct_def();
+        }
+        if matches!(self.peek(), Token::EnumKw) {
+            return self.parse_enum_def();
+        }
+        if matches!(self.peek(), Token::MatchKw) {
+            return self.parse_match();
+        }
+        if matches!(self.peek(), Token::TryKw) {
+            return self.parse_try();
+        }
+        if matches!(self.peek(), Token::RaiseKw) {
+            return self.parse_raise();
         }
         // Return statement
         if matches!(self.peek(), Token::ReturnKw) {
             self.advance();
-            let expr = self.parse_expr();
-            return Some(Stmt::Return(expr));
+            if matches!(
+                self.peek(),
+                Token::Newline | Token::Semicolon | Token::Dedent | Token::EOF
+            ) {
+                return Ok(Stmt::Return(None));
+            }
+            let expr = self.parse_expr_or_tuple()?;
+            return Ok(Stmt::Return(Some(expr)));
         }
-        if let Token::Ident(name) = self.peek().clone() {
-            if self.peek_next_is(Token::Colon) {
-                // Typed assignment without let: name: Type = expr
+        if let Token::Ident(name) = self.peek() {
+            if self.peek_next_is(Token::Comma) {
+                // Unpacking: a, b = expr
                 self.advance(); // ident
-                self.expect(Token::Colon);
-                while !matches!(self.peek(), Token::Equal) {
+                let mut names = vec![name];
+                while matches!(self.peek(), Token::Comma) {
                     self.advance();
+                    names.push(self.expect_ident("variable name")?);
                 }
-                self.expect(Token::Equal);
-                let expr = self.parse_expr();
-                return Some(Stmt::Assign { name, expr });
+                self.expect(Token::Equal)?;
+                let expr = self.parse_expr_or_tuple()?;
+                return Ok(Stmt::TupleAssign { names, expr });
+            } else if self.peek_next_is(Token::Colon) {
+                // Typed assignment without let: name: Type = expr
+                self.advance(); // ident
+                self.expect(Token::Colon)?;
+                let ty = Some(self.parse_type()?);
+                self.expect(Token::Equal)?;
+                let expr = self.parse_expr_or_tuple()?;
+                return Ok(Stmt::Assign { name, ty, expr });
             } else if self.peek_next_is(Token::Equal) {
                 self.advance(); // ident
                 self.advance(); // '='
-                let expr = self.parse_expr();
-                return Some(Stmt::Assign { name, expr });
+                let expr = self.parse_expr_or_tuple()?;
+                return Ok(Stmt::Assign {
+                    name,
+                    ty: None,
+                    expr,
+                });
             } else if self.peek_next_is(Token::PlusEqual) {
                 // Desugar: x += y  =>  x = x + y
+                let name_span = self.peek_span();
                 self.advance(); // ident
                 self.advance(); // '+='
-                let rhs = self.parse_expr();
-                let lhs = Expr::Ident(name.clone());
-                let expr = Expr::Binary {
-                    left: Box::new(lhs),
-                    op: BinOp::Add,
-                    right: Box::new(rhs),
-                };
-                return Some(Stmt::Assign { name, expr });
+                let rhs = self.parse_expr()?;
+                let lhs = Spanned::new(Expr::Ident(name.clone()), name_span);
+                let span = Span::new(name_span.start, rhs.span.end);
+                let expr = Spanned::new(
+                    Expr::Binary {
+                        left: Box::new(lhs),
+                        op: BinOp::Add,
+                        right: Box::new(rhs),
+                    },
+                    span,
+                );
+                return Ok(Stmt::Assign {
+                    name,
+                    ty: None,
+                    expr,
+                });
             }
         }
-        let expr = self.parse_expr();
-        Some(Stmt::ExprStmt(expr))
+        let expr = self.parse_expr()?;
+        if matches!(expr.node, Expr::Index { .. } | Expr::Field { .. })
+            && matches!(self.peek(), Token::Equal | Token::PlusEqual)
+        {
+            let augmented = matches!(self.advance(), Token::PlusEqual);
+            let rhs = self.parse_expr()?;
+            let value = if augmented {
+                // Desugar: xs[i] += y  =>  xs[i] = xs[i] + y, and likewise for p.x += y
+                let span = Span::new(expr.span.start, rhs.span.end);
+                Spanned::new(
+                    Expr::Binary {
+                        left: Box::new(expr.clone()),
+                        op: BinOp::Add,
+                        right: Box::new(rhs),
+                    },
+                    span,
+                )
+            } else {
+                rhs
+            };
+            return Ok(match expr.node {
+                Expr::Index { target, index } => Stmt::IndexAssign {
+                    target: *target,
+                    index: *index,
+                    expr: value,
+                },
+                Expr::Field { target, name } => Stmt::FieldAssign {
+                    target: *target,
+                    field: name,
+                    expr: value,
+                },
+                _ => unreachable!("only index and field targets are assigned"),
+            });
+        }
+        Ok(Stmt::ExprStmt(expr))
+    }
+
+    /// Parse a type annotation: a type name, optionally followed by type arguments in
+    /// `[...]` (or `<...>`, the older spelling used for `Vec<T>`).
+    /// Parse a type; a trailing `?` makes it optional, as in `i64?` or `list[str]?`.
+    fn parse_type(&mut self) -> PResult<TypeExpr> {
+        let mut ty = self.parse_type_name()?;
+        while matches!(self.peek(), Token::Question) {
+            self.advance();
+            ty = TypeExpr::Option(Box::new(ty));
+        }
+        Ok(ty)
     }
 
-    fn parse_func_def(&mut self) -> Stmt {
-        self.expect(Token::FnKw);
-        let name = match self.advance() {
-            Token::Ident(s) => s,
-            other => panic!("expected function name, found {:?}", other),
+    fn parse_type_name(&mut self) -> PResult<TypeExpr> {
+        if matches!(self.peek(), Token::FnKw) {
+            return self.parse_func_type();
+        }
+        let start = self.start();
+        let name = self.expect_ident("type")?;
+        let close = match self.peek() {
+            Token::LBracket => Token::RBracket,
+            Token::LAngle => Token::RAngle,
+            _ => return Ok(TypeExpr::Name(name)),
         };
-        self.expect(Token::LParen);
+        self.advance();
+        let mut args = vec![self.parse_type()?];
+        while matches!(self.peek(), Token::Comma) {
+            self.advance();
+            args.push(self.parse_type()?);
+        }
+        self.expect(close)?;
+        let found = args.len();
+        let mut args = args.into_iter().map(Box::new);
+        match (name.as_str(), found) {
+            ("list" | "Vec", 1) => Ok(TypeExpr::List(args.next().unwrap())),
+            ("dict" | "HashMap", 2) => {
+                Ok(TypeExpr::Dict(args.next().unwrap(), args.next().unwrap()))
+            }
+            ("tuple", _) => Ok(TypeExpr::Tuple(args.map(|t| *t).collect())),
+            ("Option", 1) => Ok(TypeExpr::Option(args.next().unwrap())),
+            _ => {
+                let expected = match name.as_str() {
+                    "list" | "Vec" | "Option" => 1,
+                    "dict" | "HashMap" => 2,
+                    _ => 0,
+                };
+                Err(ParseError::new(
+                    self.finish(start),
+                    ParseErrorKind::TypeArguments {
+                        name,
+                        expected,
+                        found,
+                    },
+                ))
+            }
+        }
+    }
+
+    /// Parse `name` or `name: T` in a parameter list.
+    /// Parse `fn(A, B) -> R`; the return type is required.
+    fn parse_func_type(&mut self) -> PResult<TypeExpr> {
+        self.expect(Token::FnKw)?;
+        self.expect(Token::LParen)?;
+        let mut params = Vec::new();
+        if !matches!(self.peek(), Token::RParen) {
+            params.push(self.parse_type()?);
+            while matches!(self.peek(), Token::Comma) {
+                self.advance();
+                params.push(self.parse_type()?);
+            }
+        }
+        self.expect(Token::RParen)?;
+        self.expect(Token::Arrow)?;
+        let ret = self.parse_type()?;
+        Ok(TypeExpr::Func(params, Box::new(ret)))
+    }
+
+    /// Parse a parenthesized parameter list, as of a function or a lambda.
+    fn parse_params(&mut self) -> PResult<Vec<Param>> {
+        self.expect(Token::LParen)?;
         let mut params = Vec::new();
         if !matches!(self.peek(), Token::RParen) {
             loop {
-                match self.advance() {
-                    Token::Ident(p) => params.push(p),
-                    other => panic!("expected parameter name, found {:?}", other),
-                }
+                params.push(self.parse_param()?);
                 if matches!(self.peek(), Token::Comma) {
                     self.advance();
                     continue;
@@ -225,195 +820,717 @@ impl Parser {
                 break;
             }
         }
-        self.expect(Token::RParen);
-        self.expect(Token::Colon);
-        self.expect(Token::Newline);
-        self.expect(Token::Indent);
-        let mut body = Vec::new();
-        self.skip_newlines();
-        while !matches!(self.peek(), Token::Dedent | Token::EOF) {
-            if let Some(stmt) = self.parse_stmt() {
-                body.push(stmt);
-            }
-            self.skip_newlines();
-        }
-        self.expect(Token::Dedent);
+        self.expect(Token::RParen)?;
+        Ok(params)
+    }
+
+    fn parse_param(&mut self) -> PResult<Param> {
+        let name = self.expect_ident("parameter name")?;
+        let ty = if matches!(self.peek(), Token::Colon) {
+            self.advance();
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        Ok(Param { name, ty })
+    }
+
+    fn parse_func_def(&mut self) -> PResult<Stmt> {
+        self.expect(Token::FnKw)?;
+        let name = self.expect_ident("function name")?;
+        let params = self.parse_params()?;
+        let ret = if matches!(self.peek(), Token::Arrow) {
+            self.advance();
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        self.expect(Token::Colon)?;
+        let mut body = self.parse_block()?;
 
         // If the last line is an expression statement, treat it as an implicit return
         if let Some(last) = body.pop() {
-            match last {
-                Stmt::ExprStmt(expr) => body.push(Stmt::Return(expr)),
-                other => body.push(other),
+            match last.node {
+                Stmt::ExprStmt(expr) => {
+                    body.push(Spanned::new(Stmt::Return(Some(expr)), last.span))
+                }
+                other => body.push(Spanned::new(other, last.span)),
             }
         }
-        Stmt::FuncDef { name, params, body }
+        Ok(Stmt::FuncDef {
+            name,
+            params,
+            ret,
+            body,
+            doc: None,
+        })
     }
 
-    fn parse_for_range(&mut self) -> Stmt {
-        self.expect(Token::ForKw);
-        let var = match self.advance() {
-            Token::Ident(s) => s,
-            other => panic!("expected loop variable name, found {:?}", other),
-        };
-        self.expect(Token::InKw);
-        let start = self.parse_expr();
-        self.expect(Token::DotDot);
-        let end = self.parse_expr();
-        self.expect(Token::Colon);
-        self.expect(Token::Newline);
-        self.expect(Token::Indent);
-        let mut body = Vec::new();
+    /// Parse a `struct` block: one `name: T` line per field and `fn` methods taking
+    /// `self`. A member that fails to parse is reported and skipped.
+    fn parse_struct_def(&mut self) -> PResult<Stmt> {
+        self.expect(Token::StructKw)?;
+        let name = self.expect_ident("struct name")?;
+        self.expect(Token::Colon)?;
+        self.expect_block_start()?;
+        let mut fields = Vec::new();
+        let mut methods = Vec::new();
         self.skip_newlines();
         while !matches!(self.peek(), Token::Dedent | Token::EOF) {
-            if let Some(stmt) = self.parse_stmt() {
-                body.push(stmt);
+            if matches!(self.peek(), Token::FnKw | Token::DocComment(_)) {
+                methods.extend(self.recovering(Self::parse_method));
+            } else {
+                fields.extend(self.recovering(Self::parse_field));
             }
             self.skip_newlines();
         }
-        self.expect(Token::Dedent);
-        Stmt::ForRange {
-            var,
-            start,
-            end,
-            body,
+        self.expect(Token::Dedent)?;
+        Ok(Stmt::StructDef {
+            name,
+            fields,
+            methods,
+            doc: None,
+        })
+    }
+
+    fn parse_field(&mut self) -> PResult<FieldDef> {
+        let name = self.expect_ident("field name")?;
+        self.expect(Token::Colon)?;
+        let ty = self.parse_type()?;
+        if !matches!(self.peek(), Token::Newline | Token::Dedent | Token::EOF) {
+            return Err(self.unexpected(&["newline"]));
+        }
+        Ok(FieldDef { name, ty })
+    }
+
+    fn parse_method(&mut self) -> PResult<Spanned<Stmt>> {
+        let doc = self.take_doc();
+        let start = self.start();
+        let mut method = self.parse_func_def()?;
+        let span = self.finish(start);
+        if let Stmt::FuncDef { doc: slot, .. } = &mut method {
+            *slot = doc;
+        }
+        if let Stmt::FuncDef { name, params, .. } = &method
+            && params.first().is_none_or(|p| p.name != "self")
+        {
+            return Err(ParseError::new(
+                span,
+                ParseErrorKind::MissingSelf {
+                    method: name.clone(),
+                },
+            ));
         }
+        Ok(Spanned::new(method, span))
     }
 
-    fn parse_if(&mut self) -> Stmt {
-        self.expect(Token::IfKw);
-        let cond = self.parse_expr();
-        self.expect(Token::Colon);
-        self.expect(Token::Newline);
-        self.expect(Token::Indent);
-        let mut then_branch = Vec::new();
+    /// Parse an `enum` block with one variant per line. A variant that fails to parse
+    /// is reported and skipped.
+    fn parse_enum_def(&mut self) -> PResult<Stmt> {
+        self.expect(Token::EnumKw)?;
+        let name = self.expect_ident("enum name")?;
+        self.expect(Token::Colon)?;
+        self.expect_block_start()?;
+        let mut variants = Vec::new();
         self.skip_newlines();
         while !matches!(self.peek(), Token::Dedent | Token::EOF) {
-            if let Some(stmt) = self.parse_stmt() {
-                then_branch.push(stmt);
-            }
+            variants.extend(self.recovering(Self::parse_variant));
             self.skip_newlines();
         }
-        self.expect(Token::Dedent);
+        self.expect(Token::Dedent)?;
+        Ok(Stmt::EnumDef { name, variants })
+    }
+
+    fn parse_variant(&mut self) -> PResult<VariantDef> {
+        let name = self.expect_ident("variant name")?;
+        let mut fields = Vec::new();
+        if matches!(self.peek(), Token::LParen) {
+            self.advance();
+            fields.push(self.parse_type()?);
+            while matches!(self.peek(), Token::Comma) {
+                self.advance();
+                fields.push(self.parse_type()?);
+            }
+            self.expect(Token::RParen)?;
+        }
+        if !matches!(self.peek(), Token::Newline | Token::Dedent | Token::EOF) {
+            return Err(self.unexpected(&["newline"]));
+        }
+        Ok(VariantDef { name, fields })
+    }
+
+    /// Parse a `match` block. An arm that fails to parse is reported and skipped.
+    fn parse_match(&mut self) -> PResult<Stmt> {
+        self.expect(Token::MatchKw)?;
+        let subject = self.parse_expr_or_tuple()?;
+        self.expect(Token::Colon)?;
+        self.expect_block_start()?;
+        let mut arms = Vec::new();
         self.skip_newlines();
+        while !matches!(self.peek(), Token::Dedent | Token::EOF) {
+            arms.extend(self.recovering(Self::parse_case));
+            self.skip_newlines();
+        }
+        self.expect(Token::Dedent)?;
+        Ok(Stmt::Match { subject, arms })
+    }
 
-        let mut else_branch = Vec::new();
-        if matches!(self.peek(), Token::ElseKw) {
-            self.expect(Token::ElseKw);
-            self.expect(Token::Colon);
-            self.expect(Token::Newline);
-            self.expect(Token::Indent);
+    /// Parse `try:` and the `except` and `finally` clauses that follow it; at least one
+    /// of them is required.
+    fn parse_try(&mut self) -> PResult<Stmt> {
+        self.expect(Token::TryKw)?;
+        self.expect(Token::Colon)?;
+        let body = self.parse_block()?;
+        self.skip_newlines();
+        let mut handlers = Vec::new();
+        while matches!(self.peek(), Token::ExceptKw) {
+            let start = self.start();
+            self.advance();
+            let mut kind = None;
+            let mut name = None;
+            if !matches!(self.peek(), Token::Colon) {
+                kind = Some(self.expect_ident("exception kind")?);
+                if matches!(self.peek(), Token::AsKw) {
+                    self.advance();
+                    name = Some(self.expect_ident("identifier")?);
+                }
+            }
+            let span = self.finish(start);
+            self.expect(Token::Colon)?;
+            let body = self.parse_block()?;
+            handlers.push(ExceptHandler {
+                span,
+                kind,
+                name,
+                body,
+            });
             self.skip_newlines();
-            while !matches!(self.peek(), Token::Dedent | Token::EOF) {
-                if let Some(stmt) = self.parse_stmt() {
-                    else_branch.push(stmt);
+        }
+        let mut finally = Vec::new();
+        if matches!(self.peek(), Token::FinallyKw) {
+            self.advance();
+            self.expect(Token::Colon)?;
+            finally = self.parse_block()?;
+        } else if handlers.is_empty() {
+            return Err(self.unexpected(&["'except'", "'finally'"]));
+        }
+        Ok(Stmt::Try {
+            body,
+            handlers,
+            finally,
+        })
+    }
+
+    fn parse_raise(&mut self) -> PResult<Stmt> {
+        self.expect(Token::RaiseKw)?;
+        if matches!(
+            self.peek(),
+            Token::Newline | Token::Semicolon | Token::Dedent | Token::EOF
+        ) {
+            return Ok(Stmt::Raise {
+                kind: None,
+                message: None,
+            });
+        }
+        let kind = self.expect_ident("exception kind")?;
+        let mut message = None;
+        if matches!(self.peek(), Token::LParen) {
+            self.advance();
+            message = Some(self.parse_expr()?);
+            self.expect(Token::RParen)?;
+        }
+        Ok(Stmt::Raise {
+            kind: Some(kind),
+            message,
+        })
+    }
+
+    fn parse_case(&mut self) -> PResult<MatchArm> {
+        self.expect(Token::CaseKw)?;
+        // `case a, b:` is the tuple pattern `(a, b)`
+        let start = self.start();
+        let first = self.parse_pattern()?;
+        let pattern = if matches!(self.peek(), Token::Comma) {
+            let mut items = vec![first];
+            while matches!(self.peek(), Token::Comma) {
+                self.advance();
+                items.push(self.parse_pattern()?);
+            }
+            Spanned::new(Pattern::Tuple(items), self.finish(start))
+        } else {
+            first
+        };
+        self.expect(Token::Colon)?;
+        let body = self.parse_block()?;
+        Ok(MatchArm { pattern, body })
+    }
+
+    fn parse_pattern(&mut self) -> PResult<Spanned<Pattern>> {
+        let start = self.start();
+        let node = match self.peek() {
+            Token::Int(_) | Token::Float(_) | Token::Str(_) | Token::TrueKw | Token::FalseKw => {
+                Pattern::Literal(self.parse_primary()?.node)
+            }
+            Token::Minus => {
+                self.advance();
+                let literal = match self.peek() {
+                    Token::Int(n) => Expr::Int(-n),
+                    Token::Float(x) => Expr::Float(-x),
+                    _ => return Err(self.unexpected(&["number"])),
+                };
+                self.advance();
+                Pattern::Literal(literal)
+            }
+            Token::LParen => {
+                self.advance();
+                let mut items = Vec::new();
+                let mut grouped = true;
+                while !matches!(self.peek(), Token::RParen) {
+                    items.push(self.parse_pattern()?);
+                    if !matches!(self.peek(), Token::Comma) {
+                        break;
+                    }
+                    self.advance();
+                    grouped = false;
+                }
+                self.expect(Token::RParen)?;
+                // `(p)` only groups; `(p,)` is a one-item tuple
+                if grouped && items.len() == 1 {
+                    items.remove(0).node
+                } else {
+                    Pattern::Tuple(items)
+                }
+            }
+            Token::Ident(name) => {
+                self.advance();
+                if name == "_" {
+                    Pattern::Wildcard
+                } else if matches!(self.peek(), Token::Dot) {
+                    self.advance();
+                    let variant = self.expect_ident("variant name")?;
+                    let mut args = Vec::new();
+                    if matches!(self.peek(), Token::LParen) {
+                        self.advance();
+                        if !matches!(self.peek(), Token::RParen) {
+                            args.push(self.parse_pattern()?);
+                            while matches!(self.peek(), Token::Comma) {
+                                self.advance();
+                                args.push(self.parse_pattern()?);
+                            }
+                        }
+                        self.expect(Token::RParen)?;
+                    }
+                    Pattern::Variant {
+                        enum_name: name,
+                        variant,
+                        args,
+                    }
+                } else {
+                    Pattern::Bind(name)
                 }
-                self.skip_newlines();
             }
-            self.expect(Token::Dedent);
+            _ => return Err(self.unexpected(&["pattern"])),
+        };
+        Ok(Spanned::new(node, self.finish(start)))
+    }
+
+    fn parse_for(&mut self) -> PResult<Stmt> {
+        self.expect(Token::ForKw)?;
+        let mut vars = vec![self.expect_ident("loop variable name")?];
+        while matches!(self.peek(), Token::Comma) {
+            self.advance();
+            vars.push(self.expect_ident("loop variable name")?);
         }
+        self.expect(Token::InKw)?;
+        let iter = self.parse_expr()?;
+        // `for i in a..b:` counts without building a range value
+        if vars.len() == 1 && matches!(self.peek(), Token::DotDot) {
+            self.advance();
+            let end = self.parse_expr()?;
+            self.expect(Token::Colon)?;
+            let body = self.parse_block()?;
+            return Ok(Stmt::ForRange {
+                var: vars.remove(0),
+                start: iter,
+                end,
+                body,
+            });
+        }
+        self.expect(Token::Colon)?;
+        let body = self.parse_block()?;
+        Ok(Stmt::ForEach { vars, iter, body })
+    }
 
-        Stmt::If {
+    fn parse_while(&mut self) -> PResult<Stmt> {
+        self.expect(Token::WhileKw)?;
+        let cond = self.parse_expr()?;
+        self.expect(Token::Colon)?;
+        let body = self.parse_block()?;
+        Ok(Stmt::While { cond, body })
+    }
+
+    /// Parse `if`, or the `elif` continuing a chain. Each `elif` becomes an `If` nested
+    /// as the sole statement of the previous branch's `else`.
+    fn parse_if(&mut self) -> PResult<Stmt> {
+        if matches!(self.peek(), Token::ElifKw) {
+            self.advance();
+        } else {
+            self.expect(Token::IfKw)?;
+        }
+        let cond = self.parse_expr()?;
+        self.expect(Token::Colon)?;
+        let then_branch = self.parse_block()?;
+
+        self.skip_newlines();
+
+        let mut else_branch = Vec::new();
+        if matches!(self.peek(), Token::ElifKw) {
+            let start = self.start();
+            let elif = self.parse_if()?;
+            else_branch.push(Spanned::new(elif, self.finish(start)));
+        } else if matches!(self.peek(), Token::ElseKw) {
+            self.expect(Token::ElseKw)?;
+            self.expect(Token::Colon)?;
+            else_branch = self.parse_block()?;
+        }
+
+        Ok(Stmt::If {
             cond,
             then_branch,
             else_branch,
+        })
+    }
+
+    /// Parse the body following a ':'. Either an indented block
+    /// (NEWLINE INDENT stmt* DEDENT), whose errors are recovered from statement by
+    /// statement, or a single simple statement on the same line.
+    fn parse_block(&mut self) -> PResult<Vec<Spanned<Stmt>>> {
+        if !matches!(self.peek(), Token::Newline | Token::EOF) {
+            return self.parse_inline_body();
+        }
+        self.expect_block_start()?;
+        let mut body = Vec::new();
+        self.skip_newlines();
+        while !matches!(self.peek(), Token::Dedent | Token::EOF) {
+            if let Some(stmt) = self.parse_stmt_recovering() {
+                body.push(stmt);
+            }
+            self.skip_newlines();
+        }
+        self.expect(Token::Dedent)?;
+        Ok(body)
+    }
+
+    /// Parse a one-line body such as `if x > 0: print(x)`, or several statements
+    /// separated by `;`. Compound statements need their own line.
+    fn parse_inline_body(&mut self) -> PResult<Vec<Spanned<Stmt>>> {
+        let mut body = Vec::new();
+        loop {
+            if matches!(
+                self.peek(),
+                Token::IfKw
+                    | Token::ForKw
+                    | Token::WhileKw
+                    | Token::FnKw
+                    | Token::StructKw
+                    | Token::EnumKw
+                    | Token::MatchKw
+                    | Token::TryKw
+            ) {
+                return Err(self.unexpected(&["newline", "simple statement"]));
+            }
+            body.extend(self.parse_stmt()?);
+            let after_semicolon = self
+                .tokens
+                .get(self.pos.wrapping_sub(1))
+                .is_some_and(|t| t.node == Token::Semicolon);
+            if !after_semicolon
+                || matches!(self.peek(), Token::Newline | Token::Dedent | Token::EOF)
+            {
+                return Ok(body);
+            }
         }
     }
 
-    fn parse_primary(&mut self) -> Expr {
-        match self.advance() {
-            Token::Int(n) => Expr::Int(n),
-            Token::Str(s) => Expr::Str(s),
-            Token::TrueKw => Expr::Bool(true),
-            Token::FalseKw => Expr::Bool(false),
+    fn parse_primary(&mut self) -> PResult<Spanned<Expr>> {
+        let start = self.start();
+        let tok_span = self.peek_span();
+        let expr = match self.peek() {
+            Token::Int(n) => Spanned::new(Expr::Int(n), tok_span),
+            Token::Float(x) => Spanned::new(Expr::Float(x), tok_span),
+            Token::Str(s) => Spanned::new(Expr::Str(s), tok_span),
+            Token::TrueKw => Spanned::new(Expr::Bool(true), tok_span),
+            Token::FalseKw => Spanned::new(Expr::Bool(false), tok_span),
+            Token::NoneKw => Spanned::new(Expr::None, tok_span),
             Token::Ident(s) => {
-                let expr = Expr::Ident(s);
-                self.parse_postfix(expr)
+                self.advance();
+                let expr = Spanned::new(Expr::Ident(s), tok_span);
+                return self.parse_postfix(expr);
             }
             Token::InterpolatedString(parts) => {
                 let mut ast_parts = Vec::new();
                 for part in parts {
                     match part {
                         FStringPart::Text(t) => ast_parts.push(StringPart::Text(t)),
-                        FStringPart::Expr(src) => {
-                            let expr = parse_embedded_expr(&src);
-                            ast_parts.push(StringPart::Expr(Box::new(expr)));
+                        FStringPart::Expr {
+                            source,
+                            offset,
+                            spec,
+                        } => {
+                            let expr = parse_embedded_expr(&source, offset)?;
+                            ast_parts.push(StringPart::Expr(Box::new(expr), spec));
                         }
                     }
                 }
-                Expr::InterpolatedString(ast_parts)
+                Spanned::new(Expr::InterpolatedString(ast_parts), tok_span)
             }
             Token::LParen => {
-                let expr = self.parse_expr();
-                self.expect(Token::RParen);
-                self.parse_postfix(expr)
+                self.advance();
+                let first = self.parse_expr_or_range()?;
+                let node = if matches!(self.peek(), Token::Comma) {
+                    let mut items = vec![first];
+                    while matches!(self.peek(), Token::Comma) {
+                        self.advance();
+                        if matches!(self.peek(), Token::RParen) {
+                            break;
+                        }
+                        items.push(self.parse_expr()?);
+                    }
+                    Expr::Tuple(items)
+                } else {
+                    first.node
+                };
+                self.expect(Token::RParen)?;
+                let expr = Spanned::new(node, self.finish(start));
+                return self.parse_postfix(expr);
             }
             Token::LBracket => {
+                self.advance();
                 let mut elems = Vec::new();
                 if !matches!(self.peek(), Token::RBracket) {
-                    elems.push(self.parse_expr());
+                    elems.push(self.parse_expr()?);
+                    while matches!(self.peek(), Token::Comma) {
+                        self.advance();
+                        if matches!(self.peek(), Token::RBracket) {
+                            break;
+                        }
+                        elems.push(self.parse_expr()?);
+                    }
+                }
+                self.expect(Token::RBracket)?;
+                let expr = Spanned::new(
+                    Expr::Call {
+                        func: Box::new(Spanned::new(Expr::Ident("vec".to_string()), tok_span)),
+                        args: elems,
+                    },
+                    self.finish(start),
+                );
+                return self.parse_postfix(expr);
+            }
+            Token::FnKw => {
+                self.advance();
+                let params = self.parse_params()?;
+                self.expect(Token::FatArrow)?;
+                let body = self.parse_expr()?;
+                return Ok(Spanned::new(
+                    Expr::Lambda {
+                        params,
+                        body: Box::new(body),
+                    },
+                    self.finish(start),
+                ));
+            }
+            Token::LBrace => {
+                self.advance();
+                let mut entries = Vec::new();
+                if !matches!(self.peek(), Token::RBrace) {
+                    entries.push(self.parse_dict_entry()?);
                     while matches!(self.peek(), Token::Comma) {
                         self.advance();
-                        elems.push(self.parse_expr());
+                        if matches!(self.peek(), Token::RBrace) {
+                            break;
+                        }
+                        entries.push(self.parse_dict_entry()?);
                     }
                 }
-                self.expect(Token::RBracket);
-                let expr = Expr::Call {
-                    func: Box::new(Expr::Ident("vec".to_string())),
-                    args: elems,
-                };
-                self.parse_postfix(expr)
+                self.expect(Token::RBrace)?;
+                let expr = Spanned::new(Expr::Dict(entries), self.finish(start));
+                return self.parse_postfix(expr);
             }
-            other => panic!("Unexpected token {:?}", other),
+            _ => return Err(self.unexpected(&["expression"])),
+        };
+        self.advance();
+        // Strings have methods and can be indexed, as in `", ".join(xs)`
+        if matches!(expr.node, Expr::Str(_) | Expr::InterpolatedString(_)) {
+            return self.parse_postfix(expr);
+        }
+        Ok(expr)
+    }
+
+    /// Parse an expression, or a tuple written without parentheses as in `x = 1, 2`,
+    /// or a range as in `r = 0..n`.
+    fn parse_expr_or_tuple(&mut self) -> PResult<Spanned<Expr>> {
+        let start = self.start();
+        let first = self.parse_expr_or_range()?;
+        if !matches!(self.peek(), Token::Comma) {
+            return Ok(first);
+        }
+        let mut items = vec![first];
+        while matches!(self.peek(), Token::Comma) {
+            self.advance();
+            items.push(self.parse_expr()?);
+        }
+        Ok(Spanned::new(Expr::Tuple(items), self.finish(start)))
+    }
+
+    /// Parse an expression, or a range `start..end`.
+    fn parse_expr_or_range(&mut self) -> PResult<Spanned<Expr>> {
+        let start = self.start();
+        let first = self.parse_expr()?;
+        if !matches!(self.peek(), Token::DotDot) {
+            return Ok(first);
         }
+        self.advance();
+        let end = self.parse_expr()?;
+        Ok(Spanned::new(
+            Expr::Range {
+                start: Box::new(first),
+                end: Box::new(end),
+            },
+            self.finish(start),
+        ))
+    }
+
+    /// Parse one `key: value` entry of a dict literal.
+    fn parse_dict_entry(&mut self) -> PResult<(Spanned<Expr>, Spanned<Expr>)> {
+        let key = self.parse_expr()?;
+        self.expect(Token::Colon)?;
+        let value = self.parse_expr()?;
+        Ok((key, value))
     }
 
-    fn parse_postfix(&mut self, mut expr: Expr) -> Expr {
+    fn parse_postfix(&mut self, mut expr: Spanned<Expr>) -> PResult<Spanned<Expr>> {
+        let start = expr.span.start;
         loop {
             match self.peek() {
                 Token::LParen => {
                     self.advance(); // consume '('
-                    let mut args = Vec::new();
-                    if !matches!(self.peek(), Token::RParen) {
-                        args.push(self.parse_expr());
-                        while matches!(self.peek(), Token::Comma) {
-                            self.advance();
-                            args.push(self.parse_expr());
+                    let node = match expr.node {
+                        // `Point(x=1, y=2)` names the fields it sets
+                        Expr::Ident(name)
+                            if matches!(self.peek(), Token::Ident(_))
+                                && self.peek_next_is(Token::Equal) =>
+                        {
+                            Expr::Construct {
+                                name,
+                                fields: self.parse_keyword_args()?,
+                            }
                         }
-                    }
-                    self.expect(Token::RParen);
-                    expr = Expr::Call {
-                        func: Box::new(expr),
-                        args,
+                        node => Expr::Call {
+                            func: Box::new(Spanned::new(node, expr.span)),
+                            args: self.parse_call_args()?,
+                        },
                     };
+                    expr = Spanned::new(node, self.finish(start));
+                }
+                Token::LBracket => {
+                    self.advance(); // consume '['
+                    let target = Box::new(expr);
+                    let index = if matches!(self.peek(), Token::DotDot) {
+                        None
+                    } else {
+                        Some(Box::new(self.parse_expr()?))
+                    };
+                    let node = if matches!(self.peek(), Token::DotDot) {
+                        self.advance();
+                        let end = if matches!(self.peek(), Token::RBracket) {
+                            None
+                        } else {
+                            Some(Box::new(self.parse_expr()?))
+                        };
+                        Expr::Slice {
+                            target,
+                            start: index,
+                            end,
+                        }
+                    } else {
+                        Expr::Index {
+                            target,
+                            index: index.expect("an index is parsed unless '..' follows"),
+                        }
+                    };
+                    self.expect(Token::RBracket)?;
+                    expr = Spanned::new(node, self.finish(start));
                 }
                 Token::Dot => {
                     self.advance(); // consume '.'
-                    let method = match self.advance() {
-                        Token::Ident(s) => s,
-                        other => panic!("expected method name after '.', found {:?}", other),
-                    };
-                    self.expect(Token::LParen);
-                    let mut args = Vec::new();
-                    if !matches!(self.peek(), Token::RParen) {
-                        args.push(self.parse_expr());
-                        while matches!(self.peek(), Token::Comma) {
-                            self.advance();
-                            args.push(self.parse_expr());
+                    let name = self.expect_ident("attribute name")?;
+                    let target = Box::new(expr);
+                    let node = if matches!(self.peek(), Token::LParen) {
+                        self.advance();
+                        Expr::MethodCall {
+                            target,
+                            method: name,
+                            args: self.parse_call_args()?,
                         }
-                    }
-                    self.expect(Token::RParen);
-                    let mut call_args = Vec::new();
-                    call_args.push(expr);
-                    call_args.extend(args);
-                    expr = Expr::Call {
-                        func: Box::new(Expr::Ident(method)),
-                        args: call_args,
+                    } else {
+                        Expr::Field { target, name }
                     };
+                    expr = Spanned::new(node, self.finish(start));
                 }
                 _ => break,
             }
         }
-        expr
+        Ok(expr)
+    }
+
+    /// Parse a comma-separated argument list after '(' up to and including ')'.
+    fn parse_call_args(&mut self) -> PResult<Vec<Spanned<Expr>>> {
+        let mut args = Vec::new();
+        if !matches!(self.peek(), Token::RParen) {
+            args.push(self.parse_expr()?);
+            while matches!(self.peek(), Token::Comma) {
+                self.advance();
+                args.push(self.parse_expr()?);
+            }
+        }
+        self.expect(Token::RParen)?;
+        Ok(args)
+    }
+
+    /// Parse `name=value, ...` after '(' up to and including ')'.
+    fn parse_keyword_args(&mut self) -> PResult<Vec<(String, Spanned<Expr>)>> {
+        let mut fields = Vec::new();
+        loop {
+            let name = self.expect_ident("keyword argument")?;
+            self.expect(Token::Equal)?;
+            fields.push((name, self.parse_expr()?));
+            if !matches!(self.peek(), Token::Comma) {
+                break;
+            }
+            self.advance();
+        }
+        self.expect(Token::RParen)?;
+        Ok(fields)
+    }
+
+    /// Consume the doc comment the lexer puts right before a `fn` or `struct`.
+    fn take_doc(&mut self) -> Option<String> {
+        match self.peek() {
+            Token::DocComment(text) => {
+                self.advance();
+                Some(text)
+            }
+            _ => None,
+        }
+    }
+
+    /// Consume the newline ending a block header and the indent opening its body.
+    /// Blank and comment-only lines in between are skipped.
+    fn expect_block_start(&mut self) -> PResult<()> {
+        self.expect(Token::Newline)?;
+        self.skip_newlines();
+        self.expect(Token::Indent)
     }
 
     fn skip_newlines(&mut self) {
@@ -422,41 +1539,136 @@ impl Parser {
         }
     }
 
-    fn expect(&mut self, expected: Token) {
-        let tok = self.advance();
-        if tok != expected {
-            panic!("expected {:?}, found {:?}", expected, tok);
+    /// Consume `expected` or report what was found instead. The offending token is
+    /// not consumed so that recovery can see statement boundaries.
+    fn expect(&mut self, expected: Token) -> PResult<()> {
+        if self.peek() == expected {
+            self.advance();
+            Ok(())
+        } else {
+            Err(self.unexpected(&[&expected.describe()]))
         }
     }
 
+    fn expect_ident(&mut self, what: &str) -> PResult<String> {
+        match self.peek() {
+            Token::Ident(s) => {
+                self.advance();
+                Ok(s)
+            }
+            _ => Err(self.unexpected(&[what])),
+        }
+    }
+
+    /// Build an error for the next token, listing what would have been accepted.
+    fn unexpected(&self, expected: &[&str]) -> ParseError {
+        ParseError::unexpected(self.peek_span(), expected, self.peek())
+    }
+
     fn peek(&self) -> Token {
-        self.tokens.get(self.pos).cloned().unwrap_or(Token::EOF)
+        self.tokens
+            .get(self.pos)
+            .map(|t| t.node.clone())
+            .unwrap_or(Token::EOF)
+    }
+
+    fn peek_span(&self) -> Span {
+        self.tokens
+            .get(self.pos)
+            .or_else(|| self.tokens.last())
+            .map(|t| t.span)
+            .unwrap_or_default()
     }
 
     fn peek_next_is(&self, expected: Token) -> bool {
         self.tokens
             .get(self.pos + 1)
-            .cloned()
-            .map_or(false, |t| t == expected)
+            .is_some_and(|t| t.node == expected)
     }
 
     fn advance(&mut self) -> Token {
         let tok = self.peek();
         if !self.is_at_end() {
+            // Layout tokens never extend the span of the node being parsed
+            if !matches!(tok, Token::Newline | Token::Indent | Token::Dedent) {
+                self.prev_end = self.tokens[self.pos].span.end;
+            }
             self.pos += 1;
         }
         tok
     }
 
+    /// Start offset of the next token, used to open a node span.
+    fn start(&self) -> usize {
+        self.peek_span().start
+    }
+
+    /// Close a node span opened at `start` with the end of the last consumed token.
+    fn finish(&self, start: usize) -> Span {
+        Span::new(start, self.prev_end.max(start))
+    }
+
     fn is_at_end(&self) -> bool {
         matches!(self.peek(), Token::EOF)
     }
 }
 
-fn parse_embedded_expr(src: &str) -> Expr {
-    let tokens = Lexer::new(src).tokenize();
+fn comparison_op(tok: &Token) -> Option<BinOp> {
+    match tok {
+        Token::EqualEqual => Some(BinOp::Eq),
+        Token::NotEqual => Some(BinOp::NotEq),
+        Token::LAngle => Some(BinOp::Lt),
+        Token::LessEqual => Some(BinOp::LtEq),
+        Token::RAngle => Some(BinOp::Gt),
+        Token::GreaterEqual => Some(BinOp::GtEq),
+        Token::InKw => Some(BinOp::In),
+        Token::IsKw => Some(BinOp::Is),
+        _ => None,
+    }
+}
+
+/// Lex and parse a complete source text, reporting lexer and parser errors together.
+pub fn parse_source(source: &str) -> Result<Vec<Spanned<Stmt>>, Vec<ParseError>> {
+    let (tokens, mut errors) = Lexer::new(source).tokenize_recovering();
+    match Parser::new(tokens).parse_program() {
+        Ok(stmts) if errors.is_empty() => Ok(stmts),
+        Ok(_) => Err(errors),
+        Err(parse_errors) => {
+            errors.extend(parse_errors);
+            errors.sort_by_key(|e| e.span.start);
+            Err(errors)
+        }
+    }
+}
+
+/// Parse an expression embedded in an f-string. Its tokens take the span of the
+/// enclosing string literal.
+/// Parse the expression of an f-string field, which starts `offset` bytes into the
+/// source, so that its spans point into the f-string.
+fn parse_embedded_expr(src: &str, offset: usize) -> PResult<Spanned<Expr>> {
+    let trimmed = src.trim_start();
+    let offset = offset + (src.len() - trimmed.len());
+    let shift = |span: Span| Span::new(span.start + offset, span.end + offset);
+    if trimmed.trim_end().is_empty() {
+        return Err(ParseError::new(
+            Span::new(offset, offset),
+            ParseErrorKind::InvalidFString("empty expression not allowed".to_string()),
+        ));
+    }
+    let (tokens, errors) = Lexer::new(trimmed).tokenize_recovering();
+    if let Some(err) = errors.into_iter().next() {
+        return Err(ParseError::new(shift(err.span), err.kind));
+    }
+    let tokens = tokens
+        .into_iter()
+        .map(|t| Spanned::new(t.node, shift(t.span)))
+        .collect();
     let mut parser = Parser::new(tokens);
-    parser.parse_expr()
+    let expr = parser.parse_expr()?;
+    if !parser.is_at_end() {
+        return Err(parser.unexpected(&["'}'"]));
+    }
+    Ok(expr)
 }
 
 #[cfg(test)]
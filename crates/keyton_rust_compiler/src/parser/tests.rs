@@ -1,33 +1,70 @@
 use super::*;
 use crate::lexer::Lexer;
 
+fn sp<T>(node: T, start: usize, end: usize) -> Spanned<T> {
+    Spanned::new(node, Span::new(start, end))
+}
+
+fn ident(name: &str, start: usize, end: usize) -> Spanned<Expr> {
+    sp(Expr::Ident(name.to_string()), start, end)
+}
+
+fn param(name: &str) -> Param {
+    Param {
+        name: name.to_string(),
+        ty: None,
+    }
+}
+
 #[test]
 fn program1_ast() {
     let input = r#"x = 12
 x = x + 1
 print(x)
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     assert_eq!(
         ast,
         vec![
-            Stmt::Assign {
-                name: "x".to_string(),
-                expr: Expr::Int(12),
-            },
-            Stmt::Assign {
-                name: "x".to_string(),
-                expr: Expr::Binary {
-                    left: Box::new(Expr::Ident("x".to_string())),
-                    op: BinOp::Add,
-                    right: Box::new(Expr::Int(1)),
+            sp(
+                Stmt::Assign {
+                    name: "x".to_string(),
+                    ty: None,
+                    expr: sp(Expr::Int(12), 4, 6),
                 },
-            },
-            Stmt::ExprStmt(Expr::Call {
-                func: Box::new(Expr::Ident("print".to_string())),
-                args: vec![Expr::Ident("x".to_string())],
-            }),
+                0,
+                6
+            ),
+            sp(
+                Stmt::Assign {
+                    name: "x".to_string(),
+                    ty: None,
+                    expr: sp(
+                        Expr::Binary {
+                            left: Box::new(ident("x", 11, 12)),
+                            op: BinOp::Add,
+                            right: Box::new(sp(Expr::Int(1), 15, 16)),
+                        },
+                        11,
+                        16
+                    ),
+                },
+                7,
+                16
+            ),
+            sp(
+                Stmt::ExprStmt(sp(
+                    Expr::Call {
+                        func: Box::new(ident("print", 17, 22)),
+                        args: vec![ident("x", 23, 24)],
+                    },
+                    17,
+                    25
+                )),
+                17,
+                25
+            ),
         ]
     );
 }
@@ -38,23 +75,41 @@ fn program4_ast() {
 x = "Hello"
 print(x)
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     assert_eq!(
         ast,
         vec![
-            Stmt::Assign {
-                name: "x".to_string(),
-                expr: Expr::Int(12),
-            },
-            Stmt::Assign {
-                name: "x".to_string(),
-                expr: Expr::Str("Hello".to_string()),
-            },
-            Stmt::ExprStmt(Expr::Call {
-                func: Box::new(Expr::Ident("print".to_string())),
-                args: vec![Expr::Ident("x".to_string())],
-            }),
+            sp(
+                Stmt::Assign {
+                    name: "x".to_string(),
+                    ty: None,
+                    expr: sp(Expr::Int(12), 4, 6),
+                },
+                0,
+                6
+            ),
+            sp(
+                Stmt::Assign {
+                    name: "x".to_string(),
+                    ty: None,
+                    expr: sp(Expr::Str("Hello".to_string()), 11, 18),
+                },
+                7,
+                18
+            ),
+            sp(
+                Stmt::ExprStmt(sp(
+                    Expr::Call {
+                        func: Box::new(ident("print", 19, 24)),
+                        args: vec![ident("x", 25, 26)],
+                    },
+                    19,
+                    27
+                )),
+                19,
+                27
+            ),
         ]
     );
 }
@@ -62,14 +117,22 @@ print(x)
 #[test]
 fn program2_ast() {
     let input = r#"print("Hello, World")"#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     assert_eq!(
         ast,
-        vec![Stmt::ExprStmt(Expr::Call {
-            func: Box::new(Expr::Ident("print".to_string())),
-            args: vec![Expr::Str("Hello, World".to_string())],
-        })]
+        vec![sp(
+            Stmt::ExprStmt(sp(
+                Expr::Call {
+                    func: Box::new(ident("print", 0, 5)),
+                    args: vec![sp(Expr::Str("Hello, World".to_string()), 6, 20)],
+                },
+                0,
+                21
+            )),
+            0,
+            21
+        )]
     );
 }
 
@@ -78,23 +141,43 @@ fn program3_ast() {
     let input = r#"x = 12
 print(f"{x}")
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     assert_eq!(
         ast,
         vec![
-            Stmt::Assign {
-                name: "x".to_string(),
-                expr: Expr::Int(12),
-            },
-            Stmt::ExprStmt(Expr::Call {
-                func: Box::new(Expr::Ident("print".to_string())),
-                args: vec![Expr::InterpolatedString(vec![
-                    StringPart::Text("".to_string()),
-                    StringPart::Expr(Box::new(Expr::Ident("x".to_string()))),
-                    StringPart::Text("".to_string()),
-                ])],
-            }),
+            sp(
+                Stmt::Assign {
+                    name: "x".to_string(),
+                    ty: None,
+                    expr: sp(Expr::Int(12), 4, 6),
+                },
+                0,
+                6
+            ),
+            sp(
+                Stmt::ExprStmt(sp(
+                    Expr::Call {
+                        func: Box::new(ident("print", 7, 12)),
+                        args: vec![sp(
+                            Expr::InterpolatedString(vec![
+                                StringPart::Text("".to_string()),
+                                StringPart::Expr(
+                                    Box::new(ident("x", 16, 17)),
+                                    FormatSpec::default()
+                                ),
+                                StringPart::Text("".to_string()),
+                            ]),
+                            13,
+                            19
+                        )],
+                    },
+                    7,
+                    20
+                )),
+                7,
+                20
+            ),
         ]
     );
 }
@@ -104,19 +187,33 @@ fn function_with_explicit_return() {
     let input = r#"fn my_sum(x, y):
     return x + y
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     assert_eq!(
         ast,
-        vec![Stmt::FuncDef {
-            name: "my_sum".to_string(),
-            params: vec!["x".to_string(), "y".to_string()],
-            body: vec![Stmt::Return(Expr::Binary {
-                left: Box::new(Expr::Ident("x".to_string())),
-                op: BinOp::Add,
-                right: Box::new(Expr::Ident("y".to_string())),
-            })],
-        }]
+        vec![sp(
+            Stmt::FuncDef {
+                name: "my_sum".to_string(),
+                params: vec![param("x"), param("y")],
+                ret: None,
+                body: vec![sp(
+                    Stmt::Return(Some(sp(
+                        Expr::Binary {
+                            left: Box::new(ident("x", 28, 29)),
+                            op: BinOp::Add,
+                            right: Box::new(ident("y", 32, 33)),
+                        },
+                        28,
+                        33
+                    ))),
+                    21,
+                    33
+                )],
+                doc: None,
+            },
+            0,
+            33
+        )]
     );
 }
 
@@ -125,19 +222,33 @@ fn function_with_implicit_return() {
     let input = r#"fn my_sum(x, y):
     x + y
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     assert_eq!(
         ast,
-        vec![Stmt::FuncDef {
-            name: "my_sum".to_string(),
-            params: vec!["x".to_string(), "y".to_string()],
-            body: vec![Stmt::Return(Expr::Binary {
-                left: Box::new(Expr::Ident("x".to_string())),
-                op: BinOp::Add,
-                right: Box::new(Expr::Ident("y".to_string())),
-            })],
-        }]
+        vec![sp(
+            Stmt::FuncDef {
+                name: "my_sum".to_string(),
+                params: vec![param("x"), param("y")],
+                ret: None,
+                body: vec![sp(
+                    Stmt::Return(Some(sp(
+                        Expr::Binary {
+                            left: Box::new(ident("x", 21, 22)),
+                            op: BinOp::Add,
+                            right: Box::new(ident("y", 25, 26)),
+                        },
+                        21,
+                        26
+                    ))),
+                    21,
+                    26
+                )],
+                doc: None,
+            },
+            0,
+            26
+        )]
     );
 }
 
@@ -146,19 +257,745 @@ fn let_syntax_ast() {
     let input = r#"let x: i64 = 12
 print(x)
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     assert_eq!(
         ast,
         vec![
-            Stmt::Assign {
-                name: "x".to_string(),
-                expr: Expr::Int(12),
+            sp(
+                Stmt::Assign {
+                    name: "x".to_string(),
+                    ty: Some(TypeExpr::Name("i64".to_string())),
+                    expr: sp(Expr::Int(12), 13, 15),
+                },
+                0,
+                15
+            ),
+            sp(
+                Stmt::ExprStmt(sp(
+                    Expr::Call {
+                        func: Box::new(ident("print", 16, 21)),
+                        args: vec![ident("x", 22, 23)],
+                    },
+                    16,
+                    24
+                )),
+                16,
+                24
+            ),
+        ]
+    );
+}
+
+#[test]
+fn block_spans_end_at_last_statement() {
+    let input = "if x:\n    y = 1\n\nz = 2\n";
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
+    assert_eq!(ast.len(), 2);
+    assert_eq!(ast[0].span, Span::new(0, 15));
+    assert_eq!(ast[1].span, Span::new(17, 22));
+}
+
+#[test]
+fn reports_multiple_errors_in_one_pass() {
+    let input = "x = (1 + 2]\ny = 3\nfn f(:)\n    z = 1\nprint(y,)\nw = 4\n";
+    let errors = parse_source(input).unwrap_err();
+    assert_eq!(
+        errors,
+        vec![
+            ParseError::unexpected(Span::new(10, 11), &["')'"], Token::RBracket),
+            ParseError::unexpected(Span::new(23, 24), &["parameter name"], Token::Colon),
+            ParseError::unexpected(Span::new(44, 45), &["expression"], Token::RParen),
+        ]
+    );
+}
+
+#[test]
+fn recovery_keeps_statements_after_an_error() {
+    let input = "if x y:\n    a = 1\nb = 2\n";
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let mut parser = Parser::new(tokens);
+    let errors = parser.parse_program().unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message(), "expected ':', found identifier 'y'");
+
+    // The broken header's block is skipped, the following statement still parses
+    let input = "if x y:\n    a = 1\nb = )\n";
+    let errors = parse_source(input).unwrap_err();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[1].span, Span::new(22, 23));
+}
+
+#[test]
+fn statement_must_end_at_newline() {
+    let errors = parse_source("x = 1 2\n").unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message(), "expected newline, found integer 2");
+}
+
+/// Render an expression with explicit parentheses to make precedence visible.
+fn show(e: &Expr) -> String {
+    match e {
+        Expr::Int(n) => n.to_string(),
+        Expr::Ident(s) => s.clone(),
+        Expr::Bool(b) => b.to_string(),
+        Expr::Binary { left, op, right } => {
+            format!("({} {:?} {})", show(&left.node), op, show(&right.node))
+        }
+        Expr::Unary { op, expr } => format!("({:?} {})", op, show(&expr.node)),
+        other => format!("{:?}", other),
+    }
+}
+
+fn parse_one_expr(src: &str) -> Spanned<Expr> {
+    let tokens = Lexer::new(src).tokenize().unwrap();
+    Parser::new(tokens).parse_expr().unwrap()
+}
+
+#[test]
+fn operator_precedence_and_associativity() {
+    let cases = [
+        ("1 + 2 * 3", "(1 Add (2 Mul 3))"),
+        ("1 - 2 - 3", "((1 Sub 2) Sub 3)"),
+        ("a // b % c * d", "(((a FloorDiv b) Mod c) Mul d)"),
+        ("-a * b", "((Neg a) Mul b)"),
+        ("--a", "(Neg (Neg a))"),
+        ("a + 1 < b * 2", "((a Add 1) Lt (b Mul 2))"),
+        ("a == b and c != d", "((a Eq b) And (c NotEq d))"),
+        ("a or b and c", "(a Or (b And c))"),
+        ("not a == b", "(Not (a Eq b))"),
+        ("not a and b", "((Not a) And b)"),
+        ("(a or b) and c", "((a Or b) And c)"),
+    ];
+    for (src, expected) in cases {
+        assert_eq!(show(&parse_one_expr(src).node), expected, "source: {}", src);
+    }
+}
+
+#[test]
+fn binary_spans_cover_both_operands() {
+    let expr = parse_one_expr("x >= -1");
+    assert_eq!(expr.span, Span::new(0, 7));
+    let Expr::Binary { right, .. } = expr.node else {
+        panic!("expected a binary expression");
+    };
+    assert_eq!(right.span, Span::new(5, 7));
+}
+
+#[test]
+fn chained_comparison_is_rejected() {
+    let errors = parse_source("a < b < c\n").unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].kind, ParseErrorKind::ChainedComparison);
+    assert_eq!(errors[0].span, Span::new(6, 7));
+}
+
+#[test]
+fn while_loop_with_break_and_continue() {
+    let input = "while x < 3:\n    continue\n    break\n";
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
+    assert_eq!(
+        ast,
+        vec![sp(
+            Stmt::While {
+                cond: sp(
+                    Expr::Binary {
+                        left: Box::new(ident("x", 6, 7)),
+                        op: BinOp::Lt,
+                        right: Box::new(sp(Expr::Int(3), 10, 11)),
+                    },
+                    6,
+                    11
+                ),
+                body: vec![sp(Stmt::Continue, 17, 25), sp(Stmt::Break, 30, 35)],
+            },
+            0,
+            35
+        )]
+    );
+}
+
+fn assign(name: &str, value: i64, start: usize, end: usize) -> Spanned<Stmt> {
+    sp(
+        Stmt::Assign {
+            name: name.to_string(),
+            ty: None,
+            expr: sp(Expr::Int(value), end - 1, end),
+        },
+        start,
+        end,
+    )
+}
+
+#[test]
+fn elif_chain_with_inline_bodies_nests_ifs() {
+    let input = "if x: y = 1\nelif z: y = 2\nelse: y = 3\n";
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
+    assert_eq!(
+        ast,
+        vec![sp(
+            Stmt::If {
+                cond: ident("x", 3, 4),
+                then_branch: vec![assign("y", 1, 6, 11)],
+                else_branch: vec![sp(
+                    Stmt::If {
+                        cond: ident("z", 17, 18),
+                        then_branch: vec![assign("y", 2, 20, 25)],
+                        else_branch: vec![assign("y", 3, 32, 37)],
+                    },
+                    12,
+                    37
+                )],
+            },
+            0,
+            37
+        )]
+    );
+}
+
+#[test]
+fn long_elif_chain_with_blocks() {
+    let input = "if a:\n    r = 1\nelif b:\n    r = 2\nelif c:\n    r = 3\nr\n";
+    let ast = parse_source(input).unwrap();
+    assert_eq!(ast.len(), 2);
+    let Stmt::If { else_branch, .. } = &ast[0].node else {
+        panic!("expected if");
+    };
+    let Stmt::If {
+        cond, else_branch, ..
+    } = &else_branch[0].node
+    else {
+        panic!("expected nested if for first elif");
+    };
+    assert_eq!(cond.node, Expr::Ident("b".to_string()));
+    let Stmt::If {
+        cond, else_branch, ..
+    } = &else_branch[0].node
+    else {
+        panic!("expected nested if for second elif");
+    };
+    assert_eq!(cond.node, Expr::Ident("c".to_string()));
+    assert!(else_branch.is_empty());
+}
+
+#[test]
+fn inline_loop_body_and_compound_inline_rejected() {
+    let ast = parse_source("for i in 0..3: total += i\n").unwrap();
+    let Stmt::ForRange { body, .. } = &ast[0].node else {
+        panic!("expected for loop");
+    };
+    assert_eq!(body.len(), 1);
+
+    let errors = parse_source("if a: if b: c\n").unwrap_err();
+    assert_eq!(
+        errors[0].message(),
+        "expected one of newline, simple statement, found 'if'"
+    );
+}
+
+#[test]
+fn semicolons_separate_statements_on_one_line() {
+    let ast = parse_source(
+        "a = 1; b = 2;
+if x: y = 1; z = 2
+w = 3
+",
+    )
+    .unwrap();
+    assert_eq!(ast[0], assign("a", 1, 0, 5));
+    assert_eq!(ast[1], assign("b", 2, 7, 12));
+    let Stmt::If { then_branch, .. } = &ast[2].node else {
+        panic!("expected if");
+    };
+    // Both statements after the `:` belong to the body
+    assert_eq!(
+        then_branch,
+        &vec![assign("y", 1, 20, 25), assign("z", 2, 27, 32)]
+    );
+    assert_eq!(ast[3], assign("w", 3, 33, 38));
+
+    let ast = parse_source(
+        "fn f():
+    return; x
+",
+    )
+    .unwrap();
+    let Stmt::FuncDef { body, .. } = &ast[0].node else {
+        panic!("expected function");
+    };
+    assert_eq!(body[0].node, Stmt::Return(None));
+    assert_eq!(body.len(), 2);
+}
+
+#[test]
+fn type_annotations_on_params_returns_and_collections() {
+    let ast = parse_source("fn f(a: i64, b) -> dict[str, list[f64]]:\n    a\n").unwrap();
+    let Stmt::FuncDef { params, ret, .. } = &ast[0].node else {
+        panic!("expected function definition");
+    };
+    assert_eq!(
+        params,
+        &vec![
+            Param {
+                name: "a".to_string(),
+                ty: Some(TypeExpr::Name("i64".to_string())),
             },
-            Stmt::ExprStmt(Expr::Call {
-                func: Box::new(Expr::Ident("print".to_string())),
-                args: vec![Expr::Ident("x".to_string())],
-            }),
+            param("b"),
         ]
     );
+    assert_eq!(
+        ret,
+        &Some(TypeExpr::Dict(
+            Box::new(TypeExpr::Name("str".to_string())),
+            Box::new(TypeExpr::List(Box::new(TypeExpr::Name("f64".to_string())))),
+        ))
+    );
+
+    let errors = parse_source("x: list[i64, str] = 1\ny: i64<str> = 2\n").unwrap_err();
+    let messages: Vec<String> = errors.iter().map(|e| e.message()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "type 'list' takes 1 type argument(s), found 2".to_string(),
+            "type 'i64' does not take type arguments".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn index_slice_and_index_assignment() {
+    let ast = parse_source("xs[i] = s[1..]\nxs[0] += t[..n][0]\n").unwrap();
+    assert_eq!(
+        ast[0].node,
+        Stmt::IndexAssign {
+            target: ident("xs", 0, 2),
+            index: ident("i", 3, 4),
+            expr: sp(
+                Expr::Slice {
+                    target: Box::new(ident("s", 8, 9)),
+                    start: Some(Box::new(sp(Expr::Int(1), 10, 11))),
+                    end: None,
+                },
+                8,
+                14,
+            ),
+        }
+    );
+
+    // `+=` reads the element back: xs[0] = xs[0] + t[..n][0]
+    let Stmt::IndexAssign { expr, .. } = &ast[1].node else {
+        panic!("expected index assignment");
+    };
+    let Expr::Binary { left, op, right } = &expr.node else {
+        panic!("expected desugared addition");
+    };
+    assert_eq!(*op, BinOp::Add);
+    assert!(matches!(left.node, Expr::Index { .. }));
+    let Expr::Index { target, .. } = &right.node else {
+        panic!("expected index of a slice");
+    };
+    assert!(matches!(
+        &target.node,
+        Expr::Slice {
+            start: None,
+            end: Some(_),
+            ..
+        }
+    ));
+}
+
+#[test]
+fn dict_literal_and_membership_tests() {
+    let ast = parse_source("d = {\"a\": 1, k: {}}\nx not in d\n").unwrap();
+    let Stmt::Assign { expr, .. } = &ast[0].node else {
+        panic!("expected assignment");
+    };
+    assert_eq!(
+        expr.node,
+        Expr::Dict(vec![
+            (
+                sp(Expr::Str("a".to_string()), 5, 8),
+                sp(Expr::Int(1), 10, 11)
+            ),
+            (ident("k", 13, 14), sp(Expr::Dict(vec![]), 16, 18)),
+        ])
+    );
+    let Stmt::ExprStmt(expr) = &ast[1].node else {
+        panic!("expected expression statement");
+    };
+    assert_eq!(
+        expr.node,
+        Expr::Binary {
+            left: Box::new(ident("x", 20, 21)),
+            op: BinOp::NotIn,
+            right: Box::new(ident("d", 29, 30)),
+        }
+    );
+}
+
+#[test]
+fn tuple_literals_and_unpacking_tests() {
+    let src = "t = (1, x)\na, b = b, a\nfor k, v in items(d):\n    print(k)\nu = (1,)\n";
+    let ast = parse_source(src).unwrap();
+    let Stmt::Assign { expr, .. } = &ast[0].node else {
+        panic!("expected assignment");
+    };
+    assert_eq!(expr.span, Span::new(4, 10));
+    assert_eq!(
+        expr.node,
+        Expr::Tuple(vec![sp(Expr::Int(1), 5, 6), ident("x", 8, 9)])
+    );
+    let Stmt::TupleAssign { names, expr } = &ast[1].node else {
+        panic!("expected tuple assignment");
+    };
+    assert_eq!(names, &vec!["a".to_string(), "b".to_string()]);
+    assert_eq!(
+        expr.node,
+        Expr::Tuple(vec![ident("b", 18, 19), ident("a", 21, 22)])
+    );
+    let Stmt::ForEach { vars, body, .. } = &ast[2].node else {
+        panic!("expected for-each loop");
+    };
+    assert_eq!(vars, &vec!["k".to_string(), "v".to_string()]);
+    assert_eq!(body.len(), 1);
+    let Stmt::Assign { expr, .. } = &ast[3].node else {
+        panic!("expected assignment");
+    };
+    assert!(matches!(&expr.node, Expr::Tuple(items) if items.len() == 1));
+}
+
+#[test]
+fn for_in_over_any_iterable_and_range_values() {
+    let ast =
+        parse_source("for c in word:\n    print(c)\nr = 0..n\nfor i in 0..3:\n    print(i)\n")
+            .unwrap();
+    let Stmt::ForEach { vars, iter, .. } = &ast[0].node else {
+        panic!("expected for-each loop");
+    };
+    assert_eq!(vars, &vec!["c".to_string()]);
+    assert_eq!(iter.node, Expr::Ident("word".to_string()));
+    let Stmt::Assign { expr, .. } = &ast[1].node else {
+        panic!("expected assignment");
+    };
+    assert_eq!(
+        expr.node,
+        Expr::Range {
+            start: Box::new(sp(Expr::Int(0), 32, 33)),
+            end: Box::new(ident("n", 35, 36)),
+        }
+    );
+    assert_eq!(expr.span, Span::new(32, 36));
+    assert!(matches!(ast[2].node, Stmt::ForRange { .. }));
+}
+
+#[test]
+fn struct_definitions_constructors_fields_and_methods() {
+    let src = "struct P:\n    x: i64\n    fn get(self, d):\n        return self.x + d\np = P(x=1)\np.x = p.get(2)\n";
+    let ast = parse_source(src).unwrap();
+    let Stmt::StructDef {
+        name,
+        fields,
+        methods,
+        ..
+    } = &ast[0].node
+    else {
+        panic!("expected struct definition");
+    };
+    assert_eq!(name, "P");
+    assert_eq!(
+        fields,
+        &vec![FieldDef {
+            name: "x".to_string(),
+            ty: TypeExpr::Name("i64".to_string()),
+        }]
+    );
+    let Stmt::FuncDef { name, params, .. } = &methods[0].node else {
+        panic!("expected method");
+    };
+    assert_eq!(name, "get");
+    assert_eq!(params, &vec![param("self"), param("d")]);
+    let Stmt::Assign { expr, .. } = &ast[1].node else {
+        panic!("expected assignment");
+    };
+    assert_eq!(
+        expr.node,
+        Expr::Construct {
+            name: "P".to_string(),
+            fields: vec![("x".to_string(), sp(Expr::Int(1), 76, 77))],
+        }
+    );
+    // `.get(...)` stays a method call on `p` instead of becoming `get(p, ...)`
+    assert_eq!(
+        ast[2].node,
+        Stmt::FieldAssign {
+            target: ident("p", 79, 80),
+            field: "x".to_string(),
+            expr: sp(
+                Expr::MethodCall {
+                    target: Box::new(ident("p", 85, 86)),
+                    method: "get".to_string(),
+                    args: vec![sp(Expr::Int(2), 91, 92)],
+                },
+                85,
+                93
+            ),
+        }
+    );
+
+    let errors = parse_source("struct Q:\n    fn f(x):\n        return x\n").unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].message(),
+        "method 'f' must take 'self' as its first parameter"
+    );
+}
+
+#[test]
+fn enum_definitions_and_match_patterns() {
+    let src = "enum Shape:\n    Circle(f64)\n    Empty\nmatch s, 1:\n    case Shape.Circle(r), -1:\n        x = r\n    case _:\n        x = 0\n";
+    let ast = parse_source(src).unwrap();
+    assert_eq!(
+        ast[0].node,
+        Stmt::EnumDef {
+            name: "Shape".to_string(),
+            variants: vec![
+                VariantDef {
+                    name: "Circle".to_string(),
+                    fields: vec![TypeExpr::Name("f64".to_string())],
+                },
+                VariantDef {
+                    name: "Empty".to_string(),
+                    fields: vec![],
+                },
+            ],
+        }
+    );
+    let Stmt::Match { subject, arms } = &ast[1].node else {
+        panic!("expected match");
+    };
+    // `match s, 1:` matches a tuple, like `case a, b:` is a tuple pattern
+    assert_eq!(subject.span, Span::new(44, 48));
+    assert_eq!(arms.len(), 2);
+    assert_eq!(
+        arms[0].pattern,
+        sp(
+            Pattern::Tuple(vec![
+                sp(
+                    Pattern::Variant {
+                        enum_name: "Shape".to_string(),
+                        variant: "Circle".to_string(),
+                        args: vec![sp(Pattern::Bind("r".to_string()), 72, 73)],
+                    },
+                    59,
+                    74
+                ),
+                sp(Pattern::Literal(Expr::Int(-1)), 76, 78),
+            ]),
+            59,
+            78
+        )
+    );
+    assert_eq!(arms[1].pattern, sp(Pattern::Wildcard, 103, 104));
+    assert_eq!(arms[1].body.len(), 1);
+
+    let errors = parse_source("match x:\n    case [1]:\n        y = 1\n").unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].message().contains("expected pattern"));
+}
+
+#[test]
+fn optional_types_none_and_is_not_none() {
+    let src = "x: i64? = None\ny: Option[list[str]] = z\nb = x is not None\n";
+    let ast = parse_source(src).unwrap();
+    assert_eq!(
+        ast[0].node,
+        Stmt::Assign {
+            name: "x".to_string(),
+            ty: Some(TypeExpr::Option(Box::new(TypeExpr::Name(
+                "i64".to_string()
+            )))),
+            expr: sp(Expr::None, 10, 14),
+        }
+    );
+    let Stmt::Assign { ty, .. } = &ast[1].node else {
+        panic!("expected assignment");
+    };
+    assert_eq!(
+        *ty,
+        Some(TypeExpr::Option(Box::new(TypeExpr::List(Box::new(
+            TypeExpr::Name("str".to_string())
+        ))))),
+    );
+    assert_eq!(
+        ast[2].node,
+        Stmt::Assign {
+            name: "b".to_string(),
+            ty: None,
+            expr: sp(
+                Expr::Binary {
+                    left: Box::new(ident("x", 44, 45)),
+                    op: BinOp::IsNot,
+                    right: Box::new(sp(Expr::None, 53, 57)),
+                },
+                44,
+                57
+            ),
+        }
+    );
+
+    // Only `None` can follow `is`
+    let errors = parse_source("b = x is 1\n").unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].message().contains("'None'"));
+}
+
+#[test]
+fn try_except_finally_and_raise() {
+    let src = "try:\n    x = 1\nexcept IndexError as e:\n    raise\nexcept:\n    pass_count = 0\nfinally:\n    raise ValueError(\"bad\")\n";
+    let ast = parse_source(src).unwrap();
+    let Stmt::Try {
+        body,
+        handlers,
+        finally,
+    } = &ast[0].node
+    else {
+        panic!("expected try statement");
+    };
+    assert_eq!(body.len(), 1);
+    assert_eq!(handlers.len(), 2);
+    assert_eq!(handlers[0].kind.as_deref(), Some("IndexError"));
+    assert_eq!(handlers[0].name.as_deref(), Some("e"));
+    assert_eq!(handlers[0].span, Span::new(15, 37));
+    assert_eq!(
+        handlers[0].body[0].node,
+        Stmt::Raise {
+            kind: None,
+            message: None,
+        }
+    );
+    assert_eq!(handlers[1].kind, None);
+    assert_eq!(
+        finally[0].node,
+        Stmt::Raise {
+            kind: Some("ValueError".to_string()),
+            message: Some(sp(Expr::Str("bad".to_string()), 106, 111)),
+        }
+    );
+
+    // A `try` needs an `except` or a `finally`
+    let errors = parse_source("try:\n    x = 1\ny = 2\n").unwrap_err();
+    assert!(errors[0].message().contains("'except'"));
+}
+
+#[test]
+fn lambdas_and_function_types() {
+    let src = "f: fn(i64, str) -> bool = fn(n, s: str) => n > 0\n";
+    let ast = parse_source(src).unwrap();
+    assert_eq!(
+        ast[0].node,
+        Stmt::Assign {
+            name: "f".to_string(),
+            ty: Some(TypeExpr::Func(
+                vec![
+                    TypeExpr::Name("i64".to_string()),
+                    TypeExpr::Name("str".to_string())
+                ],
+                Box::new(TypeExpr::Name("bool".to_string()))
+            )),
+            expr: sp(
+                Expr::Lambda {
+                    params: vec![
+                        param("n"),
+                        Param {
+                            name: "s".to_string(),
+                            ty: Some(TypeExpr::Name("str".to_string())),
+                        },
+                    ],
+                    body: Box::new(sp(
+                        Expr::Binary {
+                            left: Box::new(ident("n", 43, 44)),
+                            op: BinOp::Gt,
+                            right: Box::new(sp(Expr::Int(0), 47, 48)),
+                        },
+                        43,
+                        48
+                    )),
+                },
+                26,
+                48
+            ),
+        }
+    );
+
+    // The body of a lambda follows `=>`
+    let errors = parse_source("g = fn(x) x + 1\n").unwrap_err();
+    assert!(errors[0].message().contains("'=>'"));
+}
+
+#[test]
+fn fstring_fields_have_their_own_spans() {
+    let ast = parse_source("s = f\"a{ x + 1 :>4}\"\n").unwrap();
+    let Stmt::Assign { expr, .. } = &ast[0].node else {
+        panic!("expected assignment, found {:?}", ast[0].node);
+    };
+    assert_eq!(
+        expr.node,
+        Expr::InterpolatedString(vec![
+            StringPart::Text("a".to_string()),
+            StringPart::Expr(
+                Box::new(sp(
+                    Expr::Binary {
+                        left: Box::new(ident("x", 9, 10)),
+                        op: BinOp::Add,
+                        right: Box::new(sp(Expr::Int(1), 13, 14)),
+                    },
+                    9,
+                    14
+                )),
+                FormatSpec::parse(">4").unwrap()
+            ),
+            StringPart::Text("".to_string()),
+        ])
+    );
+
+    // Errors inside a field point into the f-string
+    let errors = parse_source("s = f\"{x + }\"\n").unwrap_err();
+    assert_eq!(errors[0].span, Span::new(11, 11));
+}
+
+#[test]
+fn doc_comments_attach_to_functions_structs_and_methods() {
+    let src = "## A point.\nstruct P:\n    x: i64\n    ## Shift by d.\n    fn shift(self, d):\n        return self.x + d\n## Twice n.\n# (an ordinary comment)\nfn twice(n):\n    return n * 2\nfn plain():\n    return 0\n";
+    let ast = parse_source(src).unwrap();
+    let doc_of = |stmt: &Stmt| match stmt {
+        Stmt::FuncDef { doc, .. } | Stmt::StructDef { doc, .. } => doc.clone(),
+        other => panic!("expected a definition, found {:?}", other),
+    };
+    assert_eq!(doc_of(&ast[0].node), Some("A point.".to_string()));
+    let Stmt::StructDef { methods, .. } = &ast[0].node else {
+        unreachable!();
+    };
+    assert_eq!(doc_of(&methods[0].node), Some("Shift by d.".to_string()));
+    assert_eq!(doc_of(&ast[1].node), Some("Twice n.".to_string()));
+    assert_eq!(doc_of(&ast[2].node), None);
+    // The definition's span starts at `fn`, after its doc comment
+    assert_eq!(&src[ast[1].span.start..ast[1].span.start + 8], "fn twice");
+}
+
+#[test]
+fn comment_and_blank_lines_may_open_a_block() {
+    let src = "fn f(n):\n    # first line of the body\n\n    return n\nif x:\n    ## not a doc comment\n    y = 1\nstruct P:\n    # fields follow\n    x: i64\n";
+    let ast = parse_source(src).unwrap();
+    assert_eq!(ast.len(), 3);
+    let Stmt::FuncDef { body, .. } = &ast[0].node else {
+        panic!("expected function");
+    };
+    assert_eq!(body.len(), 1);
+    let Stmt::If { then_branch, .. } = &ast[1].node else {
+        panic!("expected if");
+    };
+    assert_eq!(then_branch.len(), 1);
 }
@@ -2,9 +2,9 @@ use std::collections::HashMap;
 
 use crate::shir::resolver::ResolvedProgram;
 use crate::shir::sym::{SymbolId, Type};
-use crate::thir::types::{TExpr, TStmt, TStringPart, TypedProgram};
+use crate::thir::types::{TExpr, TPattern, TStmt, TStringPart, TypedProgram};
 
-use super::types::{RExpr, RStmt, RStringPart, RustProgram};
+use super::types::{RExceptHandler, RExpr, RMatchArm, RPattern, RStmt, RStringPart, RustProgram};
 
 /// Function mapping rules from source language to Rust
 #[derive(Debug, Clone)]
@@ -60,6 +60,33 @@ impl<'a> Converter<'a> {
                 sym: *sym,
                 expr: self.convert_expr(expr),
             },
+            TStmt::IndexAssign {
+                hir_id,
+                target,
+                index,
+                expr,
+            } => RStmt::IndexAssign {
+                hir_id: *hir_id,
+                target: self.convert_expr(target),
+                index: self.convert_expr(index),
+                expr: self.convert_expr(expr),
+            },
+            TStmt::TupleAssign { hir_id, syms, expr } => RStmt::TupleAssign {
+                hir_id: *hir_id,
+                syms: syms.clone(),
+                expr: self.convert_expr(expr),
+            },
+            TStmt::FieldAssign {
+                hir_id,
+                target,
+                field,
+                expr,
+            } => RStmt::FieldAssign {
+                hir_id: *hir_id,
+                target: self.convert_expr(target),
+                field: field.clone(),
+                expr: self.convert_expr(expr),
+            },
             TStmt::ExprStmt { hir_id, expr } => RStmt::ExprStmt {
                 hir_id: *hir_id,
                 expr: self.convert_expr(expr),
@@ -77,6 +104,24 @@ impl<'a> Converter<'a> {
                 end: self.convert_expr(end),
                 body: body.iter().map(|st| self.convert_stmt(st)).collect(),
             },
+            TStmt::ForEach {
+                hir_id,
+                syms,
+                iter,
+                body,
+            } => RStmt::ForEach {
+                hir_id: *hir_id,
+                syms: syms.clone(),
+                iter: self.convert_expr(iter),
+                body: body.iter().map(|st| self.convert_stmt(st)).collect(),
+            },
+            TStmt::While { hir_id, cond, body } => RStmt::While {
+                hir_id: *hir_id,
+                cond: self.convert_expr(cond),
+                body: body.iter().map(|st| self.convert_stmt(st)).collect(),
+            },
+            TStmt::Break { hir_id } => RStmt::Break { hir_id: *hir_id },
+            TStmt::Continue { hir_id } => RStmt::Continue { hir_id: *hir_id },
             TStmt::If {
                 hir_id,
                 cond,
@@ -88,6 +133,127 @@ impl<'a> Converter<'a> {
                 then_branch: then_branch.iter().map(|st| self.convert_stmt(st)).collect(),
                 else_branch: else_branch.iter().map(|st| self.convert_stmt(st)).collect(),
             },
+            TStmt::FuncDef {
+                hir_id,
+                sym,
+                params,
+                ret,
+                body,
+            } => RStmt::FuncDef {
+                hir_id: *hir_id,
+                sym: *sym,
+                params: params.clone(),
+                ret: ret.clone(),
+                body: body.iter().map(|st| self.convert_stmt(st)).collect(),
+            },
+            TStmt::Return { hir_id, expr } => RStmt::Return {
+                hir_id: *hir_id,
+                expr: expr.as_ref().map(|e| self.convert_expr(e)),
+            },
+            TStmt::StructDef {
+                hir_id,
+                name,
+                methods,
+            } => RStmt::StructDef {
+                hir_id: *hir_id,
+                name: name.clone(),
+                fields: self
+                    .resolved
+                    .symbols
+                    .structs
+                    .get(name)
+                    .map(|info| info.fields.clone())
+                    .unwrap_or_default(),
+                methods: methods.iter().map(|st| self.convert_stmt(st)).collect(),
+            },
+            TStmt::EnumDef { hir_id, name } => RStmt::EnumDef {
+                hir_id: *hir_id,
+                name: name.clone(),
+                variants: self
+                    .resolved
+                    .symbols
+                    .enums
+                    .get(name)
+                    .map(|info| info.variants.clone())
+                    .unwrap_or_default(),
+            },
+            TStmt::Match {
+                hir_id,
+                subject,
+                arms,
+            } => RStmt::Match {
+                hir_id: *hir_id,
+                subject: self.convert_expr(subject),
+                arms: arms
+                    .iter()
+                    .map(|arm| RMatchArm {
+                        hir_id: arm.hir_id,
+                        pattern: self.convert_pattern(&arm.pattern),
+                        body: arm.body.iter().map(|st| self.convert_stmt(st)).collect(),
+                    })
+                    .collect(),
+            },
+            TStmt::Try {
+                hir_id,
+                body,
+                handlers,
+                finally,
+            } => RStmt::Try {
+                hir_id: *hir_id,
+                body: body.iter().map(|st| self.convert_stmt(st)).collect(),
+                handlers: handlers
+                    .iter()
+                    .map(|handler| RExceptHandler {
+                        hir_id: handler.hir_id,
+                        kind: handler.kind.clone(),
+                        sym: handler.sym,
+                        body: handler
+                            .body
+                            .iter()
+                            .map(|st| self.convert_stmt(st))
+                            .collect(),
+                    })
+                    .collect(),
+                finally: finally.iter().map(|st| self.convert_stmt(st)).collect(),
+            },
+            TStmt::Raise {
+                hir_id,
+                kind,
+                message,
+            } => RStmt::Raise {
+                hir_id: *hir_id,
+                kind: kind.clone(),
+                message: message.as_ref().map(|e| self.convert_expr(e)),
+            },
+        }
+    }
+
+    fn convert_pattern(&mut self, p: &TPattern) -> RPattern {
+        match p {
+            TPattern::Wildcard { hir_id } => RPattern::Wildcard { hir_id: *hir_id },
+            TPattern::Bind { hir_id, sym } => RPattern::Bind {
+                hir_id: *hir_id,
+                sym: *sym,
+            },
+            TPattern::Literal { hir_id, value } => RPattern::Literal {
+                hir_id: *hir_id,
+                value: self.convert_expr(value),
+            },
+            TPattern::Variant {
+                hir_id,
+                enum_name,
+                variant,
+                args,
+            } => RPattern::Variant {
+                hir_id: *hir_id,
+                enum_name: enum_name.clone(),
+                variant: variant.clone(),
+                args: args.iter().map(|a| self.convert_pattern(a)).collect(),
+            },
+            TPattern::Tuple { hir_id, items } => RPattern::Tuple {
+                hir_id: *hir_id,
+                items: items.iter().map(|i| self.convert_pattern(i)).collect(),
+            },
         }
     }
 
@@ -98,6 +264,11 @@ impl<'a> Converter<'a> {
                 value: *value,
                 ty: ty.clone(),
             },
+            TExpr::Float { hir_id, value, ty } => RExpr::Float {
+                hir_id: *hir_id,
+                value: *value,
+                ty: ty.clone(),
+            },
             TExpr::Str { hir_id, value, ty } => RExpr::Str {
                 hir_id: *hir_id,
                 value: value.clone(),
@@ -108,6 +279,20 @@ impl<'a> Converter<'a> {
                 value: *value,
                 ty: ty.clone(),
             },
+            TExpr::None { hir_id, ty } => RExpr::None {
+                hir_id: *hir_id,
+                ty: ty.clone(),
+            },
+            TExpr::Some { hir_id, expr, ty } => RExpr::Some {
+                hir_id: *hir_id,
+                expr: Box::new(self.convert_expr(expr)),
+                ty: ty.clone(),
+            },
+            TExpr::Unwrap { hir_id, expr, ty } => RExpr::Unwrap {
+                hir_id: *hir_id,
+                expr: Box::new(self.convert_expr(expr)),
+                ty: ty.clone(),
+            },
             TExpr::Name { hir_id, sym, ty } => RExpr::Name {
                 hir_id: *hir_id,
                 sym: *sym,
@@ -126,6 +311,17 @@ impl<'a> Converter<'a> {
                 right: Box::new(self.convert_expr(right)),
                 ty: ty.clone(),
             },
+            TExpr::Unary {
+                hir_id,
+                op,
+                expr,
+                ty,
+            } => RExpr::Unary {
+                hir_id: *hir_id,
+                op: op.clone(),
+                expr: Box::new(self.convert_expr(expr)),
+                ty: ty.clone(),
+            },
             TExpr::Call {
                 hir_id,
                 func,
@@ -158,11 +354,125 @@ impl<'a> Converter<'a> {
                     ty: ty.clone(),
                 }
             }
+            TExpr::Index {
+                hir_id,
+                target,
+                index,
+                ty,
+            } => RExpr::Index {
+                hir_id: *hir_id,
+                target: Box::new(self.convert_expr(target)),
+                index: Box::new(self.convert_expr(index)),
+                ty: ty.clone(),
+            },
+            TExpr::Slice {
+                hir_id,
+                target,
+                start,
+                end,
+                ty,
+            } => RExpr::Slice {
+                hir_id: *hir_id,
+                target: Box::new(self.convert_expr(target)),
+                start: start.as_ref().map(|e| Box::new(self.convert_expr(e))),
+                end: end.as_ref().map(|e| Box::new(self.convert_expr(e))),
+                ty: ty.clone(),
+            },
             TExpr::InterpolatedString { hir_id, parts, ty } => RExpr::InterpolatedString {
                 hir_id: *hir_id,
                 parts: parts.iter().map(|p| self.convert_string_part(p)).collect(),
                 ty: ty.clone(),
             },
+            TExpr::Dict {
+                hir_id,
+                entries,
+                ty,
+            } => RExpr::Dict {
+                hir_id: *hir_id,
+                entries: entries
+                    .iter()
+                    .map(|(k, v)| (self.convert_expr(k), self.convert_expr(v)))
+                    .collect(),
+                ty: ty.clone(),
+            },
+            TExpr::Tuple { hir_id, items, ty } => RExpr::Tuple {
+                hir_id: *hir_id,
+                items: items.iter().map(|e| self.convert_expr(e)).collect(),
+                ty: ty.clone(),
+            },
+            TExpr::Range {
+                hir_id,
+                start,
+                end,
+                ty,
+            } => RExpr::Range {
+                hir_id: *hir_id,
+                start: Box::new(self.convert_expr(start)),
+                end: Box::new(self.convert_expr(end)),
+                ty: ty.clone(),
+            },
+            TExpr::Field {
+                hir_id,
+                target,
+                name,
+                ty,
+            } => RExpr::Field {
+                hir_id: *hir_id,
+                target: Box::new(self.convert_expr(target)),
+                name: name.clone(),
+                ty: ty.clone(),
+            },
+            TExpr::MethodCall {
+                hir_id,
+                target,
+                method,
+                args,
+                ty,
+            } => RExpr::MethodCall {
+                hir_id: *hir_id,
+                target: Box::new(self.convert_expr(target)),
+                method: *method,
+                args: args.iter().map(|a| self.convert_expr(a)).collect(),
+                ty: ty.clone(),
+            },
+            TExpr::Construct {
+                hir_id,
+                name,
+                fields,
+                ty,
+            } => RExpr::Construct {
+                hir_id: *hir_id,
+                name: name.clone(),
+                fields: fields
+                    .iter()
+                    .map(|(f, v)| (f.clone(), self.convert_expr(v)))
+                    .collect(),
+                ty: ty.clone(),
+            },
+            TExpr::Variant {
+                hir_id,
+                enum_name,
+                variant,
+                args,
+                ty,
+            } => RExpr::Variant {
+                hir_id: *hir_id,
+                enum_name: enum_name.clone(),
+                variant: variant.clone(),
+                args: args.iter().map(|a| self.convert_expr(a)).collect(),
+                ty: ty.clone(),
+            },
+            TExpr::Lambda {
+                hir_id,
+                params,
+                body,
+                ty,
+            } => RExpr::Lambda {
+                hir_id: *hir_id,
+                params: params.clone(),
+                body: Box::new(self.convert_expr(body)),
+                ty: ty.clone(),
+            },
         }
     }
 
@@ -172,9 +482,10 @@ impl<'a> Converter<'a> {
                 hir_id: *hir_id,
                 value: value.clone(),
             },
-            TStringPart::Expr { hir_id, expr } => RStringPart::Expr {
+            TStringPart::Expr { hir_id, expr, spec } => RStringPart::Expr {
                 hir_id: *hir_id,
                 expr: self.convert_expr(expr),
+                spec: spec.clone(),
             },
         }
     }
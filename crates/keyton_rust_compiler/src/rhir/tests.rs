@@ -1,4 +1,5 @@
 use super::{RExpr, RStmt, RStringPart, convert_to_rhir};
+use crate::format_spec::FormatSpec;
 use crate::hir::hir_types::{HirBinOp, HirId};
 use crate::hir::lower_program;
 use crate::lexer::Lexer;
@@ -13,8 +14,8 @@ fn program1_rhir() {
 x = x + 1
 print(x)
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     let mut resolved = resolve_program(&hir);
     let typed = typecheck_program(&mut resolved);
@@ -91,8 +92,8 @@ fn program4_rhir() {
 x = "Hello"
 print(x)
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     let mut resolved = resolve_program(&hir);
     let typed = typecheck_program(&mut resolved);
@@ -159,8 +160,8 @@ print(x)
 #[test]
 fn program2_rhir() {
     let input = r#"print("Hello, World")"#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     let mut resolved = resolve_program(&hir);
     let typed = typecheck_program(&mut resolved);
@@ -195,8 +196,8 @@ fn program3_rhir() {
     let input = r#"x = 12
 print(f"{x}")
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     let mut resolved = resolve_program(&hir);
     let typed = typecheck_program(&mut resolved);
@@ -239,6 +240,7 @@ print(f"{x}")
                                     sym: SymbolId(1),
                                     ty: Type::I64,
                                 },
+                                spec: FormatSpec::default(),
                             },
                             RStringPart::Text {
                                 hir_id: HirId(10),
@@ -260,8 +262,8 @@ fn program5_rhir_same_type_reuse() {
 x = 42
 print(x)
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     let mut resolved = resolve_program(&hir);
     let typed = typecheck_program(&mut resolved);
@@ -329,8 +331,8 @@ fn test_function_mapping() {
 print(123)
 print(f"value: {42}")"#;
 
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     let mut resolved = resolve_program(&hir);
     let typed = typecheck_program(&mut resolved);
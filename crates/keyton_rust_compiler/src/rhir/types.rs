@@ -1,4 +1,5 @@
-use crate::hir::hir_types::{HirBinOp, HirId};
+use crate::format_spec::FormatSpec;
+use crate::hir::hir_types::{HirBinOp, HirId, HirUnaryOp};
 use crate::shir::sym::{SymbolId, Type};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -17,6 +18,23 @@ pub enum RStmt {
         sym: SymbolId,
         expr: RExpr,
     },
+    IndexAssign {
+        hir_id: HirId,
+        target: RExpr,
+        index: RExpr,
+        expr: RExpr,
+    },
+    TupleAssign {
+        hir_id: HirId,
+        syms: Vec<SymbolId>,
+        expr: RExpr,
+    },
+    FieldAssign {
+        hir_id: HirId,
+        target: RExpr,
+        field: String,
+        expr: RExpr,
+    },
     ExprStmt {
         hir_id: HirId,
         expr: RExpr,
@@ -28,12 +46,107 @@ pub enum RStmt {
         end: RExpr,
         body: Vec<RStmt>,
     },
+    ForEach {
+        hir_id: HirId,
+        syms: Vec<SymbolId>,
+        iter: RExpr,
+        body: Vec<RStmt>,
+    },
+    While {
+        hir_id: HirId,
+        cond: RExpr,
+        body: Vec<RStmt>,
+    },
+    Break {
+        hir_id: HirId,
+    },
+    Continue {
+        hir_id: HirId,
+    },
     If {
         hir_id: HirId,
         cond: RExpr,
         then_branch: Vec<RStmt>,
         else_branch: Vec<RStmt>,
     },
+    FuncDef {
+        hir_id: HirId,
+        sym: SymbolId,
+        params: Vec<SymbolId>,
+        ret: Type,
+        body: Vec<RStmt>,
+    },
+    Return {
+        hir_id: HirId,
+        expr: Option<RExpr>,
+    },
+    StructDef {
+        hir_id: HirId,
+        name: String,
+        fields: Vec<(String, Type)>,
+        methods: Vec<RStmt>,
+    },
+    EnumDef {
+        hir_id: HirId,
+        name: String,
+        variants: Vec<(String, Vec<Type>)>,
+    },
+    Match {
+        hir_id: HirId,
+        subject: RExpr,
+        arms: Vec<RMatchArm>,
+    },
+    Try {
+        hir_id: HirId,
+        body: Vec<RStmt>,
+        handlers: Vec<RExceptHandler>,
+        finally: Vec<RStmt>,
+    },
+    Raise {
+        hir_id: HirId,
+        kind: Option<String>,
+        message: Option<RExpr>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RExceptHandler {
+    pub hir_id: HirId,
+    pub kind: Option<String>,
+    pub sym: Option<SymbolId>,
+    pub body: Vec<RStmt>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RMatchArm {
+    pub hir_id: HirId,
+    pub pattern: RPattern,
+    pub body: Vec<RStmt>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RPattern {
+    Wildcard {
+        hir_id: HirId,
+    },
+    Bind {
+        hir_id: HirId,
+        sym: SymbolId,
+    },
+    Literal {
+        hir_id: HirId,
+        value: RExpr,
+    },
+    Variant {
+        hir_id: HirId,
+        enum_name: String,
+        variant: String,
+        args: Vec<RPattern>,
+    },
+    Tuple {
+        hir_id: HirId,
+        items: Vec<RPattern>,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -43,6 +156,11 @@ pub enum RExpr {
         value: i64,
         ty: Type,
     },
+    Float {
+        hir_id: HirId,
+        value: f64,
+        ty: Type,
+    },
     Str {
         hir_id: HirId,
         value: String,
@@ -53,6 +171,22 @@ pub enum RExpr {
         value: bool,
         ty: Type,
     },
+    None {
+        hir_id: HirId,
+        ty: Type,
+    },
+    /// `Some(expr)`, an optional value that is present
+    Some {
+        hir_id: HirId,
+        expr: Box<RExpr>,
+        ty: Type,
+    },
+    /// The value of an optional variable known not to be `None`
+    Unwrap {
+        hir_id: HirId,
+        expr: Box<RExpr>,
+        ty: Type,
+    },
     Name {
         hir_id: HirId,
         sym: SymbolId,
@@ -65,6 +199,12 @@ pub enum RExpr {
         right: Box<RExpr>,
         ty: Type,
     },
+    Unary {
+        hir_id: HirId,
+        op: HirUnaryOp,
+        expr: Box<RExpr>,
+        ty: Type,
+    },
     Call {
         hir_id: HirId,
         func: Box<RExpr>,
@@ -77,17 +217,85 @@ pub enum RExpr {
         args: Vec<RExpr>,
         ty: Type,
     },
+    Index {
+        hir_id: HirId,
+        target: Box<RExpr>,
+        index: Box<RExpr>,
+        ty: Type,
+    },
+    Slice {
+        hir_id: HirId,
+        target: Box<RExpr>,
+        start: Option<Box<RExpr>>,
+        end: Option<Box<RExpr>>,
+        ty: Type,
+    },
     InterpolatedString {
         hir_id: HirId,
         parts: Vec<RStringPart>,
         ty: Type,
     },
+    Dict {
+        hir_id: HirId,
+        entries: Vec<(RExpr, RExpr)>,
+        ty: Type,
+    },
+    Tuple {
+        hir_id: HirId,
+        items: Vec<RExpr>,
+        ty: Type,
+    },
+    Range {
+        hir_id: HirId,
+        start: Box<RExpr>,
+        end: Box<RExpr>,
+        ty: Type,
+    },
+    Field {
+        hir_id: HirId,
+        target: Box<RExpr>,
+        name: String,
+        ty: Type,
+    },
+    MethodCall {
+        hir_id: HirId,
+        target: Box<RExpr>,
+        method: SymbolId,
+        args: Vec<RExpr>,
+        ty: Type,
+    },
+    Construct {
+        hir_id: HirId,
+        name: String,
+        fields: Vec<(String, RExpr)>,
+        ty: Type,
+    },
+    Variant {
+        hir_id: HirId,
+        enum_name: String,
+        variant: String,
+        args: Vec<RExpr>,
+        ty: Type,
+    },
+    Lambda {
+        hir_id: HirId,
+        params: Vec<SymbolId>,
+        body: Box<RExpr>,
+        ty: Type,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum RStringPart {
-    Text { hir_id: HirId, value: String },
-    Expr { hir_id: HirId, expr: RExpr },
+    Text {
+        hir_id: HirId,
+        value: String,
+    },
+    Expr {
+        hir_id: HirId,
+        expr: RExpr,
+        spec: FormatSpec,
+    },
 }
 
 #[derive(Debug)]
@@ -100,13 +308,28 @@ impl RExpr {
     pub fn ty(&self) -> &Type {
         match self {
             RExpr::Int { ty, .. }
+            | RExpr::Float { ty, .. }
             | RExpr::Str { ty, .. }
             | RExpr::Bool { ty, .. }
+            | RExpr::None { ty, .. }
+            | RExpr::Some { ty, .. }
+            | RExpr::Unwrap { ty, .. }
             | RExpr::Name { ty, .. }
             | RExpr::Binary { ty, .. }
+            | RExpr::Unary { ty, .. }
             | RExpr::Call { ty, .. }
             | RExpr::MacroCall { ty, .. }
-            | RExpr::InterpolatedString { ty, .. } => ty,
+            | RExpr::Index { ty, .. }
+            | RExpr::Slice { ty, .. }
+            | RExpr::InterpolatedString { ty, .. }
+            | RExpr::Dict { ty, .. }
+            | RExpr::Tuple { ty, .. }
+            | RExpr::Range { ty, .. }
+            | RExpr::Field { ty, .. }
+            | RExpr::MethodCall { ty, .. }
+            | RExpr::Construct { ty, .. }
+            | RExpr::Variant { ty, .. }
+            | RExpr::Lambda { ty, .. } => ty,
         }
     }
 }
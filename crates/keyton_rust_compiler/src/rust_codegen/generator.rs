@@ -1,16 +1,39 @@
 use std::collections::HashMap;
 
-use crate::rhir::types::{RExpr, RStmt, RStringPart, RustProgram};
+use crate::hir::hir_types::{HirBinOp, HirUnaryOp};
+use crate::rhir::types::{RExceptHandler, RExpr, RPattern, RStmt, RStringPart, RustProgram};
 use crate::shir::resolver::ResolvedProgram;
-use crate::shir::sym::{SymbolId, Type};
+use crate::shir::sym::{SymKind, SymbolId, Type};
+use kayton_plugin_sdk::manifest::{FunctionEntry, TypeKind};
 
 use super::types::RustCode;
 
 pub struct CodeGenerator<'a> {
     var_names: HashMap<SymbolId, String>,
     assigned_vars: std::collections::HashSet<SymbolId>,
+    /// Types of the program's variables, used for function parameter types
+    var_types: HashMap<SymbolId, Type>,
     resolved: &'a ResolvedProgram,
     next_var_id: u32,
+    /// Number of loops enclosing the statement being generated, within its function
+    loop_depth: usize,
+    /// Loops entered since the innermost enclosing `try` block, whose code runs in a
+    /// closure; `None` outside of `try` blocks
+    try_loops: Option<usize>,
+    /// Return type of the function being generated; `None` at the top level
+    func_ret: Option<Type>,
+    /// Variables holding the exceptions handled by the enclosing `except` bodies
+    exceptions: Vec<String>,
+    next_try_id: u32,
+    /// Variables assigned more than once. A string variable is then declared as an
+    /// owned `String`, since a later value may be built at run time.
+    reassigned: std::collections::HashSet<SymbolId>,
+    /// Variables that a lambda captures and that are assigned again or changed in place.
+    /// They live in a shared cell, so that the lambda sees their later values.
+    shared: std::collections::HashSet<SymbolId>,
+    /// Variables of `shared` that the code being generated changes in place, with the
+    /// local copy standing in for the cell until it is written back
+    rebound: HashMap<SymbolId, String>,
 }
 
 impl<'a> CodeGenerator<'a> {
@@ -18,12 +41,24 @@ impl<'a> CodeGenerator<'a> {
         Self {
             var_names: HashMap::new(),
             assigned_vars: std::collections::HashSet::new(),
+            var_types: HashMap::new(),
             resolved,
             next_var_id: 0,
+            loop_depth: 0,
+            try_loops: None,
+            func_ret: None,
+            exceptions: Vec::new(),
+            next_try_id: 0,
+            reassigned: std::collections::HashSet::new(),
+            shared: std::collections::HashSet::new(),
+            rebound: HashMap::new(),
         }
     }
 
     pub fn generate_code(&mut self, rhir_program: &RustProgram) -> RustCode {
+        self.var_types = rhir_program.var_types.clone();
+        self.reassigned = reassigned_vars(&rhir_program.rhir, &self.assigned_vars);
+        self.shared = self.shared_vars(&rhir_program.rhir);
         let mut source_code = String::new();
 
         // Add the main function
@@ -38,6 +73,12 @@ impl<'a> CodeGenerator<'a> {
             }
         }
 
+        for line in self.hoisted_declarations(&rhir_program.rhir) {
+            source_code.push_str("    ");
+            source_code.push_str(&line);
+            source_code.push('\n');
+        }
+
         // Generate statements
         for stmt in &rhir_program.rhir {
             if self.should_skip_stmt(stmt) {
@@ -57,17 +98,23 @@ impl<'a> CodeGenerator<'a> {
     }
 
     /// Same as generate_code but seeds the assigned set and inserts a prelude at the top of main.
+    /// `reports[i]` holds the globals reported to the host after the top-level statement
+    /// `i`, each with its reporting line, so that a runtime error later in the input keeps
+    /// what ran before it.
     pub fn generate_code_with_preassigned_and_prelude(
         &mut self,
         rhir_program: &RustProgram,
         pre_assigned: &std::collections::HashSet<SymbolId>,
         prelude_lines: &[String],
-        epilogue_lines: &[String],
+        reports: &[Vec<(SymbolId, String)>],
     ) -> RustCode {
         // Seed with already-assigned variable symbols so we emit `x = ...;` instead of `let mut x = ...;`
         for sym in pre_assigned.iter() {
             self.assigned_vars.insert(*sym);
         }
+        self.var_types = rhir_program.var_types.clone();
+        self.reassigned = reassigned_vars(&rhir_program.rhir, pre_assigned);
+        self.shared = self.shared_vars(&rhir_program.rhir);
 
         let mut source_code = String::new();
         source_code.push_str("fn main() {\n");
@@ -88,6 +135,25 @@ impl<'a> CodeGenerator<'a> {
                 source_code.push('\n');
             }
         }
+        // Globals that a lambda of this input captures move into their shared cells
+        let mut moved: Vec<SymbolId> = pre_assigned
+            .iter()
+            .filter(|sym| self.shared.contains(sym))
+            .copied()
+            .collect();
+        moved.sort_by_key(|sym| sym.0);
+        for sym in moved {
+            let name = self.get_or_create_var_name(sym);
+            source_code.push_str("    ");
+            source_code.push_str(&self.shared_decl(sym, &name, &name));
+            source_code.push('\n');
+        }
+
+        for line in self.hoisted_declarations(&rhir_program.rhir) {
+            source_code.push_str("    ");
+            source_code.push_str(&line);
+            source_code.push('\n');
+        }
 
         // Determine the last non-skipped expression statement with non-Unit type
         let mut last_expr_idx: Option<(usize, Type)> = None;
@@ -117,6 +183,18 @@ impl<'a> CodeGenerator<'a> {
                     let expr_str = self.convert_expr_to_string(expr);
                     source_code.push_str("let __kayton_last = ");
                     source_code.push_str(&expr_str);
+                    // Copy strings and collections so a variable is not moved before it is reported
+                    match expr.ty() {
+                        Type::Str => source_code.push_str(".to_string()"),
+                        Type::List(_)
+                        | Type::Dict(..)
+                        | Type::Tuple(_)
+                        | Type::Range
+                        | Type::Struct(_)
+                        | Type::Enum(_)
+                        | Type::Option(_) => source_code.push_str(".clone()"),
+                        _ => {}
+                    }
                     source_code.push_str(";\n");
                 }
                 _ => {
@@ -124,14 +202,24 @@ impl<'a> CodeGenerator<'a> {
                     source_code.push_str("\n");
                 }
             }
-        }
-
-        // Insert epilogue lines at end of main
-        for line in epilogue_lines {
-            source_code.push_str("    ");
-            source_code.push_str(line);
-            if !line.ends_with('\n') {
-                source_code.push('\n');
+            for (sym, line) in reports.get(idx).into_iter().flatten() {
+                source_code.push_str("    ");
+                if self.shared.contains(sym) {
+                    // The line reports the value, read out of the cell under the same name
+                    let name = self.get_or_create_var_name(*sym);
+                    let value = self.shared_value(*sym);
+                    source_code.push_str(&format!(
+                        "{{ let {} = {}; {} }}\n",
+                        name,
+                        value,
+                        line.trim_end()
+                    ));
+                    continue;
+                }
+                source_code.push_str(line);
+                if !line.ends_with('\n') {
+                    source_code.push('\n');
+                }
             }
         }
 
@@ -142,10 +230,45 @@ impl<'a> CodeGenerator<'a> {
                     source_code
                         .push_str("    unsafe { report_int(\"__last\", __kayton_last as i64); }\n");
                 }
+                Type::F64 => {
+                    source_code.push_str("    unsafe { report_f64(\"__last\", __kayton_last); }\n");
+                }
+                Type::Bool => {
+                    source_code
+                        .push_str("    unsafe { report_bool(\"__last\", __kayton_last); }\n");
+                }
                 Type::Str => {
                     source_code
                         .push_str("    unsafe { report_str(\"__last\", &__kayton_last); }\n");
                 }
+                Type::List(elem) => {
+                    if let Some(report) = vec_reporter(&elem) {
+                        source_code.push_str(&format!(
+                            "    unsafe {{ {}(\"__last\", &__kayton_last); }}\n",
+                            report
+                        ));
+                    }
+                }
+                Type::Dict(key, value) if map_reportable(&key, &value) => {
+                    source_code
+                        .push_str("    unsafe { report_map(\"__last\", &__kayton_last); }\n");
+                }
+                Type::Tuple(items) if tuple_reportable(&items) => {
+                    source_code
+                        .push_str("    unsafe { report_tuple(\"__last\", &__kayton_last); }\n");
+                }
+                // Shown as its repr, `Point(x=1, y=2)` or `Shape.Circle(2.0)`
+                Type::Struct(_) | Type::Enum(_) => {
+                    source_code.push_str(
+                        "    unsafe { report_str(\"__last\", &format!(\"{:?}\", __kayton_last)); }\n",
+                    );
+                }
+                Type::Option(inner) => {
+                    source_code.push_str(&format!(
+                        "    unsafe {{ report_str(\"__last\", &{}); }}\n",
+                        option_text("__kayton_last", &inner, false)
+                    ));
+                }
                 _ => {}
             }
         }
@@ -158,7 +281,7 @@ impl<'a> CodeGenerator<'a> {
         }
     }
 
-    /// Build prelude lines to load required plugins and fetch any used function pointers.
+    /// Build prelude lines to load required plugins and bind the plugin functions used.
     fn build_plugin_prelude_lines(&self, rhir_program: &RustProgram) -> Vec<String> {
         use std::collections::HashSet;
 
@@ -177,11 +300,11 @@ impl<'a> CodeGenerator<'a> {
             ));
         }
 
-        // Build a set of function names declared by manifests
-        let mut manifest_funcs: HashSet<&str> = HashSet::new();
+        // Functions declared by manifests, by name
+        let mut manifest_funcs: HashMap<&str, &FunctionEntry> = HashMap::new();
         for mani in self.resolved.plugins.values() {
             for f in &mani.functions {
-                manifest_funcs.insert(f.stable_name.as_str());
+                manifest_funcs.insert(f.stable_name.as_str(), f);
             }
         }
 
@@ -191,14 +314,16 @@ impl<'a> CodeGenerator<'a> {
             self.collect_used_in_stmt(s, &mut used_funcs);
         }
 
-        // Emit pointer fetches for used functions that are in manifests (no typing yet; just fetch)
+        // Bind each used plugin function to a function item of the same name, which calls
+        // the registered pointer and raises a returned error as an exception
+        let mut used_funcs: Vec<String> = used_funcs.into_iter().collect();
+        used_funcs.sort();
         for name in used_funcs {
-            if manifest_funcs.contains(name.as_str()) {
-                let nm_escaped = name.replace('"', "\\\"");
-                lines.push(format!(
-                    "let _ = unsafe {{ get_fn_ptr(\"{}\") }};",
-                    nm_escaped
-                ));
+            if let Some(line) = manifest_funcs
+                .get(name.as_str())
+                .and_then(|f| plugin_binding(f))
+            {
+                lines.push(line);
             }
         }
 
@@ -207,8 +332,24 @@ impl<'a> CodeGenerator<'a> {
 
     fn collect_used_in_stmt(&self, s: &RStmt, used: &mut std::collections::HashSet<String>) {
         match s {
-            RStmt::Assign { expr, .. } => self.collect_used_in_expr(expr, used),
+            RStmt::Assign { expr, .. } | RStmt::TupleAssign { expr, .. } => {
+                self.collect_used_in_expr(expr, used)
+            }
             RStmt::ExprStmt { expr, .. } => self.collect_used_in_expr(expr, used),
+            RStmt::FieldAssign { target, expr, .. } => {
+                self.collect_used_in_expr(target, used);
+                self.collect_used_in_expr(expr, used);
+            }
+            RStmt::IndexAssign {
+                target,
+                index,
+                expr,
+                ..
+            } => {
+                self.collect_used_in_expr(target, used);
+                self.collect_used_in_expr(index, used);
+                self.collect_used_in_expr(expr, used);
+            }
             RStmt::ForRange {
                 start, end, body, ..
             } => {
@@ -218,6 +359,23 @@ impl<'a> CodeGenerator<'a> {
                     self.collect_used_in_stmt(st, used);
                 }
             }
+            RStmt::ForEach {
+                iter: cond, body, ..
+            }
+            | RStmt::While { cond, body, .. } => {
+                self.collect_used_in_expr(cond, used);
+                for st in body {
+                    self.collect_used_in_stmt(st, used);
+                }
+            }
+            RStmt::FuncDef { body, .. } | RStmt::StructDef { methods: body, .. } => {
+                for st in body {
+                    self.collect_used_in_stmt(st, used);
+                }
+            }
+            RStmt::Return {
+                expr: Some(expr), ..
+            } => self.collect_used_in_expr(expr, used),
             RStmt::If {
                 cond,
                 then_branch,
@@ -232,6 +390,29 @@ impl<'a> CodeGenerator<'a> {
                     self.collect_used_in_stmt(st, used);
                 }
             }
+            RStmt::Match { subject, arms, .. } => {
+                self.collect_used_in_expr(subject, used);
+                for arm in arms {
+                    for st in &arm.body {
+                        self.collect_used_in_stmt(st, used);
+                    }
+                }
+            }
+            RStmt::Try {
+                body,
+                handlers,
+                finally,
+                ..
+            } => {
+                let handler_bodies = handlers.iter().flat_map(|h| &h.body);
+                for st in body.iter().chain(handler_bodies).chain(finally) {
+                    self.collect_used_in_stmt(st, used);
+                }
+            }
+            RStmt::Raise {
+                message: Some(expr),
+                ..
+            } => self.collect_used_in_expr(expr, used),
             _ => {}
         }
     }
@@ -247,6 +428,9 @@ impl<'a> CodeGenerator<'a> {
                 self.collect_used_in_expr(left, used);
                 self.collect_used_in_expr(right, used);
             }
+            RExpr::Unary { expr, .. } | RExpr::Some { expr, .. } | RExpr::Unwrap { expr, .. } => {
+                self.collect_used_in_expr(expr, used)
+            }
             RExpr::Call { func, args, .. } => {
                 self.collect_used_in_expr(func, used);
                 for a in args {
@@ -258,6 +442,18 @@ impl<'a> CodeGenerator<'a> {
                     self.collect_used_in_expr(a, used);
                 }
             }
+            RExpr::Index { target, index, .. } => {
+                self.collect_used_in_expr(target, used);
+                self.collect_used_in_expr(index, used);
+            }
+            RExpr::Slice {
+                target, start, end, ..
+            } => {
+                self.collect_used_in_expr(target, used);
+                for bound in [start, end].into_iter().flatten() {
+                    self.collect_used_in_expr(bound, used);
+                }
+            }
             RExpr::InterpolatedString { parts, .. } => {
                 for p in parts {
                     if let RStringPart::Expr { expr, .. } = p {
@@ -265,11 +461,50 @@ impl<'a> CodeGenerator<'a> {
                     }
                 }
             }
+            RExpr::Dict { entries, .. } => {
+                for (k, v) in entries {
+                    self.collect_used_in_expr(k, used);
+                    self.collect_used_in_expr(v, used);
+                }
+            }
+            RExpr::Tuple { items, .. } => {
+                for item in items {
+                    self.collect_used_in_expr(item, used);
+                }
+            }
+            RExpr::Range { start, end, .. } => {
+                self.collect_used_in_expr(start, used);
+                self.collect_used_in_expr(end, used);
+            }
+            RExpr::Field { target, .. } => self.collect_used_in_expr(target, used),
+            RExpr::MethodCall { target, args, .. } => {
+                self.collect_used_in_expr(target, used);
+                for a in args {
+                    self.collect_used_in_expr(a, used);
+                }
+            }
+            RExpr::Construct { fields, .. } => {
+                for (_, value) in fields {
+                    self.collect_used_in_expr(value, used);
+                }
+            }
+            RExpr::Variant { args, .. } => {
+                for a in args {
+                    self.collect_used_in_expr(a, used);
+                }
+            }
+            RExpr::Lambda { body, .. } => self.collect_used_in_expr(body, used),
             _ => {}
         }
     }
 
     fn convert_stmt_to_string(&mut self, stmt: &RStmt) -> String {
+        if let RStmt::FieldAssign { target, .. } | RStmt::IndexAssign { target, .. } = stmt
+            && let Some(root) = place_root(target)
+            && self.shared_cell(root) == Some("RefCell")
+        {
+            return self.with_written_back(&[root], true, |this| this.convert_stmt_to_string(stmt));
+        }
         match stmt {
             RStmt::RImportModule { .. } | RStmt::RImportItems { .. } => {
                 // rimport directives don't emit runtime code in this phase
@@ -277,22 +512,115 @@ impl<'a> CodeGenerator<'a> {
             }
             RStmt::Assign { sym, expr, .. } => {
                 let var_name = self.get_or_create_var_name(*sym);
-                let expr_str = self.convert_expr_to_string(expr);
 
                 // Check if this variable has been assigned before
                 let is_mutable = self.assigned_vars.contains(sym);
                 self.assigned_vars.insert(*sym);
+                let expr_str = if let RExpr::Name { .. } = expr {
+                    // `ys = xs` copies a list, dict or struct, so both names stay usable
+                    self.convert_moved(expr)
+                } else if is_mutable || self.reassigned.contains(sym) {
+                    self.convert_owned(expr)
+                } else {
+                    self.convert_expr_to_string(expr)
+                };
 
+                if let Some(cell) = self.shared_cell(*sym) {
+                    let expr_str = if let RExpr::Name { .. } = expr {
+                        expr_str
+                    } else {
+                        self.convert_owned(expr)
+                    };
+                    return match (is_mutable, cell) {
+                        (false, _) => self.shared_decl(*sym, &var_name, &expr_str),
+                        (true, "Cell") => format!("{}.set({});", var_name, expr_str),
+                        (true, _) => format!(
+                            "{{ let __v = {}; *{}.borrow_mut() = __v; }}",
+                            expr_str, var_name
+                        ),
+                    };
+                }
                 if is_mutable {
                     format!("{} = {};", var_name, expr_str)
+                } else if let Some(ty) = self.var_types.get(sym).and_then(rust_collection_type) {
+                    // Spelled out so that an empty `[]` or `{}` has item types
+                    format!("let mut {}: {} = {};", var_name, ty, expr_str)
                 } else {
                     format!("let mut {} = {};", var_name, expr_str)
                 }
             }
+            RStmt::TupleAssign { syms, expr, .. } => {
+                // Items of a tuple literal are unpacked as written, like a plain assignment
+                // A tuple variable is unpacked from a copy, so it can still be used afterwards
+                let expr_str = match expr {
+                    RExpr::Tuple { items, .. } => self.convert_tuple(items, false),
+                    RExpr::Name { .. } => self.convert_moved(expr),
+                    _ => self.convert_expr_to_string(expr),
+                };
+                let mut new_vars = Vec::new();
+                let mut names = Vec::new();
+                for sym in syms {
+                    names.push(self.get_or_create_var_name(*sym));
+                    if self.assigned_vars.insert(*sym) {
+                        new_vars.push(names.last().cloned().unwrap_or_default());
+                    }
+                }
+                if new_vars.len() == names.len() {
+                    let pattern = names
+                        .iter()
+                        .map(|n| format!("mut {}", n))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    format!("let ({}) = {};", pattern, expr_str)
+                } else {
+                    // Destructuring assignment needs the new names declared first
+                    let decls: String = new_vars
+                        .iter()
+                        .map(|n| format!("let mut {}; ", n))
+                        .collect();
+                    format!("{}({}) = {};", decls, names.join(", "), expr_str)
+                }
+            }
             RStmt::ExprStmt { expr, .. } => {
                 let expr_str = self.convert_expr_to_string(expr);
                 format!("{};", expr_str)
             }
+            RStmt::FieldAssign {
+                target,
+                field,
+                expr,
+                ..
+            } => {
+                let expr_str = self.convert_moved(expr);
+                let place = self.convert_place(target);
+                format!("{}.{} = {};", place, field, expr_str)
+            }
+            RStmt::IndexAssign {
+                target,
+                index,
+                expr,
+                ..
+            } => {
+                if let Type::Dict(..) = target.ty() {
+                    let dict = match target {
+                        RExpr::Index {
+                            target: inner,
+                            index: inner_index,
+                            ..
+                        } if *inner.ty() != Type::Str => {
+                            self.convert_item_place(inner, inner_index, "list")
+                        }
+                        _ => self.convert_place(target),
+                    };
+                    let key_str = self.convert_owned(index);
+                    let expr_str = self.convert_owned(expr);
+                    return format!("{}.insert({}, {});", dict, key_str, expr_str);
+                }
+                // Rust evaluates the assigned value before the place, as Python does
+                let place = self.convert_item_place(target, index, "list assignment");
+                let expr_str = self.convert_owned(expr);
+                format!("*{} = {};", place, expr_str)
+            }
             RStmt::ForRange {
                 sym,
                 start,
@@ -309,6 +637,7 @@ impl<'a> CodeGenerator<'a> {
                     "for {} in {}..{} {{\n",
                     var_name, start_str, end_str
                 ));
+                self.enter_loop();
                 for inner in body {
                     if self.should_skip_stmt(inner) {
                         continue;
@@ -317,9 +646,250 @@ impl<'a> CodeGenerator<'a> {
                     out.push_str(&self.convert_stmt_to_string(inner));
                     out.push_str("\n");
                 }
+                self.leave_loop();
                 out.push_str("}");
                 out
             }
+            RStmt::ForEach {
+                syms, iter, body, ..
+            } => {
+                let names = syms
+                    .iter()
+                    .map(|sym| self.get_or_create_var_name(*sym))
+                    .collect::<Vec<_>>();
+                let pattern = match names.as_slice() {
+                    [name] => name.clone(),
+                    _ => format!("({})", names.join(", ")),
+                };
+                let iter_str = self.convert_iterable(iter);
+                let mut out = format!("for {} in {} {{\n", pattern, iter_str);
+                self.enter_loop();
+                for inner in body {
+                    if self.should_skip_stmt(inner) {
+                        continue;
+                    }
+                    out.push_str("    ");
+                    out.push_str(&self.convert_stmt_to_string(inner));
+                    out.push('\n');
+                }
+                self.leave_loop();
+                out.push('}');
+                out
+            }
+            RStmt::While { cond, body, .. } => {
+                let cond_str = self.convert_expr_to_string(cond);
+                let mut out = String::new();
+                out.push_str(&format!("while {} {{\n", cond_str));
+                self.enter_loop();
+                for inner in body {
+                    if self.should_skip_stmt(inner) {
+                        continue;
+                    }
+                    out.push_str("    ");
+                    out.push_str(&self.convert_stmt_to_string(inner));
+                    out.push('\n');
+                }
+                self.leave_loop();
+                out.push('}');
+                out
+            }
+            RStmt::FuncDef {
+                sym,
+                params,
+                ret,
+                body,
+                ..
+            } => {
+                let name = self.get_or_create_var_name(*sym);
+                self.convert_func_def(&name, params, ret, body, false)
+            }
+            RStmt::StructDef {
+                name,
+                fields,
+                methods,
+                ..
+            } => {
+                let mut out = format!("#[derive(Clone, PartialEq)]\nstruct {} {{\n", name);
+                for (field, ty) in fields {
+                    let ty = rust_value_type(ty).unwrap_or_else(|| "()".to_string());
+                    out.push_str(&format!("    {}: {},\n", field, ty));
+                }
+                out.push_str("}\n");
+                // Printed like a Python dataclass: `Point(x=1, y=2)`
+                let repr = fields
+                    .iter()
+                    .map(|(field, ty)| match ty {
+                        Type::Option(_) => format!("{}={{}}", field),
+                        _ => format!("{}={{:?}}", field),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let values: String = fields
+                    .iter()
+                    .map(|(field, ty)| match ty {
+                        Type::Option(inner) => {
+                            format!(", {}", option_text(&format!("self.{}", field), inner, true))
+                        }
+                        _ => format!(", self.{}", field),
+                    })
+                    .collect();
+                out.push_str(&format!(
+                    "impl std::fmt::Debug for {} {{\n    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {{\n        write!(f, \"{}({})\"{})\n    }}\n}}\n",
+                    name, name, repr, values
+                ));
+                out.push_str(&format!("impl {} {{\n", name));
+                for method in methods {
+                    if self.should_skip_stmt(method) {
+                        continue;
+                    }
+                    if let RStmt::FuncDef {
+                        sym,
+                        params,
+                        ret,
+                        body,
+                        ..
+                    } = method
+                    {
+                        let method_name = self.resolved.symbols.infos[sym.0 as usize].name.clone();
+                        out.push_str("    ");
+                        out.push_str(&self.convert_func_def(&method_name, params, ret, body, true));
+                        out.push('\n');
+                    }
+                }
+                out.push('}');
+                out
+            }
+            RStmt::EnumDef { name, variants, .. } => {
+                let mut out = format!("#[derive(Clone, PartialEq)]\nenum {} {{\n", name);
+                for (variant, parts) in variants {
+                    if parts.is_empty() {
+                        out.push_str(&format!("    {},\n", variant));
+                    } else {
+                        let parts = parts
+                            .iter()
+                            .map(|ty| rust_value_type(ty).unwrap_or_else(|| "()".to_string()))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        out.push_str(&format!("    {}({}),\n", variant, parts));
+                    }
+                }
+                out.push_str("}\n");
+                // Printed as it is written in Kayton: `Shape.Circle(2.0)`, `Shape.Empty`
+                out.push_str(&format!(
+                    "impl std::fmt::Debug for {} {{\n    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {{\n        match self {{\n",
+                    name
+                ));
+                for (variant, parts) in variants {
+                    if parts.is_empty() {
+                        out.push_str(&format!(
+                            "            {}::{} => write!(f, \"{}.{}\"),\n",
+                            name, variant, name, variant
+                        ));
+                    } else {
+                        let vars: Vec<String> =
+                            (0..parts.len()).map(|i| format!("__{}", i)).collect();
+                        let repr = parts
+                            .iter()
+                            .map(|ty| match ty {
+                                Type::Option(_) => "{}",
+                                _ => "{:?}",
+                            })
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        let values = vars
+                            .iter()
+                            .zip(parts)
+                            .map(|(var, ty)| match ty {
+                                Type::Option(inner) => option_text(var, inner, true),
+                                _ => var.clone(),
+                            })
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        out.push_str(&format!(
+                            "            {}::{}({}) => write!(f, \"{}.{}({})\", {}),\n",
+                            name,
+                            variant,
+                            vars.join(", "),
+                            name,
+                            variant,
+                            repr,
+                            values
+                        ));
+                    }
+                }
+                out.push_str("        }\n    }\n}");
+                out
+            }
+            RStmt::Match { subject, arms, .. } => {
+                let subject_str = self.convert_moved(subject);
+                let mut out = format!("match {} {{\n", subject_str);
+                for arm in arms {
+                    let mut guards = Vec::new();
+                    let pattern = self.convert_pattern(&arm.pattern, &mut guards);
+                    if guards.is_empty() {
+                        out.push_str(&format!("    {} => {{\n", pattern));
+                    } else {
+                        out.push_str(&format!(
+                            "    {} if {} => {{\n",
+                            pattern,
+                            guards.join(" && ")
+                        ));
+                    }
+                    for inner in &arm.body {
+                        if self.should_skip_stmt(inner) {
+                            continue;
+                        }
+                        out.push_str("        ");
+                        out.push_str(&self.convert_stmt_to_string(inner));
+                        out.push('\n');
+                    }
+                    out.push_str("    }\n");
+                }
+                out.push('}');
+                out
+            }
+            RStmt::Return { expr: None, .. } => self.return_stmt(None),
+            RStmt::Return {
+                expr: Some(expr), ..
+            } => {
+                let expr_str = self.convert_expr_to_string(expr);
+                if *expr.ty() == Type::Str {
+                    self.return_stmt(Some(&format!("{}.to_string()", expr_str)))
+                } else {
+                    self.return_stmt(Some(&expr_str))
+                }
+            }
+            RStmt::Break { .. } => self.loop_exit_stmt("break", "Break"),
+            RStmt::Continue { .. } => self.loop_exit_stmt("continue", "Continue"),
+            RStmt::Try {
+                body,
+                handlers,
+                finally,
+                ..
+            } => self.convert_try(body, handlers, finally),
+            RStmt::Raise {
+                kind: Some(kind),
+                message,
+                ..
+            } => {
+                let message_str = match message {
+                    // Like Python, a `KeyError` shows its key quoted, as a failed lookup does
+                    Some(message) if kind == "KeyError" => {
+                        format!(
+                            "__kayton_repr_str(&{})",
+                            self.convert_expr_to_string(message)
+                        )
+                    }
+                    Some(message) => self.convert_owned(message),
+                    None => "String::new()".to_string(),
+                };
+                format!("kayton_raise(\"{}\", {});", kind, message_str)
+            }
+            RStmt::Raise { kind: None, .. } => {
+                // The resolver only accepts a bare `raise` inside an `except` body
+                let exc = self.exceptions.last().cloned().unwrap_or_default();
+                format!("kayton_raise({}.kind, {}.message.clone());", exc, exc)
+            }
             RStmt::If {
                 cond,
                 then_branch,
@@ -355,6 +925,294 @@ impl<'a> CodeGenerator<'a> {
         }
     }
 
+    fn enter_loop(&mut self) {
+        self.loop_depth += 1;
+        if let Some(loops) = &mut self.try_loops {
+            *loops += 1;
+        }
+    }
+
+    fn leave_loop(&mut self) {
+        self.loop_depth -= 1;
+        if let Some(loops) = &mut self.try_loops {
+            *loops -= 1;
+        }
+    }
+
+    /// `return`, which inside a `try` block leaves the block's closure with the value.
+    fn return_stmt(&self, value: Option<&str>) -> String {
+        match (self.try_loops, value) {
+            (Some(_), value) => format!("return KaytonFlow::Return({});", value.unwrap_or("()")),
+            (None, Some(value)) => format!("return {};", value),
+            (None, None) => "return;".to_string(),
+        }
+    }
+
+    /// `break` or `continue`; a loop around a `try` block is left from the block's
+    /// closure with the `KaytonFlow` variant `flow`.
+    fn loop_exit_stmt(&self, keyword: &str, flow: &str) -> String {
+        match self.try_loops {
+            Some(0) => format!("return KaytonFlow::{};", flow),
+            _ => format!("{};", keyword),
+        }
+    }
+
+    /// A `try` statement. The body and each handler run in a closure under
+    /// `__kayton_try`, which catches the exception they raise. The `finally` block runs
+    /// next, then an exception no handler took is raised again, and a `break`,
+    /// `continue` or `return` from inside the closures is carried out.
+    fn convert_try(
+        &mut self,
+        body: &[RStmt],
+        handlers: &[RExceptHandler],
+        finally: &[RStmt],
+    ) -> String {
+        let id = self.next_try_id;
+        self.next_try_id += 1;
+        let flow = format!("__flow{}", id);
+        let exc = format!("__exc{}", id);
+        let ret_ty = self
+            .func_ret
+            .as_ref()
+            .and_then(rust_value_type)
+            .unwrap_or_else(|| "()".to_string());
+        let closure = format!("__kayton_try(|| -> KaytonFlow<{}> {{\n", ret_ty);
+
+        let outer_loops = self.try_loops.replace(0);
+        let mut out = format!("let {} = match {}", flow, closure);
+        out.push_str(&self.convert_block(body, "    "));
+        out.push_str("    KaytonFlow::Next\n}) {\n");
+        for handler in handlers {
+            match handler.kind.as_deref() {
+                Some(kind) => out.push_str(&format!(
+                    "    Err({}) if __kayton_catches(&{}, \"{}\") => {}",
+                    exc, exc, kind, closure
+                )),
+                None => out.push_str(&format!("    Err({}) => {}", exc, closure)),
+            }
+            if let Some(sym) = handler.sym {
+                self.assigned_vars.insert(sym);
+                let name = self.get_or_create_var_name(sym);
+                out.push_str(&format!(
+                    "        let mut {} = {}.message.clone();\n",
+                    name, exc
+                ));
+            }
+            self.exceptions.push(exc.clone());
+            out.push_str(&self.convert_block(&handler.body, "        "));
+            self.exceptions.pop();
+            out.push_str("        KaytonFlow::Next\n    }),\n");
+        }
+        out.push_str("    flow => flow,\n};\n");
+        self.try_loops = outer_loops;
+
+        for inner in finally {
+            if self.should_skip_stmt(inner) {
+                continue;
+            }
+            out.push_str(&self.convert_stmt_to_string(inner));
+            out.push('\n');
+        }
+        out.push_str(&format!("match {} {{\n", flow));
+        if self.loop_depth > 0 || self.try_loops.is_some() {
+            out.push_str(&format!(
+                "    Ok(KaytonFlow::Break) => {{ {} }}\n",
+                self.loop_exit_stmt("break", "Break")
+            ));
+            out.push_str(&format!(
+                "    Ok(KaytonFlow::Continue) => {{ {} }}\n",
+                self.loop_exit_stmt("continue", "Continue")
+            ));
+        }
+        if self.func_ret.is_some() {
+            out.push_str(&format!(
+                "    Ok(KaytonFlow::Return(__value)) => {{ {} }}\n",
+                self.return_stmt(Some("__value"))
+            ));
+        }
+        out.push_str("    Ok(_) => {}\n");
+        out.push_str("    Err(__exc) => ::std::panic::resume_unwind(Box::new(__exc)),\n}");
+        out
+    }
+
+    /// The statements of a block, each line indented by `indent`.
+    fn convert_block(&mut self, body: &[RStmt], indent: &str) -> String {
+        let mut out = String::new();
+        for inner in body {
+            if self.should_skip_stmt(inner) {
+                continue;
+            }
+            out.push_str(indent);
+            out.push_str(&self.convert_stmt_to_string(inner));
+            out.push('\n');
+        }
+        out
+    }
+
+    /// A Rust pattern for a `case` pattern. Rust has no string or float patterns, so
+    /// those literals bind a temporary that `guards` compares with the literal.
+    fn convert_pattern(&mut self, pattern: &RPattern, guards: &mut Vec<String>) -> String {
+        match pattern {
+            RPattern::Wildcard { .. } => "_".to_string(),
+            RPattern::Bind { sym, .. } => {
+                self.assigned_vars.insert(*sym);
+                format!("mut {}", self.get_or_create_var_name(*sym))
+            }
+            RPattern::Literal { value, .. } => match value {
+                RExpr::Int { .. } | RExpr::Bool { .. } => self.convert_expr_to_string(value),
+                RExpr::Unary {
+                    op: HirUnaryOp::Neg,
+                    expr,
+                    ..
+                } if matches!(expr.as_ref(), RExpr::Int { .. }) => {
+                    format!("-{}", self.convert_expr_to_string(expr))
+                }
+                _ => {
+                    let temp = format!("__p{}", guards.len());
+                    let value_str = self.convert_expr_to_string(value);
+                    guards.push(format!("{} == {}", temp, value_str));
+                    temp
+                }
+            },
+            RPattern::Variant {
+                enum_name,
+                variant,
+                args,
+                ..
+            } => {
+                if args.is_empty() {
+                    return format!("{}::{}", enum_name, variant);
+                }
+                let args_str = args
+                    .iter()
+                    .map(|a| self.convert_pattern(a, guards))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("{}::{}({})", enum_name, variant, args_str)
+            }
+            RPattern::Tuple { items, .. } => {
+                let items_str = items
+                    .iter()
+                    .map(|i| self.convert_pattern(i, guards))
+                    .collect::<Vec<_>>();
+                if items_str.len() == 1 {
+                    format!("({},)", items_str[0])
+                } else {
+                    format!("({})", items_str.join(", "))
+                }
+            }
+        }
+    }
+
+    /// A function, or a method when `receiver` is set: its first parameter is then
+    /// `self`, borrowed mutably so that the method can change the fields.
+    fn convert_func_def(
+        &mut self,
+        name: &str,
+        params: &[SymbolId],
+        ret: &Type,
+        body: &[RStmt],
+        receiver: bool,
+    ) -> String {
+        // Locals are named independently of the enclosing code, and loops and `try`
+        // blocks around the definition do not enclose the body
+        let outer_names = std::mem::take(&mut self.var_names);
+        let outer_loops = std::mem::take(&mut self.loop_depth);
+        let outer_try = self.try_loops.take();
+        let outer_ret = self.func_ret.replace(ret.clone());
+        let outer_exceptions = std::mem::take(&mut self.exceptions);
+        let params_str = params
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                self.assigned_vars.insert(*p);
+                if receiver && i == 0 {
+                    self.var_names.insert(*p, "self".to_string());
+                    return "&mut self".to_string();
+                }
+                let ty = self
+                    .var_types
+                    .get(p)
+                    .and_then(rust_param_type)
+                    .unwrap_or_else(|| "_".to_string());
+                format!("mut {}: {}", self.get_or_create_var_name(*p), ty)
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut out = match rust_value_type(ret) {
+            Some(ty) => format!("fn {}({}) -> {} {{\n", name, params_str, ty),
+            None => format!("fn {}({}) {{\n", name, params_str),
+        };
+        for line in self.hoisted_declarations(body) {
+            out.push_str("    ");
+            out.push_str(&line);
+            out.push('\n');
+        }
+        for inner in body {
+            if self.should_skip_stmt(inner) {
+                continue;
+            }
+            out.push_str("    ");
+            out.push_str(&self.convert_stmt_to_string(inner));
+            out.push('\n');
+        }
+        // A `try` statement ending the body returns from every path, which Rust cannot see
+        if rust_value_type(ret).is_some() && matches!(body.last(), Some(RStmt::Try { .. })) {
+            out.push_str("    unreachable!()\n");
+        }
+        out.push('}');
+        self.var_names = outer_names;
+        self.loop_depth = outer_loops;
+        self.try_loops = outer_try;
+        self.func_ret = outer_ret;
+        self.exceptions = outer_exceptions;
+        out
+    }
+
+    /// Declare up front the variables whose first assignment is inside a loop or branch,
+    /// so that they stay visible after the block like Kayton variables do. Numbers and
+    /// bools start at zero/false so that a variable assigned on only some paths can
+    /// still be read (and reported to the REPL) afterwards.
+    fn hoisted_declarations(&mut self, body: &[RStmt]) -> Vec<String> {
+        let mut seen = self.assigned_vars.clone();
+        let mut hoisted = Vec::new();
+        first_assigned_in_blocks(body, false, &mut seen, &mut hoisted);
+        hoisted
+            .into_iter()
+            .map(|sym| {
+                self.assigned_vars.insert(sym);
+                let name = self.get_or_create_var_name(sym);
+                if self.shared.contains(&sym)
+                    && let Some(init) = self.var_types.get(&sym).and_then(shared_init)
+                {
+                    return self.shared_decl(sym, &name, init);
+                }
+                match self.var_types.get(&sym) {
+                    Some(Type::I64) => format!("let mut {}: i64 = 0;", name),
+                    Some(Type::F64) => format!("let mut {}: f64 = 0.0;", name),
+                    Some(Type::Bool) => format!("let mut {} = false;", name),
+                    // A closure of a `try` block cannot assign an uninitialized variable
+                    Some(Type::Str) => format!("let mut {} = String::new();", name),
+                    Some(ty @ Type::List(_)) => match rust_collection_type(ty) {
+                        Some(rust_ty) => format!("let mut {}: {} = Vec::new();", name, rust_ty),
+                        None => format!("let mut {};", name),
+                    },
+                    Some(ty @ Type::Dict(..)) => match rust_collection_type(ty) {
+                        Some(rust_ty) => {
+                            format!("let mut {}: {} = HashMap::new();", name, rust_ty)
+                        }
+                        None => format!("let mut {};", name),
+                    },
+                    Some(ty @ Type::Option(_)) => match rust_collection_type(ty) {
+                        Some(rust_ty) => format!("let mut {}: {} = None;", name, rust_ty),
+                        None => format!("let mut {};", name),
+                    },
+                    _ => format!("let mut {};", name),
+                }
+            })
+            .collect()
+    }
+
     fn should_skip_stmt(&self, stmt: &RStmt) -> bool {
         match stmt {
             RStmt::RImportModule { .. } | RStmt::RImportItems { .. } => true,
@@ -362,26 +1220,155 @@ impl<'a> CodeGenerator<'a> {
                 RExpr::Int { value, .. } if *value == 0 => true,
                 _ => false,
             },
+            // Functions that are never called may have parameters of unknown type
+            RStmt::FuncDef { params, ret, .. } => {
+                *ret == Type::Any
+                    || params
+                        .iter()
+                        .any(|p| self.var_types.get(p).and_then(rust_param_type).is_none())
+            }
             _ => false,
         }
     }
 
     fn convert_expr_to_string(&mut self, expr: &RExpr) -> String {
+        let written = self.written_cells(expr);
+        if !written.is_empty() {
+            return self
+                .with_written_back(&written, false, |this| this.convert_expr_to_string(expr));
+        }
         match expr {
             RExpr::Int { value, .. } => value.to_string(),
-            RExpr::Str { value, .. } => format!("\"{}\"", value),
+            RExpr::Float { value, .. } => float_literal(*value),
+            // Debug formatting escapes quotes, backslashes and line breaks
+            RExpr::Str { value, .. } => format!("{:?}", value),
             RExpr::Bool { value, .. } => value.to_string(),
-            RExpr::Name { sym, .. } => self.get_or_create_var_name(*sym),
+            RExpr::None { .. } => "None".to_string(),
+            RExpr::Some { expr, .. } => format!("Some({})", self.convert_moved(expr)),
+            RExpr::Unwrap { expr, ty, .. } => {
+                let expr_str = self.convert_expr_to_string(expr);
+                if matches!(ty, Type::I64 | Type::F64 | Type::Bool) {
+                    format!("{}.unwrap()", expr_str)
+                } else {
+                    format!("{}.clone().unwrap()", expr_str)
+                }
+            }
+            RExpr::Name { sym, ty, .. } => {
+                let name = self.get_or_create_var_name(*sym);
+                // A method's receiver is borrowed; its value is a copy
+                if name == "self" {
+                    return "self.clone()".to_string();
+                }
+                if self.shared_cell(*sym).is_some() {
+                    return self.shared_value(*sym);
+                }
+                match ty {
+                    // A function used as a value, rather than called
+                    Type::Func(..) if self.is_func_item(*sym) => match rust_value_type(ty) {
+                        Some(fn_ty) => format!("(std::rc::Rc::new({}) as {})", name, fn_ty),
+                        None => name,
+                    },
+                    Type::Func(..) => format!("{}.clone()", name),
+                    _ => name,
+                }
+            }
+            RExpr::Lambda {
+                params, body, ty, ..
+            } => self.convert_lambda(params, body, ty),
             RExpr::Binary {
-                left, op, right, ..
+                left,
+                op: op @ (HirBinOp::In | HirBinOp::NotIn),
+                right,
+                ..
+            } => {
+                let test = self.convert_membership(left, right);
+                if *op == HirBinOp::NotIn {
+                    format!("(!{})", test)
+                } else {
+                    test
+                }
+            }
+            RExpr::Binary {
+                left,
+                op: op @ (HirBinOp::Is | HirBinOp::IsNot),
+                ..
             } => {
+                let left_str = self.convert_expr_to_string(left);
+                if *op == HirBinOp::Is {
+                    format!("{}.is_none()", left_str)
+                } else {
+                    format!("{}.is_some()", left_str)
+                }
+            }
+            RExpr::Binary {
+                left, op, right, ..
+            } if *left.ty() == Type::Str
+                && matches!(
+                    op,
+                    HirBinOp::Add | HirBinOp::Lt | HirBinOp::LtEq | HirBinOp::Gt | HirBinOp::GtEq
+                ) =>
+            {
                 let left_str = self.convert_expr_to_string(left);
                 let right_str = self.convert_expr_to_string(right);
                 let op_str = match op {
-                    crate::hir::hir_types::HirBinOp::Add => "+",
+                    HirBinOp::Add => {
+                        return format!("format!(\"{{}}{{}}\", {}, {})", left_str, right_str);
+                    }
+                    HirBinOp::Lt => "<",
+                    HirBinOp::LtEq => "<=",
+                    HirBinOp::Gt => ">",
+                    _ => ">=",
+                };
+                // Either side may be a `String` or a `&str`; both compare as `&str`
+                format!("(&*{} {} &*{})", left_str, op_str, right_str)
+            }
+            RExpr::Binary {
+                left, op, right, ..
+            } => {
+                // Mixed int/float operands (and every true division) promote ints to f64
+                let float_op = *left.ty() == Type::F64
+                    || *right.ty() == Type::F64
+                    || matches!(op, HirBinOp::Div);
+                let left_str = self.convert_operand(left, float_op);
+                let right_str = self.convert_operand(right, float_op);
+                let op_str = match op {
+                    HirBinOp::Add => "+",
+                    HirBinOp::Sub => "-",
+                    HirBinOp::Mul => "*",
+                    HirBinOp::Eq => "==",
+                    HirBinOp::NotEq => "!=",
+                    HirBinOp::Lt => "<",
+                    HirBinOp::LtEq => "<=",
+                    HirBinOp::Gt => ">",
+                    HirBinOp::GtEq => ">=",
+                    HirBinOp::And => "&&",
+                    HirBinOp::Or => "||",
+                    HirBinOp::In | HirBinOp::NotIn | HirBinOp::Is | HirBinOp::IsNot => {
+                        unreachable!("converted above")
+                    }
+                    // Division goes through runtime helpers, which raise ZeroDivisionError
+                    // for a zero divisor and follow Python's rounding
+                    HirBinOp::Div | HirBinOp::FloorDiv | HirBinOp::Mod => {
+                        let helper = match (op, float_op) {
+                            (HirBinOp::Div, _) => "__kayton_div",
+                            (HirBinOp::FloorDiv, true) => "__kayton_floordiv_f64",
+                            (HirBinOp::FloorDiv, false) => "__kayton_floordiv",
+                            (_, true) => "__kayton_mod_f64",
+                            (_, false) => "__kayton_mod",
+                        };
+                        return format!("{}({}, {})", helper, left_str, right_str);
+                    }
                 };
                 format!("({} {} {})", left_str, op_str, right_str)
             }
+            RExpr::Unary { op, expr, .. } => {
+                let expr_str = self.convert_expr_to_string(expr);
+                let op_str = match op {
+                    HirUnaryOp::Neg => "-",
+                    HirUnaryOp::Not => "!",
+                };
+                format!("({}{})", op_str, expr_str)
+            }
             RExpr::Call { func, args, .. } => {
                 if let RExpr::Name { sym, .. } = func.as_ref() {
                     if let Some(info) = self.resolved.symbols.infos.get(sym.0 as usize) {
@@ -389,35 +1376,127 @@ impl<'a> CodeGenerator<'a> {
                             "vec" => {
                                 let elems = args
                                     .iter()
-                                    .map(|a| self.convert_expr_to_string(a))
+                                    .map(|a| self.convert_owned(a))
                                     .collect::<Vec<_>>()
                                     .join(", ");
                                 return format!("vec![{}]", elems);
                             }
                             "append" => {
-                                let target = self.convert_expr_to_string(&args[0]);
-                                let value = self.convert_expr_to_string(&args[1]);
+                                let target = self.convert_place(&args[0]);
+                                let value = self.convert_owned(&args[1]);
                                 return format!("{}.push({})", target, value);
                             }
                             "sum" => {
                                 let target = self.convert_expr_to_string(&args[0]);
-                                return format!("{}.iter().sum::<i64>()", target);
+                                let elem = if *expr.ty() == Type::F64 {
+                                    "f64"
+                                } else {
+                                    "i64"
+                                };
+                                return format!("{}.iter().sum::<{}>()", target, elem);
+                            }
+                            "keys" | "values" => {
+                                let target = self.convert_expr_to_string(&args[0]);
+                                return format!(
+                                    "{}.{}().cloned().collect::<Vec<_>>()",
+                                    target, info.name
+                                );
+                            }
+                            "items" => {
+                                let target = self.convert_expr_to_string(&args[0]);
+                                return format!(
+                                    "{}.iter().map(|(k, v)| (k.clone(), v.clone())).collect::<Vec<_>>()",
+                                    target
+                                );
+                            }
+                            "map" | "filter" | "sorted" if info.kind == SymKind::BuiltinFunc => {
+                                return self.convert_list_func(&info.name.clone(), args);
+                            }
+                            "len" | "split" | "join" | "strip" | "upper" | "lower" | "replace"
+                            | "startswith" | "find"
+                                if info.kind == SymKind::BuiltinFunc =>
+                            {
+                                return self.convert_str_func(&info.name.clone(), args);
                             }
                             _ => {}
                         }
                     }
                 }
-                let func_str = self.convert_expr_to_string(func);
+                // A function value computed by an expression is called through parentheses
+                let func_str = match func.as_ref() {
+                    RExpr::Name { .. } => self.convert_place(func),
+                    _ => format!("({})", self.convert_expr_to_string(func)),
+                };
+                let args_str = if self.is_user_func(func) {
+                    self.convert_user_args(args)
+                } else {
+                    args.iter()
+                        .map(|a| self.convert_expr_to_string(a))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+                format!("{}({})", func_str, args_str)
+            }
+            RExpr::MethodCall {
+                target,
+                method,
+                args,
+                ..
+            } => {
+                let method_name = self.resolved.symbols.infos[method.0 as usize].name.clone();
+                let args_str = self.convert_user_args(args);
+                let place = self.convert_place(target);
+                format!("{}.{}({})", place, method_name, args_str)
+            }
+            RExpr::Field {
+                target, name, ty, ..
+            } => {
+                let place = self.convert_place(target);
+                if matches!(ty, Type::I64 | Type::F64 | Type::Bool) {
+                    format!("{}.{}", place, name)
+                } else {
+                    format!("{}.{}.clone()", place, name)
+                }
+            }
+            RExpr::Construct { name, fields, .. } => {
+                let fields_str = fields
+                    .iter()
+                    .map(|(field, value)| format!("{}: {}", field, self.convert_moved(value)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("{} {{ {} }}", name, fields_str)
+            }
+            RExpr::Variant {
+                enum_name,
+                variant,
+                args,
+                ..
+            } => {
+                if args.is_empty() {
+                    return format!("{}::{}", enum_name, variant);
+                }
                 let args_str = args
                     .iter()
-                    .map(|a| self.convert_expr_to_string(a))
+                    .map(|a| self.convert_moved(a))
                     .collect::<Vec<_>>()
                     .join(", ");
-                format!("{}({})", func_str, args_str)
+                format!("{}::{}({})", enum_name, variant, args_str)
             }
             RExpr::MacroCall {
                 macro_name, args, ..
             } => {
+                if let [arg] = args.as_slice()
+                    && debug_formatted(arg.ty())
+                {
+                    let arg_str = self.convert_expr_to_string(arg);
+                    return format!("{}(\"{{:?}}\", {})", macro_name, arg_str);
+                }
+                if let [arg] = args.as_slice()
+                    && let Type::Option(inner) = arg.ty()
+                {
+                    let arg_str = self.convert_expr_to_string(arg);
+                    return format!("{}({})", macro_name, option_text(&arg_str, inner, false));
+                }
                 let args_str = args
                     .iter()
                     .map(|a| self.convert_expr_to_string(a))
@@ -425,9 +1504,343 @@ impl<'a> CodeGenerator<'a> {
                     .join(", ");
                 format!("{}({})", macro_name, args_str)
             }
+            RExpr::Index { target, index, .. } => {
+                if let Type::Dict(..) = target.ty() {
+                    let target_str = self.convert_expr_to_string(target);
+                    let key_str = self.convert_key(index);
+                    return format!("__kayton_dict_get(&{}, {}).clone()", target_str, key_str);
+                }
+                // The checker only accepts literal tuple indexes
+                if let (Type::Tuple(_), RExpr::Int { value, .. }) = (target.ty(), index.as_ref()) {
+                    let target_str = self.convert_expr_to_string(target);
+                    return format!("{}.{}.clone()", target_str, value);
+                }
+                let index_str = self.convert_expr_to_string(index);
+                let target_str = self.convert_expr_to_string(target);
+                if *target.ty() == Type::Str {
+                    format!("__kayton_str_index(&{}, {})", target_str, index_str)
+                } else {
+                    format!(
+                        "__kayton_item(&{}, {}, \"list\").clone()",
+                        target_str, index_str
+                    )
+                }
+            }
+            RExpr::Slice {
+                target, start, end, ..
+            } => {
+                let target_str = self.convert_expr_to_string(target);
+                let start_str = self.convert_slice_bound(start.as_deref());
+                let end_str = self.convert_slice_bound(end.as_deref());
+                let helper = if *target.ty() == Type::Str {
+                    "__kayton_str_slice"
+                } else {
+                    "__kayton_list_slice"
+                };
+                format!("{}(&{}, {}, {})", helper, target_str, start_str, end_str)
+            }
             RExpr::InterpolatedString { parts, .. } => {
                 self.convert_interpolated_string_to_format(parts)
             }
+            RExpr::Dict { entries, .. } if entries.is_empty() => "HashMap::new()".to_string(),
+            RExpr::Dict { entries, .. } => {
+                let entries_str = entries
+                    .iter()
+                    .map(|(k, v)| format!("({}, {})", self.convert_owned(k), self.convert_owned(v)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("HashMap::from([{}])", entries_str)
+            }
+            RExpr::Tuple { items, .. } => self.convert_tuple(items, true),
+            RExpr::Range { start, end, .. } => {
+                let start_str = self.convert_expr_to_string(start);
+                let end_str = self.convert_expr_to_string(end);
+                format!("({}..{})", start_str, end_str)
+            }
+        }
+    }
+
+    /// `fn(x) => body` as a reference-counted closure, so that the function value can be
+    /// copied like any other. Captured variables are copied into the closure when it is
+    /// created; for a variable in a shared cell that copies the cell, so the closure
+    /// reads the variable's value when it is called.
+    fn convert_lambda(&mut self, params: &[SymbolId], body: &RExpr, ty: &Type) -> String {
+        let Type::Func(param_tys, ret) = ty else {
+            unreachable!("lambdas have function types");
+        };
+        // The closure keeps the cells, not the copies that the enclosing code changes
+        let rebound = std::mem::take(&mut self.rebound);
+        let mut captured = Vec::new();
+        captured_vars(body, params, &mut captured);
+        let mut copies = String::new();
+        for sym in captured {
+            let is_copy = matches!(
+                self.var_types.get(&sym),
+                Some(Type::I64 | Type::F64 | Type::Bool)
+            );
+            let is_builtin = self
+                .resolved
+                .symbols
+                .infos
+                .get(sym.0 as usize)
+                .is_some_and(|info| info.kind == SymKind::BuiltinFunc);
+            if self.is_func_item(sym) || is_builtin || (is_copy && !self.shared.contains(&sym)) {
+                continue;
+            }
+            let name = self.get_or_create_var_name(sym);
+            copies.push_str(&format!("let {0} = {0}.clone(); ", name));
+        }
+        let params_str = params
+            .iter()
+            .zip(param_tys)
+            .map(|(p, t)| {
+                let name = self.get_or_create_var_name(*p);
+                format!(
+                    "{}: {}",
+                    name,
+                    rust_param_type(t).unwrap_or_else(|| "()".to_string())
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let ret_str = rust_value_type(ret)
+            .map(|t| format!(" -> {}", t))
+            .unwrap_or_default();
+        let body_str = self.convert_owned(body);
+        self.rebound = rebound;
+        let fn_ty = rust_value_type(ty).unwrap_or_else(|| "_".to_string());
+        format!(
+            "{{ {}(std::rc::Rc::new(move |{}|{} {{ {} }}) as {}) }}",
+            copies, params_str, ret_str, body_str, fn_ty
+        )
+    }
+
+    /// `map(xs, f)`, `filter(xs, f)` and `sorted(xs)` or `sorted(xs, key)`. The list is
+    /// copied, so the original can still be used.
+    fn convert_list_func(&mut self, name: &str, args: &[RExpr]) -> String {
+        let list = self.convert_expr_to_string(&args[0]);
+        let is_str = matches!(args[0].ty(), Type::List(elem) if **elem == Type::Str);
+        // Argument for the function from a borrowed item; strings are passed as `&str`
+        let arg = |item: &str| {
+            if is_str {
+                format!("{}.as_str()", item)
+            } else {
+                format!("(*{}).clone()", item)
+            }
+        };
+        let Some(f) = args.get(1) else {
+            let elem = match args[0].ty() {
+                Type::List(elem) => (**elem).clone(),
+                _ => Type::Any,
+            };
+            return format!(
+                "{{ let mut __v = {}.clone(); __v.sort_by(|__a, __b| {}); __v }}",
+                list,
+                compare(&elem, "__a", "__b")
+            );
+        };
+        let key = match f.ty() {
+            Type::Func(_, ret) => (**ret).clone(),
+            _ => Type::Any,
+        };
+        let f = self.convert_expr_to_string(f);
+        match name {
+            "map" => format!(
+                "{{ let __f = {}; {}.iter().map(|__x| __f({})).collect::<Vec<_>>() }}",
+                f,
+                list,
+                arg("__x")
+            ),
+            "filter" => format!(
+                "{{ let __f = {}; {}.iter().filter(|__x| __f({})).cloned().collect::<Vec<_>>() }}",
+                f,
+                list,
+                arg("(*__x)")
+            ),
+            _ => format!(
+                "{{ let __f = {}; let mut __v = {}.clone(); __v.sort_by(|__a, __b| {}); __v }}",
+                f,
+                list,
+                compare(
+                    &key,
+                    &format!("__f({})", arg("__a")),
+                    &format!("__f({})", arg("__b"))
+                )
+            ),
+        }
+    }
+
+    /// `len()` and the string builtins. Strings searched for or inserted are borrowed as
+    /// `&str`, whether they are `String` or `&str` values.
+    fn convert_str_func(&mut self, name: &str, args: &[RExpr]) -> String {
+        let target = self.convert_expr_to_string(&args[0]);
+        if name == "join" {
+            // `sep.join(items)`
+            let items = self.convert_expr_to_string(&args[1]);
+            return format!("{}.join(&*{})", items, target);
+        }
+        let rest: Vec<String> = args[1..]
+            .iter()
+            .map(|a| format!("&*{}", self.convert_expr_to_string(a)))
+            .collect();
+        match name {
+            // Strings count characters, like they are indexed
+            "len" if *args[0].ty() == Type::Str => {
+                format!("({}.chars().count() as i64)", target)
+            }
+            "len" => format!("({}.len() as i64)", target),
+            "split" if rest.is_empty() => format!(
+                "{}.split_whitespace().map(String::from).collect::<Vec<_>>()",
+                target
+            ),
+            "split" => format!(
+                "{}.split({}).map(String::from).collect::<Vec<_>>()",
+                target, rest[0]
+            ),
+            "strip" => format!("{}.trim().to_string()", target),
+            "upper" => format!("{}.to_uppercase()", target),
+            "lower" => format!("{}.to_lowercase()", target),
+            "replace" => format!("{}.replace({}, {})", target, rest[0], rest[1]),
+            "startswith" => format!("{}.starts_with({})", target, rest[0]),
+            _ => format!(
+                "{{ let __s = &{}; match __s.find({}) {{ Some(__i) => __s[..__i].chars().count() as i64, None => -1 }} }}",
+                target, rest[0]
+            ),
+        }
+    }
+
+    /// The Rust iterator a `for` loop walks. The items are copied up front, so the loop
+    /// body may change the variable being iterated over; a plugin iterator is walked as
+    /// it is.
+    fn convert_iterable(&mut self, iter: &RExpr) -> String {
+        let iter_str = self.convert_expr_to_string(iter);
+        match iter.ty() {
+            Type::Str => format!("{}.chars().map(String::from).collect::<Vec<_>>()", iter_str),
+            Type::Dict(..) => format!("{}.keys().cloned().collect::<Vec<_>>()", iter_str),
+            Type::List(_) | Type::Range if matches!(iter, RExpr::Name { .. }) => {
+                format!("{}.clone()", iter_str)
+            }
+            // A plugin iterator is consumed by the loop, as in Python
+            Type::Iter(_) if matches!(iter, RExpr::Name { .. }) => format!("&mut {}", iter_str),
+            _ => iter_str,
+        }
+    }
+
+    /// `(a, b)`, or `(a,)` for a single item. `owned` converts string items to `String`
+    /// as for a stored value.
+    fn convert_tuple(&mut self, items: &[RExpr], owned: bool) -> String {
+        let items_str = items
+            .iter()
+            .map(|item| {
+                if owned {
+                    self.convert_owned(item)
+                } else {
+                    self.convert_expr_to_string(item)
+                }
+            })
+            .collect::<Vec<_>>();
+        if items_str.len() == 1 {
+            format!("({},)", items_str[0])
+        } else {
+            format!("({})", items_str.join(", "))
+        }
+    }
+
+    /// Mutable reference to a list element or dict value, checked by the runtime helpers.
+    /// Nested targets such as `grid[i][j]` borrow through the outer element, reborrowed so
+    /// that rustc can coerce it to the slice the outer helper takes.
+    fn convert_item_place(&mut self, target: &RExpr, index: &RExpr, what: &str) -> String {
+        let target_str = match target {
+            RExpr::Index {
+                target: inner,
+                index: inner_index,
+                ..
+            } if *inner.ty() != Type::Str => format!(
+                "&mut *{}",
+                self.convert_item_place(inner, inner_index, "list")
+            ),
+            _ => format!("&mut {}", self.convert_place(target)),
+        };
+        if let Type::Dict(..) = target.ty() {
+            let key_str = self.convert_key(index);
+            return format!("__kayton_dict_get_mut({}, {})", target_str, key_str);
+        }
+        let index_str = self.convert_expr_to_string(index);
+        format!(
+            "__kayton_item_mut({}, {}, \"{}\")",
+            target_str, index_str, what
+        )
+    }
+
+    /// Arguments of a user function or method, which takes strings as `&str` and its own
+    /// copy of lists, dicts and structs.
+    fn convert_user_args(&mut self, args: &[RExpr]) -> String {
+        args.iter()
+            .map(|a| match a.ty() {
+                Type::Str => format!("&{}", self.convert_expr_to_string(a)),
+                Type::List(_)
+                | Type::Dict(..)
+                | Type::Tuple(_)
+                | Type::Range
+                | Type::Struct(_)
+                | Type::Enum(_)
+                | Type::Option(_) => format!("{}.clone()", self.convert_place(a)),
+                _ => self.convert_expr_to_string(a),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// A variable or field as a place that can be borrowed or assigned, such as the
+    /// receiver of a method call; other expressions are converted as values.
+    fn convert_place(&mut self, expr: &RExpr) -> String {
+        match expr {
+            RExpr::Name { sym, .. } if self.shared_cell(*sym).is_some() => {
+                self.convert_expr_to_string(expr)
+            }
+            RExpr::Name { sym, .. } => self.get_or_create_var_name(*sym),
+            RExpr::Field { target, name, .. } => {
+                format!("{}.{}", self.convert_place(target), name)
+            }
+            RExpr::Unwrap { expr, .. } => format!("{}.as_mut().unwrap()", self.convert_place(expr)),
+            _ => self.convert_expr_to_string(expr),
+        }
+    }
+
+    /// Borrow a dict key for a lookup; `String` keys are looked up by `&str`.
+    fn convert_key(&mut self, key: &RExpr) -> String {
+        let key_str = self.convert_expr_to_string(key);
+        if *key.ty() == Type::Str {
+            format!("&*{}", key_str)
+        } else {
+            format!("&{}", key_str)
+        }
+    }
+
+    /// `item in container`: dicts test their keys, lists compare elements and strings
+    /// search for a substring.
+    fn convert_membership(&mut self, item: &RExpr, container: &RExpr) -> String {
+        let container_str = self.convert_expr_to_string(container);
+        match container.ty() {
+            Type::Dict(..) => {
+                let key_str = self.convert_key(item);
+                format!("{}.contains_key({})", container_str, key_str)
+            }
+            Type::Str => {
+                let item_str = self.convert_expr_to_string(item);
+                format!("{}.contains(&*{})", container_str, item_str)
+            }
+            _ => {
+                let item_str = self.convert_owned(item);
+                format!("{}.contains(&{})", container_str, item_str)
+            }
+        }
+    }
+
+    fn convert_slice_bound(&mut self, bound: Option<&RExpr>) -> String {
+        match bound {
+            Some(expr) => format!("Some({})", self.convert_expr_to_string(expr)),
+            None => "None".to_string(),
         }
     }
 
@@ -438,16 +1851,47 @@ impl<'a> CodeGenerator<'a> {
         for part in parts {
             match part {
                 RStringPart::Text { value, .. } => {
-                    // Escape quotes and braces in the text
-                    let escaped = value
-                        .replace("\"", "\\\"")
+                    // Escape the text like a string literal, and braces for `format!`
+                    let quoted = format!("{:?}", value);
+                    let escaped = quoted[1..quoted.len() - 1]
                         .replace("{", "{{")
                         .replace("}", "}}");
                     format_string.push_str(&escaped);
                 }
-                RStringPart::Expr { expr, .. } => {
-                    format_string.push_str("{}");
-                    args.push(self.convert_expr_to_string(expr));
+                RStringPart::Expr { expr, spec, .. } => {
+                    let expr_str = self.convert_expr_to_string(expr);
+                    let ty = expr.ty();
+                    // Values shown with `{:?}` are formatted to a string first when the
+                    // options would not apply to them, as with widths of lists
+                    let (arg, debug) = match ty {
+                        Type::Option(inner) => (option_text(&expr_str, inner, spec.repr), false),
+                        Type::Str if spec.repr => {
+                            (format!("__kayton_repr_str(&{})", expr_str), false)
+                        }
+                        _ if spec.repr || (debug_formatted(ty) && *ty != Type::F64) => {
+                            if spec.has_options() {
+                                (format!("format!(\"{{:?}}\", {})", expr_str), false)
+                            } else {
+                                (expr_str, true)
+                            }
+                        }
+                        Type::I64 if matches!(spec.kind, Some('f' | 'F')) => {
+                            (format!("({} as f64)", expr_str), false)
+                        }
+                        // Floats keep their `.0` unless a precision is given
+                        Type::F64 => (
+                            expr_str,
+                            spec.precision.is_none() && !matches!(spec.kind, Some('f' | 'F')),
+                        ),
+                        _ => (expr_str, false),
+                    };
+                    let quoted = format!("{:?}", spec.rust_options());
+                    let options = &quoted[1..quoted.len() - 1];
+                    format_string.push_str(&match (options.is_empty(), debug) {
+                        (true, false) => "{}".to_string(),
+                        (_, debug) => format!("{{:{}{}}}", options, if debug { "?" } else { "" }),
+                    });
+                    args.push(arg);
                 }
             }
         }
@@ -460,8 +1904,225 @@ impl<'a> CodeGenerator<'a> {
         }
     }
 
+    /// Convert a value that is stored into a list or dict, which owns its strings.
+    fn convert_owned(&mut self, expr: &RExpr) -> String {
+        let expr_str = self.convert_expr_to_string(expr);
+        if *expr.ty() == Type::Str {
+            format!("{}.to_string()", expr_str)
+        } else {
+            expr_str
+        }
+    }
+
+    /// Convert a value that is moved into a struct field, an enum variant or a `match`.
+    /// Variables holding lists, dicts, structs or enums are copied, so that they can
+    /// still be used afterwards.
+    fn convert_moved(&mut self, expr: &RExpr) -> String {
+        match (expr, expr.ty()) {
+            (
+                RExpr::Name { .. },
+                Type::List(_)
+                | Type::Dict(..)
+                | Type::Tuple(_)
+                | Type::Range
+                | Type::Struct(_)
+                | Type::Enum(_)
+                | Type::Option(_),
+            ) => format!("{}.clone()", self.convert_place(expr)),
+            _ => self.convert_owned(expr),
+        }
+    }
+
+    /// Convert an arithmetic operand, casting an integer to `f64` when the operation is
+    /// carried out in floating point.
+    fn convert_operand(&mut self, expr: &RExpr, float_op: bool) -> String {
+        let expr_str = self.convert_expr_to_string(expr);
+        if float_op && *expr.ty() != Type::F64 {
+            format!("({} as f64)", expr_str)
+        } else {
+            expr_str
+        }
+    }
+
+    /// Whether `func` is a user function, or a function value, which take their
+    /// arguments like user functions do.
+    fn is_user_func(&self, func: &RExpr) -> bool {
+        match func {
+            RExpr::Name { sym, .. } if self.is_func_item(*sym) => true,
+            _ => matches!(func.ty(), Type::Func(..)),
+        }
+    }
+
+    /// The cell type holding a variable in `shared`: a `Cell` for numbers and bools, a
+    /// `RefCell` for other values. `None` while the variable is rebound to a local copy.
+    fn shared_cell(&self, sym: SymbolId) -> Option<&'static str> {
+        if !self.shared.contains(&sym) || self.rebound.contains_key(&sym) {
+            return None;
+        }
+        match self.var_types.get(&sym) {
+            Some(Type::I64 | Type::F64 | Type::Bool) => Some("Cell"),
+            _ => Some("RefCell"),
+        }
+    }
+
+    /// The value of a variable in `shared`. A `RefCell` is copied as a whole, so that no
+    /// borrow of it outlives the read.
+    fn shared_value(&mut self, sym: SymbolId) -> String {
+        let name = self.get_or_create_var_name(sym);
+        if self.shared_cell(sym) == Some("Cell") {
+            format!("{}.get()", name)
+        } else {
+            format!("std::cell::RefCell::clone(&{}).into_inner()", name)
+        }
+    }
+
+    /// `let name = <shared cell holding value>;` for a variable in `shared`. Collections
+    /// spell out their type, so that an empty `[]` or `{}` has item types.
+    fn shared_decl(&self, sym: SymbolId, name: &str, value: &str) -> String {
+        let cell = self.shared_cell(sym).unwrap_or("RefCell");
+        match self.var_types.get(&sym).and_then(rust_collection_type) {
+            Some(ty) => format!(
+                "let {}: std::rc::Rc<std::cell::{}<{}>> = std::rc::Rc::new(std::cell::{}::new({}));",
+                name, cell, ty, cell, value
+            ),
+            None => format!(
+                "let {} = std::rc::Rc::new(std::cell::{}::new({}));",
+                name, cell, value
+            ),
+        }
+    }
+
+    /// Variables that a lambda captures and that are assigned again or changed in place,
+    /// so that the lambda sees their later values as Python closures do. Parameters and
+    /// loop variables keep their plain declarations, as does a variable declared ahead
+    /// of its first assignment when its type has no value to start with.
+    fn shared_vars(&self, stmts: &[RStmt]) -> std::collections::HashSet<SymbolId> {
+        let mut captured = Vec::new();
+        let mut bound = std::collections::HashSet::new();
+        lambda_captures(stmts, &mut captured, &mut bound);
+        let mut changed = Vec::new();
+        self.changed_in_place(stmts, &mut changed);
+        let mut declared_ahead = Vec::new();
+        first_assigned_in_blocks(
+            stmts,
+            false,
+            &mut self.assigned_vars.clone(),
+            &mut declared_ahead,
+        );
+        for body in func_bodies(stmts) {
+            first_assigned_in_blocks(body, false, &mut Default::default(), &mut declared_ahead);
+        }
+        captured
+            .into_iter()
+            .filter(|sym| {
+                (self.reassigned.contains(sym) || changed.contains(sym)) && !bound.contains(sym)
+            })
+            .filter(|sym| {
+                self.resolved
+                    .symbols
+                    .infos
+                    .get(sym.0 as usize)
+                    .is_some_and(|info| matches!(info.kind, SymKind::LocalVar | SymKind::GlobalVar))
+            })
+            .filter(|sym| match self.var_types.get(sym) {
+                Some(ty) if shared_init(ty).is_some() => true,
+                Some(Type::Iter(_)) | None => false,
+                Some(ty) => rust_value_type(ty).is_some() && !declared_ahead.contains(sym),
+            })
+            .collect()
+    }
+
+    /// Variables that `stmts` change in place, in lambdas too: the lists given to
+    /// `append`, the receivers of methods and the targets of item and field assignments.
+    fn changed_in_place(&self, stmts: &[RStmt], out: &mut Vec<SymbolId>) {
+        for stmt in stmts {
+            if let RStmt::FieldAssign { target, .. } | RStmt::IndexAssign { target, .. } = stmt {
+                out.extend(place_root(target));
+            }
+            let (exprs, blocks) = stmt_parts(stmt);
+            exprs
+                .into_iter()
+                .for_each(|expr| self.written_vars(expr, true, out));
+            blocks
+                .into_iter()
+                .for_each(|block| self.changed_in_place(block, out));
+        }
+    }
+
+    /// Variables that `expr` changes in place through `append` or a method call.
+    /// `in_lambdas` counts the bodies of lambdas, which run when the lambda is called.
+    fn written_vars(&self, expr: &RExpr, in_lambdas: bool, out: &mut Vec<SymbolId>) {
+        match expr {
+            RExpr::Lambda { .. } if !in_lambdas => return,
+            RExpr::Call { func, args, .. }
+                if matches!(func.as_ref(), RExpr::Name { sym, .. }
+                    if self.resolved.symbols.infos[sym.0 as usize].name == "append") =>
+            {
+                out.extend(args.first().and_then(place_root));
+            }
+            RExpr::MethodCall { target, .. } => out.extend(place_root(target)),
+            _ => {}
+        }
+        for sub in sub_exprs(expr) {
+            self.written_vars(sub, in_lambdas, out);
+        }
+    }
+
+    /// Variables in `RefCell`s that `expr` changes in place, outside of lambdas.
+    fn written_cells(&self, expr: &RExpr) -> Vec<SymbolId> {
+        if self.shared.is_empty() {
+            return Vec::new();
+        }
+        let mut written = Vec::new();
+        self.written_vars(expr, false, &mut written);
+        written.retain(|sym| self.shared_cell(*sym) == Some("RefCell"));
+        written.sort_by_key(|sym| sym.0);
+        written.dedup();
+        written
+    }
+
+    /// Generate code that changes variables in `RefCell`s on local copies, written back to
+    /// the cells when it has run, so that no borrow of a cell is held while the code runs
+    /// a lambda reading it. The code is a statement if `stmt`, otherwise an expression.
+    fn with_written_back(
+        &mut self,
+        syms: &[SymbolId],
+        stmt: bool,
+        convert: impl FnOnce(&mut Self) -> String,
+    ) -> String {
+        let mut loads = String::new();
+        let mut stores = String::new();
+        for sym in syms {
+            let value = self.shared_value(*sym);
+            let name = self.get_or_create_var_name(*sym);
+            let local = format!("__{}", name);
+            loads.push_str(&format!("let mut {} = {}; ", local, value));
+            stores.push_str(&format!("*{}.borrow_mut() = {}; ", name, local));
+            self.rebound.insert(*sym, local);
+        }
+        let code = convert(self);
+        for sym in syms {
+            self.rebound.remove(sym);
+        }
+        if stmt {
+            format!("{{ {}{} {}}}", loads, code, stores)
+        } else {
+            format!("{{ {}let __r = {}; {}__r }}", loads, code, stores)
+        }
+    }
+
+    fn is_func_item(&self, sym: SymbolId) -> bool {
+        self.resolved
+            .symbols
+            .infos
+            .get(sym.0 as usize)
+            .is_some_and(|info| info.kind == SymKind::Func)
+    }
+
     fn get_or_create_var_name(&mut self, sym: SymbolId) -> String {
-        if let Some(name) = self.var_names.get(&sym) {
+        if let Some(local) = self.rebound.get(&sym) {
+            local.clone()
+        } else if let Some(name) = self.var_names.get(&sym) {
             name.clone()
         } else {
             // Get the original name from the symbol table
@@ -495,17 +2156,540 @@ impl<'a> CodeGenerator<'a> {
     }
 }
 
+/// The Rust type a value of `kind` crosses the plugin boundary as; `None` for kinds
+/// without a fixed layout yet, whose functions are not bound.
+fn plugin_abi_type(kind: &TypeKind) -> Option<String> {
+    let ty = match kind {
+        TypeKind::Unit => "()",
+        TypeKind::Bool => "bool",
+        TypeKind::I64 => "i64",
+        TypeKind::U64 => "u64",
+        TypeKind::F64 => "f64",
+        TypeKind::Iter(item) => {
+            return Some(format!(
+                "Box<dyn Iterator<Item = {}>>",
+                plugin_abi_type(item)?
+            ));
+        }
+        _ => return None,
+    };
+    Some(ty.to_string())
+}
+
+/// A function item binding the plugin function `f` to the Rust-ABI pointer its plugin
+/// registered. A `Result` return arrives as a `KaytonResult`, whose error `__kayton_check`
+/// raises.
+fn plugin_binding(f: &FunctionEntry) -> Option<String> {
+    let params = f
+        .sig
+        .params
+        .iter()
+        .map(plugin_abi_type)
+        .collect::<Option<Vec<_>>>()?;
+    let args: Vec<String> = (0..params.len()).map(|i| format!("a{}", i)).collect();
+    let call = format!("f({})", args.join(", "));
+    let (ret, abi_ret, call) = match &f.sig.ret {
+        TypeKind::Result(ok) => {
+            let ok = plugin_abi_type(ok)?;
+            (
+                ok.clone(),
+                format!("KaytonResult<{}>", ok),
+                format!("__kayton_check({})", call),
+            )
+        }
+        ret => {
+            let ret = plugin_abi_type(ret)?;
+            (ret.clone(), ret, call)
+        }
+    };
+    let decls: Vec<String> = args
+        .iter()
+        .zip(&params)
+        .map(|(a, ty)| format!("{}: {}", a, ty))
+        .collect();
+    let name = &f.stable_name;
+    Some(format!(
+        "fn {}({}) -> {} {{ let f: fn({}) -> {} = unsafe {{ ::std::mem::transmute(__kayton_plugin_fn(\"{}\")) }}; {} }}",
+        name,
+        decls.join(", "),
+        ret,
+        params.join(", "),
+        abi_ret,
+        name.escape_default(),
+        call
+    ))
+}
+
+/// Rust type of a user function parameter; `None` if it could not be inferred.
+/// Strings are borrowed; lists are passed as copies.
+fn rust_param_type(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Str => Some("&str".to_string()),
+        _ => rust_value_type(ty),
+    }
+}
+
+/// Owned Rust type of a value, as returned from a function or stored in a list;
+/// `None` for unit and unknown types.
+fn rust_value_type(ty: &Type) -> Option<String> {
+    match ty {
+        Type::I64 => Some("i64".to_string()),
+        Type::F64 => Some("f64".to_string()),
+        Type::Bool => Some("bool".to_string()),
+        Type::Str => Some("String".to_string()),
+        Type::List(elem) => rust_value_type(elem).map(|t| format!("Vec<{}>", t)),
+        Type::Dict(key, value) => Some(format!(
+            "HashMap<{}, {}>",
+            rust_value_type(key)?,
+            rust_value_type(value)?
+        )),
+        Type::Tuple(items) => {
+            let items: Option<Vec<String>> = items.iter().map(rust_value_type).collect();
+            let items = items?;
+            if items.len() == 1 {
+                Some(format!("({},)", items[0]))
+            } else {
+                Some(format!("({})", items.join(", ")))
+            }
+        }
+        Type::Range => Some("std::ops::Range<i64>".to_string()),
+        Type::Iter(item) => {
+            rust_value_type(item).map(|t| format!("Box<dyn Iterator<Item = {}>>", t))
+        }
+        Type::Struct(name) | Type::Enum(name) => Some(name.clone()),
+        Type::Option(inner) => rust_value_type(inner).map(|t| format!("Option<{}>", t)),
+        Type::Func(params, ret) => {
+            let params: Option<Vec<String>> = params.iter().map(rust_param_type).collect();
+            let ret = match **ret {
+                Type::Unit => String::new(),
+                _ => format!(" -> {}", rust_value_type(ret)?),
+            };
+            Some(format!(
+                "std::rc::Rc<dyn Fn({}){}>",
+                params?.join(", "),
+                ret
+            ))
+        }
+        Type::Unit | Type::Any => None,
+    }
+}
+
+/// Rust expression ordering the values `a` and `b` of type `ty` for `sorted`. Floats use
+/// their total order, so NaN values sort to the ends instead of panicking.
+fn compare(ty: &Type, a: &str, b: &str) -> String {
+    match ty {
+        Type::F64 => format!("{}.total_cmp(&{})", a, b),
+        Type::I64 | Type::Bool | Type::Str => format!("{}.cmp(&{})", a, b),
+        _ => format!(
+            "{}.partial_cmp(&{}).unwrap_or(std::cmp::Ordering::Equal)",
+            a, b
+        ),
+    }
+}
+
+/// Variables that `stmts` assign more than once, counting nested blocks and functions,
+/// or assign again after an earlier input did.
+fn reassigned_vars(
+    stmts: &[RStmt],
+    assigned: &std::collections::HashSet<SymbolId>,
+) -> std::collections::HashSet<SymbolId> {
+    fn count(stmts: &[RStmt], counts: &mut HashMap<SymbolId, usize>) {
+        for stmt in stmts {
+            match stmt {
+                RStmt::Assign { sym, .. } => *counts.entry(*sym).or_default() += 1,
+                RStmt::ForRange { body, .. }
+                | RStmt::ForEach { body, .. }
+                | RStmt::While { body, .. }
+                | RStmt::FuncDef { body, .. }
+                | RStmt::StructDef { methods: body, .. } => count(body, counts),
+                RStmt::If {
+                    then_branch,
+                    else_branch,
+                    ..
+                } => {
+                    count(then_branch, counts);
+                    count(else_branch, counts);
+                }
+                RStmt::Match { arms, .. } => {
+                    for arm in arms {
+                        count(&arm.body, counts);
+                    }
+                }
+                RStmt::Try {
+                    body,
+                    handlers,
+                    finally,
+                    ..
+                } => {
+                    count(body, counts);
+                    for handler in handlers {
+                        count(&handler.body, counts);
+                    }
+                    count(finally, counts);
+                }
+                _ => {}
+            }
+        }
+    }
+    let mut counts: HashMap<SymbolId, usize> = assigned.iter().map(|sym| (*sym, 1)).collect();
+    count(stmts, &mut counts);
+    counts
+        .into_iter()
+        .filter(|(_, n)| *n > 1)
+        .map(|(sym, _)| sym)
+        .collect()
+}
+
+/// Variables whose first assignment, after those in `seen`, is inside a loop, branch
+/// or `try` block of `stmts`; `nested` when `stmts` is such a block itself.
+fn first_assigned_in_blocks(
+    stmts: &[RStmt],
+    nested: bool,
+    seen: &mut std::collections::HashSet<SymbolId>,
+    out: &mut Vec<SymbolId>,
+) {
+    for stmt in stmts {
+        match stmt {
+            // `seen` records every assignment; only nested first ones are hoisted
+            RStmt::Assign { sym, .. } if seen.insert(*sym) && nested => out.push(*sym),
+            RStmt::TupleAssign { syms, .. } => {
+                for sym in syms {
+                    if seen.insert(*sym) && nested {
+                        out.push(*sym);
+                    }
+                }
+            }
+            RStmt::ForRange { body, .. }
+            | RStmt::ForEach { body, .. }
+            | RStmt::While { body, .. } => first_assigned_in_blocks(body, true, seen, out),
+            RStmt::If {
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                first_assigned_in_blocks(then_branch, true, seen, out);
+                first_assigned_in_blocks(else_branch, true, seen, out);
+            }
+            RStmt::Match { arms, .. } => {
+                for arm in arms {
+                    first_assigned_in_blocks(&arm.body, true, seen, out);
+                }
+            }
+            // The body and handlers run in closures
+            RStmt::Try {
+                body,
+                handlers,
+                finally,
+                ..
+            } => {
+                first_assigned_in_blocks(body, true, seen, out);
+                for handler in handlers {
+                    first_assigned_in_blocks(&handler.body, true, seen, out);
+                }
+                first_assigned_in_blocks(finally, nested, seen, out);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Variables that `expr` reads other than `bound`, the parameters of the lambda it is
+/// the body of; a lambda captures them.
+fn captured_vars(expr: &RExpr, bound: &[SymbolId], out: &mut Vec<SymbolId>) {
+    match expr {
+        RExpr::Name { sym, .. } => {
+            if !bound.contains(sym) && !out.contains(sym) {
+                out.push(*sym);
+            }
+        }
+        RExpr::Lambda { params, body, .. } => {
+            let bound: Vec<SymbolId> = bound.iter().chain(params).copied().collect();
+            captured_vars(body, &bound, out);
+        }
+        _ => sub_exprs(expr)
+            .into_iter()
+            .for_each(|e| captured_vars(e, bound, out)),
+    }
+}
+
+/// Variables that the lambdas in `stmts` capture, counting nested blocks and functions.
+pub fn captured_by_lambdas(stmts: &[RStmt]) -> Vec<SymbolId> {
+    let mut captured = Vec::new();
+    lambda_captures(stmts, &mut captured, &mut std::collections::HashSet::new());
+    captured
+}
+
+/// Variables that the lambdas in `stmts` capture, counting nested blocks and functions,
+/// and in `bound` the variables that are bound other than by assignment: parameters,
+/// loop variables, unpacked tuple items, exceptions and match bindings.
+fn lambda_captures(
+    stmts: &[RStmt],
+    out: &mut Vec<SymbolId>,
+    bound: &mut std::collections::HashSet<SymbolId>,
+) {
+    fn visit(expr: &RExpr, out: &mut Vec<SymbolId>) {
+        match expr {
+            RExpr::Lambda { params, body, .. } => captured_vars(body, params, out),
+            _ => sub_exprs(expr).into_iter().for_each(|e| visit(e, out)),
+        }
+    }
+    fn bind_pattern(pattern: &RPattern, bound: &mut std::collections::HashSet<SymbolId>) {
+        match pattern {
+            RPattern::Bind { sym, .. } => {
+                bound.insert(*sym);
+            }
+            RPattern::Variant { args: items, .. } | RPattern::Tuple { items, .. } => {
+                items.iter().for_each(|p| bind_pattern(p, bound))
+            }
+            _ => {}
+        }
+    }
+    for stmt in stmts {
+        match stmt {
+            RStmt::TupleAssign { syms, .. } | RStmt::ForEach { syms, .. } => bound.extend(syms),
+            RStmt::ForRange { sym, .. } => {
+                bound.insert(*sym);
+            }
+            RStmt::FuncDef { params, .. } => bound.extend(params),
+            RStmt::Match { arms, .. } => arms
+                .iter()
+                .for_each(|arm| bind_pattern(&arm.pattern, bound)),
+            RStmt::Try { handlers, .. } => bound.extend(handlers.iter().filter_map(|h| h.sym)),
+            _ => {}
+        }
+        let (exprs, blocks) = stmt_parts(stmt);
+        exprs.into_iter().for_each(|e| visit(e, out));
+        blocks
+            .into_iter()
+            .for_each(|block| lambda_captures(block, out, bound));
+    }
+}
+
+/// The expressions and blocks directly inside `stmt`.
+fn stmt_parts(stmt: &RStmt) -> (Vec<&RExpr>, Vec<&[RStmt]>) {
+    match stmt {
+        RStmt::Assign { expr, .. }
+        | RStmt::ExprStmt { expr, .. }
+        | RStmt::TupleAssign { expr, .. } => (vec![expr], vec![]),
+        RStmt::IndexAssign {
+            target,
+            index,
+            expr,
+            ..
+        } => (vec![target, index, expr], vec![]),
+        RStmt::FieldAssign { target, expr, .. } => (vec![target, expr], vec![]),
+        RStmt::ForRange {
+            start, end, body, ..
+        } => (vec![start, end], vec![body]),
+        RStmt::ForEach { iter, body, .. } => (vec![iter], vec![body]),
+        RStmt::While { cond, body, .. } => (vec![cond], vec![body]),
+        RStmt::If {
+            cond,
+            then_branch,
+            else_branch,
+            ..
+        } => (vec![cond], vec![then_branch, else_branch]),
+        RStmt::FuncDef { body, .. } => (vec![], vec![body]),
+        RStmt::StructDef { methods, .. } => (vec![], vec![methods]),
+        RStmt::Return { expr, .. } => (expr.iter().collect(), vec![]),
+        RStmt::Raise { message, .. } => (message.iter().collect(), vec![]),
+        RStmt::Match { subject, arms, .. } => (
+            vec![subject],
+            arms.iter().map(|arm| arm.body.as_slice()).collect(),
+        ),
+        RStmt::Try {
+            body,
+            handlers,
+            finally,
+            ..
+        } => {
+            let mut blocks = vec![body.as_slice(), finally.as_slice()];
+            blocks.extend(handlers.iter().map(|h| h.body.as_slice()));
+            (vec![], blocks)
+        }
+        _ => (vec![], vec![]),
+    }
+}
+
+/// The bodies of the functions and methods defined in `stmts`, nested ones included.
+fn func_bodies(stmts: &[RStmt]) -> Vec<&[RStmt]> {
+    let mut bodies = Vec::new();
+    for stmt in stmts {
+        match stmt {
+            RStmt::FuncDef { body, .. } => {
+                bodies.push(body.as_slice());
+                bodies.extend(func_bodies(body));
+            }
+            RStmt::StructDef { methods, .. } => bodies.extend(func_bodies(methods)),
+            _ => {}
+        }
+    }
+    bodies
+}
+
+/// The variable whose value a place such as `p.pos.x` or `grid[i][j]` is part of.
+fn place_root(expr: &RExpr) -> Option<SymbolId> {
+    match expr {
+        RExpr::Name { sym, .. } => Some(*sym),
+        RExpr::Field { target, .. } | RExpr::Index { target, .. } => place_root(target),
+        RExpr::Unwrap { expr, .. } => place_root(expr),
+        _ => None,
+    }
+}
+
+/// The value that a variable in a shared cell starts with when it is declared ahead of
+/// its first assignment; `None` for types without one, such as structs.
+fn shared_init(ty: &Type) -> Option<&'static str> {
+    match ty {
+        Type::I64 => Some("0i64"),
+        Type::F64 => Some("0.0"),
+        Type::Bool => Some("false"),
+        Type::Str => Some("String::new()"),
+        Type::List(_) => Some("Vec::new()"),
+        Type::Dict(..) => Some("HashMap::new()"),
+        Type::Option(_) => Some("None"),
+        _ => None,
+    }
+}
+
+/// The expressions directly inside `expr`; a lambda's body is inside it.
+fn sub_exprs(expr: &RExpr) -> Vec<&RExpr> {
+    match expr {
+        RExpr::Name { .. }
+        | RExpr::Int { .. }
+        | RExpr::Float { .. }
+        | RExpr::Str { .. }
+        | RExpr::Bool { .. }
+        | RExpr::None { .. } => vec![],
+        RExpr::Some { expr, .. } | RExpr::Unwrap { expr, .. } | RExpr::Unary { expr, .. } => {
+            vec![expr]
+        }
+        RExpr::Binary { left, right, .. } => vec![left, right],
+        RExpr::Call { func, args, .. } => std::iter::once(&**func).chain(args).collect(),
+        RExpr::MacroCall { args, .. }
+        | RExpr::Tuple { items: args, .. }
+        | RExpr::Variant { args, .. } => args.iter().collect(),
+        RExpr::Index { target, index, .. } => vec![target, index],
+        RExpr::Slice {
+            target, start, end, ..
+        } => std::iter::once(&**target)
+            .chain(start.as_deref())
+            .chain(end.as_deref())
+            .collect(),
+        RExpr::InterpolatedString { parts, .. } => parts
+            .iter()
+            .filter_map(|part| match part {
+                RStringPart::Expr { expr, .. } => Some(expr),
+                RStringPart::Text { .. } => None,
+            })
+            .collect(),
+        RExpr::Dict { entries, .. } => entries.iter().flat_map(|(k, v)| [k, v]).collect(),
+        RExpr::Range { start, end, .. } => vec![start, end],
+        RExpr::Field { target, .. } => vec![target],
+        RExpr::MethodCall { target, args, .. } => std::iter::once(&**target).chain(args).collect(),
+        RExpr::Construct { fields, .. } => fields.iter().map(|(_, v)| v).collect(),
+        RExpr::Lambda { body, .. } => vec![body],
+    }
+}
+
+/// Rust type of a list, dict or optional variable. Values that never get an item, such
+/// as a lone `[]`, `{}` or `None`, still need concrete item types, so unknown items
+/// become `i64`.
+fn rust_collection_type(ty: &Type) -> Option<String> {
+    fn concrete(ty: &Type) -> Type {
+        match ty {
+            Type::Any => Type::I64,
+            Type::List(elem) => Type::List(Box::new(concrete(elem))),
+            Type::Dict(key, value) => {
+                Type::Dict(Box::new(concrete(key)), Box::new(concrete(value)))
+            }
+            Type::Tuple(items) => Type::Tuple(items.iter().map(concrete).collect()),
+            Type::Option(inner) => Type::Option(Box::new(concrete(inner))),
+            other => other.clone(),
+        }
+    }
+    match ty {
+        Type::List(_) | Type::Dict(..) | Type::Option(_) => rust_value_type(&concrete(ty)),
+        _ => None,
+    }
+}
+
+/// Name of the runtime hook that reports a list with this element type to the host,
+/// for the element types the VM stores as a `KVec`.
+pub fn vec_reporter(elem: &Type) -> Option<&'static str> {
+    match elem {
+        Type::I64 => Some("report_vec_i64"),
+        Type::F64 => Some("report_vec_f64"),
+        Type::Bool => Some("report_vec_bool"),
+        _ => None,
+    }
+}
+
+/// Whether a value is printed with Debug formatting: floats so that `2.0` keeps its
+/// fractional part, and collections and ranges so that they show their items or bounds.
+fn debug_formatted(ty: &Type) -> bool {
+    matches!(
+        ty,
+        Type::F64
+            | Type::List(_)
+            | Type::Dict(..)
+            | Type::Tuple(_)
+            | Type::Range
+            | Type::Struct(_)
+            | Type::Enum(_)
+    )
+}
+
+/// Whether a dict with these key and value types can be reported to the host, which
+/// stores maps of `i64`, `f64`, `bool` and `str` items (keys cannot be floats).
+pub fn map_reportable(key: &Type, value: &Type) -> bool {
+    matches!(key, Type::I64 | Type::Bool | Type::Str)
+        && matches!(value, Type::I64 | Type::F64 | Type::Bool | Type::Str)
+}
+
+/// Most items of a tuple, or fields of a struct, the generated code can report; the
+/// header implements `ReportTuple` for tuples up to this length.
+pub const MAX_REPORTED_ITEMS: usize = 12;
+
+/// Whether a tuple with these item types can be reported to the host, which stores
+/// tuples of `i64`, `f64`, `bool` and `str` items.
+pub fn tuple_reportable(items: &[Type]) -> bool {
+    (1..=MAX_REPORTED_ITEMS).contains(&items.len())
+        && items
+            .iter()
+            .all(|ty| matches!(ty, Type::I64 | Type::F64 | Type::Bool | Type::Str))
+}
+
+/// Text of an optional value as Kayton shows it: the value itself or `None`. `repr`
+/// formats the value with Debug, as inside a struct.
+fn option_text(value: &str, inner: &Type, repr: bool) -> String {
+    let text = if repr && *inner == Type::Str {
+        "__kayton_repr_str(__v)"
+    } else if repr || debug_formatted(inner) {
+        "format!(\"{:?}\", __v)"
+    } else {
+        "format!(\"{}\", __v)"
+    };
+    format!(
+        "match &{} {{ Some(__v) => {}, None => \"None\".to_string() }}",
+        value, text
+    )
+}
+
+/// Render an `f64` as a Rust float literal that keeps its fractional part (`2.0`, `1e-7`).
+fn float_literal(value: f64) -> String {
+    if value.is_infinite() {
+        "f64::INFINITY".to_string()
+    } else {
+        format!("{:?}", value)
+    }
+}
+
 pub fn generate_rust_code(rhir_program: &RustProgram, resolved: &ResolvedProgram) -> RustCode {
     use std::collections::HashSet;
 
     let mut generator = CodeGenerator::new(resolved);
     let pre_assigned: HashSet<SymbolId> = HashSet::new();
     let prelude: Vec<String> = Vec::new();
-    let epilogue: Vec<String> = Vec::new();
-    generator.generate_code_with_preassigned_and_prelude(
-        rhir_program,
-        &pre_assigned,
-        &prelude,
-        &epilogue,
-    )
+    generator.generate_code_with_preassigned_and_prelude(rhir_program, &pre_assigned, &prelude, &[])
 }
@@ -2,7 +2,11 @@ pub mod generator;
 pub mod types;
 
 pub use generator::CodeGenerator;
+pub use generator::MAX_REPORTED_ITEMS;
+pub use generator::captured_by_lambdas;
 pub use generator::generate_rust_code;
+pub use generator::tuple_reportable;
+pub use generator::vec_reporter;
 pub use types::*;
 
 #[cfg(test)]
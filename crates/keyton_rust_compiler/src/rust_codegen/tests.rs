@@ -13,8 +13,8 @@ fn program1_rust_codegen() {
 x = x + 1
 print(x)
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     let mut resolved = resolve_program(&hir);
     let typed = typecheck_program(&mut resolved);
@@ -56,8 +56,8 @@ fn program4_rust_codegen() {
 x = "Hello"
 print(x)
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     let mut resolved = resolve_program(&hir);
     let typed = typecheck_program(&mut resolved);
@@ -103,8 +103,8 @@ print(x)
 #[test]
 fn program2_rust_codegen() {
     let input = r#"print("Hello, World")"#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     let mut resolved = resolve_program(&hir);
     let typed = typecheck_program(&mut resolved);
@@ -125,7 +125,7 @@ fn program2_rust_codegen() {
 }
 
 #[test]
-fn program_user_fn_inline_print_rust_codegen() {
+fn program_user_fn_print_rust_codegen() {
     let input = r#"fn my_sum(x, y):
     x + y
 
@@ -133,8 +133,8 @@ x = 1
 y = 2
 print(my_sum(x,y))
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     let mut resolved = resolve_program(&hir);
     let typed = typecheck_program(&mut resolved);
@@ -148,9 +148,12 @@ print(my_sum(x,y))
     );
 
     let expected_code = r#"fn main() {
+    fn my_sum(mut x: i64, mut y: i64) -> i64 {
+    return (x + y);
+}
     let mut x = 1;
     let mut y = 2;
-    println!((x + y));
+    println!(my_sum(x, y));
 }
 "#;
     assert_eq!(rust_code.source_code, expected_code);
@@ -161,8 +164,8 @@ fn program3_rust_codegen() {
     let input = r#"x = 12
 print(f"{x}")
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     let mut resolved = resolve_program(&hir);
     let typed = typecheck_program(&mut resolved);
@@ -189,8 +192,8 @@ fn program5_rust_codegen_same_type_reuse() {
 x = 42
 print(x)
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     let mut resolved = resolve_program(&hir);
     let typed = typecheck_program(&mut resolved);
@@ -233,8 +236,8 @@ fn test_function_mapping_rust_codegen() {
 print(123)
 print(f"value: {42}")"#;
 
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     let mut resolved = resolve_program(&hir);
     let typed = typecheck_program(&mut resolved);
@@ -256,8 +259,8 @@ fn test_string_interpolation_rust_codegen() {
 y = "world"
 print(f"Hello {x} {y}")"#;
 
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     let mut resolved = resolve_program(&hir);
     let typed = typecheck_program(&mut resolved);
@@ -272,3 +275,105 @@ print(f"Hello {x} {y}")"#;
 "#;
     assert_eq!(rust_code.source_code, expected_code);
 }
+
+#[test]
+fn operators_rust_codegen() {
+    let input = "a = 10 - 2 * 3\nb = a // 3\nc = -a % 4\nd = a > 1 and not b == 2\n";
+
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
+    let hir = lower_program(ast);
+    let mut resolved = resolve_program(&hir);
+    let typed = typecheck_program(&mut resolved);
+    let rhir_program = convert_to_rhir(&typed, &resolved);
+    let rust_code = generate_rust_code(&rhir_program, &resolved);
+
+    let expected_code = r#"fn main() {
+    let mut a = (10 - (2 * 3));
+    let mut b = __kayton_floordiv(a, 3);
+    let mut c = __kayton_mod((-a), 4);
+    let mut d = ((a > 1) && (!(b == 2)));
+}
+"#;
+    assert_eq!(rust_code.source_code, expected_code);
+}
+
+#[test]
+fn while_loop_rust_codegen() {
+    let input = "i = 0\nwhile i < 10:\n    i += 1\n    if i == 2:\n        continue\n    if i == 5:\n        break\n";
+
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
+    let hir = lower_program(ast);
+    let mut resolved = resolve_program(&hir);
+    let typed = typecheck_program(&mut resolved);
+    let rhir_program = convert_to_rhir(&typed, &resolved);
+    let rust_code = generate_rust_code(&rhir_program, &resolved);
+
+    let expected_code = r#"fn main() {
+    let mut i = 0;
+    while (i < 10) {
+    i = (i + 1);
+    if (i == 2) {
+    continue;
+}
+    if (i == 5) {
+    break;
+}
+}
+}
+"#;
+    assert_eq!(rust_code.source_code, expected_code);
+}
+
+#[test]
+fn float_rust_codegen() {
+    let input = "a = 2.0 * 3\nb = a / 4\nc = 7 // 2.5\nprint(f\"{a}\")\nprint(b)\n";
+
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
+    let hir = lower_program(ast);
+    let mut resolved = resolve_program(&hir);
+    let typed = typecheck_program(&mut resolved);
+    let rhir_program = convert_to_rhir(&typed, &resolved);
+    let rust_code = generate_rust_code(&rhir_program, &resolved);
+
+    let expected_code = r#"fn main() {
+    let mut a = (2.0 * (3 as f64));
+    let mut b = __kayton_div(a, (4 as f64));
+    let mut c = __kayton_floordiv_f64((7 as f64), 2.5);
+    println!(format!("{:?}", a));
+    println!("{:?}", b);
+}
+"#;
+    assert_eq!(rust_code.source_code, expected_code);
+}
+
+#[test]
+fn sorted_floats_use_their_total_order() {
+    let input = "xs = [2.5, 1.0]\nys = sorted(xs)\nzs = sorted(xs, fn(x) => -x)\n";
+
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
+    let hir = lower_program(ast);
+    let mut resolved = resolve_program(&hir);
+    let typed = typecheck_program(&mut resolved);
+    let rhir_program = convert_to_rhir(&typed, &resolved);
+    let rust_code = generate_rust_code(&rhir_program, &resolved);
+
+    assert!(
+        rust_code
+            .source_code
+            .contains("__v.sort_by(|__a, __b| __a.total_cmp(&__b))"),
+        "{}",
+        rust_code.source_code
+    );
+    assert!(
+        rust_code.source_code.contains(
+            "__v.sort_by(|__a, __b| __f((*__a).clone()).total_cmp(&__f((*__b).clone())))"
+        ),
+        "{}",
+        rust_code.source_code
+    );
+    assert!(!rust_code.source_code.contains("partial_cmp"));
+}
@@ -2,7 +2,7 @@ pub mod resolver;
 pub mod sym;
 pub mod types;
 
-pub use resolver::{ResolvedProgram, Resolver, resolve_program};
+pub use resolver::{ResolvedProgram, Resolver, resolve_program, resolve_program_with_spans};
 pub use sym::*;
 pub use types::*;
 
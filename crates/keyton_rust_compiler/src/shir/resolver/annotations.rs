@@ -0,0 +1,64 @@
+use crate::hir::hir_types::HirId;
+use crate::parser::TypeExpr;
+
+use super::super::sym::Type;
+use super::core::Resolver;
+use super::errors::ResolveError;
+use super::program::map_typekind;
+
+impl Resolver {
+    /// Resolve a type annotation. `hir_id` positions the error for an unknown type name.
+    pub(super) fn resolve_type(&mut self, hir_id: HirId, ty: &TypeExpr) -> Type {
+        match ty {
+            TypeExpr::Name(name) => match name.as_str() {
+                "i64" => Type::I64,
+                "f64" => Type::F64,
+                "str" => Type::Str,
+                "bool" => Type::Bool,
+                "range" => Type::Range,
+                _ if self.syms.structs.contains_key(name) => Type::Struct(name.clone()),
+                _ if self.syms.enums.contains_key(name) => Type::Enum(name.clone()),
+                _ => self.resolve_plugin_type(hir_id, name),
+            },
+            TypeExpr::List(elem) => Type::List(Box::new(self.resolve_type(hir_id, elem))),
+            TypeExpr::Dict(key, value) => Type::Dict(
+                Box::new(self.resolve_type(hir_id, key)),
+                Box::new(self.resolve_type(hir_id, value)),
+            ),
+            TypeExpr::Tuple(items) => {
+                Type::Tuple(items.iter().map(|t| self.resolve_type(hir_id, t)).collect())
+            }
+            TypeExpr::Option(inner) => Type::Option(Box::new(self.resolve_type(hir_id, inner))),
+            TypeExpr::Func(params, ret) => Type::Func(
+                params
+                    .iter()
+                    .map(|t| self.resolve_type(hir_id, t))
+                    .collect(),
+                Box::new(self.resolve_type(hir_id, ret)),
+            ),
+        }
+    }
+
+    pub(super) fn resolve_opt_type(&mut self, hir_id: HirId, ty: Option<&TypeExpr>) -> Type {
+        ty.map_or(Type::Any, |t| self.resolve_type(hir_id, t))
+    }
+
+    /// Look a type up in the manifests of the imported plugins, by its full stable name
+    /// (`reqwest::Client`) or by its last path segment (`Client`).
+    fn resolve_plugin_type(&mut self, hir_id: HirId, name: &str) -> Type {
+        let found = self
+            .plugin_manifests
+            .values()
+            .flat_map(|m| m.types.iter())
+            .find(|t| t.name == name || t.name.rsplit("::").next() == Some(name))
+            .map(|t| map_typekind(&t.kind));
+        found.unwrap_or_else(|| {
+            let span = self.spans.get(&hir_id).cloned().unwrap_or_default();
+            self.report.errors.push(ResolveError::UnknownType {
+                span,
+                name: name.to_string(),
+            });
+            Type::Any
+        })
+    }
+}
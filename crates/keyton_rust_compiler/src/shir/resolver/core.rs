@@ -3,9 +3,8 @@ use std::collections::HashMap;
 use crate::hir::hir_types::HirId;
 use crate::span::Span;
 
-use super::errors::ResolveReport;
 use super::super::sym::{FuncSig, ScopeId, SymKind, SymbolId, SymbolTable, Type};
-use super::user_funcs::UserFuncDef;
+use super::errors::ResolveReport;
 
 pub struct Resolver {
     pub syms: SymbolTable,
@@ -13,8 +12,13 @@ pub struct Resolver {
     scope_stack: Vec<ScopeId>,
     pub(super) builtins: HashMap<String, SymbolId>,
     pub(super) spans: HashMap<HirId, Span>,
-    pub(super) user_funcs: HashMap<SymbolId, UserFuncDef>,
     pub(super) plugin_manifests: HashMap<String, kayton_plugin_sdk::manifest::Manifest>,
+    /// Number of loops enclosing the statement being resolved
+    pub(super) loop_depth: usize,
+    /// Scope of the innermost function being resolved, if any
+    pub(super) func_scope: Option<ScopeId>,
+    /// Number of `except` bodies enclosing the statement being resolved
+    pub(super) except_depth: usize,
 }
 
 impl Resolver {
@@ -26,8 +30,10 @@ impl Resolver {
             scope_stack: vec![global],
             builtins: HashMap::new(),
             spans,
-            user_funcs: HashMap::new(),
             plugin_manifests: HashMap::new(),
+            loop_depth: 0,
+            func_scope: None,
+            except_depth: 0,
         }
     }
 
@@ -51,7 +57,6 @@ impl Resolver {
         ScopeId(0)
     }
 
-    #[allow(dead_code)]
     pub(super) fn enter_scope(&mut self) -> ScopeId {
         let parent = self.current_scope();
         let s = self.syms.new_scope(parent);
@@ -59,8 +64,24 @@ impl Resolver {
         s
     }
 
-    #[allow(dead_code)]
     pub(super) fn leave_scope(&mut self) {
         self.scope_stack.pop();
     }
+
+    /// Whether `scope` belongs to the function being resolved. Outside of a function
+    /// every scope does.
+    pub(super) fn in_current_function(&self, mut scope: ScopeId) -> bool {
+        let Some(func_scope) = self.func_scope else {
+            return true;
+        };
+        loop {
+            if scope == func_scope {
+                return true;
+            }
+            match self.syms.scopes[scope.0 as usize].parent {
+                Some(parent) => scope = parent,
+                None => return false,
+            }
+        }
+    }
 }
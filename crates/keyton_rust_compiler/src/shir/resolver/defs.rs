@@ -1,12 +1,25 @@
+use std::collections::HashMap;
+
 use crate::hir::hir_types::HirStmt;
 
+use super::super::sym::{EnumInfo, FuncSig, StructInfo, SymKind, Type};
 use super::core::Resolver;
-use super::super::sym::{FuncSig, SymKind, Type};
-use super::user_funcs::UserFuncDef;
 
 impl Resolver {
     pub fn collect_defs(&mut self, hir: &[HirStmt]) {
         let scope = self.current_scope();
+        // Struct and enum names come first, so annotations anywhere can name them
+        for stmt in hir {
+            match stmt {
+                HirStmt::StructDef { name, .. } => {
+                    self.syms.structs.entry(name.clone()).or_default();
+                }
+                HirStmt::EnumDef { name, .. } => {
+                    self.syms.enums.entry(name.clone()).or_default();
+                }
+                _ => {}
+            }
+        }
         for stmt in hir {
             match stmt {
                 HirStmt::RImportModule { .. } => {}
@@ -19,31 +32,141 @@ impl Resolver {
                     };
                     self.syms.define(scope, name, kind);
                 }
-                HirStmt::FuncDef { name, params, body, .. } => {
+                HirStmt::TupleAssign { names, .. } => {
+                    let kind = if scope == self.global_scope() {
+                        SymKind::GlobalVar
+                    } else {
+                        SymKind::LocalVar
+                    };
+                    for name in names {
+                        self.syms.define(scope, name, kind);
+                    }
+                }
+                HirStmt::FuncDef {
+                    hir_id,
+                    name,
+                    params,
+                    ret,
+                    ..
+                } => {
                     let sid = self.syms.define(scope, name, SymKind::Func);
+                    // Unannotated parameters and return types stay unchecked
+                    let param_types = params
+                        .iter()
+                        .map(|p| self.resolve_opt_type(*hir_id, p.ty.as_ref()))
+                        .collect();
+                    let ret = self.resolve_opt_type(*hir_id, ret.as_ref());
                     if let Some(info) = self.syms.infos.get_mut(sid.0 as usize) {
                         info.sig = Some(FuncSig {
-                            params: vec![Type::Any; params.len()],
-                            ret: Type::Any,
+                            params: param_types,
+                            ret,
                         });
                     }
-                    self.user_funcs.insert(
-                        sid,
-                        UserFuncDef {
-                            params: params.clone(),
-                            body: body.clone(),
+                }
+                HirStmt::StructDef {
+                    hir_id,
+                    name,
+                    fields,
+                    methods,
+                } => {
+                    let fields = fields
+                        .iter()
+                        .map(|f| (f.name.clone(), self.resolve_type(*hir_id, &f.ty)))
+                        .collect();
+                    // Methods get a scope of their own, apart from functions of the same name
+                    let methods_scope = self.syms.new_scope(scope);
+                    let mut method_syms = HashMap::new();
+                    for method in methods {
+                        let HirStmt::FuncDef {
+                            hir_id,
+                            name: method_name,
+                            params,
+                            ret,
+                            ..
+                        } = method
+                        else {
+                            continue;
+                        };
+                        let sid = self.syms.define(methods_scope, method_name, SymKind::Func);
+                        let mut param_types: Vec<Type> = params
+                            .iter()
+                            .map(|p| self.resolve_opt_type(*hir_id, p.ty.as_ref()))
+                            .collect();
+                        // The parser only accepts methods that take `self` first
+                        param_types[0] = Type::Struct(name.clone());
+                        let ret = self.resolve_opt_type(*hir_id, ret.as_ref());
+                        if let Some(info) = self.syms.infos.get_mut(sid.0 as usize) {
+                            info.sig = Some(FuncSig {
+                                params: param_types,
+                                ret,
+                            });
+                        }
+                        method_syms.insert(method_name.clone(), sid);
+                    }
+                    self.syms.structs.insert(
+                        name.clone(),
+                        StructInfo {
+                            fields,
+                            methods: method_syms,
                         },
                     );
                 }
-                HirStmt::ExprStmt { .. } => {}
-                HirStmt::ForRange { var, body, .. } => {
-                    self.syms.define(scope, var, SymKind::LocalVar);
-                    self.collect_defs(body);
+                HirStmt::EnumDef {
+                    hir_id,
+                    name,
+                    variants,
+                } => {
+                    let variants = variants
+                        .iter()
+                        .map(|v| {
+                            let fields = v
+                                .fields
+                                .iter()
+                                .map(|t| self.resolve_type(*hir_id, t))
+                                .collect();
+                            (v.name.clone(), fields)
+                        })
+                        .collect();
+                    self.syms.enums.insert(name.clone(), EnumInfo { variants });
+                }
+                HirStmt::ExprStmt { .. }
+                | HirStmt::IndexAssign { .. }
+                | HirStmt::FieldAssign { .. } => {}
+                // The loop variable is defined in the loop's own scope when the loop is
+                // resolved; names assigned in the body belong to the enclosing scope
+                HirStmt::ForRange { body, .. } | HirStmt::ForEach { body, .. } => {
+                    self.collect_defs(body)
                 }
-                HirStmt::If { then_branch, else_branch, .. } => {
+                HirStmt::While { body, .. } => self.collect_defs(body),
+                HirStmt::Break { .. } | HirStmt::Continue { .. } | HirStmt::Return { .. } => {}
+                HirStmt::If {
+                    then_branch,
+                    else_branch,
+                    ..
+                } => {
                     self.collect_defs(then_branch);
                     self.collect_defs(else_branch);
                 }
+                // Pattern bindings live in the arm's own scope, like loop variables
+                HirStmt::Match { arms, .. } => {
+                    for arm in arms {
+                        self.collect_defs(&arm.body);
+                    }
+                }
+                // The name an `except` binds lives in the handler's own scope too
+                HirStmt::Try {
+                    body,
+                    handlers,
+                    finally,
+                    ..
+                } => {
+                    self.collect_defs(body);
+                    for handler in handlers {
+                        self.collect_defs(&handler.body);
+                    }
+                    self.collect_defs(finally);
+                }
+                HirStmt::Raise { .. } => {}
             }
         }
     }
@@ -2,8 +2,48 @@ use crate::span::Span;
 
 #[derive(Debug, Clone)]
 pub enum ResolveError {
-    UnresolvedName { span: Span, name: String },
-    ImportError { span: Span, message: String },
+    UnresolvedName {
+        span: Span,
+        name: String,
+    },
+    ImportError {
+        span: Span,
+        message: String,
+    },
+    /// `break` or `continue` used outside of a loop body
+    OutsideLoop {
+        span: Span,
+        keyword: String,
+    },
+    /// `return` used outside of a function body
+    OutsideFunction {
+        span: Span,
+    },
+    /// A function body reading a variable of an enclosing scope
+    CapturedVariable {
+        span: Span,
+        name: String,
+    },
+    /// `raise` or `except` naming a kind not in `EXCEPTION_KINDS`
+    UnknownException {
+        span: Span,
+        name: String,
+    },
+    /// A bare `raise` outside of an `except` body
+    ReraiseOutsideExcept {
+        span: Span,
+    },
+    /// A type annotation naming neither a builtin type nor a type of an imported plugin
+    UnknownType {
+        span: Span,
+        name: String,
+    },
+    /// A struct called with positional arguments; `fields` are all its fields, in order
+    PositionalFields {
+        span: Span,
+        strukt: String,
+        fields: Vec<String>,
+    },
 }
 
 #[derive(Debug, Default)]
@@ -1,9 +1,9 @@
 use crate::hir::hir_types::{HirExpr, HirId, HirStringPart};
 
-use super::core::Resolver;
-use super::errors::ResolveError;
 use super::super::sym::{FuncSig, SymKind, SymbolId, Type};
 use super::super::types::{SExpr, SStringPart};
+use super::core::Resolver;
+use super::errors::ResolveError;
 
 impl Resolver {
     pub(super) fn resolve_expr(&mut self, e: &HirExpr) -> SExpr {
@@ -12,6 +12,10 @@ impl Resolver {
                 hir_id: *hir_id,
                 value: *value,
             },
+            HirExpr::Float { hir_id, value } => SExpr::Float {
+                hir_id: *hir_id,
+                value: *value,
+            },
             HirExpr::Str { hir_id, value } => SExpr::Str {
                 hir_id: *hir_id,
                 value: value.clone(),
@@ -20,6 +24,7 @@ impl Resolver {
                 hir_id: *hir_id,
                 value: *value,
             },
+            HirExpr::None { hir_id } => SExpr::None { hir_id: *hir_id },
             HirExpr::Ident { hir_id, name } => {
                 let sym = self.lookup_name(*hir_id, name);
                 SExpr::Name {
@@ -27,7 +32,12 @@ impl Resolver {
                     sym,
                 }
             }
-            HirExpr::Binary { hir_id, left, op, right } => {
+            HirExpr::Binary {
+                hir_id,
+                left,
+                op,
+                right,
+            } => {
                 let l = self.resolve_expr(left);
                 let r = self.resolve_expr(right);
                 SExpr::Binary {
@@ -37,16 +47,32 @@ impl Resolver {
                     right: Box::new(r),
                 }
             }
+            HirExpr::Unary { hir_id, op, expr } => SExpr::Unary {
+                hir_id: *hir_id,
+                op: op.clone(),
+                expr: Box::new(self.resolve_expr(expr)),
+            },
             HirExpr::Call { hir_id, func, args } => {
-                if let HirExpr::Ident { name, .. } = func.as_ref() {
-                    let sym = self.lookup_name(*hir_id, name);
-                    if let Some(fdef) = self.user_funcs.get(&sym) {
-                        if let Some(body_expr) = Self::last_expr_of_body(&fdef.body) {
-                            let inlined =
-                                Self::substitute_params(&fdef.params, args, &body_expr);
-                            return self.resolve_expr(&inlined);
-                        }
+                // A struct is built with named fields only: `P()` leaves them all
+                // missing, which the checker reports, and `P(1, 2)` is rejected here
+                if let HirExpr::Ident { name, .. } = func.as_ref()
+                    && self.syms.lookup(self.current_scope(), name).is_none()
+                    && let Some(info) = self.syms.structs.get(name)
+                {
+                    if !args.is_empty() {
+                        let span = self.spans.get(hir_id).cloned().unwrap_or_default();
+                        let fields = info.fields.iter().map(|(f, _)| f.clone()).collect();
+                        self.report.errors.push(ResolveError::PositionalFields {
+                            span,
+                            strukt: name.clone(),
+                            fields,
+                        });
                     }
+                    return SExpr::Construct {
+                        hir_id: *hir_id,
+                        name: name.clone(),
+                        fields: Vec::new(),
+                    };
                 }
                 let f = self.resolve_expr(func);
                 let a = args.iter().map(|x| self.resolve_expr(x)).collect();
@@ -56,6 +82,26 @@ impl Resolver {
                     args: a,
                 }
             }
+            HirExpr::Index {
+                hir_id,
+                target,
+                index,
+            } => SExpr::Index {
+                hir_id: *hir_id,
+                target: Box::new(self.resolve_expr(target)),
+                index: Box::new(self.resolve_expr(index)),
+            },
+            HirExpr::Slice {
+                hir_id,
+                target,
+                start,
+                end,
+            } => SExpr::Slice {
+                hir_id: *hir_id,
+                target: Box::new(self.resolve_expr(target)),
+                start: start.as_ref().map(|e| Box::new(self.resolve_expr(e))),
+                end: end.as_ref().map(|e| Box::new(self.resolve_expr(e))),
+            },
             HirExpr::InterpolatedString { hir_id, parts } => {
                 let parts = parts
                     .iter()
@@ -64,9 +110,10 @@ impl Resolver {
                             hir_id: *hir_id,
                             value: text.clone(),
                         },
-                        HirStringPart::Expr { hir_id, expr } => SStringPart::Expr {
+                        HirStringPart::Expr { hir_id, expr, spec } => SStringPart::Expr {
                             hir_id: *hir_id,
                             expr: self.resolve_expr(expr),
+                            spec: spec.clone(),
                         },
                     })
                     .collect();
@@ -75,52 +122,166 @@ impl Resolver {
                     parts,
                 }
             }
+            HirExpr::Dict { hir_id, entries } => SExpr::Dict {
+                hir_id: *hir_id,
+                entries: entries
+                    .iter()
+                    .map(|(k, v)| (self.resolve_expr(k), self.resolve_expr(v)))
+                    .collect(),
+            },
+            HirExpr::Tuple { hir_id, items } => SExpr::Tuple {
+                hir_id: *hir_id,
+                items: items.iter().map(|e| self.resolve_expr(e)).collect(),
+            },
+            HirExpr::Range { hir_id, start, end } => SExpr::Range {
+                hir_id: *hir_id,
+                start: Box::new(self.resolve_expr(start)),
+                end: Box::new(self.resolve_expr(end)),
+            },
+            HirExpr::Field {
+                hir_id,
+                target,
+                name,
+            } if self.enum_name(target).is_some() => SExpr::Variant {
+                hir_id: *hir_id,
+                enum_name: self.enum_name(target).unwrap_or_default(),
+                variant: name.clone(),
+                args: Vec::new(),
+            },
+            HirExpr::MethodCall {
+                hir_id,
+                target,
+                method,
+                args,
+            } if self.enum_name(target).is_some() => SExpr::Variant {
+                hir_id: *hir_id,
+                enum_name: self.enum_name(target).unwrap_or_default(),
+                variant: method.clone(),
+                args: args.iter().map(|a| self.resolve_expr(a)).collect(),
+            },
+            HirExpr::Field {
+                hir_id,
+                target,
+                name,
+            } => SExpr::Field {
+                hir_id: *hir_id,
+                target: Box::new(self.resolve_expr(target)),
+                name: name.clone(),
+            },
+            HirExpr::MethodCall {
+                hir_id,
+                target,
+                method,
+                args,
+            } => SExpr::MethodCall {
+                hir_id: *hir_id,
+                target: Box::new(self.resolve_expr(target)),
+                method: method.clone(),
+                func: self.lookup_func(method),
+                args: args.iter().map(|a| self.resolve_expr(a)).collect(),
+            },
+            HirExpr::Construct {
+                hir_id,
+                name,
+                fields,
+            } => {
+                if !self.syms.structs.contains_key(name) {
+                    let span = self.spans.get(hir_id).cloned().unwrap_or_default();
+                    self.report.errors.push(ResolveError::UnknownType {
+                        span,
+                        name: name.clone(),
+                    });
+                }
+                SExpr::Construct {
+                    hir_id: *hir_id,
+                    name: name.clone(),
+                    fields: fields
+                        .iter()
+                        .map(|(field, value)| (field.clone(), self.resolve_expr(value)))
+                        .collect(),
+                }
+            }
+            HirExpr::Lambda {
+                hir_id,
+                params,
+                body,
+            } => {
+                // Unlike a function body, the body may read variables of the enclosing
+                // scopes; the lambda captures their values when it is created
+                let param_types = params
+                    .iter()
+                    .map(|p| self.resolve_opt_type(*hir_id, p.ty.as_ref()))
+                    .collect();
+                let scope = self.enter_scope();
+                let params = params
+                    .iter()
+                    .map(|p| self.syms.define(scope, &p.name, SymKind::LocalVar))
+                    .collect();
+                let body = self.resolve_expr(body);
+                self.leave_scope();
+                SExpr::Lambda {
+                    hir_id: *hir_id,
+                    params,
+                    param_types,
+                    body: Box::new(body),
+                }
+            }
+        }
+    }
+
+    /// The enum `target` names in `Shape.Circle(...)`, unless a variable shadows it.
+    fn enum_name(&self, target: &HirExpr) -> Option<String> {
+        match target {
+            HirExpr::Ident { name, .. }
+                if self.syms.enums.contains_key(name)
+                    && self.syms.lookup(self.current_scope(), name).is_none() =>
+            {
+                Some(name.clone())
+            }
+            _ => None,
         }
     }
 
+    /// The function `target.name(...)` calls with the target as its first argument when
+    /// the target is not a struct with such a method. Unlike `lookup_name`, a name
+    /// that is not a function is not an error here; the type checker decides.
+    fn lookup_func(&mut self, name: &str) -> Option<SymbolId> {
+        if let Some(sid) = self.syms.lookup(self.current_scope(), name) {
+            let kind = self.syms.infos[sid.0 as usize].kind;
+            return matches!(kind, SymKind::Func | SymKind::BuiltinFunc).then_some(sid);
+        }
+        self.collection_builtin(name)
+    }
+
     pub(super) fn lookup_name(&mut self, use_hir: HirId, name: &str) -> SymbolId {
         if let Some(sid) = self.syms.lookup(self.current_scope(), name) {
+            let info = &self.syms.infos[sid.0 as usize];
+            // Functions are compiled to Rust `fn` items, which cannot capture variables
+            if matches!(info.kind, SymKind::GlobalVar | SymKind::LocalVar)
+                && !self.in_current_function(info.scope)
+            {
+                let span = self.spans.get(&use_hir).cloned().unwrap_or_default();
+                self.report.errors.push(ResolveError::CapturedVariable {
+                    span,
+                    name: name.to_string(),
+                });
+            }
             return sid;
         }
         if let Some(&sid) = self.builtins.get(name) {
             return sid;
         }
-        match name {
-            "vec" => {
-                let sid = self.add_builtin("vec");
-                if let Some(info) = self.syms.infos.get_mut(sid.0 as usize) {
-                    info.sig = Some(FuncSig {
-                        params: vec![],
-                        ret: Type::Any,
-                    });
-                }
-                return sid;
-            }
-            "append" => {
-                let sid = self.add_builtin("append");
-                if let Some(info) = self.syms.infos.get_mut(sid.0 as usize) {
-                    info.sig = Some(FuncSig {
-                        params: vec![Type::Any, Type::Any],
-                        ret: Type::Unit,
-                    });
-                }
-                return sid;
-            }
-            "sum" => {
-                let sid = self.add_builtin("sum");
-                if let Some(info) = self.syms.infos.get_mut(sid.0 as usize) {
-                    info.sig = Some(FuncSig {
-                        params: vec![Type::Any],
-                        ret: Type::I64,
-                    });
-                }
-                return sid;
-            }
-            _ => {}
+        if let Some(sid) = self.collection_builtin(name) {
+            return sid;
         }
-        let sid = self
-            .syms
-            .define(self.global_scope(), name, SymKind::GlobalVar);
+        // An unknown name inside a function can only be a local; at the top level it may
+        // still be a global defined by an earlier REPL input
+        let sid = match self.func_scope {
+            Some(scope) => self.syms.define(scope, name, SymKind::LocalVar),
+            None => self
+                .syms
+                .define(self.global_scope(), name, SymKind::GlobalVar),
+        };
         let span = self.spans.get(&use_hir).cloned().unwrap_or_default();
         self.report.errors.push(ResolveError::UnresolvedName {
             span,
@@ -128,4 +289,74 @@ impl Resolver {
         });
         sid
     }
+
+    /// Define a list, dict or string builtin on first use. The type checker refines the
+    /// signatures of the list and dict builtins with the element types.
+    fn collection_builtin(&mut self, name: &str) -> Option<SymbolId> {
+        let any_list = || Type::List(Box::new(Type::Any));
+        let any_dict = || Type::Dict(Box::new(Type::Any), Box::new(Type::Any));
+        let str_list = || Type::List(Box::new(Type::Str));
+        let list_sig = match name {
+            // `[a, b]` is parsed as `vec(a, b)`, which takes any number of elements
+            "vec" => Some(FuncSig {
+                params: vec![],
+                ret: any_list(),
+            }),
+            "append" => Some(FuncSig {
+                params: vec![any_list(), Type::Any],
+                ret: Type::Unit,
+            }),
+            "sum" => Some(FuncSig {
+                params: vec![any_list()],
+                ret: Type::Any,
+            }),
+            "keys" | "values" | "items" => Some(FuncSig {
+                params: vec![any_dict()],
+                ret: any_list(),
+            }),
+            // `map(xs, f)`, `filter(xs, f)` and `sorted(xs, key)`, whose key is optional
+            "map" | "filter" | "sorted" => Some(FuncSig {
+                params: vec![any_list(), Type::Any],
+                ret: any_list(),
+            }),
+            // Of a string, list or dict
+            "len" => Some(FuncSig {
+                params: vec![Type::Any],
+                ret: Type::I64,
+            }),
+            // Without a separator, `split` splits at runs of whitespace
+            "split" => Some(FuncSig {
+                params: vec![Type::Str, Type::Str],
+                ret: str_list(),
+            }),
+            "join" => Some(FuncSig {
+                params: vec![Type::Str, str_list()],
+                ret: Type::Str,
+            }),
+            "strip" | "upper" | "lower" => Some(FuncSig {
+                params: vec![Type::Str],
+                ret: Type::Str,
+            }),
+            "replace" => Some(FuncSig {
+                params: vec![Type::Str, Type::Str, Type::Str],
+                ret: Type::Str,
+            }),
+            "startswith" => Some(FuncSig {
+                params: vec![Type::Str, Type::Str],
+                ret: Type::Bool,
+            }),
+            // Index of the first match, or -1
+            "find" => Some(FuncSig {
+                params: vec![Type::Str, Type::Str],
+                ret: Type::I64,
+            }),
+            _ => None,
+        };
+        let sig = list_sig?;
+        let sid = self.add_builtin(name);
+        if let Some(info) = self.syms.infos.get_mut(sid.0 as usize) {
+            info.sig = Some(sig);
+        }
+        Some(sid)
+    }
 }
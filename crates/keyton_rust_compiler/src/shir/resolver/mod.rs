@@ -1,10 +1,10 @@
-mod errors;
+mod annotations;
 mod core;
 mod defs;
-mod stmt;
+mod errors;
 mod expr;
-mod user_funcs;
 mod program;
+mod stmt;
 
 pub use core::Resolver;
 pub use errors::{ResolveError, ResolveReport};
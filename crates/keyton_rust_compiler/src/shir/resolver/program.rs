@@ -4,26 +4,31 @@ use crate::hir::hir_types::{HirId, HirStmt};
 use crate::rimport::env::{load_active_env_registry, load_plugin_manifest};
 use crate::span::Span;
 
-use super::core::Resolver;
-use super::errors::ResolveError;
 use super::super::sym::{FuncSig, SymKind, SymbolTable, Type};
 use super::super::types::SStmt;
+use super::core::Resolver;
+use super::errors::ResolveError;
 
 impl Resolver {
     pub fn resolve_program(&mut self, hir: &[HirStmt]) -> Vec<SStmt> {
-        self.collect_defs(hir);
         if let Ok(env_dir) = load_active_env_registry() {
             let _ = env_dir;
         }
-        let mut out = Vec::with_capacity(hir.len());
+        // Load plugin manifests first so annotations anywhere can name plugin types
         for stmt in hir {
-            if let HirStmt::RImportItems { module, items, .. } = stmt {
-                let span = self.spans.get(&HirId(0)).cloned().unwrap_or_default();
+            if let HirStmt::RImportItems {
+                hir_id,
+                module,
+                items,
+            } = stmt
+            {
+                let span = self.spans.get(hir_id).cloned().unwrap_or_default();
                 match load_plugin_manifest(module) {
                     Ok(mani) => {
                         self.plugin_manifests.insert(module.clone(), mani.clone());
                         for it in items {
-                            if let Some(func) = mani.functions.iter().find(|f| &f.stable_name == it) {
+                            if let Some(func) = mani.functions.iter().find(|f| &f.stable_name == it)
+                            {
                                 let g = self.global_scope();
                                 let sid = self.syms.define(g, &it, SymKind::BuiltinFunc);
                                 if let Some(info) = self.syms.infos.get_mut(sid.0 as usize) {
@@ -46,8 +51,8 @@ impl Resolver {
                         });
                     }
                 }
-            } else if let HirStmt::RImportModule { module, .. } = stmt {
-                let span = self.spans.get(&HirId(0)).cloned().unwrap_or_default();
+            } else if let HirStmt::RImportModule { hir_id, module } = stmt {
+                let span = self.spans.get(hir_id).cloned().unwrap_or_default();
                 match load_plugin_manifest(module) {
                     Ok(mani) => {
                         self.plugin_manifests.insert(module.clone(), mani.clone());
@@ -63,21 +68,27 @@ impl Resolver {
                     }
                 }
             }
-            out.push(self.resolve_stmt(stmt));
         }
-        out
+        self.collect_defs(hir);
+        hir.iter().map(|stmt| self.resolve_stmt(stmt)).collect()
     }
 }
 
-fn map_typekind(k: &kayton_plugin_sdk::manifest::TypeKind) -> Type {
+pub(super) fn map_typekind(k: &kayton_plugin_sdk::manifest::TypeKind) -> Type {
     use kayton_plugin_sdk::manifest::TypeKind as TK;
     match k {
         TK::I64 => Type::I64,
-        TK::F64 => Type::Any,
+        TK::F64 => Type::F64,
         TK::U64 => Type::Any,
-        TK::Bool => Type::Any,
+        TK::Bool => Type::Bool,
         TK::StaticStr | TK::StringBuf => Type::Str,
-        TK::VecI64 | TK::VecF64 | TK::Dynamic | TK::Unit => Type::Any,
+        TK::VecI64 => Type::List(Box::new(Type::I64)),
+        TK::VecF64 => Type::List(Box::new(Type::F64)),
+        TK::Option(inner) => Type::Option(Box::new(map_typekind(inner))),
+        // An error is raised as an exception, so a call has the value's type
+        TK::Result(ok) => map_typekind(ok),
+        TK::Iter(item) => Type::Iter(Box::new(map_typekind(item))),
+        TK::Dynamic | TK::Unit => Type::Any,
     }
 }
 
@@ -85,16 +96,16 @@ pub struct ResolvedProgram {
     pub shir: Vec<SStmt>,
     pub symbols: SymbolTable,
     pub plugins: HashMap<String, kayton_plugin_sdk::manifest::Manifest>,
+    /// Source spans of HIR nodes, used to position diagnostics
+    pub spans: HashMap<HirId, Span>,
+    pub errors: Vec<ResolveError>,
 }
 
 pub fn resolve_program(hir: &[HirStmt]) -> ResolvedProgram {
     resolve_program_with_spans(hir, HashMap::new())
 }
 
-pub fn resolve_program_with_spans(
-    hir: &[HirStmt],
-    spans: HashMap<HirId, Span>,
-) -> ResolvedProgram {
+pub fn resolve_program_with_spans(hir: &[HirStmt], spans: HashMap<HirId, Span>) -> ResolvedProgram {
     let mut resolver = Resolver::new(spans);
     resolver.add_builtin("print");
     let shir = resolver.resolve_program(hir);
@@ -102,5 +113,7 @@ pub fn resolve_program_with_spans(
         shir,
         symbols: resolver.syms,
         plugins: resolver.plugin_manifests,
+        spans: resolver.spans,
+        errors: resolver.report.errors,
     }
 }
@@ -1,58 +1,195 @@
-use crate::hir::hir_types::{HirId, HirStmt};
+use crate::hir::hir_types::{HirExceptHandler, HirId, HirPattern, HirStmt};
+use crate::parser::Param;
 
+use super::super::sym::{EXCEPTION_KINDS, SymKind, SymbolId};
+use super::super::types::{SExceptHandler, SMatchArm, SPattern, SStmt};
 use super::core::Resolver;
 use super::errors::ResolveError;
-use super::super::sym::SymKind;
-use super::super::types::{SExpr, SStmt};
 
 impl Resolver {
     pub(super) fn resolve_stmt(&mut self, s: &HirStmt) -> SStmt {
         match s {
-            HirStmt::RImportModule { hir_id, module } => {
-                SStmt::RImportModule {
-                    hir_id: *hir_id,
-                    module: module.clone(),
-                }
-            }
-            HirStmt::RImportItems { hir_id, module, items } => SStmt::RImportItems {
+            HirStmt::RImportModule { hir_id, module } => SStmt::RImportModule {
+                hir_id: *hir_id,
+                module: module.clone(),
+            },
+            HirStmt::RImportItems {
+                hir_id,
+                module,
+                items,
+            } => SStmt::RImportItems {
                 hir_id: *hir_id,
                 module: module.clone(),
                 items: items.clone(),
             },
-            HirStmt::Assign { hir_id, name, expr } => {
-                let scope = self.current_scope();
-                let sym = self
-                    .syms
-                    .lookup(scope, name)
-                    .or_else(|| self.builtins.get(name).copied())
-                    .unwrap_or_else(|| {
-                        let kind = if scope == self.global_scope() {
-                            SymKind::GlobalVar
-                        } else {
-                            SymKind::LocalVar
-                        };
-                        let sid = self.syms.define(scope, name, kind);
-                        let span = self.spans.get(hir_id).cloned().unwrap_or_default();
-                        self.report.errors.push(ResolveError::UnresolvedName {
-                            span,
-                            name: name.clone(),
-                        });
-                        sid
-                    });
+            HirStmt::Assign {
+                hir_id,
+                name,
+                ty,
+                expr,
+            } => {
+                let sym = self.lookup_assigned(*hir_id, name);
+                let ty = ty.as_ref().map(|t| self.resolve_type(*hir_id, t));
                 let rexpr = self.resolve_expr(expr);
                 SStmt::Assign {
                     hir_id: *hir_id,
                     sym,
+                    ty,
                     expr: rexpr,
                 }
             }
-            HirStmt::FuncDef { .. } => SStmt::ExprStmt {
-                hir_id: HirId(0),
-                expr: SExpr::Int {
-                    hir_id: HirId(0),
-                    value: 0,
-                },
+            HirStmt::TupleAssign {
+                hir_id,
+                names,
+                expr,
+            } => {
+                let syms = names
+                    .iter()
+                    .map(|name| self.lookup_assigned(*hir_id, name))
+                    .collect();
+                SStmt::TupleAssign {
+                    hir_id: *hir_id,
+                    syms,
+                    expr: self.resolve_expr(expr),
+                }
+            }
+            HirStmt::IndexAssign {
+                hir_id,
+                target,
+                index,
+                expr,
+            } => SStmt::IndexAssign {
+                hir_id: *hir_id,
+                target: self.resolve_expr(target),
+                index: self.resolve_expr(index),
+                expr: self.resolve_expr(expr),
             },
+            HirStmt::FieldAssign {
+                hir_id,
+                target,
+                field,
+                expr,
+            } => SStmt::FieldAssign {
+                hir_id: *hir_id,
+                target: self.resolve_expr(target),
+                field: field.clone(),
+                expr: self.resolve_expr(expr),
+            },
+            HirStmt::FuncDef {
+                hir_id,
+                name,
+                params,
+                body,
+                ..
+            } => {
+                let outer = self.current_scope();
+                let sym = self
+                    .syms
+                    .lookup(outer, name)
+                    .unwrap_or_else(|| self.syms.define(outer, name, SymKind::Func));
+                self.resolve_func(*hir_id, sym, params, body)
+            }
+            HirStmt::StructDef {
+                hir_id,
+                name,
+                methods,
+                ..
+            } => {
+                let method_syms = self.syms.structs[name].methods.clone();
+                let methods = methods
+                    .iter()
+                    .filter_map(|method| match method {
+                        HirStmt::FuncDef {
+                            hir_id,
+                            name,
+                            params,
+                            body,
+                            ..
+                        } => Some(self.resolve_func(*hir_id, method_syms[name], params, body)),
+                        _ => None,
+                    })
+                    .collect();
+                SStmt::StructDef {
+                    hir_id: *hir_id,
+                    name: name.clone(),
+                    methods,
+                }
+            }
+            HirStmt::EnumDef { hir_id, name, .. } => SStmt::EnumDef {
+                hir_id: *hir_id,
+                name: name.clone(),
+            },
+            HirStmt::Match {
+                hir_id,
+                subject,
+                arms,
+            } => {
+                let subject = self.resolve_expr(subject);
+                let arms = arms
+                    .iter()
+                    .map(|arm| {
+                        // Names bound by the pattern are visible in the arm body only
+                        self.enter_scope();
+                        let pattern = self.resolve_pattern(&arm.pattern);
+                        let body = arm.body.iter().map(|st| self.resolve_stmt(st)).collect();
+                        self.leave_scope();
+                        SMatchArm {
+                            hir_id: arm.hir_id,
+                            pattern,
+                            body,
+                        }
+                    })
+                    .collect();
+                SStmt::Match {
+                    hir_id: *hir_id,
+                    subject,
+                    arms,
+                }
+            }
+            HirStmt::Try {
+                hir_id,
+                body,
+                handlers,
+                finally,
+            } => SStmt::Try {
+                hir_id: *hir_id,
+                body: body.iter().map(|st| self.resolve_stmt(st)).collect(),
+                handlers: handlers.iter().map(|h| self.resolve_handler(h)).collect(),
+                finally: finally.iter().map(|st| self.resolve_stmt(st)).collect(),
+            },
+            HirStmt::Raise {
+                hir_id,
+                kind,
+                message,
+            } => {
+                match kind {
+                    Some(kind) => self.check_exception_kind(*hir_id, kind),
+                    None if self.except_depth == 0 => {
+                        let span = self.spans.get(hir_id).cloned().unwrap_or_default();
+                        self.report
+                            .errors
+                            .push(ResolveError::ReraiseOutsideExcept { span });
+                    }
+                    None => {}
+                }
+                SStmt::Raise {
+                    hir_id: *hir_id,
+                    kind: kind.clone(),
+                    message: message.as_ref().map(|e| self.resolve_expr(e)),
+                }
+            }
+            HirStmt::Return { hir_id, expr } => {
+                if self.func_scope.is_none() {
+                    let span = self.spans.get(hir_id).cloned().unwrap_or_default();
+                    self.report
+                        .errors
+                        .push(ResolveError::OutsideFunction { span });
+                }
+                SStmt::Return {
+                    hir_id: *hir_id,
+                    expr: expr.as_ref().map(|e| self.resolve_expr(e)),
+                }
+            }
             HirStmt::ForRange {
                 hir_id,
                 var,
@@ -60,14 +197,13 @@ impl Resolver {
                 end,
                 body,
             } => {
-                let scope = self.current_scope();
-                let sym = self
-                    .syms
-                    .lookup(scope, var)
-                    .unwrap_or_else(|| self.syms.define(scope, var, SymKind::LocalVar));
                 let s = self.resolve_expr(start);
                 let e = self.resolve_expr(end);
-                let body_resolved = body.iter().map(|st| self.resolve_stmt(st)).collect();
+                // Like the generated Rust `for`, the loop variable is not visible after the loop
+                let scope = self.enter_scope();
+                let sym = self.syms.define(scope, var, SymKind::LocalVar);
+                let body_resolved = self.resolve_loop_body(body);
+                self.leave_scope();
                 SStmt::ForRange {
                     hir_id: *hir_id,
                     sym,
@@ -76,6 +212,44 @@ impl Resolver {
                     body: body_resolved,
                 }
             }
+            HirStmt::ForEach {
+                hir_id,
+                vars,
+                iter,
+                body,
+            } => {
+                let it = self.resolve_expr(iter);
+                let scope = self.enter_scope();
+                let syms = vars
+                    .iter()
+                    .map(|var| self.syms.define(scope, var, SymKind::LocalVar))
+                    .collect();
+                let body_resolved = self.resolve_loop_body(body);
+                self.leave_scope();
+                SStmt::ForEach {
+                    hir_id: *hir_id,
+                    syms,
+                    iter: it,
+                    body: body_resolved,
+                }
+            }
+            HirStmt::While { hir_id, cond, body } => {
+                let c = self.resolve_expr(cond);
+                let body_resolved = self.resolve_loop_body(body);
+                SStmt::While {
+                    hir_id: *hir_id,
+                    cond: c,
+                    body: body_resolved,
+                }
+            }
+            HirStmt::Break { hir_id } => {
+                self.check_inside_loop(*hir_id, "break");
+                SStmt::Break { hir_id: *hir_id }
+            }
+            HirStmt::Continue { hir_id } => {
+                self.check_inside_loop(*hir_id, "continue");
+                SStmt::Continue { hir_id: *hir_id }
+            }
             HirStmt::If {
                 hir_id,
                 cond,
@@ -101,4 +275,150 @@ impl Resolver {
             }
         }
     }
+
+    /// Resolve the parameters and body of a function or method defined as `sym`.
+    fn resolve_func(
+        &mut self,
+        hir_id: HirId,
+        sym: SymbolId,
+        params: &[Param],
+        body: &[HirStmt],
+    ) -> SStmt {
+        let scope = self.enter_scope();
+        let params = params
+            .iter()
+            .map(|p| self.syms.define(scope, &p.name, SymKind::LocalVar))
+            .collect();
+        self.collect_defs(body);
+        // Loops around the definition do not enclose the body
+        let outer_func = self.func_scope.replace(scope);
+        let outer_loops = std::mem::take(&mut self.loop_depth);
+        let outer_excepts = std::mem::take(&mut self.except_depth);
+        let body = body.iter().map(|st| self.resolve_stmt(st)).collect();
+        self.except_depth = outer_excepts;
+        self.loop_depth = outer_loops;
+        self.func_scope = outer_func;
+        self.leave_scope();
+        SStmt::FuncDef {
+            hir_id,
+            sym,
+            params,
+            body,
+        }
+    }
+
+    fn resolve_handler(&mut self, handler: &HirExceptHandler) -> SExceptHandler {
+        if let Some(kind) = &handler.kind {
+            self.check_exception_kind(handler.hir_id, kind);
+        }
+        let scope = self.enter_scope();
+        let sym = handler
+            .name
+            .as_ref()
+            .map(|name| self.syms.define(scope, name, SymKind::LocalVar));
+        self.except_depth += 1;
+        let body = handler
+            .body
+            .iter()
+            .map(|st| self.resolve_stmt(st))
+            .collect();
+        self.except_depth -= 1;
+        self.leave_scope();
+        SExceptHandler {
+            hir_id: handler.hir_id,
+            kind: handler.kind.clone(),
+            sym,
+            body,
+        }
+    }
+
+    fn check_exception_kind(&mut self, hir_id: HirId, kind: &str) {
+        if !EXCEPTION_KINDS.contains(&kind) {
+            let span = self.spans.get(&hir_id).cloned().unwrap_or_default();
+            self.report.errors.push(ResolveError::UnknownException {
+                span,
+                name: kind.to_string(),
+            });
+        }
+    }
+
+    fn resolve_pattern(&mut self, pattern: &HirPattern) -> SPattern {
+        match pattern {
+            HirPattern::Wildcard { hir_id } => SPattern::Wildcard { hir_id: *hir_id },
+            HirPattern::Bind { hir_id, name } => {
+                let scope = self.current_scope();
+                SPattern::Bind {
+                    hir_id: *hir_id,
+                    sym: self.syms.define(scope, name, SymKind::LocalVar),
+                }
+            }
+            HirPattern::Literal { hir_id, value } => SPattern::Literal {
+                hir_id: *hir_id,
+                value: self.resolve_expr(value),
+            },
+            HirPattern::Variant {
+                hir_id,
+                enum_name,
+                variant,
+                args,
+            } => {
+                if !self.syms.enums.contains_key(enum_name) {
+                    let span = self.spans.get(hir_id).cloned().unwrap_or_default();
+                    self.report.errors.push(ResolveError::UnknownType {
+                        span,
+                        name: enum_name.clone(),
+                    });
+                }
+                SPattern::Variant {
+                    hir_id: *hir_id,
+                    enum_name: enum_name.clone(),
+                    variant: variant.clone(),
+                    args: args.iter().map(|p| self.resolve_pattern(p)).collect(),
+                }
+            }
+            HirPattern::Tuple { hir_id, items } => SPattern::Tuple {
+                hir_id: *hir_id,
+                items: items.iter().map(|p| self.resolve_pattern(p)).collect(),
+            },
+        }
+    }
+
+    /// The symbol an assignment writes to; `collect_defs` has already defined it.
+    fn lookup_assigned(&mut self, hir_id: HirId, name: &str) -> SymbolId {
+        let scope = self.current_scope();
+        self.syms
+            .lookup(scope, name)
+            .or_else(|| self.builtins.get(name).copied())
+            .unwrap_or_else(|| {
+                let kind = if scope == self.global_scope() {
+                    SymKind::GlobalVar
+                } else {
+                    SymKind::LocalVar
+                };
+                let sid = self.syms.define(scope, name, kind);
+                let span = self.spans.get(&hir_id).cloned().unwrap_or_default();
+                self.report.errors.push(ResolveError::UnresolvedName {
+                    span,
+                    name: name.to_string(),
+                });
+                sid
+            })
+    }
+
+    fn resolve_loop_body(&mut self, body: &[HirStmt]) -> Vec<SStmt> {
+        self.loop_depth += 1;
+        let resolved = body.iter().map(|st| self.resolve_stmt(st)).collect();
+        self.loop_depth -= 1;
+        resolved
+    }
+
+    fn check_inside_loop(&mut self, hir_id: HirId, keyword: &str) {
+        if self.loop_depth == 0 {
+            let span = self.spans.get(&hir_id).cloned().unwrap_or_default();
+            self.report.errors.push(ResolveError::OutsideLoop {
+                span,
+                keyword: keyword.to_string(),
+            });
+        }
+    }
 }
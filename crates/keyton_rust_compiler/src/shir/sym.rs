@@ -14,20 +14,143 @@ pub enum SymKind {
     BuiltinFunc,
 }
 
+/// Exception kinds `raise` and `except` accept. `Exception` matches every kind; the
+/// others are raised by failing operations, e.g. `IndexError` by a list index out of
+/// range, and by plugin functions returning an error.
+pub const EXCEPTION_KINDS: &[&str] = &[
+    "Exception",
+    "RuntimeError",
+    "IndexError",
+    "KeyError",
+    "ValueError",
+    "TypeError",
+    "ZeroDivisionError",
+    "NotFoundError",
+];
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Type {
     I64,
+    F64,
     Str,
+    Bool,
     Unit,
+    /// `list[T]`; the element type is `Any` until an element is known, as in `[]`
+    List(Box<Type>),
+    /// `dict[K, V]`; like lists, either part is `Any` until an entry is known
+    Dict(Box<Type>, Box<Type>),
+    /// `tuple[A, B, ...]`, as produced by `items()`
+    Tuple(Vec<Type>),
+    /// A range of `i64` values, `start..end`
+    Range,
+    /// `iter[T]`: an iterator over `T` items returned by a plugin function
+    Iter(Box<Type>),
+    /// An instance of a user struct, by name; see `SymbolTable::structs`
+    Struct(String),
+    /// A value of a user enum, by name; see `SymbolTable::enums`
+    Enum(String),
+    /// `T?`: a `T` or `None`. A lone `None` is `Option(Any)`
+    Option(Box<Type>),
+    /// `fn(A, B) -> R`: a function or lambda used as a value
+    Func(Vec<Type>, Box<Type>),
     Any,
 }
 
+impl Type {
+    /// The more specific of two compatible types, filling in `Any` parts from the other:
+    /// `list[any]` and `list[i64]` join to `list[i64]`. `None` if they do not fit.
+    pub fn join(&self, other: &Type) -> Option<Type> {
+        match (self, other) {
+            (Type::Any, t) | (t, Type::Any) => Some(t.clone()),
+            (Type::List(a), Type::List(b)) => Some(Type::List(Box::new(a.join(b)?))),
+            (Type::Option(a), Type::Option(b)) => Some(Type::Option(Box::new(a.join(b)?))),
+            (Type::Func(p1, r1), Type::Func(p2, r2)) if p1.len() == p2.len() => Some(Type::Func(
+                p1.iter()
+                    .zip(p2)
+                    .map(|(x, y)| x.join(y))
+                    .collect::<Option<_>>()?,
+                Box::new(r1.join(r2)?),
+            )),
+            (Type::Dict(k1, v1), Type::Dict(k2, v2)) => {
+                Some(Type::Dict(Box::new(k1.join(k2)?), Box::new(v1.join(v2)?)))
+            }
+            (Type::Tuple(a), Type::Tuple(b)) if a.len() == b.len() => Some(Type::Tuple(
+                a.iter()
+                    .zip(b)
+                    .map(|(x, y)| x.join(y))
+                    .collect::<Option<_>>()?,
+            )),
+            (a, b) if a == b => Some(a.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::I64 => write!(f, "i64"),
+            Type::F64 => write!(f, "f64"),
+            Type::Str => write!(f, "str"),
+            Type::Bool => write!(f, "bool"),
+            Type::Unit => write!(f, "unit"),
+            Type::List(elem) => write!(f, "list[{}]", elem),
+            Type::Dict(key, value) => write!(f, "dict[{}, {}]", key, value),
+            Type::Tuple(items) => {
+                let items: Vec<String> = items.iter().map(|t| t.to_string()).collect();
+                write!(f, "tuple[{}]", items.join(", "))
+            }
+            Type::Range => write!(f, "range"),
+            Type::Iter(item) => write!(f, "iter[{}]", item),
+            Type::Struct(name) | Type::Enum(name) => write!(f, "{}", name),
+            Type::Option(inner) => write!(f, "Option[{}]", inner),
+            Type::Func(params, ret) => {
+                let params: Vec<String> = params.iter().map(|t| t.to_string()).collect();
+                write!(f, "fn({}) -> {}", params.join(", "), ret)
+            }
+            Type::Any => write!(f, "any"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FuncSig {
     pub params: Vec<Type>,
     pub ret: Type,
 }
 
+/// Fields and methods of a user struct. Methods are `Func` symbols whose first
+/// parameter is `self`.
+#[derive(Debug, Clone, Default)]
+pub struct StructInfo {
+    pub fields: Vec<(String, Type)>,
+    pub methods: HashMap<String, SymbolId>,
+}
+
+impl StructInfo {
+    pub fn field(&self, name: &str) -> Option<&Type> {
+        self.fields
+            .iter()
+            .find(|(f, _)| f == name)
+            .map(|(_, ty)| ty)
+    }
+}
+
+/// Variants of a user enum in declaration order, each with its payload types.
+#[derive(Debug, Clone, Default)]
+pub struct EnumInfo {
+    pub variants: Vec<(String, Vec<Type>)>,
+}
+
+impl EnumInfo {
+    pub fn variant(&self, name: &str) -> Option<&[Type]> {
+        self.variants
+            .iter()
+            .find(|(v, _)| v == name)
+            .map(|(_, fields)| fields.as_slice())
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SymInfo {
     pub name: String,
@@ -46,6 +169,10 @@ pub struct Scope {
 pub struct SymbolTable {
     pub infos: Vec<SymInfo>,
     pub scopes: Vec<Scope>,
+    /// User structs by name
+    pub structs: HashMap<String, StructInfo>,
+    /// User enums by name
+    pub enums: HashMap<String, EnumInfo>,
 }
 
 impl SymbolTable {
@@ -60,6 +187,8 @@ impl SymbolTable {
             Self {
                 infos: Vec::new(),
                 scopes,
+                structs: HashMap::new(),
+                enums: HashMap::new(),
             },
             ScopeId(0),
         )
@@ -1,5 +1,6 @@
 use super::resolver::ResolveError;
 use super::*;
+use crate::format_spec::FormatSpec;
 use crate::hir::hir_types::{HirBinOp, HirId};
 use crate::hir::{lower_program, lower_program_with_spans};
 use crate::lexer::Lexer;
@@ -11,8 +12,8 @@ fn program1_shir() {
 x = x + 1
 print(x)
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     let resolved = resolve_program(&hir);
 
@@ -22,6 +23,7 @@ print(x)
             SStmt::Assign {
                 hir_id: HirId(1),
                 sym: SymbolId(1),
+                ty: None,
                 expr: SExpr::Int {
                     hir_id: HirId(2),
                     value: 12,
@@ -30,6 +32,7 @@ print(x)
             SStmt::Assign {
                 hir_id: HirId(3),
                 sym: SymbolId(1),
+                ty: None,
                 expr: SExpr::Binary {
                     hir_id: HirId(4),
                     left: Box::new(SExpr::Name {
@@ -74,8 +77,8 @@ fn program4_shir() {
 x = "Hello"
 print(x)
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     let resolved = resolve_program(&hir);
 
@@ -85,6 +88,7 @@ print(x)
             SStmt::Assign {
                 hir_id: HirId(1),
                 sym: SymbolId(1),
+                ty: None,
                 expr: SExpr::Int {
                     hir_id: HirId(2),
                     value: 12,
@@ -93,6 +97,7 @@ print(x)
             SStmt::Assign {
                 hir_id: HirId(3),
                 sym: SymbolId(1),
+                ty: None,
                 expr: SExpr::Str {
                     hir_id: HirId(4),
                     value: "Hello".to_string(),
@@ -126,8 +131,8 @@ print(x)
 #[test]
 fn program2_shir() {
     let input = r#"print("Hello, World")"#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     let resolved = resolve_program(&hir);
     assert_eq!(
@@ -154,8 +159,8 @@ fn program3_shir() {
     let input = r#"x = 12
 print(f"{x}")
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     let resolved = resolve_program(&hir);
     assert_eq!(
@@ -164,6 +169,7 @@ print(f"{x}")
             SStmt::Assign {
                 hir_id: HirId(1),
                 sym: SymbolId(1),
+                ty: None,
                 expr: SExpr::Int {
                     hir_id: HirId(2),
                     value: 12,
@@ -190,6 +196,7 @@ print(f"{x}")
                                     hir_id: HirId(9),
                                     sym: SymbolId(1),
                                 },
+                                spec: FormatSpec::default(),
                             },
                             SStringPart::Text {
                                 hir_id: HirId(10),
@@ -207,8 +214,8 @@ print(f"{x}")
 fn unresolved_name_reports_error() {
     // x is used but never defined; should be reported and given a fresh symbol id
     let input = r#"y = x"#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let (hir, spans) = lower_program_with_spans(ast);
 
     let mut resolver = Resolver::new(spans);
@@ -220,6 +227,7 @@ fn unresolved_name_reports_error() {
         vec![SStmt::Assign {
             hir_id: HirId(1),
             sym: SymbolId(0),
+            ty: None,
             expr: SExpr::Name {
                 hir_id: HirId(2),
                 sym: SymbolId(1),
@@ -230,10 +238,10 @@ fn unresolved_name_reports_error() {
     assert_eq!(resolver.report.errors.len(), 1);
     match &resolver.report.errors[0] {
         ResolveError::UnresolvedName { span, name } => {
-            assert_eq!(*span, crate::span::Span::new(2, 2));
+            assert_eq!(*span, crate::span::Span::new(4, 5));
             assert_eq!(name, "x");
         }
-        ResolveError::ImportError { .. } => {}
+        other => panic!("unexpected resolve error: {:?}", other),
     }
 
     // Symbols: y then x, both globals in scope 0
@@ -248,8 +256,8 @@ fn unresolved_name_reports_error() {
 fn unresolved_name_in_call_reports_error() {
     // x is used in a call but never defined; should be reported
     let input = r#"print(x)"#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let (hir, spans) = lower_program_with_spans(ast);
 
     let mut resolver = Resolver::new(spans);
@@ -279,7 +287,7 @@ fn unresolved_name_in_call_reports_error() {
         ResolveError::UnresolvedName { name, .. } => {
             assert_eq!(name, "x");
         }
-        ResolveError::ImportError { .. } => {}
+        other => panic!("unexpected resolve error: {:?}", other),
     }
 
     // Symbols: print (builtin) then x (global)
@@ -289,3 +297,122 @@ fn unresolved_name_in_call_reports_error() {
     assert_eq!(resolver.syms.infos[0].kind, SymKind::BuiltinFunc);
     assert_eq!(resolver.syms.infos[1].kind, SymKind::GlobalVar);
 }
+
+#[test]
+fn break_and_continue_outside_loop_are_rejected() {
+    let input = "i = 0\nwhile i < 3:\n    i += 1\n    continue\nbreak\nif i == 3:\n    continue\n";
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
+    let (hir, spans) = lower_program_with_spans(ast);
+    let resolved = resolve_program_with_spans(&hir, spans);
+
+    let errors: Vec<(String, crate::span::Span)> = resolved
+        .errors
+        .iter()
+        .map(|e| match e {
+            ResolveError::OutsideLoop { span, keyword } => (keyword.clone(), *span),
+            other => panic!("unexpected resolve error: {:?}", other),
+        })
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            ("break".to_string(), crate::span::Span::new(43, 48)),
+            ("continue".to_string(), crate::span::Span::new(64, 72)),
+        ]
+    );
+}
+
+#[test]
+fn functions_get_their_own_scope() {
+    let input = "x = 1\nfn f(x):\n    y = x + 1\n    y\nfn g():\n    x\nreturn 2\n";
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
+    let (hir, spans) = lower_program_with_spans(ast);
+    let resolved = resolve_program_with_spans(&hir, spans);
+
+    let SStmt::FuncDef { params, body, .. } = &resolved.shir[1] else {
+        panic!("expected function definition");
+    };
+    let SStmt::Assign { sym, .. } = &body[0] else {
+        panic!("expected assignment");
+    };
+    for local in [params[0], *sym] {
+        assert_eq!(
+            resolved.symbols.infos[local.0 as usize].kind,
+            SymKind::LocalVar
+        );
+    }
+    let SStmt::Assign { sym: global_x, .. } = &resolved.shir[0] else {
+        panic!("expected assignment");
+    };
+    assert_ne!(params[0], *global_x);
+
+    let errors: Vec<String> = resolved
+        .errors
+        .iter()
+        .map(|e| match e {
+            ResolveError::CapturedVariable { span, name } => format!("{}@{}", name, span.start),
+            ResolveError::OutsideFunction { span } => format!("return@{}", span.start),
+            other => panic!("unexpected resolve error: {:?}", other),
+        })
+        .collect();
+    assert_eq!(errors, vec!["x@47".to_string(), "return@49".to_string()]);
+}
+
+#[test]
+fn loop_variable_is_scoped_to_its_loop() {
+    let input = "for i in 0..3:\n    if i > 1:\n        last = i\nprint(i)\n";
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
+    let (hir, spans) = lower_program_with_spans(ast);
+    let resolved = resolve_program_with_spans(&hir, spans);
+
+    let SStmt::ForRange { sym, body, .. } = &resolved.shir[0] else {
+        panic!("expected for loop");
+    };
+    let loop_var = &resolved.symbols.infos[sym.0 as usize];
+    assert_eq!(loop_var.kind, SymKind::LocalVar);
+    assert_ne!(loop_var.scope, ScopeId(0));
+
+    let SStmt::If { then_branch, .. } = &body[0] else {
+        panic!("expected if");
+    };
+    let SStmt::Assign { sym: last, .. } = &then_branch[0] else {
+        panic!("expected assignment");
+    };
+    assert_eq!(
+        resolved.symbols.infos[last.0 as usize].kind,
+        SymKind::GlobalVar
+    );
+
+    let errors: Vec<String> = resolved
+        .errors
+        .iter()
+        .map(|e| match e {
+            ResolveError::UnresolvedName { name, .. } => name.clone(),
+            other => panic!("unexpected resolve error: {:?}", other),
+        })
+        .collect();
+    assert_eq!(errors, vec!["i".to_string()]);
+}
+
+#[test]
+fn unknown_exceptions_and_bare_raise_outside_except_are_rejected() {
+    let input = "try:\n    raise Oops(\"x\")\nexcept Missing:\n    raise\nraise\n";
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
+    let (hir, spans) = lower_program_with_spans(ast);
+    let resolved = resolve_program_with_spans(&hir, spans);
+
+    let errors: Vec<String> = resolved
+        .errors
+        .iter()
+        .map(|e| match e {
+            ResolveError::UnknownException { name, .. } => name.clone(),
+            ResolveError::ReraiseOutsideExcept { span } => format!("raise at {}", span.start),
+            other => panic!("unexpected resolve error: {:?}", other),
+        })
+        .collect();
+    assert_eq!(errors, vec!["Oops", "Missing", "raise at 51"]);
+}
@@ -1,5 +1,6 @@
-use super::sym::SymbolId;
-use crate::hir::hir_types::{HirBinOp, HirId};
+use super::sym::{SymbolId, Type};
+use crate::format_spec::FormatSpec;
+use crate::hir::hir_types::{HirBinOp, HirId, HirUnaryOp};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum SStmt {
@@ -15,6 +16,25 @@ pub enum SStmt {
     Assign {
         hir_id: HirId,
         sym: SymbolId,
+        /// Declared type, if the assignment was annotated
+        ty: Option<Type>,
+        expr: SExpr,
+    },
+    IndexAssign {
+        hir_id: HirId,
+        target: SExpr,
+        index: SExpr,
+        expr: SExpr,
+    },
+    TupleAssign {
+        hir_id: HirId,
+        syms: Vec<SymbolId>,
+        expr: SExpr,
+    },
+    FieldAssign {
+        hir_id: HirId,
+        target: SExpr,
+        field: String,
         expr: SExpr,
     },
     ExprStmt {
@@ -28,12 +48,114 @@ pub enum SStmt {
         end: SExpr,
         body: Vec<SStmt>,
     },
+    /// Loop over the items of an iterable; `syms` are locals of the loop scope and
+    /// unpack each item when there are several
+    ForEach {
+        hir_id: HirId,
+        syms: Vec<SymbolId>,
+        iter: SExpr,
+        body: Vec<SStmt>,
+    },
+    While {
+        hir_id: HirId,
+        cond: SExpr,
+        body: Vec<SStmt>,
+    },
+    Break {
+        hir_id: HirId,
+    },
+    Continue {
+        hir_id: HirId,
+    },
     If {
         hir_id: HirId,
         cond: SExpr,
         then_branch: Vec<SStmt>,
         else_branch: Vec<SStmt>,
     },
+    /// A user function; `params` are locals of the function's own scope
+    FuncDef {
+        hir_id: HirId,
+        sym: SymbolId,
+        params: Vec<SymbolId>,
+        body: Vec<SStmt>,
+    },
+    Return {
+        hir_id: HirId,
+        expr: Option<SExpr>,
+    },
+    /// A user struct; its fields are in `SymbolTable::structs` and `methods` are
+    /// `FuncDef`s
+    StructDef {
+        hir_id: HirId,
+        name: String,
+        methods: Vec<SStmt>,
+    },
+    /// A user enum; its variants are in `SymbolTable::enums`
+    EnumDef {
+        hir_id: HirId,
+        name: String,
+    },
+    Match {
+        hir_id: HirId,
+        subject: SExpr,
+        arms: Vec<SMatchArm>,
+    },
+    Try {
+        hir_id: HirId,
+        body: Vec<SStmt>,
+        handlers: Vec<SExceptHandler>,
+        finally: Vec<SStmt>,
+    },
+    /// `kind` is one of `EXCEPTION_KINDS`; a bare `raise` has neither kind nor message
+    Raise {
+        hir_id: HirId,
+        kind: Option<String>,
+        message: Option<SExpr>,
+    },
+}
+
+/// `except Kind as name:`; `kind` is `None` for a bare `except:`, which, like
+/// `except Exception:`, handles every exception.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SExceptHandler {
+    pub hir_id: HirId,
+    pub kind: Option<String>,
+    pub sym: Option<SymbolId>,
+    pub body: Vec<SStmt>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SMatchArm {
+    pub hir_id: HirId,
+    pub pattern: SPattern,
+    pub body: Vec<SStmt>,
+}
+
+/// A `case` pattern; `Bind` defines a variable local to its arm.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SPattern {
+    Wildcard {
+        hir_id: HirId,
+    },
+    Bind {
+        hir_id: HirId,
+        sym: SymbolId,
+    },
+    Literal {
+        hir_id: HirId,
+        value: SExpr,
+    },
+    Variant {
+        hir_id: HirId,
+        enum_name: String,
+        variant: String,
+        args: Vec<SPattern>,
+    },
+    Tuple {
+        hir_id: HirId,
+        items: Vec<SPattern>,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -42,6 +164,10 @@ pub enum SExpr {
         hir_id: HirId,
         value: i64,
     },
+    Float {
+        hir_id: HirId,
+        value: f64,
+    },
     Str {
         hir_id: HirId,
         value: String,
@@ -50,6 +176,9 @@ pub enum SExpr {
         hir_id: HirId,
         value: bool,
     },
+    None {
+        hir_id: HirId,
+    },
     Name {
         hir_id: HirId,
         sym: SymbolId,
@@ -60,19 +189,89 @@ pub enum SExpr {
         op: HirBinOp,
         right: Box<SExpr>,
     },
+    Unary {
+        hir_id: HirId,
+        op: HirUnaryOp,
+        expr: Box<SExpr>,
+    },
     Call {
         hir_id: HirId,
         func: Box<SExpr>,
         args: Vec<SExpr>,
     },
+    Index {
+        hir_id: HirId,
+        target: Box<SExpr>,
+        index: Box<SExpr>,
+    },
+    Slice {
+        hir_id: HirId,
+        target: Box<SExpr>,
+        start: Option<Box<SExpr>>,
+        end: Option<Box<SExpr>>,
+    },
     InterpolatedString {
         hir_id: HirId,
         parts: Vec<SStringPart>,
     },
+    Dict {
+        hir_id: HirId,
+        entries: Vec<(SExpr, SExpr)>,
+    },
+    Tuple {
+        hir_id: HirId,
+        items: Vec<SExpr>,
+    },
+    Range {
+        hir_id: HirId,
+        start: Box<SExpr>,
+        end: Box<SExpr>,
+    },
+    Field {
+        hir_id: HirId,
+        target: Box<SExpr>,
+        name: String,
+    },
+    /// `target.method(args)`. Which struct the method belongs to depends on the type of
+    /// `target`; `func` is the free function called with the target as its first
+    /// argument when the target is not a struct, as in `xs.append(1)`.
+    MethodCall {
+        hir_id: HirId,
+        target: Box<SExpr>,
+        method: String,
+        func: Option<SymbolId>,
+        args: Vec<SExpr>,
+    },
+    Construct {
+        hir_id: HirId,
+        name: String,
+        fields: Vec<(String, SExpr)>,
+    },
+    /// `Shape.Circle(r)`, or `Shape.Empty` without arguments
+    Variant {
+        hir_id: HirId,
+        enum_name: String,
+        variant: String,
+        args: Vec<SExpr>,
+    },
+    /// `param_types` are the annotated types, `Any` where a parameter has none
+    Lambda {
+        hir_id: HirId,
+        params: Vec<SymbolId>,
+        param_types: Vec<Type>,
+        body: Box<SExpr>,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum SStringPart {
-    Text { hir_id: HirId, value: String },
-    Expr { hir_id: HirId, expr: SExpr },
+    Text {
+        hir_id: HirId,
+        value: String,
+    },
+    Expr {
+        hir_id: HirId,
+        expr: SExpr,
+        spec: FormatSpec,
+    },
 }
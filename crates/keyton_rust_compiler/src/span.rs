@@ -1,3 +1,4 @@
+/// Half-open byte range `[start, end)` into the source text.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
 pub struct Span {
     pub start: usize,
@@ -8,6 +9,11 @@ impl Span {
     pub fn new(start: usize, end: usize) -> Self {
         Self { start, end }
     }
+
+    /// Smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -21,3 +27,50 @@ impl<T> Spanned<T> {
         Self { node, span }
     }
 }
+
+/// Maps byte offsets to 1-based line and column numbers. Columns count characters,
+/// so a multi-byte character such as `я` or `数` is one column.
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    source: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let mut line_starts = vec![0];
+        for (i, b) in source.bytes().enumerate() {
+            if b == b'\n' {
+                line_starts.push(i + 1);
+            }
+        }
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    /// 1-based (line, column) of a byte offset. Offsets past the end clamp to the last position.
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.source.len());
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(i) => i,
+            Err(i) => i - 1,
+        };
+        let before = &self.source.as_bytes()[self.line_starts[line]..offset];
+        // Count the bytes that start a character, skipping UTF-8 continuation bytes
+        let col = before.iter().filter(|b| !(0x80..0xC0).contains(*b)).count();
+        (line + 1, col + 1)
+    }
+
+    /// Byte range of a 1-based line, excluding its trailing newline.
+    pub fn line_range(&self, line: usize) -> Span {
+        let start = self.line_starts[line - 1];
+        let end = self
+            .line_starts
+            .get(line)
+            .map(|next| next - 1)
+            .unwrap_or(self.source.len());
+        Span::new(start, end)
+    }
+}
@@ -1,15 +1,20 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use crate::hir::hir_types::{HirBinOp, HirId};
+use crate::format_spec::FormatSpec;
+use crate::hir::hir_types::{HirBinOp, HirId, HirUnaryOp};
 use crate::shir::resolver::ResolvedProgram;
-use crate::shir::sym::{ScopeId, SymKind, SymbolId, SymbolTable, Type};
-use crate::shir::types::{SExpr, SStmt, SStringPart};
+use crate::shir::sym::{FuncSig, ScopeId, SymKind, SymbolId, SymbolTable, Type};
+use crate::shir::types::{SExpr, SPattern, SStmt, SStringPart};
 
-use super::types::{TExpr, TStmt, TStringPart, TypeError, TypeReport, TypedProgram};
+use super::patterns::missing_case;
+use super::types::{
+    TExceptHandler, TExpr, TMatchArm, TPattern, TStmt, TStringPart, TypeError, TypeReport,
+    TypedProgram,
+};
 
 pub fn typecheck_program(resolved: &mut ResolvedProgram) -> TypedProgram {
     let mut c = Checker::new(&mut resolved.symbols);
-    let thir = resolved.shir.iter().map(|s| c.check_stmt(s)).collect();
+    let thir = c.check_program(&resolved.shir);
     TypedProgram {
         thir,
         var_types: c.var_types,
@@ -35,7 +40,7 @@ pub fn typecheck_program_with_env(
         c.var_types.insert(sid, ty.clone());
     }
 
-    let thir = resolved.shir.iter().map(|s| c.check_stmt(s)).collect();
+    let thir = c.check_program(&resolved.shir);
     TypedProgram {
         thir,
         var_types: c.var_types,
@@ -43,11 +48,42 @@ pub fn typecheck_program_with_env(
     }
 }
 
+/// A user function definition, kept until a call decides its parameter types.
+#[derive(Clone)]
+struct FuncDef {
+    hir_id: HirId,
+    params: Vec<SymbolId>,
+    body: Vec<SStmt>,
+}
+
+/// Return type information of the function whose body is being checked.
+struct ReturnCtx {
+    func: SymbolId,
+    /// Annotated return type, `Any` when it is inferred from the `return` statements
+    declared: Type,
+    found: Option<Type>,
+}
+
 struct Checker<'a> {
     symbols: &'a mut SymbolTable,
     var_types: HashMap<SymbolId, Type>,
+    /// Variables whose type was fixed by an annotation; they cannot be shadowed by
+    /// assigning a value of another type.
+    declared: HashMap<SymbolId, Type>,
     errors: Vec<TypeError>,
-    current_scope: ScopeId,
+    funcs: HashMap<SymbolId, FuncDef>,
+    /// Definition order of `funcs`, so uncalled functions are checked deterministically
+    func_order: Vec<SymbolId>,
+    /// Checked function bodies; `None` while the body is being checked
+    instances: HashMap<SymbolId, Option<TStmt>>,
+    returns: Vec<ReturnCtx>,
+    /// Set while checking functions that are never called; their parameter types are unknown
+    speculative: bool,
+    /// Optional variables known not to be `None` here, after `if x is not None:`
+    narrowed: HashSet<SymbolId>,
+    /// Loops around the statement being checked; a variable cannot change its type
+    /// inside one, since the next iteration would see the old variable
+    loop_depth: usize,
 }
 
 impl<'a> Checker<'a> {
@@ -55,11 +91,221 @@ impl<'a> Checker<'a> {
         Self {
             symbols,
             var_types: HashMap::new(),
+            declared: HashMap::new(),
             errors: Vec::new(),
-            current_scope: ScopeId(0),
+            funcs: HashMap::new(),
+            func_order: Vec::new(),
+            instances: HashMap::new(),
+            returns: Vec::new(),
+            speculative: false,
+            narrowed: HashSet::new(),
+            loop_depth: 0,
+        }
+    }
+
+    fn check_program(&mut self, shir: &[SStmt]) -> Vec<TStmt> {
+        self.collect_funcs(shir);
+        let mut thir: Vec<TStmt> = shir.iter().map(|s| self.check_stmt(s)).collect();
+
+        // Functions that were never called are still checked, with their annotated
+        // parameter types or `Any`
+        self.speculative = true;
+        for sym in self.func_order.clone() {
+            if !self.instances.contains_key(&sym) {
+                self.instantiate(sym, &[]);
+            }
+        }
+        self.speculative = false;
+
+        self.fill_funcs(&mut thir);
+        thir
+    }
+
+    fn collect_funcs(&mut self, stmts: &[SStmt]) {
+        for s in stmts {
+            match s {
+                SStmt::FuncDef {
+                    hir_id,
+                    sym,
+                    params,
+                    body,
+                } => {
+                    self.funcs.insert(
+                        *sym,
+                        FuncDef {
+                            hir_id: *hir_id,
+                            params: params.clone(),
+                            body: body.clone(),
+                        },
+                    );
+                    self.func_order.push(*sym);
+                    self.collect_funcs(body);
+                }
+                SStmt::ForRange { body, .. }
+                | SStmt::ForEach { body, .. }
+                | SStmt::While { body, .. } => self.collect_funcs(body),
+                SStmt::StructDef { methods, .. } => self.collect_funcs(methods),
+                SStmt::Match { arms, .. } => {
+                    for arm in arms {
+                        self.collect_funcs(&arm.body);
+                    }
+                }
+                SStmt::Try {
+                    body,
+                    handlers,
+                    finally,
+                    ..
+                } => {
+                    self.collect_funcs(body);
+                    for handler in handlers {
+                        self.collect_funcs(&handler.body);
+                    }
+                    self.collect_funcs(finally);
+                }
+                SStmt::If {
+                    then_branch,
+                    else_branch,
+                    ..
+                } => {
+                    self.collect_funcs(then_branch);
+                    self.collect_funcs(else_branch);
+                }
+                _ => {}
+            }
         }
     }
 
+    /// Check the body of a user function. Unannotated parameters take the types of
+    /// `args`, the arguments of the first call or the parameters of the function type
+    /// the function is passed as.
+    fn instantiate(&mut self, sym: SymbolId, args: &[(HirId, Type)]) {
+        let Some(def) = self.funcs.get(&sym).cloned() else {
+            return;
+        };
+        self.instances.insert(sym, None);
+
+        let mut sig = self.symbols.infos[sym.0 as usize]
+            .sig
+            .clone()
+            .unwrap_or(FuncSig {
+                params: vec![Type::Any; def.params.len()],
+                ret: Type::Any,
+            });
+        for (i, param) in def.params.iter().enumerate() {
+            if sig.params[i] == Type::Any
+                && let Some((arg_id, arg_ty)) = args.get(i)
+            {
+                sig.params[i] = arg_ty.clone();
+                if sig.params[i] == Type::Any && !self.speculative {
+                    self.errors.push(TypeError::CannotInferParam {
+                        hir_id: *arg_id,
+                        param: self.symbols.infos[param.0 as usize].name.clone(),
+                    });
+                }
+            }
+            self.var_types.insert(*param, sig.params[i].clone());
+        }
+        // Recursive calls see the parameter types while the body is checked
+        self.symbols.infos[sym.0 as usize].sig = Some(sig.clone());
+
+        self.returns.push(ReturnCtx {
+            func: sym,
+            declared: sig.ret.clone(),
+            found: None,
+        });
+        // The body may run anywhere, not only where it is first called
+        let narrowed = std::mem::take(&mut self.narrowed);
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        let body: Vec<TStmt> = def.body.iter().map(|st| self.check_stmt(st)).collect();
+        self.narrowed = narrowed;
+        self.loop_depth = loop_depth;
+        let ctx = self.returns.pop().expect("return context pushed above");
+
+        let ret = if ctx.declared != Type::Any {
+            ctx.declared
+        } else {
+            ctx.found.unwrap_or(Type::Unit)
+        };
+        if ret != Type::Unit && !definitely_returns(&body) {
+            self.errors.push(TypeError::MissingReturn {
+                hir_id: def.hir_id,
+                func: self.symbols.infos[sym.0 as usize].name.clone(),
+            });
+        }
+        sig.ret = ret.clone();
+        self.symbols.infos[sym.0 as usize].sig = Some(sig);
+
+        self.instances.insert(
+            sym,
+            Some(TStmt::FuncDef {
+                hir_id: def.hir_id,
+                sym,
+                params: def.params,
+                ret,
+                body,
+            }),
+        );
+    }
+
+    /// Replace the placeholders left by `check_stmt` with the checked function bodies.
+    fn fill_funcs(&mut self, stmts: &mut [TStmt]) {
+        for s in stmts {
+            match s {
+                TStmt::FuncDef { sym, .. } => {
+                    if let Some(Some(instance)) = self.instances.remove(sym) {
+                        *s = instance;
+                    }
+                    if let TStmt::FuncDef { body, .. } = s {
+                        self.fill_funcs(body);
+                    }
+                }
+                TStmt::ForRange { body, .. }
+                | TStmt::ForEach { body, .. }
+                | TStmt::While { body, .. } => self.fill_funcs(body),
+                TStmt::StructDef { methods, .. } => self.fill_funcs(methods),
+                TStmt::Match { arms, .. } => {
+                    for arm in arms {
+                        self.fill_funcs(&mut arm.body);
+                    }
+                }
+                TStmt::Try {
+                    body,
+                    handlers,
+                    finally,
+                    ..
+                } => {
+                    self.fill_funcs(body);
+                    for handler in handlers {
+                        self.fill_funcs(&mut handler.body);
+                    }
+                    self.fill_funcs(finally);
+                }
+                TStmt::If {
+                    then_branch,
+                    else_branch,
+                    ..
+                } => {
+                    self.fill_funcs(then_branch);
+                    self.fill_funcs(else_branch);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Return type of a user function, as far as it is known while its body may still
+    /// be being checked.
+    fn func_ret(&self, sym: SymbolId) -> Option<Type> {
+        if let Some(ctx) = self.returns.iter().rev().find(|c| c.func == sym) {
+            return Some(if ctx.declared != Type::Any {
+                ctx.declared.clone()
+            } else {
+                ctx.found.clone().unwrap_or(Type::Any)
+            });
+        }
+        None
+    }
+
     fn check_stmt(&mut self, s: &SStmt) -> TStmt {
         match s {
             SStmt::RImportModule { .. } | SStmt::RImportItems { .. } => {
@@ -73,40 +319,121 @@ impl<'a> Checker<'a> {
                     },
                 }
             }
-            SStmt::Assign { hir_id, sym, expr } => {
-                let texpr = self.check_expr(expr);
-                let expr_ty = texpr.ty().clone();
-
-                // Check if this symbol already has a type
-                let existing_ty = self.var_types.get(sym).cloned();
-
-                let final_sym = match existing_ty {
-                    None => {
-                        // First assignment - use the original symbol
-                        self.var_types.insert(*sym, expr_ty);
-                        *sym
-                    }
-                    Some(existing_ty) => {
-                        if existing_ty == expr_ty {
-                            // Same type - reuse the symbol
-                            *sym
-                        } else {
-                            // Different type - create a new symbol for shadowing
-                            let var_name = self.symbols.infos[sym.0 as usize].name.clone();
-                            let kind = self.symbols.infos[sym.0 as usize].kind;
-
-                            // Create new symbol with same name but different ID
-                            let new_sym =
-                                self.symbols.define_new(self.current_scope, &var_name, kind);
-                            self.var_types.insert(new_sym, expr_ty);
-                            new_sym
+            SStmt::Assign {
+                hir_id,
+                sym,
+                ty,
+                expr,
+            } => {
+                let declared = ty.clone().or_else(|| self.declared.get(sym).cloned());
+                let mut texpr = self.check_expr_expecting(expr, declared.as_ref());
+                self.narrowed.remove(sym);
+
+                let mut expr_ty = texpr.ty().clone();
+                if let Some(decl) = declared {
+                    self.coerce(texpr.hir_id(), &mut texpr, &decl);
+                    if decl != Type::Any {
+                        if ty.is_some() {
+                            self.declared.insert(*sym, decl.clone());
                         }
+                        expr_ty = decl;
                     }
-                };
+                } else if let Some(existing) = self.var_types.get(sym).cloned()
+                    && let Type::Option(inner) = &existing
+                    && inner.join(&expr_ty).is_some()
+                {
+                    // `x = None` followed by `x = 1` keeps `x` optional
+                    self.coerce(texpr.hir_id(), &mut texpr, &existing);
+                    expr_ty = texpr.ty().clone();
+                }
 
                 TStmt::Assign {
                     hir_id: *hir_id,
-                    sym: final_sym,
+                    sym: self.assign_var(*hir_id, *sym, expr_ty),
+                    expr: texpr,
+                }
+            }
+            SStmt::TupleAssign { hir_id, syms, expr } => {
+                let texpr = self.check_expr(expr);
+                let item_tys = self.unpack(texpr.hir_id(), syms.len(), texpr.ty());
+                let syms = syms
+                    .iter()
+                    .zip(item_tys)
+                    .map(|(sym, mut ty)| {
+                        self.narrowed.remove(sym);
+                        if let Some(decl) = self.declared.get(sym).cloned() {
+                            self.require(texpr.hir_id(), decl.clone(), ty);
+                            ty = decl;
+                        }
+                        self.assign_var(*hir_id, *sym, ty)
+                    })
+                    .collect();
+                TStmt::TupleAssign {
+                    hir_id: *hir_id,
+                    syms,
+                    expr: texpr,
+                }
+            }
+            SStmt::IndexAssign {
+                hir_id,
+                target,
+                index,
+                expr,
+            } => {
+                let ttarget = self.check_expr(target);
+                let tindex = match ttarget.ty() {
+                    Type::Dict(..) => self.check_expr(index),
+                    _ => self.check_index(index),
+                };
+                let mut texpr = self.check_expr(expr);
+                match ttarget.ty() {
+                    Type::Any => {}
+                    Type::List(elem) => self.coerce(texpr.hir_id(), &mut texpr, elem),
+                    Type::Dict(key, value) => {
+                        self.require(tindex.hir_id(), (**key).clone(), tindex.ty().clone());
+                        self.coerce(texpr.hir_id(), &mut texpr, value);
+                        self.require_hashable(tindex.hir_id(), tindex.ty());
+                        // Inserting into `{}` decides the key and value types of the variable
+                        let entry =
+                            Type::Dict(Box::new(tindex.ty().clone()), Box::new(texpr.ty().clone()));
+                        if let TExpr::Name { sym, .. } = &ttarget
+                            && let Some(joined) = ttarget.ty().join(&entry)
+                        {
+                            self.var_types.insert(*sym, joined);
+                        }
+                    }
+                    ty @ (Type::Str | Type::Tuple(_)) => {
+                        self.errors.push(TypeError::ItemAssignment {
+                            hir_id: *hir_id,
+                            ty: ty.clone(),
+                        })
+                    }
+                    other => self.errors.push(TypeError::NotIndexable {
+                        hir_id: *hir_id,
+                        ty: other.clone(),
+                    }),
+                }
+                TStmt::IndexAssign {
+                    hir_id: *hir_id,
+                    target: ttarget,
+                    index: tindex,
+                    expr: texpr,
+                }
+            }
+            SStmt::FieldAssign {
+                hir_id,
+                target,
+                field,
+                expr,
+            } => {
+                let ttarget = self.check_expr(target);
+                let mut texpr = self.check_expr(expr);
+                let field_ty = self.field_type(*hir_id, ttarget.ty(), field);
+                self.coerce(texpr.hir_id(), &mut texpr, &field_ty);
+                TStmt::FieldAssign {
+                    hir_id: *hir_id,
+                    target: ttarget,
+                    field: field.clone(),
                     expr: texpr,
                 }
             }
@@ -117,6 +444,111 @@ impl<'a> Checker<'a> {
                     expr: texpr,
                 }
             }
+            SStmt::StructDef {
+                hir_id,
+                name,
+                methods,
+            } => TStmt::StructDef {
+                hir_id: *hir_id,
+                name: name.clone(),
+                methods: methods.iter().map(|m| self.check_stmt(m)).collect(),
+            },
+            SStmt::EnumDef { hir_id, name } => TStmt::EnumDef {
+                hir_id: *hir_id,
+                name: name.clone(),
+            },
+            SStmt::Match {
+                hir_id,
+                subject,
+                arms,
+            } => {
+                let tsubject = self.check_expr(subject);
+                let ty = tsubject.ty().clone();
+                let errors_before = self.errors.len();
+                let patterns: Vec<TPattern> = arms
+                    .iter()
+                    .map(|arm| self.check_pattern(&arm.pattern, &ty))
+                    .collect();
+                // Patterns that do not fit the subject would make the search meaningless
+                if self.errors.len() == errors_before && ty != Type::Any {
+                    let rows: Vec<&TPattern> = patterns.iter().collect();
+                    if let Some(missing) = missing_case(&rows, &ty, &self.symbols.enums) {
+                        self.errors.push(TypeError::NonExhaustiveMatch {
+                            hir_id: *hir_id,
+                            ty: ty.clone(),
+                            missing,
+                        });
+                    }
+                }
+                let arms = arms
+                    .iter()
+                    .zip(patterns)
+                    .map(|(arm, pattern)| TMatchArm {
+                        hir_id: arm.hir_id,
+                        pattern,
+                        body: arm.body.iter().map(|st| self.check_stmt(st)).collect(),
+                    })
+                    .collect();
+                TStmt::Match {
+                    hir_id: *hir_id,
+                    subject: tsubject,
+                    arms,
+                }
+            }
+            SStmt::Try {
+                hir_id,
+                body,
+                handlers,
+                finally,
+            } => {
+                let outer = self.narrowed.clone();
+                let body_t: Vec<TStmt> = body.iter().map(|st| self.check_stmt(st)).collect();
+                // A handler may run after any statement of the body
+                let handlers_t = handlers
+                    .iter()
+                    .map(|handler| {
+                        self.narrowed = outer.clone();
+                        self.forget_assigned(body);
+                        if let Some(sym) = handler.sym {
+                            self.var_types.insert(sym, Type::Str);
+                        }
+                        TExceptHandler {
+                            hir_id: handler.hir_id,
+                            kind: handler.kind.clone(),
+                            sym: handler.sym,
+                            body: handler.body.iter().map(|st| self.check_stmt(st)).collect(),
+                        }
+                    })
+                    .collect();
+                self.narrowed = outer;
+                self.forget_assigned(body);
+                for handler in handlers {
+                    self.forget_assigned(&handler.body);
+                }
+                let finally_t = finally.iter().map(|st| self.check_stmt(st)).collect();
+                TStmt::Try {
+                    hir_id: *hir_id,
+                    body: body_t,
+                    handlers: handlers_t,
+                    finally: finally_t,
+                }
+            }
+            SStmt::Raise {
+                hir_id,
+                kind,
+                message,
+            } => {
+                let message = message.as_ref().map(|e| {
+                    let te = self.check_expr(e);
+                    self.require(te.hir_id(), Type::Str, te.ty().clone());
+                    te
+                });
+                TStmt::Raise {
+                    hir_id: *hir_id,
+                    kind: kind.clone(),
+                    message,
+                }
+            }
             SStmt::ForRange {
                 hir_id,
                 sym,
@@ -129,12 +561,12 @@ impl<'a> Checker<'a> {
                 let tend = self.check_expr(end);
                 self.require(*hir_id, Type::I64, tstart.ty().clone());
                 self.require(*hir_id, Type::I64, tend.ty().clone());
+                self.forget_assigned(body);
 
                 // Loop variable is I64 in the loop body scope; for simplicity, set its type
                 self.var_types.insert(*sym, Type::I64);
 
-                // Typecheck body statements
-                let body_t: Vec<TStmt> = body.iter().map(|st| self.check_stmt(st)).collect();
+                let body_t = self.check_loop_body(body);
 
                 TStmt::ForRange {
                     hir_id: *hir_id,
@@ -144,16 +576,129 @@ impl<'a> Checker<'a> {
                     body: body_t,
                 }
             }
+            SStmt::ForEach {
+                hir_id,
+                syms,
+                iter,
+                body,
+            } => {
+                let titer = self.check_expr(iter);
+                let item = self.iter_item(titer.hir_id(), titer.ty());
+                let item_tys = match syms.len() {
+                    1 => vec![item],
+                    n => self.unpack(titer.hir_id(), n, &item),
+                };
+                for (sym, ty) in syms.iter().zip(item_tys) {
+                    self.var_types.insert(*sym, ty);
+                }
+                self.forget_assigned(body);
+                let body_t = self.check_loop_body(body);
+                TStmt::ForEach {
+                    hir_id: *hir_id,
+                    syms: syms.clone(),
+                    iter: titer,
+                    body: body_t,
+                }
+            }
+            SStmt::While { hir_id, cond, body } => {
+                // A later iteration may see the values assigned by an earlier one
+                self.forget_assigned(body);
+                let tcond = self.check_condition(cond);
+                let outer = self.narrowed.clone();
+                if let Some((sym, true)) = none_test(&tcond) {
+                    self.narrowed.insert(sym);
+                }
+                let body_t = self.check_loop_body(body);
+                self.narrowed = outer;
+                self.forget_assigned(body);
+                TStmt::While {
+                    hir_id: *hir_id,
+                    cond: tcond,
+                    body: body_t,
+                }
+            }
+            SStmt::FuncDef {
+                hir_id,
+                sym,
+                params,
+                ..
+            } => {
+                // The body is checked once a call decides the parameter types; see `fill_funcs`
+                TStmt::FuncDef {
+                    hir_id: *hir_id,
+                    sym: *sym,
+                    params: params.clone(),
+                    ret: Type::Unit,
+                    body: Vec::new(),
+                }
+            }
+            SStmt::Return { hir_id, expr } => {
+                // A lambda returned where a function type is declared takes its types
+                let declared = self.returns.last().map(|ctx| ctx.declared.clone());
+                let mut texpr = expr
+                    .as_ref()
+                    .map(|e| self.check_expr_expecting(e, declared.as_ref()));
+                let (at, ty) = match &texpr {
+                    Some(te) => (te.hir_id(), te.ty().clone()),
+                    None => (*hir_id, Type::Unit),
+                };
+                if let Some(ctx) = self.returns.last_mut() {
+                    let expected = if ctx.declared != Type::Any {
+                        Some(ctx.declared.clone())
+                    } else {
+                        match &ctx.found {
+                            // A recursive call may have been typed before any base case
+                            None | Some(Type::Any) => {
+                                ctx.found = Some(ty.clone());
+                                None
+                            }
+                            Some(found) => Some(found.clone()),
+                        }
+                    };
+                    if let Some(expected) = expected {
+                        match &mut texpr {
+                            Some(te) => {
+                                self.coerce(at, te, &expected);
+                                // A `return None` before `return 1` leaves the type to the latter
+                                if let Some(ctx) = self.returns.last_mut()
+                                    && ctx.declared == Type::Any
+                                    && let Some(joined) = expected.join(te.ty())
+                                {
+                                    ctx.found = Some(joined);
+                                }
+                            }
+                            None => self.require(at, expected, ty),
+                        }
+                    }
+                }
+                TStmt::Return {
+                    hir_id: *hir_id,
+                    expr: texpr,
+                }
+            }
+            SStmt::Break { hir_id } => TStmt::Break { hir_id: *hir_id },
+            SStmt::Continue { hir_id } => TStmt::Continue { hir_id: *hir_id },
             SStmt::If {
                 hir_id,
                 cond,
                 then_branch,
                 else_branch,
             } => {
-                let tcond = self.check_expr(cond);
-                // For now, accept any type for condition
+                let tcond = self.check_condition(cond);
+                let outer = self.narrowed.clone();
+                let test = none_test(&tcond);
+                if let Some((sym, true)) = test {
+                    self.narrowed.insert(sym);
+                }
                 let then_t: Vec<TStmt> = then_branch.iter().map(|st| self.check_stmt(st)).collect();
+                self.narrowed = outer.clone();
+                if let Some((sym, false)) = test {
+                    self.narrowed.insert(sym);
+                }
                 let else_t: Vec<TStmt> = else_branch.iter().map(|st| self.check_stmt(st)).collect();
+                self.narrowed = outer;
+                self.forget_assigned(then_branch);
+                self.forget_assigned(else_branch);
                 TStmt::If {
                     hir_id: *hir_id,
                     cond: tcond,
@@ -171,6 +716,11 @@ impl<'a> Checker<'a> {
                 value: *value,
                 ty: Type::I64,
             },
+            SExpr::Float { hir_id, value } => TExpr::Float {
+                hir_id: *hir_id,
+                value: *value,
+                ty: Type::F64,
+            },
             SExpr::Str { hir_id, value } => TExpr::Str {
                 hir_id: *hir_id,
                 value: value.clone(),
@@ -179,29 +729,45 @@ impl<'a> Checker<'a> {
             SExpr::Bool { hir_id, value } => TExpr::Bool {
                 hir_id: *hir_id,
                 value: *value,
-                ty: Type::Any,
+                ty: Type::Bool,
+            },
+            SExpr::None { hir_id } => TExpr::None {
+                hir_id: *hir_id,
+                ty: Type::Option(Box::new(Type::Any)),
             },
             SExpr::Name { hir_id, sym } => {
                 let var_name = &self.symbols.infos[sym.0 as usize].name;
                 let kind = self.symbols.infos[sym.0 as usize].kind;
+                let scope = self.symbols.infos[sym.0 as usize].scope;
 
-                // Find the most recent shadowed symbol with the same name
+                // Find the most recent shadowed symbol with the same name; shadowing
+                // symbols are defined in the scope of the one they shadow
                 let mut final_sym = *sym;
                 for i in (0..self.symbols.infos.len()).rev() {
                     let info = &self.symbols.infos[i];
-                    if info.name == *var_name && info.kind == kind {
-                        if self.var_types.contains_key(&SymbolId(i as u32)) {
-                            final_sym = SymbolId(i as u32);
-                            break;
-                        }
+                    if info.name == *var_name
+                        && info.kind == kind
+                        && info.scope == scope
+                        && self.var_types.contains_key(&SymbolId(i as u32))
+                    {
+                        final_sym = SymbolId(i as u32);
+                        break;
                     }
                 }
 
                 let ty = self.lookup_var_type(*hir_id, final_sym);
-                TExpr::Name {
+                let name = TExpr::Name {
                     hir_id: *hir_id,
                     sym: final_sym,
-                    ty,
+                    ty: ty.clone(),
+                };
+                match ty {
+                    Type::Option(inner) if self.narrowed.contains(sym) => TExpr::Unwrap {
+                        hir_id: *hir_id,
+                        expr: Box::new(name),
+                        ty: *inner,
+                    },
+                    _ => name,
                 }
             }
             SExpr::Binary {
@@ -210,15 +776,84 @@ impl<'a> Checker<'a> {
                 op,
                 right,
             } => {
-                let l = self.check_expr(left);
+                let l = match (op, left.as_ref()) {
+                    // `x is None` tests the variable itself, even where it is narrowed
+                    (HirBinOp::Is | HirBinOp::IsNot, SExpr::Name { sym, .. }) => {
+                        let narrowed = self.narrowed.remove(sym);
+                        let l = self.check_expr(left);
+                        if narrowed {
+                            self.narrowed.insert(*sym);
+                        }
+                        l
+                    }
+                    _ => self.check_expr(left),
+                };
                 let r = self.check_expr(right);
                 let (lhs_ty, rhs_ty) = (l.ty().clone(), r.ty().clone());
                 let out_ty = match op {
-                    HirBinOp::Add => {
-                        // Keep it simple: only I64 + I64 => I64
-                        self.require(*hir_id, Type::I64, lhs_ty.clone());
-                        self.require(*hir_id, Type::I64, rhs_ty.clone());
-                        Type::I64
+                    // `+` joins two strings; a number on the left keeps `+` numeric
+                    HirBinOp::Add if lhs_ty == Type::Str => {
+                        self.require(*hir_id, Type::Str, rhs_ty);
+                        Type::Str
+                    }
+                    HirBinOp::Add
+                    | HirBinOp::Sub
+                    | HirBinOp::Mul
+                    | HirBinOp::FloorDiv
+                    | HirBinOp::Mod => self.numeric_operands(*hir_id, &lhs_ty, &rhs_ty),
+                    HirBinOp::Div => {
+                        // True division always produces a float, as in Python
+                        self.numeric_operands(*hir_id, &lhs_ty, &rhs_ty);
+                        Type::F64
+                    }
+                    HirBinOp::Eq | HirBinOp::NotEq => {
+                        // Equality needs both sides of the same type; ints and floats mix
+                        if !(is_numeric(&lhs_ty) && is_numeric(&rhs_ty)) {
+                            self.require(*hir_id, lhs_ty, rhs_ty);
+                        }
+                        Type::Bool
+                    }
+                    // Strings are ordered by their characters
+                    HirBinOp::Lt | HirBinOp::LtEq | HirBinOp::Gt | HirBinOp::GtEq
+                        if lhs_ty == Type::Str =>
+                    {
+                        self.require(*hir_id, Type::Str, rhs_ty);
+                        Type::Bool
+                    }
+                    HirBinOp::Lt | HirBinOp::LtEq | HirBinOp::Gt | HirBinOp::GtEq => {
+                        self.numeric_operands(*hir_id, &lhs_ty, &rhs_ty);
+                        Type::Bool
+                    }
+                    HirBinOp::And | HirBinOp::Or => {
+                        self.require(*hir_id, Type::Bool, lhs_ty);
+                        self.require(*hir_id, Type::Bool, rhs_ty);
+                        Type::Bool
+                    }
+                    // Dicts test their keys, lists their elements and strings substrings
+                    HirBinOp::In | HirBinOp::NotIn => {
+                        match rhs_ty {
+                            Type::Dict(key, _) => self.require(l.hir_id(), *key, lhs_ty),
+                            Type::List(elem) => self.require(l.hir_id(), *elem, lhs_ty),
+                            Type::Str => self.require(l.hir_id(), Type::Str, lhs_ty),
+                            Type::Range => self.require(l.hir_id(), Type::I64, lhs_ty),
+                            Type::Any => {}
+                            other => self.errors.push(TypeError::NotContainer {
+                                hir_id: r.hir_id(),
+                                ty: other,
+                            }),
+                        }
+                        Type::Bool
+                    }
+                    // Only an optional value can be `None`
+                    HirBinOp::Is | HirBinOp::IsNot => {
+                        if !matches!(lhs_ty, Type::Option(_) | Type::Any) {
+                            self.errors.push(TypeError::TypeMismatch {
+                                hir_id: l.hir_id(),
+                                expected: Type::Option(Box::new(lhs_ty.clone())),
+                                found: lhs_ty,
+                            });
+                        }
+                        Type::Bool
                     }
                 };
                 TExpr::Binary {
@@ -229,41 +864,253 @@ impl<'a> Checker<'a> {
                     ty: out_ty,
                 }
             }
+            SExpr::Unary { hir_id, op, expr } => {
+                let inner = self.check_expr(expr);
+                let ty = match op {
+                    HirUnaryOp::Neg if *inner.ty() == Type::F64 => Type::F64,
+                    HirUnaryOp::Neg => {
+                        self.require(*hir_id, Type::I64, inner.ty().clone());
+                        Type::I64
+                    }
+                    HirUnaryOp::Not => {
+                        self.require(*hir_id, Type::Bool, inner.ty().clone());
+                        Type::Bool
+                    }
+                };
+                TExpr::Unary {
+                    hir_id: *hir_id,
+                    op: op.clone(),
+                    expr: Box::new(inner),
+                    ty,
+                }
+            }
             SExpr::Call { hir_id, func, args } => {
-                // Extract all needed information before any mutable borrows
-                let func_info = Self::extract_func_info(&self.symbols, func);
-
                 // Type subexpressions
-                let tf = self.check_expr(func);
-                let targs: Vec<_> = args.iter().map(|a| self.check_expr(a)).collect();
-
-                // Check arity and parameters
-                if let Some(sig) = &func_info.sig {
-                    if sig.params.len() != targs.len() {
-                        self.errors.push(TypeError::ArityMismatch {
+                let tf = self.check_callee(func);
+                let mut targs: Vec<TExpr> = Vec::new();
+                for (i, arg) in args.iter().enumerate() {
+                    let expected = self.param_expectation(func, tf.ty(), &targs, i);
+                    let targ = self.check_expr_expecting(arg, expected.as_ref());
+                    targs.push(targ);
+                }
+                let callee_ty = tf.ty().clone();
+                let ty = self.check_call(*hir_id, func, &callee_ty, &mut targs);
+                TExpr::Call {
+                    hir_id: *hir_id,
+                    func: Box::new(tf),
+                    args: targs,
+                    ty,
+                }
+            }
+            SExpr::MethodCall {
+                hir_id,
+                target,
+                method,
+                func,
+                args,
+            } => {
+                // The target is the first argument, `self` for a struct method
+                let mut targs = vec![self.check_expr(target)];
+                let method_sym = match targs[0].ty() {
+                    Type::Struct(name) => self
+                        .symbols
+                        .structs
+                        .get(name)
+                        .and_then(|s| s.methods.get(method))
+                        .copied(),
+                    _ => None,
+                };
+                let callee_sym = method_sym.or(*func);
+                for (i, arg) in args.iter().enumerate() {
+                    let expected = callee_sym.and_then(|sym| {
+                        let callee = SExpr::Name {
+                            hir_id: *hir_id,
+                            sym,
+                        };
+                        self.param_expectation(&callee, &Type::Any, &targs, i + 1)
+                    });
+                    let targ = self.check_expr_expecting(arg, expected.as_ref());
+                    targs.push(targ);
+                }
+                let Some(sym) = callee_sym else {
+                    if *targs[0].ty() != Type::Any {
+                        self.errors.push(TypeError::NoAttribute {
                             hir_id: *hir_id,
-                            expected: sig.params.len(),
-                            found: targs.len(),
+                            ty: targs[0].ty().clone(),
+                            name: method.clone(),
                         });
                     }
-                    for (i, targ) in targs.iter().enumerate() {
-                        if let Some(exp) = sig.params.get(i) {
-                            self.require(*hir_id, exp.clone(), targ.ty().clone());
-                        }
+                    // Checking goes on with the target standing in for the unknown callee
+                    let target = targs.remove(0);
+                    return TExpr::Call {
+                        hir_id: *hir_id,
+                        func: Box::new(target),
+                        args: targs,
+                        ty: Type::Any,
+                    };
+                };
+                let callee = SExpr::Name {
+                    hir_id: *hir_id,
+                    sym,
+                };
+                let ty = self.check_call(*hir_id, &callee, &Type::Any, &mut targs);
+                if method_sym.is_some() {
+                    let target = targs.remove(0);
+                    TExpr::MethodCall {
+                        hir_id: *hir_id,
+                        target: Box::new(target),
+                        method: sym,
+                        args: targs,
+                        ty,
                     }
                 } else {
-                    // Not a known callable symbol
-                    self.errors.push(TypeError::NotCallable {
+                    TExpr::Call {
                         hir_id: *hir_id,
-                        callee: func_info.name,
-                    });
+                        func: Box::new(self.check_callee(&callee)),
+                        args: targs,
+                        ty,
+                    }
                 }
-
-                TExpr::Call {
+            }
+            SExpr::Field {
+                hir_id,
+                target,
+                name,
+            } => {
+                let ttarget = self.check_expr(target);
+                let ty = self.field_type(*hir_id, ttarget.ty(), name);
+                TExpr::Field {
                     hir_id: *hir_id,
-                    func: Box::new(tf),
-                    args: targs,
-                    ty: func_info.ret_ty,
+                    target: Box::new(ttarget),
+                    name: name.clone(),
+                    ty,
+                }
+            }
+            SExpr::Construct {
+                hir_id,
+                name,
+                fields,
+            } => {
+                let mut fields: Vec<(String, TExpr)> = fields
+                    .iter()
+                    .map(|(field, value)| (field.clone(), self.check_expr(value)))
+                    .collect();
+                // An unknown struct was already reported by the resolver
+                if let Some(info) = self.symbols.structs.get(name).cloned() {
+                    for (field, value) in &mut fields {
+                        match info.field(field) {
+                            Some(ty) => self.coerce(value.hir_id(), value, ty),
+                            None => self.errors.push(TypeError::UnexpectedField {
+                                hir_id: value.hir_id(),
+                                strukt: name.clone(),
+                                field: field.clone(),
+                            }),
+                        }
+                    }
+                    let missing: Vec<String> = info
+                        .fields
+                        .iter()
+                        .filter(|(field, _)| !fields.iter().any(|(f, _)| f == field))
+                        .map(|(field, _)| field.clone())
+                        .collect();
+                    if !missing.is_empty() {
+                        self.errors.push(TypeError::MissingField {
+                            hir_id: *hir_id,
+                            strukt: name.clone(),
+                            fields: missing,
+                        });
+                    }
+                }
+                TExpr::Construct {
+                    hir_id: *hir_id,
+                    name: name.clone(),
+                    fields,
+                    ty: Type::Struct(name.clone()),
+                }
+            }
+            SExpr::Variant {
+                hir_id,
+                enum_name,
+                variant,
+                args,
+            } => {
+                let mut args: Vec<TExpr> = args.iter().map(|a| self.check_expr(a)).collect();
+                let ty = Type::Enum(enum_name.clone());
+                let parts = self.variant_parts(*hir_id, &ty, enum_name, variant, args.len());
+                for (arg, part) in args.iter_mut().zip(parts) {
+                    self.coerce(arg.hir_id(), arg, &part);
+                }
+                TExpr::Variant {
+                    hir_id: *hir_id,
+                    enum_name: enum_name.clone(),
+                    variant: variant.clone(),
+                    args,
+                    ty,
+                }
+            }
+            SExpr::Index {
+                hir_id,
+                target,
+                index,
+            } => {
+                let ttarget = self.check_expr(target);
+                let (tindex, ty) = match ttarget.ty() {
+                    Type::Dict(key, value) => {
+                        let tindex = self.check_expr(index);
+                        self.require(tindex.hir_id(), (**key).clone(), tindex.ty().clone());
+                        (tindex, (**value).clone())
+                    }
+                    // Items of a tuple may differ in type, so the index must be a literal
+                    Type::Tuple(items) => {
+                        let tindex = self.check_index(index);
+                        let item = match &tindex {
+                            TExpr::Int { value, .. } => {
+                                usize::try_from(*value).ok().and_then(|i| items.get(i))
+                            }
+                            _ => None,
+                        };
+                        let ty = match item {
+                            Some(ty) => ty.clone(),
+                            None => {
+                                self.errors.push(TypeError::TupleIndex {
+                                    hir_id: tindex.hir_id(),
+                                    len: items.len(),
+                                });
+                                Type::Any
+                            }
+                        };
+                        (tindex, ty)
+                    }
+                    target_ty => {
+                        let tindex = self.check_index(index);
+                        (tindex, self.sequence_item(*hir_id, target_ty))
+                    }
+                };
+                TExpr::Index {
+                    hir_id: *hir_id,
+                    target: Box::new(ttarget),
+                    index: Box::new(tindex),
+                    ty,
+                }
+            }
+            SExpr::Slice {
+                hir_id,
+                target,
+                start,
+                end,
+            } => {
+                let ttarget = self.check_expr(target);
+                let tstart = start.as_ref().map(|e| Box::new(self.check_index(e)));
+                let tend = end.as_ref().map(|e| Box::new(self.check_index(e)));
+                // A slice has the type of the sequence it was taken from
+                self.sequence_item(*hir_id, ttarget.ty());
+                let ty = ttarget.ty().clone();
+                TExpr::Slice {
+                    hir_id: *hir_id,
+                    target: Box::new(ttarget),
+                    start: tstart,
+                    end: tend,
+                    ty,
                 }
             }
             SExpr::InterpolatedString { hir_id, parts } => {
@@ -274,12 +1121,14 @@ impl<'a> Checker<'a> {
                             hir_id: *hir_id,
                             value: value.clone(),
                         },
-                        SStringPart::Expr { hir_id, expr } => {
+                        SStringPart::Expr { hir_id, expr, spec } => {
                             let te = self.check_expr(expr);
-                            // Allow any type inside interpolation (implicitly to-string).
+                            // Any type can be interpolated; only format options are checked
+                            self.check_format_spec(*hir_id, spec, te.ty());
                             TStringPart::Expr {
                                 hir_id: *hir_id,
                                 expr: te,
+                                spec: spec.clone(),
                             }
                         }
                     })
@@ -290,6 +1139,681 @@ impl<'a> Checker<'a> {
                     ty: Type::Str,
                 }
             }
+            SExpr::Dict { hir_id, entries } => {
+                let entries: Vec<(TExpr, TExpr)> = entries
+                    .iter()
+                    .map(|(k, v)| (self.check_expr(k), self.check_expr(v)))
+                    .collect();
+                let key = self.join_items(entries.iter().map(|(k, _)| k));
+                let value = self.join_items(entries.iter().map(|(_, v)| v));
+                if let Some((first, _)) = entries.first() {
+                    self.require_hashable(first.hir_id(), &key);
+                }
+                TExpr::Dict {
+                    hir_id: *hir_id,
+                    entries,
+                    ty: Type::Dict(Box::new(key), Box::new(value)),
+                }
+            }
+            SExpr::Tuple { hir_id, items } => {
+                let items: Vec<TExpr> = items.iter().map(|e| self.check_expr(e)).collect();
+                let ty = Type::Tuple(items.iter().map(|e| e.ty().clone()).collect());
+                TExpr::Tuple {
+                    hir_id: *hir_id,
+                    items,
+                    ty,
+                }
+            }
+            SExpr::Range { hir_id, start, end } => {
+                let tstart = self.check_expr(start);
+                let tend = self.check_expr(end);
+                self.require(tstart.hir_id(), Type::I64, tstart.ty().clone());
+                self.require(tend.hir_id(), Type::I64, tend.ty().clone());
+                TExpr::Range {
+                    hir_id: *hir_id,
+                    start: Box::new(tstart),
+                    end: Box::new(tend),
+                    ty: Type::Range,
+                }
+            }
+            SExpr::Lambda {
+                hir_id,
+                params,
+                param_types,
+                body,
+            } => self.check_lambda(*hir_id, params, param_types, body, None),
+        }
+    }
+
+    /// Check the function called by a call. A function named directly is called, not
+    /// used as a value, so it needs no function type.
+    fn check_callee(&mut self, func: &SExpr) -> TExpr {
+        if let SExpr::Name { hir_id, sym } = func
+            && matches!(
+                self.symbols.infos[sym.0 as usize].kind,
+                SymKind::Func | SymKind::BuiltinFunc
+            )
+        {
+            return TExpr::Name {
+                hir_id: *hir_id,
+                sym: *sym,
+                ty: Type::Any,
+            };
+        }
+        self.check_expr(func)
+    }
+
+    /// Check `e` where a value of type `expected` is wanted. Only function types are
+    /// passed down: they give the parameters of a lambda, or of a function that is
+    /// passed as a value before it is ever called, their types.
+    fn check_expr_expecting(&mut self, e: &SExpr, expected: Option<&Type>) -> TExpr {
+        match (e, expected) {
+            (
+                SExpr::Lambda {
+                    hir_id,
+                    params,
+                    param_types,
+                    body,
+                },
+                _,
+            ) => self.check_lambda(*hir_id, params, param_types, body, expected),
+            (SExpr::Name { hir_id, sym }, Some(Type::Func(params, _)))
+                if self.funcs.contains_key(sym) && !self.instances.contains_key(sym) =>
+            {
+                let args: Vec<(HirId, Type)> =
+                    params.iter().map(|t| (*hir_id, t.clone())).collect();
+                self.instantiate(*sym, &args);
+                self.check_expr(e)
+            }
+            _ => self.check_expr(e),
+        }
+    }
+
+    /// The function type the `i`th argument of a call of `func` is expected to have,
+    /// given the arguments before it: the annotated parameter type, or a function of the
+    /// elements of the list given to `map`, `filter` and `sorted`.
+    fn param_expectation(
+        &self,
+        func: &SExpr,
+        callee_ty: &Type,
+        prev_args: &[TExpr],
+        i: usize,
+    ) -> Option<Type> {
+        if let Some(name) = Self::collection_builtin(self.symbols, func) {
+            if !matches!(name, "map" | "filter" | "sorted") || i != 1 {
+                return None;
+            }
+            let item = match prev_args.first().map(|a| a.ty()) {
+                Some(Type::List(elem)) => (**elem).clone(),
+                _ => Type::Any,
+            };
+            return Some(Type::Func(vec![item], Box::new(Type::Any)));
+        }
+        let params = match callee_ty {
+            Type::Func(params, _) => params.clone(),
+            _ => Self::extract_func_info(self.symbols, func).sig?.params,
+        };
+        params
+            .get(i)
+            .filter(|t| matches!(t, Type::Func(..)))
+            .cloned()
+    }
+
+    /// Check `fn(x) => body`. Unannotated parameters take their types from `expected`,
+    /// the function type the lambda is passed or assigned as, or else from their use in
+    /// the body.
+    fn check_lambda(
+        &mut self,
+        hir_id: HirId,
+        params: &[SymbolId],
+        param_types: &[Type],
+        body: &SExpr,
+        expected: Option<&Type>,
+    ) -> TExpr {
+        let (expected_params, expected_ret) = match expected {
+            Some(Type::Func(params, ret)) => (params.clone(), (**ret).clone()),
+            _ => (Vec::new(), Type::Any),
+        };
+        let mut types = Vec::new();
+        for (i, (param, declared)) in params.iter().zip(param_types).enumerate() {
+            let ty = match expected_params.get(i) {
+                Some(exp) if *declared == Type::Any => exp.clone(),
+                _ => declared.clone(),
+            };
+            let ty = match ty {
+                Type::Any => self.param_type_from_use(*param, body).unwrap_or(Type::Any),
+                ty => ty,
+            };
+            if ty == Type::Any && !self.speculative {
+                self.errors.push(TypeError::CannotInferParam {
+                    hir_id,
+                    param: self.symbols.infos[param.0 as usize].name.clone(),
+                });
+            }
+            self.var_types.insert(*param, ty.clone());
+            types.push(ty);
+        }
+        let mut tbody = self.check_expr(body);
+        let ret = if expected_ret != Type::Any {
+            self.coerce(tbody.hir_id(), &mut tbody, &expected_ret);
+            expected_ret
+        } else {
+            tbody.ty().clone()
+        };
+        TExpr::Lambda {
+            hir_id,
+            params: params.to_vec(),
+            body: Box::new(tbody),
+            ty: Type::Func(types, Box::new(ret)),
+        }
+    }
+
+    /// Type of the lambda parameter `param` as `e` uses it: the type of the other operand
+    /// of an operator, the item type of the collection it is tested `in`, or the
+    /// annotated type of the parameter it is passed as.
+    fn param_type_from_use(&self, param: SymbolId, e: &SExpr) -> Option<Type> {
+        let is_param = |e: &SExpr| matches!(e, SExpr::Name { sym, .. } if *sym == param);
+        let found = match e {
+            SExpr::Binary {
+                left, op, right, ..
+            } => match op {
+                HirBinOp::In | HirBinOp::NotIn if is_param(left) => {
+                    match self.operand_type(right)? {
+                        Type::List(item) | Type::Dict(item, _) => Some(*item),
+                        Type::Str => Some(Type::Str),
+                        _ => None,
+                    }
+                }
+                HirBinOp::In | HirBinOp::NotIn | HirBinOp::Is | HirBinOp::IsNot => None,
+                _ if is_param(left) => self.operand_type(right),
+                _ if is_param(right) => self.operand_type(left),
+                _ => None,
+            },
+            SExpr::Call { func, args, .. } => {
+                let sig = Self::extract_func_info(self.symbols, func).sig;
+                args.iter()
+                    .zip(sig.map(|s| s.params).unwrap_or_default())
+                    .find(|(arg, ty)| is_param(arg) && *ty != Type::Any)
+                    .map(|(_, ty)| ty)
+            }
+            _ => None,
+        };
+        if found.is_some() {
+            return found;
+        }
+        match e {
+            SExpr::Binary { left, right, .. } => self
+                .param_type_from_use(param, left)
+                .or_else(|| self.param_type_from_use(param, right)),
+            SExpr::Unary { expr, .. } => self.param_type_from_use(param, expr),
+            SExpr::Call { args, .. }
+            | SExpr::Tuple { items: args, .. }
+            | SExpr::Variant { args, .. } => {
+                args.iter().find_map(|a| self.param_type_from_use(param, a))
+            }
+            SExpr::MethodCall { target, args, .. } => self
+                .param_type_from_use(param, target)
+                .or_else(|| args.iter().find_map(|a| self.param_type_from_use(param, a))),
+            SExpr::Index { target, index, .. } => self
+                .param_type_from_use(param, target)
+                .or_else(|| self.param_type_from_use(param, index)),
+            SExpr::Field { target, .. } => self.param_type_from_use(param, target),
+            SExpr::InterpolatedString { parts, .. } => parts.iter().find_map(|p| match p {
+                SStringPart::Expr { expr, .. } => self.param_type_from_use(param, expr),
+                SStringPart::Text { .. } => None,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Type of a literal or of a variable whose type is already known.
+    fn operand_type(&self, e: &SExpr) -> Option<Type> {
+        let ty = match e {
+            SExpr::Int { .. } => Type::I64,
+            SExpr::Float { .. } => Type::F64,
+            SExpr::Str { .. } | SExpr::InterpolatedString { .. } => Type::Str,
+            SExpr::Bool { .. } => Type::Bool,
+            SExpr::Name { sym, .. } => self.var_types.get(sym)?.clone(),
+            _ => return None,
+        };
+        (ty != Type::Any).then_some(ty)
+    }
+
+    /// Check a call of `func`, of type `callee_ty`, with the already checked `args` and
+    /// return its result type.
+    fn check_call(
+        &mut self,
+        hir_id: HirId,
+        func: &SExpr,
+        callee_ty: &Type,
+        args: &mut [TExpr],
+    ) -> Type {
+        // The first call of a user function decides its parameter types
+        let user_func = match func {
+            SExpr::Name { sym, .. } if self.funcs.contains_key(sym) => Some(*sym),
+            _ => None,
+        };
+        if let Some(sym) = user_func
+            && !self.instances.contains_key(&sym)
+        {
+            let arg_types: Vec<(HirId, Type)> =
+                args.iter().map(|a| (a.hir_id(), a.ty().clone())).collect();
+            self.instantiate(sym, &arg_types);
+        }
+
+        let mut func_info = match callee_ty {
+            // A function value: a lambda, or a function passed in or assigned
+            Type::Func(params, ret) => FuncInfo {
+                name: "<fn>".to_string(),
+                sig: Some(FuncSig {
+                    params: params.clone(),
+                    ret: (**ret).clone(),
+                }),
+                ret_ty: (**ret).clone(),
+            },
+            _ => Self::extract_func_info(self.symbols, func),
+        };
+        if let Some(ret) = user_func.and_then(|sym| self.func_ret(sym)) {
+            func_info.ret_ty = ret;
+        }
+
+        // Check arity and parameters; `[a, b]` calls `vec` with any number of elements,
+        // `sorted` takes its key function optionally and `split` its separator
+        let builtin = Self::collection_builtin(self.symbols, func);
+        if let Some(sig) = &func_info.sig {
+            if sig.params.len() != args.len()
+                && builtin != Some("vec")
+                && !(matches!(builtin, Some("sorted" | "split")) && args.len() == 1)
+            {
+                self.errors.push(TypeError::ArityMismatch {
+                    hir_id,
+                    expected: sig.params.len(),
+                    found: args.len(),
+                });
+            }
+            for (i, arg) in args.iter_mut().enumerate() {
+                if let Some(exp) = sig.params.get(i) {
+                    self.coerce(hir_id, arg, exp);
+                }
+            }
+        } else {
+            // Not a known callable symbol
+            self.errors.push(TypeError::NotCallable {
+                hir_id,
+                callee: func_info.name,
+            });
+        }
+
+        match builtin {
+            Some(name) => self.check_collection_builtin(hir_id, name, args),
+            None => func_info.ret_ty,
+        }
+    }
+
+    /// Check a `case` pattern against the type of the subject, recording the types of
+    /// the names it binds.
+    fn check_pattern(&mut self, pattern: &SPattern, ty: &Type) -> TPattern {
+        match pattern {
+            SPattern::Wildcard { hir_id } => TPattern::Wildcard { hir_id: *hir_id },
+            SPattern::Bind { hir_id, sym } => {
+                self.var_types.insert(*sym, ty.clone());
+                TPattern::Bind {
+                    hir_id: *hir_id,
+                    sym: *sym,
+                }
+            }
+            SPattern::Literal { hir_id, value } => {
+                let tvalue = self.check_expr(value);
+                self.require(*hir_id, ty.clone(), tvalue.ty().clone());
+                TPattern::Literal {
+                    hir_id: *hir_id,
+                    value: tvalue,
+                }
+            }
+            SPattern::Variant {
+                hir_id,
+                enum_name,
+                variant,
+                args,
+            } => {
+                let enum_ty = Type::Enum(enum_name.clone());
+                self.require(*hir_id, ty.clone(), enum_ty.clone());
+                let parts = self.variant_parts(*hir_id, &enum_ty, enum_name, variant, args.len());
+                TPattern::Variant {
+                    hir_id: *hir_id,
+                    enum_name: enum_name.clone(),
+                    variant: variant.clone(),
+                    args: args
+                        .iter()
+                        .zip(parts)
+                        .map(|(arg, part)| self.check_pattern(arg, &part))
+                        .collect(),
+                }
+            }
+            SPattern::Tuple { hir_id, items } => {
+                let item_tys = self.unpack(*hir_id, items.len(), ty);
+                TPattern::Tuple {
+                    hir_id: *hir_id,
+                    items: items
+                        .iter()
+                        .zip(item_tys)
+                        .map(|(item, item_ty)| self.check_pattern(item, &item_ty))
+                        .collect(),
+                }
+            }
+        }
+    }
+
+    /// Payload types of `enum_name.variant` given `found` values, padded with `Any`
+    /// when the variant is unknown or takes another number of values.
+    fn variant_parts(
+        &mut self,
+        hir_id: HirId,
+        ty: &Type,
+        enum_name: &str,
+        variant: &str,
+        found: usize,
+    ) -> Vec<Type> {
+        // An unknown enum was already reported by the resolver
+        let Some(info) = self.symbols.enums.get(enum_name) else {
+            return vec![Type::Any; found];
+        };
+        match info.variant(variant) {
+            Some(parts) if parts.len() == found => parts.to_vec(),
+            Some(parts) => {
+                self.errors.push(TypeError::ArityMismatch {
+                    hir_id,
+                    expected: parts.len(),
+                    found,
+                });
+                vec![Type::Any; found]
+            }
+            None => {
+                self.errors.push(TypeError::NoAttribute {
+                    hir_id,
+                    ty: ty.clone(),
+                    name: variant.to_string(),
+                });
+                vec![Type::Any; found]
+            }
+        }
+    }
+
+    /// Type of the field `name` of a value of type `ty`.
+    fn field_type(&mut self, hir_id: HirId, ty: &Type, name: &str) -> Type {
+        match ty {
+            Type::Struct(strukt) => {
+                if let Some(field) = self.symbols.structs.get(strukt).and_then(|s| s.field(name)) {
+                    return field.clone();
+                }
+            }
+            Type::Any => return Type::Any,
+            _ => {}
+        }
+        self.errors.push(TypeError::NoAttribute {
+            hir_id,
+            ty: ty.clone(),
+            name: name.to_string(),
+        });
+        Type::Any
+    }
+
+    /// Type of the items a `for` loop visits: the elements of a list, the characters of
+    /// a string, the keys of a dict, the numbers of a range and the items of a plugin
+    /// iterator. Other plugin types iterate like the Kayton type their manifest kind
+    /// maps to.
+    fn iter_item(&mut self, hir_id: HirId, ty: &Type) -> Type {
+        match ty {
+            Type::List(elem) | Type::Iter(elem) => (**elem).clone(),
+            Type::Str => Type::Str,
+            Type::Dict(key, _) => (**key).clone(),
+            Type::Range => Type::I64,
+            Type::Any => Type::Any,
+            other => {
+                self.errors.push(TypeError::NotIterable {
+                    hir_id,
+                    ty: other.clone(),
+                });
+                Type::Any
+            }
+        }
+    }
+
+    /// Record the type of an assigned variable. Assigning a value of another type
+    /// shadows the variable with a new symbol; the returned symbol is the one written.
+    fn assign_var(&mut self, hir_id: HirId, sym: SymbolId, ty: Type) -> SymbolId {
+        let Some(existing_ty) = self.var_types.get(&sym).cloned() else {
+            // First assignment - use the original symbol
+            self.var_types.insert(sym, ty);
+            return sym;
+        };
+        if existing_ty == ty {
+            // Same type - reuse the symbol
+            sym
+        } else if let (Type::List(_) | Type::Dict(..) | Type::Option(_), Some(joined)) =
+            (&existing_ty, existing_ty.join(&ty))
+        {
+            // `xs = []` followed by `xs = [1]` is still one list, and likewise for `{}`;
+            // an optional cleared with `y = None` keeps its item type
+            self.var_types.insert(sym, joined);
+            sym
+        } else {
+            // Different type - create a new symbol with the same name for shadowing
+            let info = &self.symbols.infos[sym.0 as usize];
+            if self.loop_depth > 0 && !self.speculative {
+                self.errors.push(TypeError::ShadowingImpossible {
+                    hir_id,
+                    var_name: info.name.clone(),
+                });
+            }
+            let (name, kind, scope) = (info.name.clone(), info.kind, info.scope);
+            let new_sym = self.symbols.define_new(scope, &name, kind);
+            self.var_types.insert(new_sym, ty);
+            new_sym
+        }
+    }
+
+    fn check_loop_body(&mut self, body: &[SStmt]) -> Vec<TStmt> {
+        self.loop_depth += 1;
+        let body_t = body.iter().map(|st| self.check_stmt(st)).collect();
+        self.loop_depth -= 1;
+        body_t
+    }
+
+    /// Item types of a tuple unpacked into `n` names.
+    fn unpack(&mut self, hir_id: HirId, n: usize, ty: &Type) -> Vec<Type> {
+        match ty {
+            Type::Tuple(items) if items.len() == n => items.clone(),
+            Type::Any => vec![Type::Any; n],
+            other => {
+                self.errors.push(TypeError::UnpackMismatch {
+                    hir_id,
+                    expected: n,
+                    found: other.clone(),
+                });
+                vec![Type::Any; n]
+            }
+        }
+    }
+
+    /// The one type shared by the elements of a list literal (or the keys or values of
+    /// a dict literal); elements that do not fit are reported.
+    fn join_items<'e>(&mut self, items: impl Iterator<Item = &'e TExpr>) -> Type {
+        let mut joined = Type::Any;
+        for item in items {
+            match joined.join(item.ty()) {
+                Some(j) => joined = j,
+                None => self.require(item.hir_id(), joined.clone(), item.ty().clone()),
+            }
+        }
+        joined
+    }
+
+    /// Dict keys are hashed; floats, lists and dicts cannot be keys.
+    fn require_hashable(&mut self, hir_id: HirId, ty: &Type) {
+        if !is_hashable(ty) {
+            self.errors.push(TypeError::UnhashableKey {
+                hir_id,
+                ty: ty.clone(),
+            });
+        }
+    }
+
+    /// Indexes and slice bounds are `i64`; negative values count from the end.
+    fn check_index(&mut self, index: &SExpr) -> TExpr {
+        let tindex = self.check_expr(index);
+        self.require(tindex.hir_id(), Type::I64, tindex.ty().clone());
+        tindex
+    }
+
+    /// Type of one element of a sequence: a character of a string is itself a string.
+    fn sequence_item(&mut self, hir_id: HirId, ty: &Type) -> Type {
+        match ty {
+            Type::Str => Type::Str,
+            Type::List(elem) => (**elem).clone(),
+            Type::Any => Type::Any,
+            other => {
+                self.errors.push(TypeError::NotIndexable {
+                    hir_id,
+                    ty: other.clone(),
+                });
+                Type::Any
+            }
+        }
+    }
+
+    /// Conditions of `if`/`while` must be `bool`; there is no implicit truthiness.
+    fn check_condition(&mut self, cond: &SExpr) -> TExpr {
+        let tcond = self.check_expr(cond);
+        self.require(tcond.hir_id(), Type::Bool, tcond.ty().clone());
+        tcond
+    }
+
+    /// Name of the builtin that `func` refers to, among those whose result type or
+    /// arity depends on their arguments: list, dict and higher-order builtins such as
+    /// `append`, `keys` and `map`, `len` and `split`.
+    fn collection_builtin(symbols: &SymbolTable, func: &SExpr) -> Option<&'static str> {
+        let SExpr::Name { sym, .. } = func else {
+            return None;
+        };
+        let info = &symbols.infos[sym.0 as usize];
+        if info.kind != SymKind::BuiltinFunc {
+            return None;
+        }
+        [
+            "vec", "append", "sum", "keys", "values", "items", "map", "filter", "sorted", "len",
+            "split",
+        ]
+        .into_iter()
+        .find(|name| info.name == *name)
+    }
+
+    /// Result type of a list or dict builtin, from the item types of its arguments.
+    fn check_collection_builtin(&mut self, hir_id: HirId, name: &str, args: &[TExpr]) -> Type {
+        match (name, args) {
+            ("vec", elems) => Type::List(Box::new(self.join_items(elems.iter()))),
+            ("append", [list, value]) => {
+                if let Type::List(elem) = list.ty() {
+                    match elem.join(value.ty()) {
+                        // Appending to `[]` decides the element type of the variable
+                        Some(joined) if **elem == Type::Any => {
+                            if let TExpr::Name { sym, .. } = list {
+                                self.var_types.insert(*sym, Type::List(Box::new(joined)));
+                            }
+                        }
+                        Some(_) => {}
+                        None => self.require(value.hir_id(), (**elem).clone(), value.ty().clone()),
+                    }
+                }
+                Type::Unit
+            }
+            ("sum", [list]) => match list.ty() {
+                Type::List(elem) if **elem == Type::F64 => Type::F64,
+                Type::List(elem) if matches!(**elem, Type::I64 | Type::Any) => Type::I64,
+                other => {
+                    let expected = Type::List(Box::new(Type::I64));
+                    if *other != Type::Any {
+                        self.require(list.hir_id(), expected, other.clone());
+                    }
+                    Type::I64
+                }
+            },
+            // A non-dict argument was already reported against the signature
+            ("keys" | "values" | "items", [dict]) => {
+                let (key, value) = match dict.ty() {
+                    Type::Dict(key, value) => ((**key).clone(), (**value).clone()),
+                    _ => (Type::Any, Type::Any),
+                };
+                let item = match name {
+                    "keys" => key,
+                    "values" => value,
+                    _ => Type::Tuple(vec![key, value]),
+                };
+                Type::List(Box::new(item))
+            }
+            ("len", [value]) => {
+                if !matches!(
+                    value.ty(),
+                    Type::Str | Type::List(_) | Type::Dict(..) | Type::Any
+                ) {
+                    self.errors.push(TypeError::NoLen {
+                        hir_id: value.hir_id(),
+                        ty: value.ty().clone(),
+                    });
+                }
+                Type::I64
+            }
+            ("split", _) => Type::List(Box::new(Type::Str)),
+            // `map` collects what the function returns, `filter` keeps the elements it
+            // accepts and `sorted` orders them by the key it returns
+            ("map" | "filter" | "sorted", [list, rest @ ..]) => {
+                let elem = match list.ty() {
+                    Type::List(elem) => (**elem).clone(),
+                    _ => Type::Any,
+                };
+                let Some(f) = rest.first() else {
+                    self.require_ordered(hir_id, &elem);
+                    return list.ty().clone();
+                };
+                let ret = match f.ty() {
+                    Type::Func(params, ret) if params.len() == 1 => (**ret).clone(),
+                    Type::Any => Type::Any,
+                    other => {
+                        self.errors.push(TypeError::TypeMismatch {
+                            hir_id: f.hir_id(),
+                            expected: Type::Func(vec![elem.clone()], Box::new(Type::Any)),
+                            found: other.clone(),
+                        });
+                        Type::Any
+                    }
+                };
+                match name {
+                    "map" => Type::List(Box::new(ret)),
+                    "filter" => {
+                        self.require(f.hir_id(), Type::Bool, ret);
+                        list.ty().clone()
+                    }
+                    _ => {
+                        self.require_ordered(f.hir_id(), &ret);
+                        list.ty().clone()
+                    }
+                }
+            }
+            _ => Type::Any,
+        }
+    }
+
+    /// `sorted` compares numbers, strings and bools.
+    fn require_ordered(&mut self, hir_id: HirId, ty: &Type) {
+        if !matches!(
+            ty,
+            Type::I64 | Type::F64 | Type::Str | Type::Bool | Type::Any
+        ) {
+            self.errors.push(TypeError::TypeMismatch {
+                hir_id,
+                expected: Type::I64,
+                found: ty.clone(),
+            });
         }
     }
 
@@ -323,19 +1847,19 @@ impl<'a> Checker<'a> {
         // Look for the most recent symbol with the same name
         for i in (0..self.symbols.infos.len()).rev() {
             let info = &self.symbols.infos[i];
-            if info.name == *var_name && info.kind == kind {
-                if let Some(ty) = self.var_types.get(&SymbolId(i as u32)) {
-                    return ty.clone();
-                }
+            if info.name == *var_name
+                && info.kind == kind
+                && let Some(ty) = self.var_types.get(&SymbolId(i as u32))
+            {
+                return ty.clone();
             }
         }
 
         // Extract symbol info before any mutable operations
         match kind {
-            SymKind::BuiltinFunc | SymKind::Func => {
-                // As a value, treat functions as Any for now (no first-class function type).
-                Type::Any
-            }
+            SymKind::Func => self.func_value_type(hir_id, sym),
+            // Builtins have no single function type
+            SymKind::BuiltinFunc => Type::Any,
             _ => {
                 // Unknown var type (used before any assignment) ⇒ error but fall back to Any.
                 self.errors.push(TypeError::UnknownVarType { hir_id, sym });
@@ -344,6 +1868,122 @@ impl<'a> Checker<'a> {
         }
     }
 
+    /// Type of a user function used as a value. A function that was not called yet is
+    /// checked here; its unannotated parameters cannot be inferred.
+    fn func_value_type(&mut self, hir_id: HirId, sym: SymbolId) -> Type {
+        if self.funcs.contains_key(&sym) && !self.instances.contains_key(&sym) {
+            self.instantiate(sym, &[]);
+        }
+        let Some(sig) = self.symbols.infos[sym.0 as usize].sig.clone() else {
+            return Type::Any;
+        };
+        if let Some(i) = sig.params.iter().position(|t| *t == Type::Any)
+            && let Some(param) = self.funcs.get(&sym).and_then(|def| def.params.get(i))
+            && !self.speculative
+        {
+            self.errors.push(TypeError::CannotInferParam {
+                hir_id,
+                param: self.symbols.infos[param.0 as usize].name.clone(),
+            });
+        }
+        let ret = self.func_ret(sym).unwrap_or(sig.ret);
+        Type::Func(sig.params, Box::new(ret))
+    }
+
+    /// Like `require`, but a `T` is also accepted where `T?` is expected and is wrapped
+    /// as the optional value.
+    fn coerce(&mut self, hir_id: HirId, expr: &mut TExpr, expected: &Type) {
+        match (expected, expr.ty()) {
+            (Type::Option(inner), found) if !matches!(found, Type::Option(_) | Type::Any) => {
+                self.require(hir_id, (**inner).clone(), found.clone());
+                let ty = Type::Option(Box::new(inner.join(found).unwrap_or(Type::Any)));
+                let value = std::mem::replace(
+                    expr,
+                    TExpr::None {
+                        hir_id,
+                        ty: Type::Any,
+                    },
+                );
+                *expr = TExpr::Some {
+                    hir_id: value.hir_id(),
+                    expr: Box::new(value),
+                    ty,
+                };
+            }
+            (_, found) => self.require(hir_id, expected.clone(), found.clone()),
+        }
+    }
+
+    /// Drop the narrowing of variables that `body` assigns; they may be `None` again.
+    fn forget_assigned(&mut self, body: &[SStmt]) {
+        for s in body {
+            match s {
+                SStmt::Assign { sym, .. } => {
+                    self.narrowed.remove(sym);
+                }
+                SStmt::TupleAssign { syms, .. } => {
+                    for sym in syms {
+                        self.narrowed.remove(sym);
+                    }
+                }
+                SStmt::ForRange { body, .. }
+                | SStmt::ForEach { body, .. }
+                | SStmt::While { body, .. } => self.forget_assigned(body),
+                SStmt::If {
+                    then_branch,
+                    else_branch,
+                    ..
+                } => {
+                    self.forget_assigned(then_branch);
+                    self.forget_assigned(else_branch);
+                }
+                SStmt::Match { arms, .. } => {
+                    for arm in arms {
+                        self.forget_assigned(&arm.body);
+                    }
+                }
+                SStmt::Try {
+                    body,
+                    handlers,
+                    finally,
+                    ..
+                } => {
+                    self.forget_assigned(body);
+                    for handler in handlers {
+                        self.forget_assigned(&handler.body);
+                    }
+                    self.forget_assigned(finally);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Check that a value of type `ty` takes the presentation type and precision of
+    /// an f-string field. A repr is a string, whatever the value.
+    fn check_format_spec(&mut self, hir_id: HirId, spec: &FormatSpec, ty: &Type) {
+        let ty = if spec.repr { &Type::Str } else { ty };
+        let fits = match spec.kind {
+            _ if *ty == Type::Any => true,
+            Some('d' | 'x' | 'X' | 'o' | 'b') => *ty == Type::I64,
+            Some('f' | 'F') => is_numeric(ty),
+            Some('s') => *ty == Type::Str,
+            // Python only rounds floats; Rust would ignore a precision on an integer
+            _ => !(spec.precision.is_some() && *ty == Type::I64),
+        };
+        if !fits {
+            let option = match (spec.kind, spec.precision) {
+                (Some(kind), _) => kind.to_string(),
+                (None, precision) => format!(".{}", precision.unwrap_or_default()),
+            };
+            self.errors.push(TypeError::BadFormat {
+                hir_id,
+                option,
+                ty: ty.clone(),
+            });
+        }
+    }
+
     fn require(&mut self, hir_id: HirId, expected: Type, found: Type) {
         if !self.is_compatible(&expected, &found) {
             self.errors.push(TypeError::TypeMismatch {
@@ -355,7 +1995,75 @@ impl<'a> Checker<'a> {
     }
 
     fn is_compatible(&self, expected: &Type, found: &Type) -> bool {
-        expected == found || *expected == Type::Any || *found == Type::Any
+        expected.join(found).is_some()
+    }
+
+    /// Check the operands of an arithmetic or ordering operator. An int mixed with a
+    /// float is promoted, so the result is `F64` if either side is a float.
+    fn numeric_operands(&mut self, hir_id: HirId, lhs: &Type, rhs: &Type) -> Type {
+        for ty in [lhs, rhs] {
+            if !is_numeric(ty) {
+                self.require(hir_id, Type::I64, ty.clone());
+            }
+        }
+        if *lhs == Type::F64 || *rhs == Type::F64 {
+            Type::F64
+        } else {
+            Type::I64
+        }
+    }
+}
+
+/// Whether every path through `body` ends in a `return` or a `raise`.
+fn definitely_returns(body: &[TStmt]) -> bool {
+    match body.last() {
+        Some(TStmt::Return { .. }) => true,
+        Some(TStmt::If {
+            then_branch,
+            else_branch,
+            ..
+        }) => definitely_returns(then_branch) && definitely_returns(else_branch),
+        // A match that leaves a value unhandled is reported on its own
+        Some(TStmt::Match { arms, .. }) => arms.iter().all(|arm| definitely_returns(&arm.body)),
+        Some(TStmt::Raise { .. }) => true,
+        Some(TStmt::Try {
+            body,
+            handlers,
+            finally,
+            ..
+        }) => {
+            definitely_returns(finally)
+                || (definitely_returns(body)
+                    && handlers.iter().all(|h| definitely_returns(&h.body)))
+        }
+        _ => false,
+    }
+}
+
+/// The variable an `x is None` (`false`) or `x is not None` (`true`) condition tests.
+fn none_test(cond: &TExpr) -> Option<(SymbolId, bool)> {
+    let TExpr::Binary { left, op, .. } = cond else {
+        return None;
+    };
+    let TExpr::Name { sym, .. } = left.as_ref() else {
+        return None;
+    };
+    match op {
+        HirBinOp::Is => Some((*sym, false)),
+        HirBinOp::IsNot => Some((*sym, true)),
+        _ => None,
+    }
+}
+
+fn is_numeric(ty: &Type) -> bool {
+    matches!(ty, Type::I64 | Type::F64 | Type::Any)
+}
+
+fn is_hashable(ty: &Type) -> bool {
+    match ty {
+        Type::I64 | Type::Str | Type::Bool | Type::Any => true,
+        Type::Tuple(items) => items.iter().all(is_hashable),
+        _ => false,
     }
 }
 
@@ -369,12 +2077,54 @@ impl TExpr {
     pub fn ty(&self) -> &Type {
         match self {
             TExpr::Int { ty, .. }
+            | TExpr::Float { ty, .. }
             | TExpr::Str { ty, .. }
             | TExpr::Bool { ty, .. }
+            | TExpr::None { ty, .. }
+            | TExpr::Some { ty, .. }
+            | TExpr::Unwrap { ty, .. }
             | TExpr::Name { ty, .. }
             | TExpr::Binary { ty, .. }
+            | TExpr::Unary { ty, .. }
             | TExpr::Call { ty, .. }
-            | TExpr::InterpolatedString { ty, .. } => ty,
+            | TExpr::Index { ty, .. }
+            | TExpr::Slice { ty, .. }
+            | TExpr::InterpolatedString { ty, .. }
+            | TExpr::Dict { ty, .. }
+            | TExpr::Tuple { ty, .. }
+            | TExpr::Range { ty, .. }
+            | TExpr::Field { ty, .. }
+            | TExpr::MethodCall { ty, .. }
+            | TExpr::Construct { ty, .. }
+            | TExpr::Variant { ty, .. }
+            | TExpr::Lambda { ty, .. } => ty,
+        }
+    }
+
+    pub fn hir_id(&self) -> HirId {
+        match self {
+            TExpr::Int { hir_id, .. }
+            | TExpr::Float { hir_id, .. }
+            | TExpr::Str { hir_id, .. }
+            | TExpr::Bool { hir_id, .. }
+            | TExpr::None { hir_id, .. }
+            | TExpr::Some { hir_id, .. }
+            | TExpr::Unwrap { hir_id, .. }
+            | TExpr::Name { hir_id, .. }
+            | TExpr::Binary { hir_id, .. }
+            | TExpr::Unary { hir_id, .. }
+            | TExpr::Call { hir_id, .. }
+            | TExpr::Index { hir_id, .. }
+            | TExpr::Slice { hir_id, .. }
+            | TExpr::InterpolatedString { hir_id, .. }
+            | TExpr::Dict { hir_id, .. }
+            | TExpr::Tuple { hir_id, .. }
+            | TExpr::Range { hir_id, .. }
+            | TExpr::Field { hir_id, .. }
+            | TExpr::MethodCall { hir_id, .. }
+            | TExpr::Construct { hir_id, .. }
+            | TExpr::Variant { hir_id, .. }
+            | TExpr::Lambda { hir_id, .. } => *hir_id,
         }
     }
 }
@@ -1,4 +1,5 @@
 pub mod checker;
+mod patterns;
 pub mod types;
 
 pub use checker::{typecheck_program, typecheck_program_with_env};
@@ -0,0 +1,133 @@
+//! Exhaustiveness of `match` statements.
+//!
+//! Rows of patterns are searched for a value none of them matches, one column at a
+//! time: a column of an enum, `bool` or tuple type is split by constructor, any other
+//! column only by whether a row matches everything there.
+
+use std::collections::HashMap;
+
+use crate::hir::hir_types::HirId;
+use crate::shir::sym::{EnumInfo, Type};
+
+use super::types::{TExpr, TPattern};
+
+static WILDCARD: TPattern = TPattern::Wildcard { hir_id: HirId(0) };
+
+/// A value of type `ty` that none of `patterns` matches, written as a pattern.
+pub(super) fn missing_case(
+    patterns: &[&TPattern],
+    ty: &Type,
+    enums: &HashMap<String, EnumInfo>,
+) -> Option<String> {
+    let rows: Vec<Vec<&TPattern>> = patterns.iter().map(|p| vec![*p]).collect();
+    uncovered(&rows, std::slice::from_ref(ty), enums).map(|mut w| w.remove(0))
+}
+
+/// One constructor of a type: its name and the types of its parts.
+struct Ctor {
+    name: String,
+    parts: Vec<Type>,
+}
+
+/// All constructors of `ty`, or `None` when there are too many to list.
+fn constructors(ty: &Type, enums: &HashMap<String, EnumInfo>) -> Option<Vec<Ctor>> {
+    match ty {
+        Type::Enum(name) => enums.get(name).map(|info| {
+            info.variants
+                .iter()
+                .map(|(variant, parts)| Ctor {
+                    name: variant.clone(),
+                    parts: parts.clone(),
+                })
+                .collect()
+        }),
+        Type::Bool => Some(
+            ["True", "False"]
+                .into_iter()
+                .map(|name| Ctor {
+                    name: name.to_string(),
+                    parts: Vec::new(),
+                })
+                .collect(),
+        ),
+        Type::Tuple(items) => Some(vec![Ctor {
+            name: String::new(),
+            parts: items.clone(),
+        }]),
+        _ => None,
+    }
+}
+
+/// Values matched by none of `rows`, one pattern per column of `tys`.
+fn uncovered(
+    rows: &[Vec<&TPattern>],
+    tys: &[Type],
+    enums: &HashMap<String, EnumInfo>,
+) -> Option<Vec<String>> {
+    let Some((ty, rest_tys)) = tys.split_first() else {
+        return rows.is_empty().then(Vec::new);
+    };
+    let Some(ctors) = constructors(ty, enums) else {
+        // Only rows matching anything in this column can cover the rest
+        let rows: Vec<Vec<&TPattern>> = rows
+            .iter()
+            .filter(|row| is_wildcard(row[0]))
+            .map(|row| row[1..].to_vec())
+            .collect();
+        let mut witness = uncovered(&rows, rest_tys, enums)?;
+        witness.insert(0, "_".to_string());
+        return Some(witness);
+    };
+    for ctor in ctors {
+        let rows: Vec<Vec<&TPattern>> = rows
+            .iter()
+            .filter_map(|row| specialize(row, &ctor))
+            .collect();
+        let tys: Vec<Type> = ctor.parts.iter().chain(rest_tys).cloned().collect();
+        if let Some(mut witness) = uncovered(&rows, &tys, enums) {
+            let parts: Vec<String> = witness.drain(..ctor.parts.len()).collect();
+            witness.insert(0, render(ty, &ctor.name, &parts));
+            return Some(witness);
+        }
+    }
+    None
+}
+
+/// The row without its first pattern, that pattern replaced by its parts, if the
+/// first pattern matches values built with `ctor`.
+fn specialize<'p>(row: &[&'p TPattern], ctor: &Ctor) -> Option<Vec<&'p TPattern>> {
+    let (first, rest) = row.split_first()?;
+    let mut parts: Vec<&TPattern> = match first {
+        TPattern::Wildcard { .. } | TPattern::Bind { .. } => vec![&WILDCARD; ctor.parts.len()],
+        TPattern::Variant { variant, args, .. } if *variant == ctor.name => args.iter().collect(),
+        TPattern::Tuple { items, .. } => items.iter().collect(),
+        TPattern::Literal { value, .. } => {
+            let name = match value {
+                TExpr::Bool { value: true, .. } => "True",
+                TExpr::Bool { value: false, .. } => "False",
+                _ => return None,
+            };
+            if name != ctor.name {
+                return None;
+            }
+            Vec::new()
+        }
+        TPattern::Variant { .. } => return None,
+    };
+    parts.extend_from_slice(rest);
+    Some(parts)
+}
+
+fn is_wildcard(pattern: &TPattern) -> bool {
+    matches!(pattern, TPattern::Wildcard { .. } | TPattern::Bind { .. })
+}
+
+fn render(ty: &Type, ctor: &str, parts: &[String]) -> String {
+    match ty {
+        Type::Tuple(_) if parts.len() == 1 => format!("({},)", parts[0]),
+        Type::Tuple(_) => format!("({})", parts.join(", ")),
+        Type::Enum(name) if parts.is_empty() => format!("{}.{}", name, ctor),
+        Type::Enum(name) => format!("{}.{}({})", name, ctor, parts.join(", ")),
+        _ => ctor.to_string(),
+    }
+}
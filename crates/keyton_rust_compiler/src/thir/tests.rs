@@ -1,4 +1,5 @@
 use super::{TExpr, TStmt, TStringPart, typecheck_program};
+use crate::format_spec::FormatSpec;
 use crate::hir::hir_types::{HirBinOp, HirId};
 use crate::hir::lower_program;
 use crate::lexer::Lexer;
@@ -12,8 +13,8 @@ fn program1_thir() {
 x = x + 1
 print(x)
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     let mut resolved = resolve_program(&hir);
     let typed = typecheck_program(&mut resolved);
@@ -93,8 +94,8 @@ fn program4_thir() {
 x = "Hello"
 print(x)
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     let mut resolved = resolve_program(&hir);
     let typed = typecheck_program(&mut resolved);
@@ -164,8 +165,8 @@ print(x)
 #[test]
 fn program2_thir() {
     let input = r#"print("Hello, World")"#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     let mut resolved = resolve_program(&hir);
     let typed = typecheck_program(&mut resolved);
@@ -203,8 +204,8 @@ fn program3_thir() {
     let input = r#"x = 12
 print(f"{x}")
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     let mut resolved = resolve_program(&hir);
     let typed = typecheck_program(&mut resolved);
@@ -250,6 +251,7 @@ print(f"{x}")
                                     sym: SymbolId(1),
                                     ty: Type::I64,
                                 },
+                                spec: FormatSpec::default(),
                             },
                             TStringPart::Text {
                                 hir_id: HirId(10),
@@ -271,8 +273,8 @@ fn program5_thir_same_type_reuse() {
 x = 42
 print(x)
 "#;
-    let tokens = Lexer::new(input).tokenize();
-    let ast = Parser::new(tokens).parse_program();
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
     let hir = lower_program(ast);
     let mut resolved = resolve_program(&hir);
     let typed = typecheck_program(&mut resolved);
@@ -335,3 +337,569 @@ print(x)
     // Var type snapshot: x(1): Int (same symbol reused)
     assert_eq!(typed.var_types.get(&SymbolId(1)), Some(&Type::I64));
 }
+
+fn typecheck_source(input: &str) -> super::TypedProgram {
+    let tokens = Lexer::new(input).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse_program().unwrap();
+    let hir = lower_program(ast);
+    let mut resolved = resolve_program(&hir);
+    typecheck_program(&mut resolved)
+}
+
+#[test]
+fn comparisons_and_logic_yield_bool() {
+    let typed = typecheck_source("a = 7 // 2 - -1\nb = a >= 3 and not a == 4 or False\n");
+    assert!(
+        typed.report.errors.is_empty(),
+        "unexpected type errors: {:?}",
+        typed.report.errors
+    );
+    // Symbols: print (0), a (1), b (2)
+    assert_eq!(typed.var_types.get(&SymbolId(1)), Some(&Type::I64));
+    assert_eq!(typed.var_types.get(&SymbolId(2)), Some(&Type::Bool));
+}
+
+#[test]
+fn operator_operand_mismatches_are_reported() {
+    let typed = typecheck_source("a = 1 * True\nb = not 1\nc = 1 == \"1\"\n");
+    assert_eq!(
+        typed.report.errors,
+        vec![
+            super::TypeError::TypeMismatch {
+                hir_id: HirId(2),
+                expected: Type::I64,
+                found: Type::Bool,
+            },
+            super::TypeError::TypeMismatch {
+                hir_id: HirId(6),
+                expected: Type::Bool,
+                found: Type::I64,
+            },
+            super::TypeError::TypeMismatch {
+                hir_id: HirId(9),
+                expected: Type::I64,
+                found: Type::Str,
+            },
+        ]
+    );
+}
+
+#[test]
+fn floats_promote_mixed_int_arithmetic() {
+    let typed = typecheck_source("a = 1.5 * 2\nb = 7 / 2\nc = 3 // 2\nd = -a < 1\ne = 2 == 2.0\n");
+    assert!(
+        typed.report.errors.is_empty(),
+        "unexpected type errors: {:?}",
+        typed.report.errors
+    );
+    // Symbols: print (0), a (1), b (2), c (3), d (4), e (5)
+    assert_eq!(typed.var_types.get(&SymbolId(1)), Some(&Type::F64));
+    assert_eq!(typed.var_types.get(&SymbolId(2)), Some(&Type::F64));
+    assert_eq!(typed.var_types.get(&SymbolId(3)), Some(&Type::I64));
+    assert_eq!(typed.var_types.get(&SymbolId(4)), Some(&Type::Bool));
+    assert_eq!(typed.var_types.get(&SymbolId(5)), Some(&Type::Bool));
+
+    let typed = typecheck_source("a = 1.5 + \"x\"\n");
+    assert_eq!(
+        typed.report.errors,
+        vec![super::TypeError::TypeMismatch {
+            hir_id: HirId(2),
+            expected: Type::I64,
+            found: Type::Str,
+        }]
+    );
+}
+
+#[test]
+fn conditions_must_be_bool() {
+    let typed = typecheck_source(
+        "x = 1\nif x:\n    x = 2\nwhile \"s\":\n    break\nif x > 0:\n    x = 3\n",
+    );
+    assert_eq!(
+        typed.report.errors,
+        vec![
+            super::TypeError::TypeMismatch {
+                hir_id: HirId(4),
+                expected: Type::Bool,
+                found: Type::I64,
+            },
+            super::TypeError::TypeMismatch {
+                hir_id: HirId(8),
+                expected: Type::Bool,
+                found: Type::Str,
+            },
+        ]
+    );
+}
+
+fn mismatches(typed: &super::TypedProgram) -> Vec<(Type, Type)> {
+    typed
+        .report
+        .errors
+        .iter()
+        .map(|e| match e {
+            super::TypeError::TypeMismatch {
+                expected, found, ..
+            } => (expected.clone(), found.clone()),
+            other => panic!("unexpected error {:?}", other),
+        })
+        .collect()
+}
+
+#[test]
+fn annotated_variables_keep_their_declared_type() {
+    let typed = typecheck_source("let x: i64 = \"a\"\ny: str = \"b\"\ny = 3\nz: f64 = 1.5\n");
+    assert_eq!(
+        mismatches(&typed),
+        vec![(Type::I64, Type::Str), (Type::Str, Type::I64)]
+    );
+    let z = typed
+        .var_types
+        .iter()
+        .find(|(sid, _)| sid.0 == 3)
+        .map(|(_, ty)| ty.clone());
+    assert_eq!(z, Some(Type::F64));
+}
+
+#[test]
+fn annotated_params_and_return_are_checked() {
+    let typed = typecheck_source(
+        "fn half(x: f64) -> f64:\n    x / 2\nfn name(n: i64) -> str:\n    n + 1\na = half(\"s\")\nb = name(2)\nc = half(4.0)\n",
+    );
+    assert_eq!(
+        mismatches(&typed),
+        vec![(Type::F64, Type::Str), (Type::Str, Type::I64)]
+    );
+}
+
+#[test]
+fn function_types_come_from_first_call_and_returns() {
+    let typed = typecheck_source(
+        "fn fact(n):\n    if n <= 1:\n        return 1\n    n * fact(n - 1)\nfn half(x):\n    x / 2\na = fact(5)\nb = half(3)\n",
+    );
+    assert!(
+        typed.report.errors.is_empty(),
+        "type errors: {:?}",
+        typed.report.errors
+    );
+    let ret_of = |idx: usize| match &typed.thir[idx] {
+        TStmt::FuncDef { ret, body, .. } => {
+            assert!(!body.is_empty());
+            ret.clone()
+        }
+        other => panic!("expected function definition, found {:?}", other),
+    };
+    assert_eq!(ret_of(0), Type::I64);
+    assert_eq!(ret_of(1), Type::F64);
+}
+
+#[test]
+fn function_return_paths_are_checked() {
+    let typed = typecheck_source(
+        "fn pick(flag: bool):\n    if flag:\n        return 1\n    y = 2\nfn both(flag: bool):\n    if flag:\n        return 1\n    return \"no\"\n",
+    );
+    assert_eq!(
+        typed.report.errors,
+        vec![
+            super::TypeError::MissingReturn {
+                hir_id: HirId(1),
+                func: "pick".to_string(),
+            },
+            super::TypeError::TypeMismatch {
+                hir_id: HirId(14),
+                expected: Type::I64,
+                found: Type::Str,
+            },
+        ]
+    );
+}
+
+#[test]
+fn variables_cannot_change_type_inside_loops() {
+    let typed = typecheck_source(
+        "y = 3\nwhile y > 0:\n    y = \"s\"\nn = 1\nfor i in 0..3:\n    n = 2.5\nz = 1\nz = \"ok\"\n",
+    );
+    let shadowed: Vec<&str> = typed
+        .report
+        .errors
+        .iter()
+        .map(|e| match e {
+            super::TypeError::ShadowingImpossible { var_name, .. } => var_name.as_str(),
+            other => panic!("unexpected error: {:?}", other),
+        })
+        .collect();
+    // Outside a loop `z` is shadowed as before
+    assert_eq!(shadowed, ["y", "n"]);
+}
+
+#[test]
+fn indexing_is_typed_against_the_sequence() {
+    let typed = typecheck_source(
+        "s = \"abc\"\nc = s[0]\nt = s[1..]\nxs = [1, 2]\nxs[0] = xs[1]\nn = 5\nd = n[0]\ns[0] = \"z\"\ne = s[\"k\"]\n",
+    );
+    let errors: Vec<String> = typed
+        .report
+        .errors
+        .iter()
+        .map(|e| match e {
+            super::TypeError::NotIndexable { ty, .. } => format!("not indexable: {}", ty),
+            super::TypeError::ItemAssignment { ty, .. } => format!("item assignment: {}", ty),
+            super::TypeError::TypeMismatch {
+                expected, found, ..
+            } => format!("expected {}, found {}", expected, found),
+            other => panic!("unexpected error {:?}", other),
+        })
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            "not indexable: i64",
+            "item assignment: str",
+            "expected i64, found str",
+        ]
+    );
+    // Symbols: print (0), s (1), c (2), t (3)
+    assert_eq!(typed.var_types.get(&SymbolId(2)), Some(&Type::Str));
+    assert_eq!(typed.var_types.get(&SymbolId(3)), Some(&Type::Str));
+}
+
+#[test]
+fn list_element_types_are_inferred() {
+    let typed = typecheck_source(
+        "xs = []\nappend(xs, 1.5)\nt = sum(xs)\nns = [1, 2]\nbad = [1, \"a\"]\nappend(ns, True)\n",
+    );
+    let errors: Vec<String> = typed
+        .report
+        .errors
+        .iter()
+        .map(|e| match e {
+            super::TypeError::TypeMismatch {
+                expected, found, ..
+            } => format!("expected {}, found {}", expected, found),
+            other => panic!("unexpected error {:?}", other),
+        })
+        .collect();
+    assert_eq!(
+        errors,
+        vec!["expected i64, found str", "expected i64, found bool"]
+    );
+    // Symbols: print (0), xs (1), t (2), ns (3)
+    let f64_list = Type::List(Box::new(Type::F64));
+    assert_eq!(typed.var_types.get(&SymbolId(1)), Some(&f64_list));
+    assert_eq!(typed.var_types.get(&SymbolId(2)), Some(&Type::F64));
+    assert_eq!(
+        typed.var_types.get(&SymbolId(3)),
+        Some(&Type::List(Box::new(Type::I64)))
+    );
+}
+
+#[test]
+fn dict_key_and_value_types_are_checked() {
+    let typed = typecheck_source(
+        "d = {}\nd[\"a\"] = 1\nn = d[\"a\"]\nd[1] = 2\nbad = {[1]: 2}\nok = \"a\" in d\nno = 1 in 5\nks = keys(d)\n",
+    );
+    let errors: Vec<String> = typed
+        .report
+        .errors
+        .iter()
+        .map(|e| match e {
+            super::TypeError::TypeMismatch {
+                expected, found, ..
+            } => format!("expected {}, found {}", expected, found),
+            super::TypeError::UnhashableKey { ty, .. } => format!("unhashable {}", ty),
+            super::TypeError::NotContainer { ty, .. } => format!("not a container {}", ty),
+            other => panic!("unexpected error {:?}", other),
+        })
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            "expected str, found i64",
+            "unhashable list[i64]",
+            "not a container i64"
+        ]
+    );
+    // Symbols: print (0), d (1), n (2); assigning into `{}` decides its types
+    let str_to_int = Type::Dict(Box::new(Type::Str), Box::new(Type::I64));
+    assert_eq!(typed.var_types.get(&SymbolId(1)), Some(&str_to_int));
+    assert_eq!(typed.var_types.get(&SymbolId(2)), Some(&Type::I64));
+    // `ks = keys(d)` is the only list
+    let str_list = Type::List(Box::new(Type::Str));
+    assert!(typed.var_types.values().any(|ty| *ty == str_list));
+}
+
+#[test]
+fn tuple_unpacking_checks_arity_and_item_types() {
+    let typed = typecheck_source(
+        "p = (1, \"a\")\nn, s = p\nx, y, z = p\nw = p[1]\nbad = p[2]\nfor k, v in items({\"a\": 1.5}):\n    print(k)\nq, r = 5\n",
+    );
+    let errors: Vec<String> = typed
+        .report
+        .errors
+        .iter()
+        .map(|e| match e {
+            super::TypeError::UnpackMismatch {
+                expected, found, ..
+            } => format!("unpack {} from {}", expected, found),
+            super::TypeError::TupleIndex { len, .. } => format!("tuple index of {}", len),
+            other => panic!("unexpected error {:?}", other),
+        })
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            "unpack 3 from tuple[i64, str]",
+            "tuple index of 2",
+            "unpack 2 from i64"
+        ]
+    );
+    let types: Vec<&Type> = typed.var_types.values().collect();
+    assert!(types.contains(&&Type::Tuple(vec![Type::I64, Type::Str])));
+    assert!(types.contains(&&Type::F64));
+    // Symbols: print (0), p (1), n (2), s (3); w is the fifth global
+    assert_eq!(typed.var_types.get(&SymbolId(2)), Some(&Type::I64));
+    assert_eq!(typed.var_types.get(&SymbolId(3)), Some(&Type::Str));
+}
+
+#[test]
+fn for_in_derives_the_item_type_of_each_iterable() {
+    let typed = typecheck_source(
+        "for x in [1.5]:\n    a = x\nfor c in \"hi\":\n    b = c\nfor k in {\"k\": 1}:\n    d = k\nr = 0..3\nfor i in r:\n    e = i\nfor j in 5:\n    print(j)\nok = 2 in r\n",
+    );
+    let errors: Vec<String> = typed
+        .report
+        .errors
+        .iter()
+        .map(|e| match e {
+            super::TypeError::NotIterable { ty, .. } => format!("not iterable {}", ty),
+            other => panic!("unexpected error {:?}", other),
+        })
+        .collect();
+    assert_eq!(errors, vec!["not iterable i64"]);
+    // Symbols: print (0), a (1), b (2), d (3), r (4), e (5)
+    assert_eq!(typed.var_types.get(&SymbolId(1)), Some(&Type::F64));
+    assert_eq!(typed.var_types.get(&SymbolId(2)), Some(&Type::Str));
+    assert_eq!(typed.var_types.get(&SymbolId(3)), Some(&Type::Str));
+    assert_eq!(typed.var_types.get(&SymbolId(4)), Some(&Type::Range));
+    assert_eq!(typed.var_types.get(&SymbolId(5)), Some(&Type::I64));
+}
+
+#[test]
+fn struct_fields_constructors_and_methods_are_checked() {
+    let typed = typecheck_source(
+        "struct P:\n    x: i64\n    name: str\n    fn label(self, n) -> str:\n        return self.name\np = P(x=1, name=\"a\")\nn = p.x\ns = p.label(2)\np.x = \"b\"\nq = P(x=1.5, nam=\"c\")\nz = p.y\nw = n.label(1)\n",
+    );
+    let errors: Vec<String> = typed
+        .report
+        .errors
+        .iter()
+        .map(|e| match e {
+            super::TypeError::TypeMismatch {
+                expected, found, ..
+            } => format!("expected {}, found {}", expected, found),
+            super::TypeError::MissingField { strukt, fields, .. } => {
+                format!("missing {}.{}", strukt, fields.join(","))
+            }
+            super::TypeError::UnexpectedField { strukt, field, .. } => {
+                format!("unexpected {}.{}", strukt, field)
+            }
+            super::TypeError::NoAttribute { ty, name, .. } => format!("no {}.{}", ty, name),
+            other => panic!("unexpected error {:?}", other),
+        })
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            "expected i64, found str",
+            "expected i64, found f64",
+            "unexpected P.nam",
+            "missing P.name",
+            "no P.y",
+            "no i64.label"
+        ]
+    );
+    let types: Vec<&Type> = typed.var_types.values().collect();
+    assert!(types.contains(&&Type::Struct("P".to_string())));
+    assert!(types.contains(&&Type::Str));
+}
+
+#[test]
+fn enum_variants_and_match_exhaustiveness_are_checked() {
+    let typed = typecheck_source(
+        "enum Shape:\n    Circle(f64)\n    Rect(f64, f64)\n    Empty\ns = Shape.Circle(1.0)\nmatch s:\n    case Shape.Circle(r):\n        a = r\n    case Shape.Empty:\n        a = 0.0\nmatch (s, True):\n    case (Shape.Rect(w, h), _):\n        b = 1\n    case (_, False):\n        b = 2\n    case (Shape.Circle(_), True):\n        b = 3\nmatch 3:\n    case 1:\n        c = 1\nt = Shape.Rect(1.0)\nu = Shape.Square(2.0)\nmatch s:\n    case Shape.Circle(\"x\"):\n        d = 1\n    case _:\n        d = 2\n",
+    );
+    let errors: Vec<String> = typed
+        .report
+        .errors
+        .iter()
+        .map(|e| match e {
+            super::TypeError::TypeMismatch {
+                expected, found, ..
+            } => format!("expected {}, found {}", expected, found),
+            super::TypeError::ArityMismatch {
+                expected, found, ..
+            } => format!("arity {} != {}", expected, found),
+            super::TypeError::NoAttribute { ty, name, .. } => format!("no {}.{}", ty, name),
+            super::TypeError::NonExhaustiveMatch { ty, missing, .. } => {
+                format!("{} misses {}", ty, missing)
+            }
+            other => panic!("unexpected error {:?}", other),
+        })
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            "Shape misses Shape.Rect(_, _)",
+            "tuple[Shape, bool] misses (Shape.Empty, True)",
+            "i64 misses _",
+            "arity 2 != 1",
+            "no Shape.Square",
+            "expected f64, found str",
+        ]
+    );
+    let types: Vec<&Type> = typed.var_types.values().collect();
+    assert!(types.contains(&&Type::Enum("Shape".to_string())));
+    assert!(types.contains(&&Type::F64));
+}
+
+#[test]
+fn optional_values_are_wrapped_and_narrowed() {
+    let typed = typecheck_source(
+        "x: i64? = None\nx = 2\ny = x + 1\nif x is not None:\n    z = x + 1\nn = 5\nif n is None:\n    n = 0\nw: str? = 3\nfn first(xs: list[i64]) -> i64?:\n    for v in xs:\n        return v\n    return None\nf = first([1])\n",
+    );
+    let errors: Vec<String> = typed
+        .report
+        .errors
+        .iter()
+        .map(|e| match e {
+            super::TypeError::TypeMismatch {
+                expected, found, ..
+            } => format!("expected {}, found {}", expected, found),
+            other => panic!("unexpected error {:?}", other),
+        })
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            "expected i64, found Option[i64]",
+            "expected Option[i64], found i64",
+            "expected str, found i64",
+        ]
+    );
+    // `x = 2` stores `Some(2)`; inside the `if`, `x` reads as its value
+    let wrapped = typed.thir.iter().any(|s| {
+        matches!(s, TStmt::Assign { expr: TExpr::Some { expr, .. }, .. } if matches!(**expr, TExpr::Int { value: 2, .. }))
+    });
+    assert!(wrapped);
+    let narrowed = typed.thir.iter().any(|s| match s {
+        TStmt::If { then_branch, .. } => matches!(
+            then_branch.as_slice(),
+            [TStmt::Assign { expr: TExpr::Binary { left, .. }, .. }]
+                if matches!(**left, TExpr::Unwrap { ty: Type::I64, .. })
+        ),
+        _ => false,
+    });
+    assert!(narrowed);
+    let types: Vec<&Type> = typed.var_types.values().collect();
+    assert!(types.contains(&&Type::Option(Box::new(Type::I64))));
+}
+
+#[test]
+fn raise_takes_a_str_message_and_except_binds_it() {
+    let typed = typecheck_source(
+        "try:\n    raise ValueError(3)\nexcept ValueError as e:\n    m = e\nfn f(n: i64) -> i64:\n    if n > 0:\n        return n\n    raise ValueError(\"none\")\nr = f(1)\n",
+    );
+    let errors: Vec<String> = typed
+        .report
+        .errors
+        .iter()
+        .map(|e| match e {
+            super::TypeError::TypeMismatch {
+                expected, found, ..
+            } => format!("expected {}, found {}", expected, found),
+            other => panic!("unexpected error {:?}", other),
+        })
+        .collect();
+    // A function ending in `raise` needs no final `return`
+    assert_eq!(errors, vec!["expected str, found i64"]);
+    let TStmt::Try { handlers, .. } = &typed.thir[0] else {
+        panic!("expected try statement");
+    };
+    let sym = handlers[0].sym.expect("handler binds a name");
+    assert_eq!(typed.var_types.get(&sym), Some(&Type::Str));
+}
+
+#[test]
+fn lambdas_take_parameter_types_from_where_they_are_passed() {
+    let typed = typecheck_source(
+        "fn apply(f: fn(i64) -> i64, x: i64) -> i64:\n    return f(x)\nk = 2\nr = apply(fn(x) => x * k, 3)\nys = map([1, 2], fn(x) => x > 1)\ng = fn(s: str) => s\nbad = fn(y) => y\n",
+    );
+    let errors: Vec<String> = typed
+        .report
+        .errors
+        .iter()
+        .map(|e| match e {
+            super::TypeError::CannotInferParam { param, .. } => format!("cannot infer {}", param),
+            other => panic!("unexpected error {:?}", other),
+        })
+        .collect();
+    // A lambda assigned without a function type has nothing to infer `y` from
+    assert_eq!(errors, vec!["cannot infer y"]);
+    let type_of = |i: usize| match &typed.thir[i] {
+        TStmt::Assign { sym, .. } => typed.var_types.get(sym).cloned(),
+        other => panic!("expected assignment, found {:?}", other),
+    };
+    assert_eq!(type_of(2), Some(Type::I64));
+    assert_eq!(type_of(3), Some(Type::List(Box::new(Type::Bool))));
+    assert_eq!(
+        type_of(4),
+        Some(Type::Func(vec![Type::Str], Box::new(Type::Str)))
+    );
+}
+
+#[test]
+fn strings_concatenate_compare_and_have_typed_methods() {
+    let typed = typecheck_source(
+        "s = \"a\" + \"b\"\nlt = s < \"c\"\nn = len(s) + \"abc\".find(\"c\")\nws = s.upper().split(\",\")\nj = \"-\".join(ws)\nok = j.strip().startswith(\"A\")\nbad = s + 1\nnone = len(3)\n",
+    );
+    let errors: Vec<String> = typed
+        .report
+        .errors
+        .iter()
+        .map(|e| match e {
+            super::TypeError::TypeMismatch {
+                expected, found, ..
+            } => format!("expected {}, found {}", expected, found),
+            super::TypeError::NoLen { ty, .. } => format!("no len {}", ty),
+            other => panic!("unexpected error {:?}", other),
+        })
+        .collect();
+    assert_eq!(errors, vec!["expected str, found i64", "no len i64"]);
+    let type_of = |i: usize| match &typed.thir[i] {
+        TStmt::Assign { sym, .. } => typed.var_types.get(sym).cloned(),
+        other => panic!("expected assignment, found {:?}", other),
+    };
+    assert_eq!(type_of(0), Some(Type::Str));
+    assert_eq!(type_of(1), Some(Type::Bool));
+    assert_eq!(type_of(2), Some(Type::I64));
+    assert_eq!(type_of(3), Some(Type::List(Box::new(Type::Str))));
+    assert_eq!(type_of(4), Some(Type::Str));
+    assert_eq!(type_of(5), Some(Type::Bool));
+}
+
+#[test]
+fn fstring_format_options_must_suit_the_value() {
+    let typed = typecheck_source(
+        "n = 3\nr = 0.5\ns = \"x\"\nok = f\"{n:>4} {n:.2f} {r:.1f} {n:x} {s:^5s} {s!r:>6}\"\nbad = f\"{s:d} {n:.2} {n!r:x}\"\n",
+    );
+    let errors: Vec<String> = typed
+        .report
+        .errors
+        .iter()
+        .map(|e| match e {
+            super::TypeError::BadFormat { option, ty, .. } => format!("{} on {}", option, ty),
+            other => panic!("unexpected error {:?}", other),
+        })
+        .collect();
+    assert_eq!(errors, vec!["d on str", ".2 on i64", "x on str"]);
+}
@@ -1,4 +1,5 @@
-use crate::hir::hir_types::{HirBinOp, HirId};
+use crate::format_spec::FormatSpec;
+use crate::hir::hir_types::{HirBinOp, HirId, HirUnaryOp};
 use crate::shir::sym::{SymbolId, Type};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -9,6 +10,26 @@ pub enum TStmt {
         sym: SymbolId,
         expr: TExpr,
     },
+    /// `target[index] = expr`
+    IndexAssign {
+        hir_id: HirId,
+        target: TExpr,
+        index: TExpr,
+        expr: TExpr,
+    },
+    /// `a, b = expr`; `syms` may differ from the resolved ones when a name is shadowed
+    TupleAssign {
+        hir_id: HirId,
+        syms: Vec<SymbolId>,
+        expr: TExpr,
+    },
+    /// `target.field = expr`
+    FieldAssign {
+        hir_id: HirId,
+        target: TExpr,
+        field: String,
+        expr: TExpr,
+    },
     ExprStmt {
         hir_id: HirId,
         expr: TExpr,
@@ -20,12 +41,111 @@ pub enum TStmt {
         end: TExpr,
         body: Vec<TStmt>,
     },
+    ForEach {
+        hir_id: HirId,
+        syms: Vec<SymbolId>,
+        iter: TExpr,
+        body: Vec<TStmt>,
+    },
+    While {
+        hir_id: HirId,
+        cond: TExpr,
+        body: Vec<TStmt>,
+    },
+    Break {
+        hir_id: HirId,
+    },
+    Continue {
+        hir_id: HirId,
+    },
     If {
         hir_id: HirId,
         cond: TExpr,
         then_branch: Vec<TStmt>,
         else_branch: Vec<TStmt>,
     },
+    /// A user function, typed with the parameter types of its first call unless they
+    /// were annotated. Parameter types are recorded in `var_types`.
+    FuncDef {
+        hir_id: HirId,
+        sym: SymbolId,
+        params: Vec<SymbolId>,
+        ret: Type,
+        body: Vec<TStmt>,
+    },
+    Return {
+        hir_id: HirId,
+        expr: Option<TExpr>,
+    },
+    /// A user struct; `methods` are `FuncDef`s checked like functions
+    StructDef {
+        hir_id: HirId,
+        name: String,
+        methods: Vec<TStmt>,
+    },
+    EnumDef {
+        hir_id: HirId,
+        name: String,
+    },
+    Match {
+        hir_id: HirId,
+        subject: TExpr,
+        arms: Vec<TMatchArm>,
+    },
+    Try {
+        hir_id: HirId,
+        body: Vec<TStmt>,
+        handlers: Vec<TExceptHandler>,
+        finally: Vec<TStmt>,
+    },
+    /// `message` is a `str`; a bare `raise` has neither kind nor message
+    Raise {
+        hir_id: HirId,
+        kind: Option<String>,
+        message: Option<TExpr>,
+    },
+}
+
+/// `except Kind as name:`; the name holds the exception's message, a `str`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TExceptHandler {
+    pub hir_id: HirId,
+    pub kind: Option<String>,
+    pub sym: Option<SymbolId>,
+    pub body: Vec<TStmt>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TMatchArm {
+    pub hir_id: HirId,
+    pub pattern: TPattern,
+    pub body: Vec<TStmt>,
+}
+
+/// A `case` pattern; the types of bound names are recorded in `var_types`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TPattern {
+    Wildcard {
+        hir_id: HirId,
+    },
+    Bind {
+        hir_id: HirId,
+        sym: SymbolId,
+    },
+    Literal {
+        hir_id: HirId,
+        value: TExpr,
+    },
+    Variant {
+        hir_id: HirId,
+        enum_name: String,
+        variant: String,
+        args: Vec<TPattern>,
+    },
+    Tuple {
+        hir_id: HirId,
+        items: Vec<TPattern>,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -35,6 +155,11 @@ pub enum TExpr {
         value: i64,
         ty: Type,
     },
+    Float {
+        hir_id: HirId,
+        value: f64,
+        ty: Type,
+    },
     Str {
         hir_id: HirId,
         value: String,
@@ -45,6 +170,22 @@ pub enum TExpr {
         value: bool,
         ty: Type,
     },
+    None {
+        hir_id: HirId,
+        ty: Type,
+    },
+    /// A value stored where `T?` is expected
+    Some {
+        hir_id: HirId,
+        expr: Box<TExpr>,
+        ty: Type,
+    },
+    /// An optional variable read where `is not None` has narrowed it to its value
+    Unwrap {
+        hir_id: HirId,
+        expr: Box<TExpr>,
+        ty: Type,
+    },
     Name {
         hir_id: HirId,
         sym: SymbolId,
@@ -57,23 +198,99 @@ pub enum TExpr {
         right: Box<TExpr>,
         ty: Type,
     },
+    Unary {
+        hir_id: HirId,
+        op: HirUnaryOp,
+        expr: Box<TExpr>,
+        ty: Type,
+    },
     Call {
         hir_id: HirId,
         func: Box<TExpr>,
         args: Vec<TExpr>,
         ty: Type,
     },
+    Index {
+        hir_id: HirId,
+        target: Box<TExpr>,
+        index: Box<TExpr>,
+        ty: Type,
+    },
+    Slice {
+        hir_id: HirId,
+        target: Box<TExpr>,
+        start: Option<Box<TExpr>>,
+        end: Option<Box<TExpr>>,
+        ty: Type,
+    },
     InterpolatedString {
         hir_id: HirId,
         parts: Vec<TStringPart>,
         ty: Type,
     },
+    Dict {
+        hir_id: HirId,
+        entries: Vec<(TExpr, TExpr)>,
+        ty: Type,
+    },
+    Tuple {
+        hir_id: HirId,
+        items: Vec<TExpr>,
+        ty: Type,
+    },
+    Range {
+        hir_id: HirId,
+        start: Box<TExpr>,
+        end: Box<TExpr>,
+        ty: Type,
+    },
+    Field {
+        hir_id: HirId,
+        target: Box<TExpr>,
+        name: String,
+        ty: Type,
+    },
+    /// A call of the struct method `method` on `target`
+    MethodCall {
+        hir_id: HirId,
+        target: Box<TExpr>,
+        method: SymbolId,
+        args: Vec<TExpr>,
+        ty: Type,
+    },
+    Construct {
+        hir_id: HirId,
+        name: String,
+        fields: Vec<(String, TExpr)>,
+        ty: Type,
+    },
+    Variant {
+        hir_id: HirId,
+        enum_name: String,
+        variant: String,
+        args: Vec<TExpr>,
+        ty: Type,
+    },
+    /// `fn(x) => body`; `ty` is the `Func` type the parameters were checked with
+    Lambda {
+        hir_id: HirId,
+        params: Vec<SymbolId>,
+        body: Box<TExpr>,
+        ty: Type,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum TStringPart {
-    Text { hir_id: HirId, value: String },
-    Expr { hir_id: HirId, expr: TExpr },
+    Text {
+        hir_id: HirId,
+        value: String,
+    },
+    Expr {
+        hir_id: HirId,
+        expr: TExpr,
+        spec: FormatSpec,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -100,6 +317,88 @@ pub enum TypeError {
         hir_id: HirId,
         var_name: String,
     },
+    /// A function with a non-unit return type whose body can end without `return`
+    MissingReturn {
+        hir_id: HirId,
+        func: String,
+    },
+    /// An unannotated parameter whose first call does not pin down its type
+    CannotInferParam {
+        hir_id: HirId,
+        param: String,
+    },
+    /// Indexing or slicing a value that is neither a list nor a string
+    NotIndexable {
+        hir_id: HirId,
+        ty: Type,
+    },
+    /// Assigning to an element of an immutable sequence such as a string
+    ItemAssignment {
+        hir_id: HirId,
+        ty: Type,
+    },
+    /// A dict key of a type that cannot be hashed, such as a list or a float
+    UnhashableKey {
+        hir_id: HirId,
+        ty: Type,
+    },
+    /// The right side of `in` is not a list, dict, string or range
+    NotContainer {
+        hir_id: HirId,
+        ty: Type,
+    },
+    /// `len()` of a value that is not a string, list or dict
+    NoLen {
+        hir_id: HirId,
+        ty: Type,
+    },
+    /// An f-string format option the value cannot take, as in `{name:.2f}`
+    BadFormat {
+        hir_id: HirId,
+        option: String,
+        ty: Type,
+    },
+    /// A `for` loop over a value that has no items
+    NotIterable {
+        hir_id: HirId,
+        ty: Type,
+    },
+    /// Unpacking a value that is not a tuple of `expected` items
+    UnpackMismatch {
+        hir_id: HirId,
+        expected: usize,
+        found: Type,
+    },
+    /// A tuple index that is not an integer literal below `len`
+    TupleIndex {
+        hir_id: HirId,
+        len: usize,
+    },
+    /// A field or method that the value's type does not have
+    NoAttribute {
+        hir_id: HirId,
+        ty: Type,
+        name: String,
+    },
+    /// A struct constructor that leaves fields unset, in declaration order
+    MissingField {
+        hir_id: HirId,
+        strukt: String,
+        fields: Vec<String>,
+    },
+    /// A struct constructor setting a field the struct does not have
+    UnexpectedField {
+        hir_id: HirId,
+        strukt: String,
+        field: String,
+    },
+    /// A `match` with no arm for some values of the subject; `missing` is one of them,
+    /// written as a pattern
+    NonExhaustiveMatch {
+        hir_id: HirId,
+        ty: Type,
+        missing: String,
+    },
 }
 
 #[derive(Debug, Default)]